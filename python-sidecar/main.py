@@ -11,6 +11,12 @@ from PIL import Image, ImageEnhance
 import cv2
 import numpy as np
 
+try:
+    # 名前欄OCR用（tesseract本体が必要。未導入ならOCRのみ無効）
+    import pytesseract
+except ImportError:
+    pytesseract = None
+
 
 def configure_model_home() -> Optional[Path]:
     candidates = []
@@ -179,6 +185,39 @@ def process_image(base64_image):
             "error": str(e)
         }
 
+def ocr_name(base64_image, box, languages="jpn+eng"):
+    try:
+        if pytesseract is None:
+            raise RuntimeError("pytesseract is not installed")
+
+        image_data = base64.b64decode(base64_image.split(',')[1] if ',' in base64_image else base64_image)
+        image = Image.open(io.BytesIO(image_data)).convert("L")
+
+        # 名前欄を切り出し（boxは用紙に対する割合）
+        width, height = image.size
+        left = int(float(box.get("x", 0)) * width)
+        top = int(float(box.get("y", 0)) * height)
+        right = int((float(box.get("x", 0)) + float(box.get("width", 1))) * width)
+        bottom = int((float(box.get("y", 0)) + float(box.get("height", 1))) * height)
+        region = image.crop((left, top, max(right, left + 1), max(bottom, top + 1)))
+
+        # 小さい欄は拡大して二値化（手書き文字の認識率を上げる）
+        if region.height < 64:
+            scale = 64 / region.height
+            region = region.resize((int(region.width * scale), 64), Image.LANCZOS)
+        array = np.array(region)
+        _, binary = cv2.threshold(array, 0, 255, cv2.THRESH_BINARY + cv2.THRESH_OTSU)
+
+        # 1行のテキストとして読む
+        text = pytesseract.image_to_string(
+            Image.fromarray(binary),
+            lang=languages or "jpn+eng",
+            config="--psm 7",
+        )
+        return {"type": "result", "success": True, "text": text.strip()}
+    except Exception as e:
+        return {"type": "result", "success": False, "error": str(e)}
+
 def main():
     # 標準入力からJSONを読み込み、常駐で処理
     while True:
@@ -193,6 +232,9 @@ def main():
                 result = process_image(data.get("image", ""))
                 print(json.dumps(result), flush=True)
                 sys.stdout.flush()
+            elif cmd == "ocr":
+                result = ocr_name(data.get("image", ""), data.get("box") or {}, data.get("languages", "jpn+eng"))
+                print(json.dumps(result), flush=True)
             elif cmd == "health" or cmd == "warmup":
                 print(json.dumps({"success": True, "status": "ready"}), flush=True)
             elif cmd == "shutdown":
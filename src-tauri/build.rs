@@ -1,3 +1,24 @@
+// ライセンス検証の公開鍵（Ed25519, base64）。licensing.rs が option_env! で埋め込む
+const LICENSE_PUBLIC_KEY_ENV: &str = "NURIEMON_LICENSE_PUBLIC_KEY";
+
 fn main() {
+    println!("cargo:rerun-if-env-changed={}", LICENSE_PUBLIC_KEY_ENV);
+    let key = std::env::var(LICENSE_PUBLIC_KEY_ENV).unwrap_or_default();
+    let key = key.trim();
+    // 32バイトの鍵は base64 で44文字（末尾 '='）
+    let well_formed = key.len() == 44 && key.ends_with('=');
+    if std::env::var("PROFILE").as_deref() == Ok("release") && !well_formed {
+        // 鍵なしで配布するとすべてのトークンが不正扱いになるため、リリースビルドは止める
+        panic!(
+            "{} にライセンスの公開鍵（Ed25519, base64）を設定してからリリースビルドしてください",
+            LICENSE_PUBLIC_KEY_ENV
+        );
+    }
+    if !key.is_empty() && !well_formed {
+        println!(
+            "cargo:warning={} の形式が不正です（32バイトのbase64が必要）",
+            LICENSE_PUBLIC_KEY_ENV
+        );
+    }
     tauri_build::build()
 }
@@ -0,0 +1,241 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+use crate::workspace::WorkspaceState;
+
+const FILE_PREFIX: &str = "web-access-";
+const FILE_EXTENSION: &str = ".jsonl";
+// 1ファイルの上限（超えたら同じ日付で連番を振る）
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+// 残すファイル数（古いものから削除）
+const MAX_LOG_FILES: usize = 30;
+const DEFAULT_RECENT_LIMIT: usize = 200;
+const MAX_RECENT_LIMIT: usize = 5000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessLogEntry {
+    pub timestamp: String,
+    pub ip: Option<String>,
+    pub method: String,
+    // クエリ文字列はトークンを含むため記録しない
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    // WebSocketでQRセッションに参加した記録のみ（どの端末がどのセッションに入ったか）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+// 書き込みはワークスペースのロックを待つ可能性があるため専用スレッドで行う
+static LOG_SENDER: OnceCell<Mutex<Sender<AccessLogEntry>>> = OnceCell::new();
+
+fn log_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    let state = app_handle.try_state::<WorkspaceState>()?;
+    let conn = state.lock().ok()?;
+    conn.workspace_root().map(|root| root.join("logs"))
+}
+
+struct LogWriter {
+    path: Option<PathBuf>,
+    file: Option<File>,
+}
+
+impl LogWriter {
+    // 日付ごとのファイル。サイズ上限を超えたら -1, -2 ... と連番にする
+    fn target_path(dir: &Path) -> PathBuf {
+        let date = chrono::Local::now().format("%Y%m%d").to_string();
+        let mut index = 0;
+        loop {
+            let name = if index == 0 {
+                format!("{}{}{}", FILE_PREFIX, date, FILE_EXTENSION)
+            } else {
+                format!("{}{}-{}{}", FILE_PREFIX, date, index, FILE_EXTENSION)
+            };
+            let path = dir.join(name);
+            let full = fs::metadata(&path)
+                .map(|m| m.len() >= MAX_FILE_BYTES)
+                .unwrap_or(false);
+            if !full {
+                return path;
+            }
+            index += 1;
+        }
+    }
+
+    fn write(&mut self, dir: &Path, entry: &AccessLogEntry) -> Result<(), String> {
+        let path = Self::target_path(dir);
+        if self.path.as_ref() != Some(&path) || self.file.is_none() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("ログディレクトリの作成に失敗しました: {}", e))?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("アクセスログを開けませんでした: {}", e))?;
+            self.file = Some(file);
+            self.path = Some(path);
+            prune_old_files(dir);
+        }
+
+        let line = serde_json::to_string(entry)
+            .map_err(|e| format!("アクセスログのシリアライズに失敗しました: {}", e))?;
+        let file = self.file.as_mut().ok_or("アクセスログが開かれていません")?;
+        writeln!(file, "{}", line)
+            .map_err(|e| format!("アクセスログの書き込みに失敗しました: {}", e))
+    }
+}
+
+// 新しい順（ファイル名の日付・連番順）に並べたログファイル
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<(String, u32, PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let stem = name
+                        .strip_prefix(FILE_PREFIX)?
+                        .strip_suffix(FILE_EXTENSION)?
+                        .to_string();
+                    let (date, index) = match stem.split_once('-') {
+                        Some((date, index)) => (date.to_string(), index.parse().ok()?),
+                        None => (stem, 0),
+                    };
+                    Some((date, index, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| (&b.0, b.1).cmp(&(&a.0, a.1)));
+    files.into_iter().map(|(_, _, path)| path).collect()
+}
+
+fn prune_old_files(dir: &Path) {
+    for path in log_files(dir).into_iter().skip(MAX_LOG_FILES) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("[access_log] failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// 書き込みスレッドを起動（アプリ起動時に一度だけ）
+pub fn start(app_handle: AppHandle) {
+    let (tx, rx) = mpsc::channel::<AccessLogEntry>();
+    if LOG_SENDER.set(Mutex::new(tx)).is_err() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let mut writer = LogWriter {
+            path: None,
+            file: None,
+        };
+        for entry in rx {
+            // ワークスペース未選択の間は記録しない
+            let Some(dir) = log_dir(&app_handle) else {
+                continue;
+            };
+            if let Err(e) = writer.write(&dir, &entry) {
+                eprintln!("[access_log] {}", e);
+                writer.file = None;
+            }
+        }
+    });
+}
+
+fn record(entry: AccessLogEntry) {
+    if let Some(sender) = LOG_SENDER.get() {
+        let _ = sender.lock().unwrap().send(entry);
+    }
+}
+
+/// 全リクエストをJSONLへ記録するミドルウェア（認証・レート制限で拒否したものも含む）
+pub async fn access_log(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let ip = req.peer_addr().map(|addr| addr.ip().to_string());
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.chars().take(256).collect());
+
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    };
+    record(AccessLogEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        ip,
+        method,
+        path,
+        status,
+        duration_ms: started.elapsed().as_millis() as u64,
+        user_agent,
+        session_id: None,
+    });
+    result
+}
+
+/// WebSocketでQRセッションに参加したことを記録する
+pub fn record_session_join(ip: Option<String>, session_id: &str) {
+    record(AccessLogEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        ip,
+        method: "JOIN".to_string(),
+        path: "/ws".to_string(),
+        status: 200,
+        duration_ms: 0,
+        user_agent: None,
+        session_id: Some(session_id.to_string()),
+    });
+}
+
+/// 直近のアクセスログ（新しい順）
+#[tauri::command]
+pub fn get_recent_access_logs(
+    app_handle: AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<AccessLogEntry>, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_RECENT_LIMIT)
+        .clamp(1, MAX_RECENT_LIMIT);
+    let Some(dir) = log_dir(&app_handle) else {
+        return Err("ワークスペースが選択されていません".to_string());
+    };
+
+    let mut entries = Vec::new();
+    for path in log_files(&dir) {
+        let file =
+            File::open(&path).map_err(|e| format!("アクセスログを開けませんでした: {}", e))?;
+        let mut lines: Vec<AccessLogEntry> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        lines.reverse();
+        entries.extend(lines.into_iter().take(limit - entries.len()));
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    Ok(entries)
+}
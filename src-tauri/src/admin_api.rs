@@ -0,0 +1,410 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::{self, Next};
+use actix_web::{web, Error, HttpResponse};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::RngCore;
+use serde::Deserialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::background_scheduler::ACTIVE_BACKGROUND_KEY;
+use crate::db::Database;
+use crate::events::{
+    emit_data_change, BackgroundChangedPayload, DataChangeEvent, ImageDeletedPayload,
+    ImageUpsertedPayload,
+};
+use crate::server_state::ServerState;
+use crate::web_server::WebServerState;
+use crate::workspace::{read_global_setting, write_global_setting, WorkspaceState};
+
+// 管理トークンを保存するグローバル設定キー
+const ADMIN_TOKEN_KEY: &str = "web_admin_token";
+// Authorization: Bearer の代わりに使えるヘッダー
+const ADMIN_TOKEN_HEADER: &str = "X-Admin-Token";
+
+static ADMIN_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+// 管理APIから変更できる設定（表示の調整だけ。動作モードや取り込み先などは管理画面から変える）
+const WRITABLE_SETTINGS: &[&str] = &["ground_position", "deletion_time"];
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// 管理トークン（未生成ならこのPCで生成して保存）
+fn admin_token(app_handle: &AppHandle) -> Result<String, String> {
+    let mut cached = ADMIN_TOKEN
+        .lock()
+        .map_err(|_| "ADMIN_TOKEN lock error".to_string())?;
+    if let Some(token) = cached.as_ref() {
+        return Ok(token.clone());
+    }
+    let token = match read_global_setting(app_handle, ADMIN_TOKEN_KEY)? {
+        Some(token) if !token.trim().is_empty() => token,
+        _ => {
+            let token = generate_token();
+            write_global_setting(app_handle, ADMIN_TOKEN_KEY, &token)?;
+            token
+        }
+    };
+    *cached = Some(token.clone());
+    Ok(token)
+}
+
+// 比較時間からトークンを推測されないよう全バイトを比較する
+fn token_matches(expected: &str, provided: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), provided.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn provided_token(req: &ServiceRequest) -> Option<String> {
+    let headers = req.headers();
+    if let Some(token) = headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        return Some(token.trim().to_string());
+    }
+    headers
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string())
+}
+
+fn error_json(status: actix_web::http::StatusCode, code: &str, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({
+        "error": code,
+        "message": message,
+    }))
+}
+
+// トークンを盗み見られないよう、HTTPSかこのPC自身からの接続に限る
+fn is_secure_channel(req: &ServiceRequest) -> bool {
+    req.app_config().secure() || req.peer_addr().is_some_and(|addr| addr.ip().is_loopback())
+}
+
+/// /api/admin/* を管理トークンで保護するミドルウェア
+async fn admin_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if !is_secure_channel(&req) {
+        println!(
+            "[admin_api] rejected insecure {} from {:?}",
+            req.path(),
+            req.peer_addr()
+        );
+        let res = error_json(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "insecure_channel",
+            "管理APIはHTTPSかこのPCからのみ利用できます",
+        );
+        return Ok(req.into_response(res).map_into_boxed_body());
+    }
+    let expected = req
+        .app_data::<web::Data<WebServerState>>()
+        .map(|data| admin_token(&data.app_handle))
+        .transpose()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let authorized = match (expected, provided_token(&req)) {
+        (Some(expected), Some(provided)) => token_matches(&expected, &provided),
+        _ => false,
+    };
+    if !authorized {
+        println!(
+            "[admin_api] rejected {} from {:?}",
+            req.path(),
+            req.peer_addr()
+        );
+        let res = error_json(
+            actix_web::http::StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "管理トークンが必要です",
+        );
+        return Ok(req.into_response(res).map_into_boxed_body());
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Webサーバーに管理APIのルートを登録
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/admin")
+            .wrap(middleware::from_fn(admin_guard))
+            .route("/status", web::get().to(get_status))
+            .route("/images/hide-all", web::post().to(hide_all_images))
+            .route("/images/{id}/hide", web::post().to(set_image_hidden))
+            .route("/images/{id}", web::delete().to(delete_image))
+            .route("/settings/{key}", web::put().to(put_setting))
+            .route("/background", web::put().to(put_background)),
+    );
+}
+
+// ワークスペースDBを使う処理（未接続なら503）
+fn with_db<T>(
+    data: &WebServerState,
+    f: impl FnOnce(&Database) -> Result<T, String>,
+) -> Result<T, Error> {
+    let state: tauri::State<WorkspaceState> = data.app_handle.state();
+    let conn = state.lock().map_err(|_| {
+        actix_web::error::ErrorInternalServerError("ワークスペース接続のロックに失敗")
+    })?;
+    let db = conn
+        .get()
+        .map_err(actix_web::error::ErrorServiceUnavailable)?;
+    f(db).map_err(actix_web::error::ErrorInternalServerError)
+}
+
+async fn get_status(data: web::Data<WebServerState>) -> Result<HttpResponse, Error> {
+    let server_state: tauri::State<ServerState> = data.app_handle.state();
+    let (python_running, python_pending) = crate::python_runtime_status();
+    let watcher = crate::file_watcher::watcher_status();
+    let counts = with_db(&data, |db| {
+        let (original, processed) = db
+            .get_image_counts()
+            .map_err(|e| format!("Failed to get image counts: {}", e))?;
+        let displayed = db
+            .get_displayed_images()
+            .map_err(|e| format!("Failed to get displayed images: {}", e))?
+            .len();
+        Ok(serde_json::json!({
+            "original": original,
+            "processed": processed,
+            "displayed": displayed,
+        }))
+    })
+    .ok();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "appVersion": data.app_handle.package_info().version.to_string(),
+        "uptimeSeconds": crate::heartbeat::uptime_secs(),
+        "server": {
+            "httpPort": server_state.get_server_port(),
+            "httpsPort": server_state.get_https_port(),
+            "authMode": server_state.web_auth.mode().as_str(),
+        },
+        "websocketConnections": crate::websocket::connections_snapshot().len(),
+        "websocketMetrics": crate::websocket::ws_metrics(&data.app_handle),
+        "queues": {
+            "pythonRunning": python_running,
+            "pythonPending": python_pending,
+            "autoImportInFlight": watcher.imports_in_flight,
+        },
+        "images": counts,
+    })))
+}
+
+async fn hide_all_images(data: web::Data<WebServerState>) -> Result<HttpResponse, Error> {
+    let app_handle = data.app_handle.clone();
+    let ids = with_db(&data, |db| {
+        db.hide_all_processed_images()
+            .map_err(|e| format!("Failed to hide images: {}", e))
+    })?;
+    // 表示中の画面からは削除と同じ扱いで取り除く
+    for id in &ids {
+        emit_data_change(
+            &app_handle,
+            DataChangeEvent::ImageDeleted(ImageDeletedPayload { id: id.clone() }),
+        )
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    println!("[admin_api] hid {} images", ids.len());
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "hidden": ids.len() })))
+}
+
+#[derive(Deserialize)]
+struct HideRequest {
+    #[serde(default = "default_hidden")]
+    hidden: bool,
+}
+
+fn default_hidden() -> bool {
+    true
+}
+
+async fn set_image_hidden(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+    body: Option<web::Json<HideRequest>>,
+) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    let hidden = body.map(|b| b.hidden).unwrap_or(true);
+    let app_handle = data.app_handle.clone();
+
+    let found = with_db(&data, |db| {
+        let updated = db
+            .set_image_hidden(&id, hidden)
+            .map_err(|e| format!("Failed to update image: {}", e))?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        let event = if hidden {
+            DataChangeEvent::ImageDeleted(ImageDeletedPayload { id: id.clone() })
+        } else {
+            let meta = db
+                .get_image(&id)
+                .map_err(|e| format!("Failed to get image: {}", e))?
+                .ok_or_else(|| format!("画像が見つかりません: {}", id))?;
+            DataChangeEvent::ImageUpserted(ImageUpsertedPayload::from(&meta))
+        };
+        emit_data_change(&app_handle, event)?;
+        Ok(true)
+    })?;
+
+    if !found {
+        return Ok(error_json(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "not_found",
+            "画像が見つかりません",
+        ));
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "id": id, "hidden": hidden })))
+}
+
+async fn delete_image(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    let app_handle = data.app_handle.clone();
+
+    let found = with_db(&data, |db| {
+        let exists = db
+            .get_image(&id)
+            .map_err(|e| format!("Failed to get image: {}", e))?
+            .is_some();
+        if exists {
+            crate::delete_image_and_notify(&app_handle, db, &id)?;
+        }
+        Ok(exists)
+    })?;
+
+    if !found {
+        return Ok(error_json(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "not_found",
+            "画像が見つかりません",
+        ));
+    }
+    println!("[admin_api] deleted image {}", id);
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "id": id, "deleted": true })))
+}
+
+// 地面の位置は画面の高さに対する割合、削除時間は分数か "unlimited"
+fn is_valid_setting(key: &str, value: &str) -> bool {
+    match key {
+        "ground_position" => value
+            .parse::<i32>()
+            .is_ok_and(|position| (0..=100).contains(&position)),
+        "deletion_time" => value == "unlimited" || value.parse::<u32>().is_ok_and(|m| m > 0),
+        _ => false,
+    }
+}
+
+#[derive(Deserialize)]
+struct SettingRequest {
+    value: String,
+}
+
+async fn put_setting(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+    body: web::Json<SettingRequest>,
+) -> Result<HttpResponse, Error> {
+    let key = path.into_inner();
+    let value = body.into_inner().value;
+    if !WRITABLE_SETTINGS.contains(&key.as_str()) {
+        return Ok(error_json(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "setting_not_allowed",
+            "この設定は管理APIから変更できません",
+        ));
+    }
+    if !is_valid_setting(&key, &value) {
+        return Ok(error_json(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "invalid_value",
+            "設定値が不正です",
+        ));
+    }
+    let app_handle = data.app_handle.clone();
+
+    with_db(&data, |db| {
+        db.save_app_setting_from(&key, &value, "admin-api")
+            .map_err(|e| format!("Failed to save app setting: {}", e))?;
+        let version = db
+            .settings_version()
+            .map_err(|e| format!("Failed to get settings version: {}", e))?;
+        emit_data_change(
+            &app_handle,
+            crate::app_setting_event(key.clone(), value.clone(), version),
+        )
+    })?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "key": key, "value": value })))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackgroundRequest {
+    background_id: String,
+}
+
+async fn put_background(
+    data: web::Data<WebServerState>,
+    body: web::Json<BackgroundRequest>,
+) -> Result<HttpResponse, Error> {
+    let background_id = body.into_inner().background_id;
+    let app_handle = data.app_handle.clone();
+
+    let found = with_db(&data, |db| {
+        let is_background = db
+            .get_image(&background_id)
+            .map_err(|e| format!("Failed to get image: {}", e))?
+            .map(|m| m.image_type == "background")
+            .unwrap_or(false);
+        if !is_background {
+            return Ok(false);
+        }
+        db.save_app_setting(ACTIVE_BACKGROUND_KEY, &background_id)
+            .map_err(|e| format!("Failed to save app setting: {}", e))?;
+        emit_data_change(
+            &app_handle,
+            DataChangeEvent::BackgroundChanged(BackgroundChangedPayload::for_background(
+                db,
+                &background_id,
+            )),
+        )?;
+        Ok(true)
+    })?;
+
+    if !found {
+        return Ok(error_json(
+            actix_web::http::StatusCode::NOT_FOUND,
+            "not_found",
+            "背景が見つかりません",
+        ));
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "backgroundId": background_id })))
+}
+
+/// 管理APIのトークンを表示（技術スタッフがスクリプトに設定する）
+#[tauri::command]
+pub fn get_admin_api_token(app_handle: AppHandle) -> Result<String, String> {
+    admin_token(&app_handle)
+}
+
+/// 管理APIのトークンを再発行（以前のトークンは即座に無効）
+#[tauri::command]
+pub fn regenerate_admin_api_token(app_handle: AppHandle) -> Result<String, String> {
+    let token = generate_token();
+    write_global_setting(&app_handle, ADMIN_TOKEN_KEY, &token)?;
+    *ADMIN_TOKEN
+        .lock()
+        .map_err(|_| "ADMIN_TOKEN lock error".to_string())? = Some(token.clone());
+    Ok(token)
+}
@@ -0,0 +1,49 @@
+// 書き込み途中で落ちても元のファイルが壊れないよう、同じフォルダの一時ファイルに書いてから置き換える
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 置き換え先と同じフォルダに作る（rename が同じボリューム内で完結するように）
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()))
+}
+
+// rename をディスクに残すためフォルダも同期する（Windows ではフォルダを開けないため行わない）
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// 一時ファイルに書いて置き換える。durable なら置き換え前後に fsync する
+/// （キャッシュなど電源断で失っても困らないものは false でよい）
+pub fn write(path: &Path, contents: impl AsRef<[u8]>, durable: bool) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        if durable {
+            file.sync_all()?;
+        }
+        drop(file);
+        fs::rename(&tmp, path)?;
+        if durable {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                sync_dir(parent)?;
+            }
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
@@ -0,0 +1,204 @@
+use crate::db::{current_timestamp, generate_id, BackgroundScheduleEntry};
+use crate::events::{emit_data_change, BackgroundChangedPayload, DataChangeEvent};
+use crate::workspace::WorkspaceState;
+use chrono::{Local, NaiveTime};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+
+// 現在有効な背景IDを保存する app_settings のキー
+pub const ACTIVE_BACKGROUND_KEY: &str = "active_background_id";
+// スケジュール未設定時の再確認間隔（変更時は即座に起こされる）
+const IDLE_RECHECK: Duration = Duration::from_secs(60 * 60);
+
+// スケジュール変更/ワークスペース切替時にスケジューラを起こす
+static SCHEDULE_CHANGED: Lazy<Notify> = Lazy::new(Notify::new);
+
+pub fn notify_schedule_changed() {
+    SCHEDULE_CHANGED.notify_one();
+}
+
+fn parse_start_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+// 現在時刻で有効なエントリと、次の切り替えまでの待ち時間を求める
+fn resolve_active(
+    entries: &[BackgroundScheduleEntry],
+    now: NaiveTime,
+) -> Option<(&BackgroundScheduleEntry, Duration)> {
+    let mut timed: Vec<(NaiveTime, &BackgroundScheduleEntry)> = entries
+        .iter()
+        .filter(|e| e.enabled)
+        .filter_map(|e| parse_start_time(&e.start_time).map(|t| (t, e)))
+        .collect();
+    timed.sort_by_key(|(t, _)| *t);
+
+    // 当日中で直近に開始したもの、なければ前日最後のもの
+    let active = timed
+        .iter()
+        .rev()
+        .find(|(t, _)| *t <= now)
+        .or_else(|| timed.last())?;
+    // 次の開始時刻（当日中になければ翌日の最初）
+    let (next, _) = timed
+        .iter()
+        .find(|(t, _)| *t > now)
+        .or_else(|| timed.first())?;
+
+    let mut wait = next.signed_duration_since(now);
+    if wait <= chrono::Duration::zero() {
+        wait += chrono::Duration::days(1);
+    }
+    Some((active.1, wait.to_std().unwrap_or(IDLE_RECHECK)))
+}
+
+// スケジュールを評価して背景を切り替える。次回評価までの待ち時間を返す
+fn apply_schedule(app_handle: &AppHandle) -> Duration {
+    let workspace: State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = workspace.lock() else {
+        return IDLE_RECHECK;
+    };
+    let Ok(db) = conn.get() else {
+        return IDLE_RECHECK;
+    };
+
+    let entries = match db.get_background_schedule() {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[background_scheduler] failed to load schedule: {}", e);
+            return IDLE_RECHECK;
+        }
+    };
+    let Some((entry, wait)) = resolve_active(&entries, Local::now().time()) else {
+        return IDLE_RECHECK;
+    };
+
+    let current = db.get_app_setting(ACTIVE_BACKGROUND_KEY).ok().flatten();
+    if current.as_deref() != Some(entry.background_id.as_str()) {
+        match db.save_app_setting(ACTIVE_BACKGROUND_KEY, &entry.background_id) {
+            Ok(()) => {
+                println!(
+                    "[background_scheduler] activated background {} ({})",
+                    entry.background_id, entry.start_time
+                );
+                let _ = emit_data_change(
+                    app_handle,
+                    DataChangeEvent::BackgroundChanged(BackgroundChangedPayload::for_background(
+                        db,
+                        &entry.background_id,
+                    )),
+                );
+            }
+            Err(e) => eprintln!("[background_scheduler] failed to activate: {}", e),
+        }
+    }
+
+    // 端数で境界直前に起きないよう1秒だけ余裕を持たせる
+    wait + Duration::from_secs(1)
+}
+
+/// 背景スケジューラを起動（アプリ起動時に一度だけ呼ぶ）
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait = apply_schedule(&app_handle);
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = SCHEDULE_CHANGED.notified() => {}
+            }
+        }
+    });
+}
+
+/// 背景スケジュールを追加
+#[tauri::command]
+pub fn add_background_schedule_entry(
+    workspace: State<'_, WorkspaceState>,
+    background_id: String,
+    start_time: String,
+) -> Result<BackgroundScheduleEntry, String> {
+    let time = parse_start_time(&start_time)
+        .ok_or_else(|| format!("開始時刻の形式が不正です（HH:MM）: {}", start_time))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let is_background = db
+        .get_image(&background_id)
+        .map_err(|e| format!("Failed to get image: {}", e))?
+        .map(|img| img.image_type == "background")
+        .unwrap_or(false);
+    if !is_background {
+        return Err(format!("背景画像が見つかりません: {}", background_id));
+    }
+
+    let now = current_timestamp();
+    let entry = BackgroundScheduleEntry {
+        id: generate_id(),
+        background_id,
+        start_time: time.format("%H:%M").to_string(),
+        enabled: true,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    db.save_background_schedule_entry(&entry)
+        .map_err(|e| format!("Failed to save background schedule: {}", e))?;
+
+    notify_schedule_changed();
+    Ok(entry)
+}
+
+/// 背景スケジュールの一覧
+#[tauri::command]
+pub fn list_background_schedule(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<Vec<BackgroundScheduleEntry>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.get_background_schedule()
+        .map_err(|e| format!("Failed to get background schedule: {}", e))
+}
+
+/// 背景スケジュールの有効/無効を切り替え
+#[tauri::command]
+pub fn set_background_schedule_enabled(
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.set_background_schedule_enabled(&id, enabled)
+        .map_err(|e| format!("Failed to update background schedule: {}", e))?;
+
+    notify_schedule_changed();
+    Ok(())
+}
+
+/// 背景スケジュールを削除
+#[tauri::command]
+pub fn delete_background_schedule_entry(
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.delete_background_schedule_entry(&id)
+        .map_err(|e| format!("Failed to delete background schedule: {}", e))?;
+
+    notify_schedule_changed();
+    Ok(())
+}
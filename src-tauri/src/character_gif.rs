@@ -0,0 +1,263 @@
+// キャラクターごとの共有用GIF（保存済みの動きの設定で数秒分の動きを描き、ループするGIFにする）
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, Frame, Rgba, RgbaImage};
+use serde::Serialize;
+use std::f32::consts::TAU;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::MovementSettings;
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const FRAME_WIDTH: u32 = 360;
+const FRAME_HEIGHT: u32 = 360;
+const DEFAULT_FRAMES: u32 = 24;
+const MAX_FRAMES: u32 = 120;
+const FRAME_DELAY_MS: u32 = 80;
+const SKY: Rgba<u8> = Rgba([224, 246, 255, 255]);
+const WATER: Rgba<u8> = Rgba([189, 230, 247, 255]);
+const GROUND: Rgba<u8> = Rgba([168, 214, 120, 255]);
+// 歩くキャラクターの地面の高さ（下端からの割合）
+const GROUND_RATIO: f32 = 0.12;
+// /share/{token}.gif のトークンの用途（画像IDだけでは配信しない）
+const TOKEN_SCOPE: &str = "share-gif";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterGifExport {
+    pub path: String,
+    pub token: String,
+    // Webサーバーが起動していなければ None
+    pub url: Option<String>,
+}
+
+/// 共有用GIFの保存先（/share/{token}.gif もここから配信する）
+pub(crate) fn gif_path(exports_dir: &Path, image_id: &str) -> PathBuf {
+    exports_dir.join("gifs").join(format!("{}.gif", image_id))
+}
+
+/// 画像IDとして使える文字だけか（配信時のパス確認用）
+fn is_valid_id(image_id: &str) -> bool {
+    !image_id.is_empty()
+        && image_id.len() <= 64
+        && image_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 署名付きトークンを検証して画像IDを返す（/share/{token}.gif から呼ぶ）
+pub(crate) fn verify_share_token(app_handle: &AppHandle, token: &str) -> Option<String> {
+    app_handle
+        .state::<ServerState>()
+        .web_auth
+        .verify_scoped_token(TOKEN_SCOPE, token)
+        .filter(|image_id| is_valid_id(image_id))
+}
+
+fn sprite_ratio(size: &str) -> f32 {
+    match size {
+        "small" => 0.35,
+        "large" => 0.65,
+        _ => 0.5,
+    }
+}
+
+// ランダムの動きを画像ごとに毎回同じにするための位相
+fn seed_phase(image_id: &str) -> f32 {
+    let hash = image_id
+        .bytes()
+        .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+    (hash % 1000) as f32 / 1000.0 * TAU
+}
+
+/// 1周分（t: 0〜1）のうちの位置。中心からのずれ（画面に対する割合）と左向きかを返す
+fn offset_at(settings: &MovementSettings, pattern: &str, t: f32) -> (f32, f32, bool) {
+    let range = 0.12 + 0.2 * settings.speed.clamp(0.0, 1.0);
+    let amplitude = settings.amplitude.unwrap_or(1.0).clamp(0.2, 2.0);
+    let angle = TAU * t;
+    let mut x = range * angle.sin();
+    // x の増減で向きを決める（sin の微分）
+    let mut facing_left = angle.cos() < 0.0;
+    let y = match pattern {
+        "zigzag" => {
+            // 1周で4回折り返す三角波
+            let phase = (t * 4.0).fract();
+            let tri = if phase < 0.5 {
+                phase * 2.0
+            } else {
+                2.0 - phase * 2.0
+            };
+            (tri - 0.5) * 0.2 * amplitude
+        }
+        "bounce" => -(TAU * t * 2.0).sin().abs() * 0.25 * amplitude,
+        "circle" => {
+            x = range * angle.cos();
+            facing_left = angle.sin() > 0.0;
+            range * angle.sin()
+        }
+        "wave" => (angle * 3.0).sin() * 0.12 * amplitude,
+        "random" => {
+            let seed = seed_phase(&settings.image_id);
+            ((angle + seed).sin() * 0.6 + (angle * 3.0 + seed * 2.0).sin() * 0.4) * 0.12 * amplitude
+        }
+        _ => (angle * 2.0).sin() * 0.03 * amplitude,
+    };
+    // 歩くキャラクターは地面から浮かない（跳ねる動きだけ上に出る）
+    let y = if settings.movement_type == "walk" {
+        y.min(0.0)
+    } else {
+        y
+    };
+    if let Some(bias) = settings.direction_bias {
+        x += bias.clamp(-1.0, 1.0) * 0.1;
+    }
+    (x, y, facing_left)
+}
+
+fn background(movement_type: &str) -> RgbaImage {
+    let mut frame = RgbaImage::from_pixel(
+        FRAME_WIDTH,
+        FRAME_HEIGHT,
+        if movement_type == "swim" { WATER } else { SKY },
+    );
+    if movement_type == "walk" {
+        let ground_top = FRAME_HEIGHT - (FRAME_HEIGHT as f32 * GROUND_RATIO) as u32;
+        for y in ground_top..FRAME_HEIGHT {
+            for x in 0..FRAME_WIDTH {
+                frame.put_pixel(x, y, GROUND);
+            }
+        }
+    }
+    frame
+}
+
+fn render_frames(
+    character: &RgbaImage,
+    settings: &MovementSettings,
+    pattern: &str,
+    frames: u32,
+) -> Vec<Frame> {
+    let sprite_size = (FRAME_HEIGHT as f32 * sprite_ratio(&settings.size)) as u32;
+    // 縦横比を保って sprite_size の枠に収める
+    let scale = (sprite_size as f32 / character.width().max(1) as f32)
+        .min(sprite_size as f32 / character.height().max(1) as f32);
+    let sprite = imageops::resize(
+        character,
+        ((character.width() as f32 * scale) as u32).max(1),
+        ((character.height() as f32 * scale) as u32).max(1),
+        imageops::FilterType::Lanczos3,
+    );
+    let flipped = imageops::flip_horizontal(&sprite);
+    let base = background(&settings.movement_type);
+    // 基準の位置（歩くキャラクターは地面に立たせる）
+    let base_y = if settings.movement_type == "walk" {
+        FRAME_HEIGHT as f32 * (1.0 - GROUND_RATIO) - sprite.height() as f32
+    } else {
+        (FRAME_HEIGHT - sprite.height()) as f32 / 2.0
+    };
+    let base_x = (FRAME_WIDTH - sprite.width()) as f32 / 2.0;
+
+    (0..frames)
+        .map(|i| {
+            let (dx, dy, facing_left) = offset_at(settings, pattern, i as f32 / frames as f32);
+            let mut frame = base.clone();
+            let x = base_x + dx * FRAME_WIDTH as f32;
+            let y = base_y + dy * FRAME_HEIGHT as f32;
+            let sprite = if facing_left { &flipped } else { &sprite };
+            imageops::overlay(&mut frame, sprite, x as i64, y as i64);
+            Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(FRAME_DELAY_MS, 1))
+        })
+        .collect()
+}
+
+fn write_gif(path: &Path, frames: Vec<Frame>) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("フォルダ作成エラー: {}", e))?;
+    }
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("GIFファイルの作成に失敗しました: {}", e))?;
+    let mut encoder = GifEncoder::new_with_speed(std::io::BufWriter::new(file), 10);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|e| format!("GIFの書き込みに失敗しました: {}", e))?;
+    encoder
+        .encode_frames(frames)
+        .map_err(|e| format!("GIFの書き込みに失敗しました: {}", e))
+}
+
+/// キャラクターが動くGIFを書き出し、保存先と共有URLを返す（movement を省略すると保存済みの動き）
+#[tauri::command]
+pub async fn export_character_gif(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    image_id: String,
+    movement: Option<String>,
+    frames: Option<u32>,
+) -> Result<CharacterGifExport, String> {
+    let frames = frames.unwrap_or(DEFAULT_FRAMES);
+    if !(2..=MAX_FRAMES).contains(&frames) {
+        return Err(format!("フレーム数は2〜{}で指定してください", MAX_FRAMES));
+    }
+    let (image, settings) = {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        let image = db
+            .get_image(&image_id)
+            .map_err(|e| format!("Failed to get image: {}", e))?
+            .ok_or_else(|| format!("画像が見つかりません: {}", image_id))?;
+        let settings = db
+            .get_movement_settings(&image_id)
+            .map_err(|e| format!("Failed to get movement settings: {}", e))?;
+        (image, settings)
+    };
+    if image.image_type != "processed" {
+        return Err("GIFにできるのは処理済みの画像だけです".to_string());
+    }
+    let settings = settings.unwrap_or_else(|| MovementSettings {
+        image_id: image_id.clone(),
+        movement_type: "walk".to_string(),
+        movement_pattern: "normal".to_string(),
+        speed: 0.5,
+        size: "medium".to_string(),
+        created_at: String::new(),
+        updated_at: String::new(),
+        gravity: None,
+        bounce_elasticity: None,
+        rotation_speed: None,
+        amplitude: None,
+        direction_bias: None,
+        z_order: None,
+    });
+    let pattern = movement
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| settings.movement_pattern.clone());
+    let output = gif_path(&crate::frame_capture::exports_dir(&app_handle)?, &image.id);
+    let server_state = app_handle.state::<ServerState>();
+    let token = server_state
+        .web_auth
+        .issue_scoped_token(TOKEN_SCOPE, &image.id);
+    let url = server_state
+        .get_qr_manager()
+        .map(|qr_manager| qr_manager.share_url(&format!("{}.gif", token)));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let character = image::open(image.resolve_file_path())
+            .map_err(|e| format!("画像の読み込みに失敗しました: {}", e))?
+            .to_rgba8();
+        write_gif(
+            &output,
+            render_frames(&character, &settings, &pattern, frames),
+        )?;
+        println!("[character_gif] exported {}", output.display());
+        Ok(CharacterGifExport {
+            path: output.to_string_lossy().to_string(),
+            token,
+            url,
+        })
+    })
+    .await
+    .map_err(|e| format!("GIFの作成に失敗しました: {}", e))?
+}
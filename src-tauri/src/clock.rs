@@ -0,0 +1,126 @@
+use crate::workspace::WorkspaceState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// これ以上ずれていたら警告する（ミリ秒）
+const SKEW_WARN_THRESHOLD_MS: i64 = 30_000;
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+// 直近に測定した補正値（ワークスペース未接続時の保持用）
+static MEASURED_OFFSET_MS: Mutex<Option<i64>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkewReport {
+    pub offset_ms: i64,
+    pub skewed: bool,
+    pub source: String,
+    pub checked_at: String,
+}
+
+pub fn measured_offset_ms() -> Option<i64> {
+    MEASURED_OFFSET_MS.lock().ok().and_then(|guard| *guard)
+}
+
+// HTTPのDateヘッダーからサーバー時刻との差分を測定（往復時間の中点で補正）
+async fn measure_offset_ms(url: &str) -> Result<i64, String> {
+    let client = reqwest::Client::new();
+    let sent_at = Utc::now();
+    let response = client
+        .head(url)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .map_err(|e| format!("time check request failed: {}", e))?;
+    let received_at = Utc::now();
+
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| "time check response has no Date header".to_string())?;
+    let server_time = DateTime::parse_from_rfc2822(date)
+        .map_err(|e| format!("invalid Date header: {}", e))?
+        .with_timezone(&Utc);
+
+    let midpoint = sent_at + (received_at - sent_at) / 2;
+    Ok((server_time - midpoint).num_milliseconds())
+}
+
+fn time_check_url(app_handle: &AppHandle) -> Option<String> {
+    crate::resolve_relay_setting(app_handle, "baseUrl")
+        .map(|base| format!("{}/healthz", base.trim_end_matches('/')))
+}
+
+// 測定結果を保持し、接続中のワークスペースがあれば保存
+fn apply_offset(app_handle: &AppHandle, offset_ms: i64) {
+    if let Ok(mut guard) = MEASURED_OFFSET_MS.lock() {
+        *guard = Some(offset_ms);
+    }
+    let workspace: State<WorkspaceState> = app_handle.state();
+    if let Ok(conn) = workspace.lock() {
+        if let Ok(db) = conn.get() {
+            if let Err(e) = db.save_clock_offset_ms(offset_ms) {
+                eprintln!("[clock] failed to save offset: {}", e);
+            }
+        }
+    }
+}
+
+async fn run_check(app_handle: &AppHandle, url: Option<String>) -> Result<ClockSkewReport, String> {
+    let url = url
+        .or_else(|| time_check_url(app_handle))
+        .ok_or_else(|| "時刻確認先のURLが設定されていません".to_string())?;
+    let offset_ms = measure_offset_ms(&url).await?;
+    let skewed = offset_ms.abs() >= SKEW_WARN_THRESHOLD_MS;
+
+    apply_offset(app_handle, offset_ms);
+
+    let report = ClockSkewReport {
+        offset_ms,
+        skewed,
+        source: url,
+        checked_at: Utc::now().to_rfc3339(),
+    };
+    if skewed {
+        eprintln!(
+            "[clock] warn: local clock differs from server by {} ms",
+            offset_ms
+        );
+        let _ = crate::events::emit_routed(app_handle, "clock-skew-detected", &report);
+    }
+    Ok(report)
+}
+
+/// 起動時の時刻確認（失敗はログのみ）
+pub async fn check_at_startup(app_handle: AppHandle) {
+    if let Err(e) = run_check(&app_handle, None).await {
+        eprintln!("[clock] startup time check skipped: {}", e);
+    }
+}
+
+/// 時刻ずれを再確認（urlを省略するとRelayのhealthzを使用）
+#[tauri::command]
+pub async fn check_clock_skew(
+    app_handle: AppHandle,
+    url: Option<String>,
+) -> Result<ClockSkewReport, String> {
+    run_check(&app_handle, url).await
+}
+
+/// 現在のワークスペースに保存された時刻補正値（ミリ秒）
+#[tauri::command]
+pub fn get_clock_offset(workspace: State<'_, WorkspaceState>) -> Result<i64, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    match conn.get() {
+        Ok(db) => db
+            .clock_offset_ms()
+            .map_err(|e| format!("Failed to get clock offset: {}", e)),
+        Err(_) => Ok(measured_offset_ms().unwrap_or(0)),
+    }
+}
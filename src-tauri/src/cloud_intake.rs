@@ -0,0 +1,577 @@
+use crate::workspace::WorkspaceState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 15;
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+// 1回のポーリングで取り込む最大件数（残りは次回）
+const MAX_FILES_PER_POLL: usize = 20;
+// 取り込み済みとして覚えておくリモートファイル数
+const MAX_SEEN_IDS: usize = 5000;
+// 有効期限のこの秒数前にはトークンを更新する
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+// 再起動・ワークスペースの切り替え後もポーリングを続けるため、設定を app_settings に保存する
+const CONFIG_KEY: &str = "cloud_intake_config";
+// OAuthトークンの保存先（secret_storeのサービス名）
+const SECRET_SERVICE: &str = "nuriemon";
+
+static INTAKE_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> = Mutex::new(None);
+static STATUS: Mutex<Option<CloudIntakeStatus>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CloudProvider {
+    GoogleDrive,
+    Dropbox,
+}
+
+impl CloudProvider {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::GoogleDrive => "google_drive",
+            Self::Dropbox => "dropbox",
+        }
+    }
+
+    fn token_endpoint(&self) -> &'static str {
+        match self {
+            Self::GoogleDrive => "https://oauth2.googleapis.com/token",
+            Self::Dropbox => "https://api.dropboxapi.com/oauth2/token",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudIntakeConfig {
+    pub provider: CloudProvider,
+    // Google Drive: フォルダID / Dropbox: フォルダのパス（"/scans" など）
+    pub folder: String,
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+// secret_store（キーチェーンか暗号化ファイル）に保存するOAuthトークン
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    // 有効期限（UNIX秒）。不明ならNone
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CloudIntakeStatus {
+    pub provider: CloudProvider,
+    pub folder: String,
+    pub last_poll_at: Option<String>,
+    pub last_error: Option<String>,
+    pub imported_total: u64,
+}
+
+struct RemoteFile {
+    id: String,
+    name: String,
+}
+
+fn secret_account(provider: CloudProvider) -> String {
+    format!("cloud_intake_{}", provider.as_str())
+}
+
+fn load_token(provider: CloudProvider) -> Result<CloudToken, String> {
+    let raw = crate::secret_store::load(SECRET_SERVICE, &secret_account(provider))?
+        .ok_or_else(|| format!("{} のトークンが登録されていません", provider.as_str()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("保存済みトークンの形式が不正です: {}", e))
+}
+
+fn store_token(provider: CloudProvider, token: &CloudToken) -> Result<(), String> {
+    let raw = serde_json::to_string(token)
+        .map_err(|e| format!("トークンのシリアライズに失敗しました: {}", e))?;
+    crate::secret_store::save(SECRET_SERVICE, &secret_account(provider), &raw)
+}
+
+// 期限切れ間近ならリフレッシュトークンで更新してから返す
+async fn access_token(client: &reqwest::Client, provider: CloudProvider) -> Result<String, String> {
+    let mut token = load_token(provider)?;
+    let now = chrono::Utc::now().timestamp();
+    let expiring = token
+        .expires_at
+        .map(|at| at - TOKEN_REFRESH_MARGIN_SECS <= now)
+        .unwrap_or(false);
+    let (Some(refresh_token), Some(client_id), true) = (
+        token.refresh_token.clone(),
+        token.client_id.clone(),
+        expiring,
+    ) else {
+        return Ok(token.access_token);
+    };
+
+    let mut form = vec![
+        ("grant_type", "refresh_token".to_string()),
+        ("refresh_token", refresh_token),
+        ("client_id", client_id),
+    ];
+    if let Some(secret) = token.client_secret.clone() {
+        form.push(("client_secret", secret));
+    }
+    let response: serde_json::Value = client
+        .post(provider.token_endpoint())
+        .form(&form)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("トークンの更新に失敗しました: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("トークン応答の解析に失敗しました: {}", e))?;
+
+    token.access_token = response
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .ok_or("トークン応答に access_token がありません")?
+        .to_string();
+    token.expires_at = response
+        .get("expires_in")
+        .and_then(|v| v.as_i64())
+        .map(|secs| now + secs);
+    store_token(provider, &token)?;
+    Ok(token.access_token)
+}
+
+async fn list_google_drive(
+    client: &reqwest::Client,
+    token: &str,
+    folder: &str,
+) -> Result<Vec<RemoteFile>, String> {
+    let query = format!(
+        "'{}' in parents and trashed = false and mimeType contains 'image/'",
+        folder.replace('\'', "\\'")
+    );
+    let mut files = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut params = vec![
+            ("q", query.as_str()),
+            ("fields", "nextPageToken,files(id,name)"),
+            ("orderBy", "createdTime"),
+            ("pageSize", "100"),
+        ];
+        if let Some(page_token) = page_token.as_deref() {
+            params.push(("pageToken", page_token));
+        }
+        let response: serde_json::Value = client
+            .get("https://www.googleapis.com/drive/v3/files")
+            .bearer_auth(token)
+            .query(&params)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Google Driveの一覧取得に失敗しました: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Google Driveの応答の解析に失敗しました: {}", e))?;
+
+        if let Some(entries) = response.get("files").and_then(|v| v.as_array()) {
+            files.extend(entries.iter().filter_map(|f| {
+                Some(RemoteFile {
+                    id: f.get("id")?.as_str()?.to_string(),
+                    name: f.get("name")?.as_str()?.to_string(),
+                })
+            }));
+        }
+
+        // 100件を超えるフォルダは続きのページを読む
+        match response.get("nextPageToken").and_then(|v| v.as_str()) {
+            Some(next) => page_token = Some(next.to_string()),
+            None => break,
+        }
+    }
+    Ok(files)
+}
+
+async fn list_dropbox(
+    client: &reqwest::Client,
+    token: &str,
+    folder: &str,
+) -> Result<Vec<RemoteFile>, String> {
+    let mut files = Vec::new();
+    let mut request = (
+        "https://api.dropboxapi.com/2/files/list_folder",
+        serde_json::json!({ "path": folder, "recursive": false }),
+    );
+    loop {
+        let response: serde_json::Value = client
+            .post(request.0)
+            .bearer_auth(token)
+            .json(&request.1)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Dropboxの一覧取得に失敗しました: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Dropboxの応答の解析に失敗しました: {}", e))?;
+
+        if let Some(entries) = response.get("entries").and_then(|v| v.as_array()) {
+            files.extend(entries.iter().filter_map(|entry| {
+                if entry.get(".tag")?.as_str()? != "file" {
+                    return None;
+                }
+                Some(RemoteFile {
+                    id: entry.get("id")?.as_str()?.to_string(),
+                    name: entry.get("name")?.as_str()?.to_string(),
+                })
+            }));
+        }
+
+        let has_more = response
+            .get("has_more")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let cursor = response.get("cursor").and_then(|v| v.as_str());
+        match (has_more, cursor) {
+            (true, Some(cursor)) => {
+                request = (
+                    "https://api.dropboxapi.com/2/files/list_folder/continue",
+                    serde_json::json!({ "cursor": cursor }),
+                );
+            }
+            _ => break,
+        }
+    }
+    Ok(files)
+}
+
+async fn download(
+    client: &reqwest::Client,
+    provider: CloudProvider,
+    token: &str,
+    file: &RemoteFile,
+) -> Result<Vec<u8>, String> {
+    let request = match provider {
+        CloudProvider::GoogleDrive => client
+            .get(format!(
+                "https://www.googleapis.com/drive/v3/files/{}",
+                file.id
+            ))
+            .query(&[("alt", "media")]),
+        CloudProvider::Dropbox => client
+            .post("https://content.dropboxapi.com/2/files/download")
+            .header(
+                "Dropbox-API-Arg",
+                serde_json::json!({ "path": file.id }).to_string(),
+            ),
+    };
+    let bytes = request
+        .bearer_auth(token)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("{} のダウンロードに失敗しました: {}", file.name, e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("{} のダウンロードに失敗しました: {}", file.name, e))?;
+    Ok(bytes.to_vec())
+}
+
+// 取り込み済みIDは app_settings に "cloud_intake_seen:<provider>:<folder>" で保存
+fn seen_key(config: &CloudIntakeConfig) -> String {
+    format!(
+        "cloud_intake_seen:{}:{}",
+        config.provider.as_str(),
+        config.folder
+    )
+}
+
+fn load_seen(app_handle: &AppHandle, config: &CloudIntakeConfig) -> Result<Vec<String>, String> {
+    let state = app_handle.state::<WorkspaceState>();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    Ok(db
+        .get_app_setting(&seen_key(config))
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+fn save_seen(
+    app_handle: &AppHandle,
+    config: &CloudIntakeConfig,
+    seen: &[String],
+) -> Result<(), String> {
+    let start = seen.len().saturating_sub(MAX_SEEN_IDS);
+    let value = serde_json::to_string(&seen[start..])
+        .map_err(|e| format!("Failed to serialize seen ids: {}", e))?;
+    let state = app_handle.state::<WorkspaceState>();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_internal_setting(&seen_key(config), &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+fn workspace_root(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let state = app_handle.state::<WorkspaceState>();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    conn.workspace_root()
+        .ok_or_else(|| "ワークスペースが選択されていません".to_string())
+}
+
+// ダウンロードしたファイルの置き場所（同名があれば連番を付ける）
+fn inbox_path(dir: &Path, name: &str) -> PathBuf {
+    let safe = crate::file_name::sanitize_for_storage(name);
+    let mut path = dir.join(&safe);
+    let mut index = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}", index, safe));
+        index += 1;
+    }
+    path
+}
+
+// 新しいファイルをダウンロードして取り込みキューへ渡す。取り込んだ件数を返す
+async fn poll_once(
+    app_handle: &AppHandle,
+    client: &reqwest::Client,
+    config: &CloudIntakeConfig,
+) -> Result<usize, String> {
+    let root = workspace_root(app_handle)?;
+    let token = access_token(client, config.provider).await?;
+    let files = match config.provider {
+        CloudProvider::GoogleDrive => list_google_drive(client, &token, &config.folder).await?,
+        CloudProvider::Dropbox => list_dropbox(client, &token, &config.folder).await?,
+    };
+
+    let mut seen = load_seen(app_handle, config)?;
+    let known: HashSet<String> = seen.iter().cloned().collect();
+    let pending: Vec<RemoteFile> = files
+        .into_iter()
+        .filter(|f| !known.contains(&f.id))
+        .filter(|f| crate::file_watcher::is_image_file(Path::new(&f.name)))
+        .take(MAX_FILES_PER_POLL)
+        .collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let inbox = root
+        .join("images")
+        .join("inbox")
+        .join(config.provider.as_str());
+    std::fs::create_dir_all(&inbox).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    // 1件の失敗で残りを止めない（失敗したものは取り込み済みにせず次回やり直す）
+    let mut imported = 0;
+    for file in pending {
+        if let Err(e) = import_file(app_handle, client, config, &token, &inbox, &root, &file).await
+        {
+            eprintln!("[cloud_intake] skipped {}: {}", file.name, e);
+            crate::heartbeat::record_error(format!("cloud-intake: {}", e));
+            continue;
+        }
+        // 取り込みを始めたものは再ダウンロードしない
+        seen.push(file.id);
+        save_seen(app_handle, config, &seen)?;
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+async fn import_file(
+    app_handle: &AppHandle,
+    client: &reqwest::Client,
+    config: &CloudIntakeConfig,
+    token: &str,
+    inbox: &Path,
+    root: &Path,
+    file: &RemoteFile,
+) -> Result<(), String> {
+    let data = download(client, config.provider, token, file).await?;
+    let path = inbox_path(inbox, &file.name);
+    std::fs::write(&path, data).map_err(|e| format!("Failed to save downloaded file: {}", e))?;
+    crate::file_watcher::process_new_image(
+        app_handle.clone(),
+        path,
+        root.to_string_lossy().to_string(),
+    )
+}
+
+fn update_status(f: impl FnOnce(&mut CloudIntakeStatus)) {
+    if let Ok(mut guard) = STATUS.lock() {
+        if let Some(status) = guard.as_mut() {
+            f(status);
+        }
+    }
+}
+
+fn stop_task() {
+    if let Ok(mut guard) = INTAKE_TASK.lock() {
+        if let Some(handle) = guard.take() {
+            handle.abort();
+        }
+    }
+    if let Ok(mut guard) = STATUS.lock() {
+        *guard = None;
+    }
+}
+
+fn load_config(app_handle: &AppHandle) -> Result<Option<CloudIntakeConfig>, String> {
+    let state = app_handle.state::<WorkspaceState>();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    Ok(db
+        .get_app_setting(CONFIG_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+        .and_then(|value| serde_json::from_str(&value).ok()))
+}
+
+fn save_config(app_handle: &AppHandle, config: Option<&CloudIntakeConfig>) -> Result<(), String> {
+    let state = app_handle.state::<WorkspaceState>();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    match config {
+        Some(config) => {
+            let value = serde_json::to_string(config)
+                .map_err(|e| format!("設定のシリアライズに失敗しました: {}", e))?;
+            db.save_app_setting(CONFIG_KEY, &value)
+        }
+        None => db.delete_app_setting(CONFIG_KEY),
+    }
+    .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+// ポーリングを開始（既存のポーリングは置き換える）
+fn spawn_polling(app_handle: AppHandle, config: CloudIntakeConfig) -> Result<(), String> {
+    if config.folder.trim().is_empty() {
+        return Err("取り込むフォルダを指定してください".to_string());
+    }
+    // トークン未登録なら開始しない
+    load_token(config.provider)?;
+    workspace_root(&app_handle)?;
+
+    stop_task();
+
+    let interval = Duration::from_secs(
+        config
+            .interval_secs
+            .unwrap_or(DEFAULT_INTERVAL_SECS)
+            .max(MIN_INTERVAL_SECS),
+    );
+    if let Ok(mut guard) = STATUS.lock() {
+        *guard = Some(CloudIntakeStatus {
+            provider: config.provider,
+            folder: config.folder.clone(),
+            last_poll_at: None,
+            last_error: None,
+            imported_total: 0,
+        });
+    }
+
+    let client = reqwest::Client::new();
+    let handle = tauri::async_runtime::spawn(async move {
+        loop {
+            // 一時停止中はクラウド側に残したまま次回に回す
+            if crate::maintenance::is_enabled() {
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+            let result = poll_once(&app_handle, &client, &config).await;
+            update_status(|status| {
+                status.last_poll_at = Some(chrono::Utc::now().to_rfc3339());
+                match &result {
+                    Ok(count) => {
+                        status.imported_total += *count as u64;
+                        status.last_error = None;
+                    }
+                    Err(e) => status.last_error = Some(e.clone()),
+                }
+            });
+            if let Err(e) = result {
+                eprintln!("[cloud_intake] {}", e);
+                crate::heartbeat::record_error(format!("cloud-intake: {}", e));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    if let Ok(mut guard) = INTAKE_TASK.lock() {
+        *guard = Some(handle);
+    }
+    Ok(())
+}
+
+/// ワークスペース接続後に、そのワークスペースで保存されたポーリングを再開する
+/// （呼び出し元が接続のロックを持っているため別スレッドで）
+pub fn schedule_resume(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        stop_task();
+        match load_config(&app_handle) {
+            Ok(Some(config)) => {
+                if let Err(e) = spawn_polling(app_handle, config) {
+                    eprintln!("[cloud_intake] failed to resume: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[cloud_intake] failed to load config: {}", e),
+        }
+    });
+}
+
+/// クラウドのOAuthトークンを保存（キーチェーンが使えなければ暗号化ファイル）
+#[tauri::command]
+pub fn save_cloud_intake_token(provider: CloudProvider, token: CloudToken) -> Result<(), String> {
+    if token.access_token.trim().is_empty() {
+        return Err("アクセストークンが空です".to_string());
+    }
+    store_token(provider, &token)
+}
+
+#[tauri::command]
+pub fn delete_cloud_intake_token(provider: CloudProvider) -> Result<(), String> {
+    crate::secret_store::delete(SECRET_SERVICE, &secret_account(provider))
+}
+
+/// クラウドフォルダのポーリングを開始（既存のポーリングは置き換える。設定はワークスペースに保存）
+#[tauri::command]
+pub fn start_cloud_intake(app_handle: AppHandle, config: CloudIntakeConfig) -> Result<(), String> {
+    spawn_polling(app_handle.clone(), config.clone())?;
+    save_config(&app_handle, Some(&config))
+}
+
+#[tauri::command]
+pub fn stop_cloud_intake(app_handle: AppHandle) -> Result<(), String> {
+    stop_task();
+    save_config(&app_handle, None)
+}
+
+/// ポーリングの状態（停止中ならNone）
+#[tauri::command]
+pub fn get_cloud_intake_status() -> Result<Option<CloudIntakeStatus>, String> {
+    Ok(STATUS.lock().ok().and_then(|guard| guard.clone()))
+}
@@ -0,0 +1,135 @@
+// コマンド単位の計測（どのコマンドがUIを止めているかを調べるため、呼び出し回数・所要時間・引数の大きさ・失敗数を記録する）
+// invoke_handler で全コマンドを包んで計測する。同期コマンドは実行が終わるまで、
+// 非同期コマンドはタスクを起動するまでがIPCスレッドを止めている時間になる
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::ipc::InvokeBody;
+
+// これより長くIPCスレッドを止めたら遅いコマンドとして数え、ログに出す
+const SLOW_THRESHOLD: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default, Clone)]
+struct CommandStats {
+    calls: u64,
+    errors: u64,
+    slow_calls: u64,
+    total_us: u64,
+    max_us: u64,
+    total_arg_bytes: u64,
+    max_arg_bytes: u64,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandMetric {
+    pub command: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub slow_calls: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub total_ms: f64,
+    pub avg_arg_bytes: u64,
+    pub max_arg_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+static METRICS: Lazy<Mutex<HashMap<String, CommandStats>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 書き込まずにバイト数だけ数える（大きな画像データを複製しないため）
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 引数のバイト数（JSONはシリアライズ後の長さ）
+pub fn payload_size(body: &InvokeBody) -> u64 {
+    match body {
+        InvokeBody::Raw(bytes) => bytes.len() as u64,
+        InvokeBody::Json(value) => {
+            let mut counter = ByteCounter(0);
+            let _ = serde_json::to_writer(&mut counter, value);
+            counter.0
+        }
+    }
+}
+
+/// 1回分の呼び出しを記録（error は許可外・未登録などで実行できなかった理由）
+pub fn record(command: &str, arg_bytes: u64, elapsed: Duration, error: Option<&str>) {
+    let elapsed_us = elapsed.as_micros() as u64;
+    if elapsed >= SLOW_THRESHOLD {
+        eprintln!(
+            "[command_metrics] slow command '{}': {} ms ({} bytes)",
+            command,
+            elapsed.as_millis(),
+            arg_bytes
+        );
+    }
+    let Ok(mut metrics) = METRICS.lock() else {
+        return;
+    };
+    let stats = metrics.entry(command.to_string()).or_default();
+    stats.calls += 1;
+    stats.total_us += elapsed_us;
+    stats.max_us = stats.max_us.max(elapsed_us);
+    stats.total_arg_bytes += arg_bytes;
+    stats.max_arg_bytes = stats.max_arg_bytes.max(arg_bytes);
+    if elapsed >= SLOW_THRESHOLD {
+        stats.slow_calls += 1;
+    }
+    if let Some(error) = error {
+        stats.errors += 1;
+        stats.last_error = Some(error.to_string());
+    }
+}
+
+/// 記録済みの計測値（合計時間の長い順）
+pub fn snapshot() -> Vec<CommandMetric> {
+    let mut list: Vec<CommandMetric> = METRICS
+        .lock()
+        .map(|metrics| {
+            metrics
+                .iter()
+                .map(|(command, stats)| {
+                    let calls = stats.calls.max(1);
+                    CommandMetric {
+                        command: command.clone(),
+                        calls: stats.calls,
+                        errors: stats.errors,
+                        error_rate: stats.errors as f64 / calls as f64,
+                        slow_calls: stats.slow_calls,
+                        avg_ms: stats.total_us as f64 / calls as f64 / 1000.0,
+                        max_ms: stats.max_us as f64 / 1000.0,
+                        total_ms: stats.total_us as f64 / 1000.0,
+                        avg_arg_bytes: stats.total_arg_bytes / calls,
+                        max_arg_bytes: stats.max_arg_bytes,
+                        last_error: stats.last_error.clone(),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    list.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    list
+}
+
+#[tauri::command]
+pub fn get_command_metrics() -> Result<Vec<CommandMetric>, String> {
+    Ok(snapshot())
+}
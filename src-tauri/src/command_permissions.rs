@@ -0,0 +1,77 @@
+// ウィンドウごとに呼び出し可能なコマンドの許可リスト
+// （侵害されたWebViewから危険なコマンドを呼べないよう、invoke時に検証する）
+
+/// ウィンドウの権限範囲
+pub enum CommandScope {
+    // すべてのコマンドを許可
+    All,
+    // 列挙したコマンドのみ許可
+    Only(&'static [&'static str]),
+}
+
+// 表示専用ウィンドウが共通で使う読み取り系（ワークスペースへの接続やファイル操作はメインウィンドウのみ）
+const VIEWER_COMMON: &[&str] = &[
+    "get_current_workspace",
+    "get_all_images",
+    "get_processed_images_preview",
+    "get_image_metadata",
+    "get_all_movement_settings",
+    "get_settings_delta",
+    "get_global_setting",
+    "get_global_settings",
+    "read_bundle_global_settings",
+    "read_user_provisioning_settings",
+    "read_env_provisioning_settings",
+    "read_env_overrides",
+];
+
+// アニメーションウィンドウ: 表示に必要な範囲（期限切れの削除は display_expiry が行う）
+const ANIMATION_ONLY: &[&str] = &[
+    "get_clock_offset",
+    "mark_display_started",
+    "persist_scene_state",
+    "load_scene_state",
+    "submit_animation_frame",
+    "report_animation_frame_error",
+];
+
+// QR表示ウィンドウ: Webサーバー/Relay連携に必要な範囲（Relayへの登録はデバイストークンをRust側で付ける）
+const QR_DISPLAY_ONLY: &[&str] = &[
+    "start_web_server",
+    "generate_qr_code",
+    "generate_qr_from_text",
+    "get_qr_session_status",
+    "get_maintenance_mode",
+    "get_feature_flags",
+    "relay_register_pc",
+    "relay_pending_sid",
+];
+
+fn scope_for(window_label: &str) -> Option<(CommandScope, &'static [&'static str])> {
+    match window_label {
+        "main" => Some((CommandScope::All, &[])),
+        // 複製表示用のアニメーションウィンドウ（animation-<suffix>）も同じ範囲
+        "animation" => Some((CommandScope::Only(VIEWER_COMMON), ANIMATION_ONLY)),
+        label if label.starts_with("animation-") => {
+            Some((CommandScope::Only(VIEWER_COMMON), ANIMATION_ONLY))
+        }
+        "qr-display" => Some((CommandScope::Only(VIEWER_COMMON), QR_DISPLAY_ONLY)),
+        _ => None,
+    }
+}
+
+/// 指定ウィンドウからのコマンド呼び出しを許可するか
+pub fn is_allowed(window_label: &str, command: &str) -> bool {
+    // プラグインのコマンドはcapabilitiesで管理されるため対象外
+    if command.starts_with("plugin:") {
+        return true;
+    }
+    match scope_for(window_label) {
+        Some((CommandScope::All, _)) => true,
+        Some((CommandScope::Only(common), extra)) => {
+            common.contains(&command) || extra.contains(&command)
+        }
+        // 未登録のウィンドウは一切許可しない
+        None => false,
+    }
+}
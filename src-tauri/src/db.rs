@@ -1,9 +1,11 @@
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use crate::file_name;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ImageMetadata {
     pub id: String,
@@ -16,11 +18,32 @@ pub struct ImageMetadata {
     pub height: Option<i32>,
     pub storage_location: String, // 保存先のパス
     #[serde(default)]
-    pub file_path: Option<String>, // ファイルの完全パス
+    pub file_path: Option<String>, // ファイルの完全パス（DBにはワークスペースからの相対パスで保存）
     #[serde(default)]
     pub is_hidden: i32, // 0 or 1
     #[serde(default)]
     pub display_started_at: Option<String>,
+    // テンプレートの名前欄からOCRで読み取った表示名
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+impl ImageMetadata {
+    // 実ファイルのパス（file_path未設定の旧データは保存先とタイプから推測）
+    pub fn resolve_file_path(&self) -> PathBuf {
+        if let Some(fp) = self.file_path.clone() {
+            return PathBuf::from(fp);
+        }
+        let base = PathBuf::from(self.storage_location.clone());
+        let subdir = match self.image_type.as_str() {
+            "processed" => Path::new("images").join("processed"),
+            "original" => Path::new("images").join("originals"),
+            "background" => Path::new("images").join("backgrounds"),
+            "bgm" | "sound_effect" | "soundEffect" => Path::new("audio").to_path_buf(),
+            _ => Path::new("images").join("processed"),
+        };
+        base.join(subdir).join(self.saved_file_name.clone())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,16 +75,354 @@ pub struct MovementSettings {
     pub size: String,             // "small", "medium", "large"
     pub created_at: String,
     pub updated_at: String,
+    // 拡張物理パラメータ（未設定ならアニメーション側の既定値）
+    #[serde(default)]
+    pub gravity: Option<f32>,
+    #[serde(default)]
+    pub bounce_elasticity: Option<f32>, // 0.0 to 1.0
+    #[serde(default)]
+    pub rotation_speed: Option<f32>, // deg/sec
+    #[serde(default)]
+    pub amplitude: Option<f32>,
+    #[serde(default)]
+    pub direction_bias: Option<f32>, // -1.0 (左) to 1.0 (右)
+    #[serde(default)]
+    pub z_order: Option<i32>,
+}
+
+const MOVEMENT_SETTINGS_COLUMNS: &str =
+    "image_id, movement_type, movement_pattern, speed, size, created_at, updated_at, \
+     gravity, bounce_elasticity, rotation_speed, amplitude, direction_bias, z_order";
+
+fn movement_settings_from_row(row: &rusqlite::Row) -> Result<MovementSettings> {
+    Ok(MovementSettings {
+        image_id: row.get(0)?,
+        movement_type: row.get(1)?,
+        movement_pattern: row.get(2)?,
+        speed: row.get(3)?,
+        size: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        gravity: row.get(7)?,
+        bounce_elasticity: row.get(8)?,
+        rotation_speed: row.get(9)?,
+        amplitude: row.get(10)?,
+        direction_bias: row.get(11)?,
+        z_order: row.get(12)?,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MovementPreset {
+    pub id: String,
+    pub name: String, // "butterfly", "fish", "robot" など
+    pub movement_type: String,
+    pub movement_pattern: String,
+    pub speed: f32,
+    pub size: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// Relayへ送れなかったメッセージ（送信順に並ぶ）
+#[derive(Debug, Clone)]
+pub struct RelayOutboxEntry {
+    pub id: i64,
+    pub payload: String,
+    pub attempts: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayOutboxStats {
+    pub pending: i64,
+    pub oldest_created_at: Option<String>,
+    // 先頭のメッセージの送信試行回数と直近のエラー
+    pub head_attempts: i64,
+    pub last_error: Option<String>,
+}
+
+/// 作品のダウンロード用共有リンク
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub id: String,
+    pub image_id: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub downloads: i64,
+}
+
+const SHARE_LINK_COLUMNS: &str = "id, image_id, created_at, expires_at, revoked_at, downloads";
+
+fn share_link_from_row(row: &rusqlite::Row) -> Result<ShareLink> {
+    Ok(ShareLink {
+        id: row.get(0)?,
+        image_id: row.get(1)?,
+        created_at: row.get(2)?,
+        expires_at: row.get(3)?,
+        revoked_at: row.get(4)?,
+        downloads: row.get(5)?,
+    })
+}
+
+/// 展示終了時に発行した持ち帰り用リンク（作品ごとに1件）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Takeaway {
+    pub image_id: String,
+    pub share_link_id: String,
+    // 展示から消えた後も受け取れるよう exports/takeaways/ に残した控え
+    pub file_path: String,
+    pub issued_at: String,
+    pub first_redeemed_at: Option<String>,
+    pub redemptions: i64,
+}
+
+fn takeaway_from_row(row: &rusqlite::Row) -> Result<Takeaway> {
+    Ok(Takeaway {
+        image_id: row.get(0)?,
+        share_link_id: row.get(1)?,
+        file_path: row.get(2)?,
+        issued_at: row.get(3)?,
+        first_redeemed_at: row.get(4)?,
+        redemptions: row.get(5)?,
+    })
+}
+
+const TAKEAWAY_COLUMNS: &str =
+    "image_id, share_link_id, file_path, issued_at, first_redeemed_at, redemptions";
+
+/// 持ち帰りの集計（イベントレポート用）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeawayStats {
+    // QRを表示した作品数
+    pub issued: i64,
+    // 1回以上ダウンロードされた作品数
+    pub redeemed: i64,
+    // ダウンロードの合計回数
+    pub downloads: i64,
+}
+
+// スマホ操作の追従のなめらかさ（動きタイプごと、フレームレートに依存しない毎秒単位）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ControlSmoothing {
+    pub movement_type: String,
+    pub acceleration: f32, // px/s^2
+    pub max_velocity: f32, // px/s
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl ControlSmoothing {
+    // 未設定の動きタイプはアニメーション側の従来の体感に近い値
+    pub fn default_for(movement_type: &str) -> Self {
+        let (acceleration, max_velocity) = match movement_type {
+            "walk" => (1200.0, 300.0),
+            _ => (900.0, 450.0),
+        };
+        Self {
+            movement_type: movement_type.to_string(),
+            acceleration,
+            max_velocity,
+            updated_at: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackgroundScheduleEntry {
+    pub id: String,
+    pub background_id: String, // images.id（image_type = "background"）
+    pub start_time: String,    // ローカル時刻 "HH:MM"
+    pub enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// 背景のパララックス用レイヤー（奥行きが大きいほどゆっくり動く）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParallaxLayer {
+    pub image_id: String,
+    pub depth: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackgroundSettings {
+    pub background_id: String,
+    pub fit_mode: String, // "cover", "contain", "fill", "none"
+    pub focal_x: f32,     // 0.0〜1.0（画像内の注視点）
+    pub focal_y: f32,
+    #[serde(default)]
+    pub parallax_layers: Vec<ParallaxLayer>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl BackgroundSettings {
+    // 未設定の背景は従来どおり中央基準のcover
+    pub fn default_for(background_id: &str) -> Self {
+        Self {
+            background_id: background_id.to_string(),
+            fit_mode: "cover".to_string(),
+            focal_x: 0.5,
+            focal_y: 0.5,
+            parallax_layers: Vec::new(),
+            updated_at: String::new(),
+        }
+    }
+}
+
+// テンプレート上の名前欄（用紙に対する割合 0.0〜1.0、左上原点）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct NameBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub name_box: Option<NameBox>,
+    #[serde(default)]
+    pub ocr_enabled: bool,
+    // Tesseractの言語コード（例: "jpn", "eng"）
+    #[serde(default)]
+    pub ocr_languages: Vec<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+// app_settings に保存する時刻補正値のキー
+const CLOCK_OFFSET_KEY: &str = "clock_offset_ms";
+// 設定変更の履歴として残す件数（古いものから消す）
+const SETTINGS_HISTORY_LIMIT: i64 = 5000;
+// ファイル名正規化の移行済みフラグ
+const FILE_NAME_MIGRATION_KEY: &str = "migration_file_names_v1";
+// 画像パスの相対化の移行済みフラグ
+const RELATIVE_PATH_MIGRATION_KEY: &str = "migration_relative_paths_v1";
+// ワークスペース直下を指す保存先
+const WORKSPACE_ROOT_MARKER: &str = ".";
+
+// ワークスペースDBのスキーマ版（互換性のない変更をしたら上げる）
+pub const WORKSPACE_SCHEMA_VERSION: i64 = 1;
+
+/// ワークスペースの識別情報（書き出し・Relay登録・サポート用の情報に添える）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceInfo {
+    pub workspace_id: String,
+    pub display_name: Option<String>,
+    pub created_at: String,
+    // 作成したアプリのバージョン（この仕組みより前のワークスペースは初めて開いたときの版）
+    pub app_version: String,
+    pub schema_version: i64,
+    // 秘密情報の保存先（キーチェーン/暗号化ファイル）。DBには保存しない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_backend: Option<crate::secret_store::SecretBackend>,
+}
+
+/// 設定変更の履歴
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsHistoryEntry {
+    pub id: i64,
+    // "app"（ワークスペースのapp_settings） / "global"（global_settings.json）
+    pub scope: String,
+    pub key: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_at: String,
+    // 変更元のウィンドウ（"main" など）、管理API・内部処理なら "admin-api" / "system"
+    pub source: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct QrSessionRow {
+    pub session_id: String,
+    pub image_id: String,
+    pub created_at: String,
+    pub used: bool,
+    pub remote_ip: Option<String>,
+    pub joined_at: Option<String>,
+    pub printed: bool,
 }
 
 pub struct Database {
     conn: Connection,
+    // ワークスペースのルート（<root>/.nuriemon/nuriemon.db の <root>）
+    root: Option<PathBuf>,
 }
 
 impl Database {
     pub fn new(db_path: PathBuf) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-        Ok(Database { conn })
+        let conn = Connection::open(&db_path)?;
+        let root = db_path
+            .parent()
+            .and_then(|p| p.parent())
+            .map(|p| p.to_path_buf());
+        Ok(Database { conn, root })
+    }
+
+    // ワークスペース内のパスはルートからの相対パス（区切りは"/"）にして保存する
+    fn to_stored_path(&self, path: &str) -> String {
+        let Some(root) = &self.root else {
+            return path.to_string();
+        };
+        match Path::new(path).strip_prefix(root) {
+            Ok(rel) if rel.as_os_str().is_empty() => WORKSPACE_ROOT_MARKER.to_string(),
+            Ok(rel) => rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => path.to_string(),
+        }
+    }
+
+    // 相対パスを現在のワークスペースの場所で絶対パスへ戻す
+    fn resolve_stored_path(&self, stored: String) -> String {
+        let Some(root) = &self.root else {
+            return stored;
+        };
+        if stored == WORKSPACE_ROOT_MARKER {
+            return root.to_string_lossy().to_string();
+        }
+        if Path::new(&stored).is_absolute() {
+            return stored;
+        }
+        root.join(&stored).to_string_lossy().to_string()
+    }
+
+    fn resolve_image_paths(&self, mut image: ImageMetadata) -> ImageMetadata {
+        image.storage_location = self.resolve_stored_path(image.storage_location);
+        image.file_path = image.file_path.map(|fp| self.resolve_stored_path(fp));
+        image
+    }
+
+    // 同期フォルダ・ネットワークドライブ向けの設定
+    // WAL は -wal/-shm が別々に同期されて壊れやすいため使わず、書き込みのたびに確実にディスクへ反映し、
+    // 他のプロセス（別のPCのインスタンスを含む）が同時に開けないよう排他ロックを取る
+    pub fn use_sync_safe_journal(&self) -> Result<()> {
+        self.conn
+            .query_row("PRAGMA journal_mode = DELETE", [], |_| Ok(()))?;
+        self.conn.execute_batch(
+            "PRAGMA synchronous = FULL;
+             PRAGMA locking_mode = EXCLUSIVE;",
+        )
+    }
+
+    // 接続確認（ヘルスチェック用）
+    pub fn ping(&self) -> Result<()> {
+        self.conn.query_row("SELECT 1", [], |_| Ok(()))
     }
 
     pub fn initialize(&self) -> Result<()> {
@@ -108,6 +469,85 @@ impl Database {
             [],
         )?;
 
+        // 動き設定プリセットテーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS movement_presets (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                movement_type TEXT NOT NULL,
+                movement_pattern TEXT NOT NULL,
+                speed REAL NOT NULL,
+                size TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // 操作のなめらかさ設定テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS control_smoothing (
+                movement_type TEXT PRIMARY KEY,
+                acceleration REAL NOT NULL,
+                max_velocity REAL NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // スマホからの操作回数（日別・画像別、ハイライト動画用）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS control_stats (
+                day TEXT NOT NULL,
+                image_id TEXT NOT NULL,
+                count INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, image_id)
+            )",
+            [],
+        )?;
+
+        // 背景の時間帯スケジュールテーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS background_schedule (
+                id TEXT PRIMARY KEY,
+                background_id TEXT NOT NULL,
+                start_time TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (background_id) REFERENCES images(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // 背景ごとの表示設定テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS background_settings (
+                background_id TEXT PRIMARY KEY,
+                fit_mode TEXT NOT NULL DEFAULT 'cover',
+                focal_x REAL NOT NULL DEFAULT 0.5,
+                focal_y REAL NOT NULL DEFAULT 0.5,
+                parallax_layers TEXT NOT NULL DEFAULT '[]',
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (background_id) REFERENCES images(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // 用紙テンプレート（名前欄の位置とOCR設定）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                name_box TEXT,
+                ocr_enabled INTEGER NOT NULL DEFAULT 0,
+                ocr_languages TEXT NOT NULL DEFAULT '[\"jpn\",\"eng\"]',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         // インデックス作成
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_images_created_at ON images (created_at DESC)",
@@ -157,11 +597,76 @@ impl Database {
                 }
             }
         }
+        // display_name カラムの追加（テンプレートOCRの結果）
+        match self
+            .conn
+            .execute("ALTER TABLE images ADD COLUMN display_name TEXT", [])
+        {
+            Ok(_) => {}
+            Err(e) => {
+                if !e.to_string().contains("duplicate column name") {
+                    return Err(e);
+                }
+            }
+        }
+        // content_hash カラムの追加（自動取り込みの重複防止）
+        match self
+            .conn
+            .execute("ALTER TABLE images ADD COLUMN content_hash TEXT", [])
+        {
+            Ok(_) => {}
+            Err(e) => {
+                if !e.to_string().contains("duplicate column name") {
+                    return Err(e);
+                }
+            }
+        }
+        // 取り込み元ファイルの扱い（left / archived / deleted）と現在の場所
+        for column in ["source_action TEXT", "source_path TEXT"] {
+            match self
+                .conn
+                .execute(&format!("ALTER TABLE images ADD COLUMN {}", column), [])
+            {
+                Ok(_) => {}
+                Err(e) => {
+                    if !e.to_string().contains("duplicate column name") {
+                        return Err(e);
+                    }
+                }
+            }
+        }
         // インデックス
         let _ = self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_images_hidden ON images (is_hidden)",
             [],
         );
+        let _ = self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_images_content_hash ON images (content_hash)
+             WHERE content_hash IS NOT NULL",
+            [],
+        );
+
+        // 動き設定の拡張物理パラメータ（旧行はNULLのまま）
+        for column in [
+            "gravity REAL",
+            "bounce_elasticity REAL",
+            "rotation_speed REAL",
+            "amplitude REAL",
+            "direction_bias REAL",
+            "z_order INTEGER",
+        ] {
+            match self.conn.execute(
+                &format!("ALTER TABLE movement_settings ADD COLUMN {}", column),
+                [],
+            ) {
+                Ok(_) => {}
+                Err(e) => {
+                    if !e.to_string().contains("duplicate column name") {
+                        return Err(e);
+                    }
+                }
+            }
+        }
 
         // アプリケーション設定テーブル
         self.conn.execute(
@@ -174,65 +679,357 @@ impl Database {
             [],
         )?;
 
-        Ok(())
-    }
-
-    // 画像メタデータの保存
-    pub fn save_image_metadata(&self, metadata: &ImageMetadata) -> Result<()> {
+        // 設定の差分同期用のバージョン（削除は墓標として残す）
+        match self.conn.execute(
+            "ALTER TABLE app_settings ADD COLUMN version INTEGER NOT NULL DEFAULT 0",
+            [],
+        ) {
+            Ok(_) => {}
+            Err(e) => {
+                if !e.to_string().contains("duplicate column name") {
+                    return Err(e);
+                }
+            }
+        }
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_settings_tombstones (
+                key TEXT PRIMARY KEY,
+                version INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        // 設定変更の履歴
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scope TEXT NOT NULL,
+                key TEXT NOT NULL,
+                old_value TEXT,
+                new_value TEXT,
+                changed_at TEXT NOT NULL,
+                source TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_settings_history_key ON settings_history(key, id)",
+            [],
+        )?;
+        // ワークスペースの識別情報（1行のみ）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS workspace_info (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                workspace_id TEXT NOT NULL,
+                display_name TEXT,
+                created_at TEXT NOT NULL,
+                app_version TEXT NOT NULL,
+                schema_version INTEGER NOT NULL
+            )",
+            [],
+        )?;
         self.conn.execute(
-            "INSERT INTO images (id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT OR IGNORE INTO workspace_info (id, workspace_id, created_at, app_version, schema_version)
+             VALUES (1, ?1, ?2, ?3, ?4)",
             params![
-                metadata.id,
-                metadata.original_file_name,
-                metadata.saved_file_name,
-                metadata.image_type,
-                metadata.created_at,
-                metadata.size,
-                metadata.width,
-                metadata.height,
-                metadata.storage_location,
-                metadata.file_path,
+                generate_id(),
+                current_timestamp(),
+                env!("CARGO_PKG_VERSION"),
+                WORKSPACE_SCHEMA_VERSION
             ],
         )?;
-        Ok(())
-    }
-
-    // 特定の画像メタデータを取得
-    pub fn get_image(&self, id: &str) -> Result<Option<ImageMetadata>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at 
-             FROM images 
-             WHERE id = ?1"
+        self.conn.execute(
+            "UPDATE workspace_info SET schema_version = ?1 WHERE schema_version < ?1",
+            params![WORKSPACE_SCHEMA_VERSION],
         )?;
-
-        let mut images = stmt.query_map([id], |row| {
-            Ok(ImageMetadata {
-                id: row.get(0)?,
-                original_file_name: row.get(1)?,
-                saved_file_name: row.get(2)?,
-                image_type: row.get(3)?,
-                created_at: row.get(4)?,
-                size: row.get(5)?,
-                width: row.get(6)?,
-                height: row.get(7)?,
-                storage_location: row.get(8)?,
-                file_path: row.get(9)?,
-                is_hidden: row.get(10).unwrap_or(0),
-                display_started_at: row.get(11).ok(),
-            })
-        })?;
-
-        match images.next() {
-            Some(image) => Ok(Some(image?)),
-            None => Ok(None),
-        }
-    }
-
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS qr_sessions (
+                session_id TEXT PRIMARY KEY,
+                image_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                used INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        // 参加した端末の記録（見守りのため、どのIPがいつ参加したか）と、印刷して配ったQRか（削除までの期限が異なる）
+        for column in [
+            "remote_ip TEXT",
+            "joined_at TEXT",
+            "printed INTEGER NOT NULL DEFAULT 0",
+        ] {
+            match self.conn.execute(
+                &format!("ALTER TABLE qr_sessions ADD COLUMN {}", column),
+                [],
+            ) {
+                Ok(_) => {}
+                Err(e) => {
+                    if !e.to_string().contains("duplicate column name") {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS relay_outbox (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT
+            )",
+            [],
+        )?;
+        // アニメーション画面の状態ジャーナル（最新行から復元する）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS scene_state (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                state TEXT NOT NULL,
+                saved_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        // 作品のダウンロード用共有リンク（トークンの署名とは別に期限と取り消しをここで管理）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS share_links (
+                id TEXT PRIMARY KEY,
+                image_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                revoked_at TEXT,
+                downloads INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_share_links_image_id ON share_links(image_id)",
+            [],
+        )?;
+        // 展示終了時の持ち帰りQR（どの共有リンクで何回受け取られたか）
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS takeaways (
+                image_id TEXT PRIMARY KEY,
+                share_link_id TEXT NOT NULL UNIQUE,
+                file_path TEXT NOT NULL,
+                issued_at TEXT NOT NULL,
+                first_redeemed_at TEXT,
+                redemptions INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // 既存行のファイル名を正規化（一度だけ）
+        self.migrate_file_names()?;
+        // 絶対パスで保存された既存行を相対パスへ（一度だけ）
+        self.migrate_relative_paths()?;
+
+        Ok(())
+    }
+
+    // 元ファイル名をNFCへ、保存ファイル名を安全な形へ移行する
+    // 保存ファイル名はディスク上のファイルをリネームできた場合のみ更新する
+    fn migrate_file_names(&self) -> Result<()> {
+        if self.get_app_setting(FILE_NAME_MIGRATION_KEY)?.is_some() {
+            return Ok(());
+        }
+
+        let images = self.get_all_images()?;
+        let tx = self.conn.unchecked_transaction()?;
+        for image in images {
+            let original = file_name::normalize(&image.original_file_name);
+            if original != image.original_file_name {
+                tx.execute(
+                    "UPDATE images SET original_file_name = ?1 WHERE id = ?2",
+                    params![original, image.id],
+                )?;
+            }
+
+            let saved = file_name::sanitize_for_storage(&image.saved_file_name);
+            if saved == image.saved_file_name {
+                continue;
+            }
+            let old_path = image.resolve_file_path();
+            let new_path = old_path.with_file_name(&saved);
+            if !old_path.exists() || new_path.exists() {
+                continue;
+            }
+            if let Err(e) = std::fs::rename(&old_path, &new_path) {
+                eprintln!(
+                    "[db] failed to rename {} -> {}: {}",
+                    old_path.display(),
+                    new_path.display(),
+                    e
+                );
+                continue;
+            }
+            let file_path = image
+                .file_path
+                .as_ref()
+                .map(|_| self.to_stored_path(&new_path.to_string_lossy()));
+            tx.execute(
+                "UPDATE images SET saved_file_name = ?1, file_path = ?2 WHERE id = ?3",
+                params![saved, file_path, image.id],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)",
+            params![FILE_NAME_MIGRATION_KEY, "1", current_timestamp()],
+        )?;
+        tx.commit()
+    }
+
+    // 絶対パスをワークスペースからの相対パスへ書き換える
+    // 別の場所から移動済みのワークスペースは images/ か audio/ 以降を今の場所で探す
+    fn migrate_relative_paths(&self) -> Result<()> {
+        if self.get_app_setting(RELATIVE_PATH_MIGRATION_KEY)?.is_some() {
+            return Ok(());
+        }
+        let Some(root) = self.root.clone() else {
+            return Ok(());
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, storage_location, file_path FROM images")?;
+        let rows: Vec<(String, String, Option<String>)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_>>()?;
+
+        let relocate = |path: &str| -> String {
+            let stored = self.to_stored_path(path);
+            if !Path::new(&stored).is_absolute() {
+                return stored;
+            }
+            let components: Vec<String> = Path::new(path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            let found = components
+                .iter()
+                .rposition(|c| c == "images" || c == "audio")
+                .map(|i| components[i..].join("/"))
+                .filter(|rel| root.join(rel).exists());
+            found.unwrap_or(stored)
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, storage_location, file_path) in rows {
+            let mut new_storage = self.to_stored_path(&storage_location);
+            // 元の場所が無くなっている＝ワークスペースごと移動済み
+            if Path::new(&new_storage).is_absolute() && !Path::new(&new_storage).exists() {
+                new_storage = WORKSPACE_ROOT_MARKER.to_string();
+            }
+            let new_file_path = file_path.as_deref().map(&relocate);
+            if new_storage == storage_location && new_file_path == file_path {
+                continue;
+            }
+            tx.execute(
+                "UPDATE images SET storage_location = ?1, file_path = ?2 WHERE id = ?3",
+                params![new_storage, new_file_path, id],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)",
+            params![RELATIVE_PATH_MIGRATION_KEY, "1", current_timestamp()],
+        )?;
+        tx.commit()
+    }
+
+    // 画像メタデータの保存
+    pub fn save_image_metadata(&self, metadata: &ImageMetadata) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO images (id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, display_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                metadata.id,
+                file_name::normalize(&metadata.original_file_name),
+                metadata.saved_file_name,
+                metadata.image_type,
+                metadata.created_at,
+                metadata.size,
+                metadata.width,
+                metadata.height,
+                self.to_stored_path(&metadata.storage_location),
+                metadata
+                    .file_path
+                    .as_deref()
+                    .map(|fp| self.to_stored_path(fp)),
+                metadata.display_name,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 同じ内容の画像が取り込み済みならそのIDを返す
+    pub fn find_image_by_content_hash(&self, content_hash: &str) -> Result<Option<String>> {
+        match self.conn.query_row(
+            "SELECT id FROM images WHERE content_hash = ?1",
+            params![content_hash],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_image_content_hash(&self, id: &str, content_hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE images SET content_hash = ?2 WHERE id = ?1",
+            params![id, content_hash],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_image_source(
+        &self,
+        id: &str,
+        action: &str,
+        source_path: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE images SET source_action = ?2, source_path = ?3 WHERE id = ?1",
+            params![id, action, source_path],
+        )?;
+        Ok(())
+    }
+
+    // 特定の画像メタデータを取得
+    pub fn get_image(&self, id: &str) -> Result<Option<ImageMetadata>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at, display_name 
+             FROM images 
+             WHERE id = ?1"
+        )?;
+
+        let mut images = stmt.query_map([id], |row| {
+            Ok(ImageMetadata {
+                id: row.get(0)?,
+                original_file_name: row.get(1)?,
+                saved_file_name: row.get(2)?,
+                image_type: row.get(3)?,
+                created_at: row.get(4)?,
+                size: row.get(5)?,
+                width: row.get(6)?,
+                height: row.get(7)?,
+                storage_location: row.get(8)?,
+                file_path: row.get(9)?,
+                is_hidden: row.get(10).unwrap_or(0),
+                display_started_at: row.get(11).ok(),
+                display_name: row.get(12).ok().flatten(),
+            })
+        })?;
+
+        match images.next() {
+            Some(image) => Ok(Some(self.resolve_image_paths(image?))),
+            None => Ok(None),
+        }
+    }
+
     // 画像メタデータの取得（全件）
     pub fn get_all_images(&self) -> Result<Vec<ImageMetadata>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at 
+            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at, display_name 
              FROM images 
              ORDER BY created_at DESC"
         )?;
@@ -251,12 +1048,13 @@ impl Database {
                 file_path: row.get(9)?,
                 is_hidden: row.get(10).unwrap_or(0),
                 display_started_at: row.get(11).ok(),
+                display_name: row.get(12).ok().flatten(),
             })
         })?;
 
         let mut result = Vec::new();
         for image in images {
-            result.push(image?);
+            result.push(self.resolve_image_paths(image?));
         }
         Ok(result)
     }
@@ -302,7 +1100,7 @@ impl Database {
     #[allow(dead_code)]
     pub fn get_image_by_id(&self, id: &str) -> Result<Option<ImageMetadata>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at 
+            "SELECT id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, is_hidden, display_started_at, display_name 
              FROM images 
              WHERE id = ?1"
         )?;
@@ -321,15 +1119,68 @@ impl Database {
                 file_path: row.get(9)?,
                 is_hidden: row.get(10).unwrap_or(0),
                 display_started_at: row.get(11).ok(),
+                display_name: row.get(12).ok().flatten(),
             })
         })?;
 
         match images.next() {
-            Some(image) => Ok(Some(image?)),
+            Some(image) => Ok(Some(self.resolve_image_paths(image?))),
             None => Ok(None),
         }
     }
 
+    // 画像ファイルを書き換えた後のサイズ情報を更新
+    pub fn update_image_dimensions(
+        &self,
+        id: &str,
+        width: i32,
+        height: i32,
+        size: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE images SET width = ?1, height = ?2, size = ?3 WHERE id = ?4",
+            params![width, height, size, id],
+        )?;
+        Ok(())
+    }
+
+    // 画像の表示/非表示を切り替え
+    pub fn set_image_hidden(&self, id: &str, hidden: bool) -> Result<usize> {
+        self.conn.execute(
+            "UPDATE images SET is_hidden = ?1 WHERE id = ?2",
+            params![hidden as i32, id],
+        )
+    }
+
+    // 表示中の処理済み画像をすべて非表示にし、対象のIDを返す
+    pub fn hide_all_processed_images(&self) -> Result<Vec<String>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let ids = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM images
+                 WHERE image_type = 'processed' AND (is_hidden IS NULL OR is_hidden = 0)",
+            )?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<Vec<String>>>()?
+        };
+        tx.execute(
+            "UPDATE images SET is_hidden = 1
+             WHERE image_type = 'processed' AND (is_hidden IS NULL OR is_hidden = 0)",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(ids)
+    }
+
+    // OCRで読み取った表示名を更新（Noneでクリア）
+    pub fn update_image_display_name(&self, id: &str, display_name: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE images SET display_name = ?1 WHERE id = ?2",
+            params![display_name, id],
+        )?;
+        Ok(())
+    }
+
     // 画像の削除
     pub fn delete_image(&self, id: &str) -> Result<()> {
         self.conn
@@ -338,7 +1189,7 @@ impl Database {
     }
 
     pub fn mark_display_started_if_null(&self, id: &str) -> Result<()> {
-        let now = current_timestamp();
+        let now = current_timestamp_with_offset(self.clock_offset_ms()?);
         self.conn.execute(
             "UPDATE images SET display_started_at = COALESCE(display_started_at, ?1) WHERE id = ?2",
             params![now, id],
@@ -346,11 +1197,21 @@ impl Database {
         Ok(())
     }
 
+    // 表示中（display_started_atが記録済み）の処理済み画像のIDと表示開始時刻
+    pub fn get_displayed_images(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, display_started_at FROM images
+             WHERE image_type = 'processed' AND is_hidden = 0 AND display_started_at IS NOT NULL",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
     // 画像のfile_pathを更新
     pub fn update_image_file_path(&self, id: &str, file_path: &str) -> Result<()> {
         self.conn.execute(
             "UPDATE images SET file_path = ?1 WHERE id = ?2",
-            params![file_path, id],
+            params![self.to_stored_path(file_path), id],
         )?;
         Ok(())
     }
@@ -413,12 +1274,68 @@ impl Database {
         Ok((original_count, processed_count))
     }
 
+    // テーブルごとの行数（サポート用の統計）
+    pub fn table_row_counts(&self) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        tables
+            .into_iter()
+            .map(|table| {
+                let count = self.conn.query_row(
+                    &format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")),
+                    [],
+                    |row| row.get(0),
+                )?;
+                Ok((table, count))
+            })
+            .collect()
+    }
+
+    // 操作回数を加算（day はローカル日付 "YYYY-MM-DD"）
+    pub fn add_control_counts(&self, day: &str, counts: &[(String, u64)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (image_id, count) in counts {
+            tx.execute(
+                "INSERT INTO control_stats (day, image_id, count) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(day, image_id) DO UPDATE SET count = count + excluded.count",
+                params![day, image_id, *count as i64],
+            )?;
+        }
+        tx.commit()
+    }
+
+    // 指定日の操作回数が多い順の画像IDと回数
+    pub fn get_control_stats(&self, day: &str, limit: i64) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT image_id, count FROM control_stats
+             WHERE day = ?1 ORDER BY count DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![day, limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    // 指定時刻以降に作成された処理済み画像の件数
+    pub fn count_processed_images_since(&self, since: &str) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM images WHERE image_type = 'processed' AND created_at >= ?1",
+            params![since],
+            |row| row.get(0),
+        )
+    }
+
     // 動き設定の保存
     pub fn save_movement_settings(&self, settings: &MovementSettings) -> Result<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO movement_settings 
-             (image_id, movement_type, movement_pattern, speed, size, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            &format!(
+                "INSERT OR REPLACE INTO movement_settings ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                MOVEMENT_SETTINGS_COLUMNS
+            ),
             params![
                 settings.image_id,
                 settings.movement_type,
@@ -427,6 +1344,12 @@ impl Database {
                 settings.size,
                 settings.created_at,
                 settings.updated_at,
+                settings.gravity,
+                settings.bounce_elasticity,
+                settings.rotation_speed,
+                settings.amplitude,
+                settings.direction_bias,
+                settings.z_order,
             ],
         )?;
         Ok(())
@@ -434,23 +1357,12 @@ impl Database {
 
     // 動き設定の取得
     pub fn get_movement_settings(&self, image_id: &str) -> Result<Option<MovementSettings>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT image_id, movement_type, movement_pattern, speed, size, created_at, updated_at
-             FROM movement_settings 
-             WHERE image_id = ?1",
-        )?;
-
-        let mut settings = stmt.query_map([image_id], |row| {
-            Ok(MovementSettings {
-                image_id: row.get(0)?,
-                movement_type: row.get(1)?,
-                movement_pattern: row.get(2)?,
-                speed: row.get(3)?,
-                size: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM movement_settings WHERE image_id = ?1",
+            MOVEMENT_SETTINGS_COLUMNS
+        ))?;
+
+        let mut settings = stmt.query_map([image_id], movement_settings_from_row)?;
 
         match settings.next() {
             Some(setting) => Ok(Some(setting?)),
@@ -460,41 +1372,550 @@ impl Database {
 
     // すべての動き設定を取得
     pub fn get_all_movement_settings(&self) -> Result<Vec<MovementSettings>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM movement_settings",
+            MOVEMENT_SETTINGS_COLUMNS
+        ))?;
+
+        let settings = stmt.query_map([], movement_settings_from_row)?;
+
+        let mut result = Vec::new();
+        for setting in settings {
+            result.push(setting?);
+        }
+        Ok(result)
+    }
+
+    // 画像メタデータと動き設定を単一トランザクションで保存
+    pub fn save_image_with_settings(
+        &self,
+        metadata: &ImageMetadata,
+        settings: &MovementSettings,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO images (id, original_file_name, saved_file_name, image_type, created_at, size, width, height, storage_location, file_path, display_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                metadata.id,
+                file_name::normalize(&metadata.original_file_name),
+                metadata.saved_file_name,
+                metadata.image_type,
+                metadata.created_at,
+                metadata.size,
+                metadata.width,
+                metadata.height,
+                self.to_stored_path(&metadata.storage_location),
+                metadata
+                    .file_path
+                    .as_deref()
+                    .map(|fp| self.to_stored_path(fp)),
+                metadata.display_name,
+            ],
+        )?;
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO movement_settings ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                MOVEMENT_SETTINGS_COLUMNS
+            ),
+            params![
+                metadata.id,
+                settings.movement_type,
+                settings.movement_pattern,
+                settings.speed,
+                settings.size,
+                settings.created_at,
+                settings.updated_at,
+                settings.gravity,
+                settings.bounce_elasticity,
+                settings.rotation_speed,
+                settings.amplitude,
+                settings.direction_bias,
+                settings.z_order,
+            ],
+        )?;
+        tx.commit()
+    }
+
+    // 操作のなめらかさ設定の保存
+    pub fn save_control_smoothing(&self, smoothing: &ControlSmoothing) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO control_smoothing (movement_type, acceleration, max_velocity, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                smoothing.movement_type,
+                smoothing.acceleration,
+                smoothing.max_velocity,
+                current_timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 動きタイプの設定（未保存なら既定値）
+    pub fn get_control_smoothing(&self, movement_type: &str) -> Result<ControlSmoothing> {
+        match self.conn.query_row(
+            "SELECT movement_type, acceleration, max_velocity, updated_at
+             FROM control_smoothing WHERE movement_type = ?1",
+            params![movement_type],
+            |row| {
+                Ok(ControlSmoothing {
+                    movement_type: row.get(0)?,
+                    acceleration: row.get(1)?,
+                    max_velocity: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            },
+        ) {
+            Ok(smoothing) => Ok(smoothing),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                Ok(ControlSmoothing::default_for(movement_type))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // 指定の動きタイプの画像ID一覧
+    pub fn get_image_ids_by_movement_type(&self, movement_type: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT image_id FROM movement_settings WHERE movement_type = ?1")?;
+        let rows = stmt.query_map(params![movement_type], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    // プリセットの保存（同名は上書き）
+    pub fn save_movement_preset(&self, preset: &MovementPreset) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO movement_presets
+             (id, name, movement_type, movement_pattern, speed, size, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(name) DO UPDATE SET
+                movement_type = excluded.movement_type,
+                movement_pattern = excluded.movement_pattern,
+                speed = excluded.speed,
+                size = excluded.size,
+                updated_at = excluded.updated_at",
+            params![
+                preset.id,
+                preset.name,
+                preset.movement_type,
+                preset.movement_pattern,
+                preset.speed,
+                preset.size,
+                preset.created_at,
+                preset.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // プリセット一覧の取得
+    pub fn get_movement_presets(&self) -> Result<Vec<MovementPreset>> {
         let mut stmt = self.conn.prepare(
-            "SELECT image_id, movement_type, movement_pattern, speed, size, created_at, updated_at
-             FROM movement_settings",
-        )?;
-
-        let settings = stmt.query_map([], |row| {
-            Ok(MovementSettings {
-                image_id: row.get(0)?,
-                movement_type: row.get(1)?,
-                movement_pattern: row.get(2)?,
-                speed: row.get(3)?,
-                size: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
+            "SELECT id, name, movement_type, movement_pattern, speed, size, created_at, updated_at
+             FROM movement_presets
+             ORDER BY name",
+        )?;
+
+        let presets = stmt.query_map([], |row| {
+            Ok(MovementPreset {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                movement_type: row.get(2)?,
+                movement_pattern: row.get(3)?,
+                speed: row.get(4)?,
+                size: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
             })
         })?;
 
         let mut result = Vec::new();
-        for setting in settings {
-            result.push(setting?);
+        for preset in presets {
+            result.push(preset?);
+        }
+        Ok(result)
+    }
+
+    // 名前でプリセットを取得
+    pub fn get_movement_preset_by_name(&self, name: &str) -> Result<Option<MovementPreset>> {
+        match self.conn.query_row(
+            "SELECT id, name, movement_type, movement_pattern, speed, size, created_at, updated_at
+             FROM movement_presets
+             WHERE name = ?1",
+            params![name],
+            |row| {
+                Ok(MovementPreset {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    movement_type: row.get(2)?,
+                    movement_pattern: row.get(3)?,
+                    speed: row.get(4)?,
+                    size: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                })
+            },
+        ) {
+            Ok(preset) => Ok(Some(preset)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // プリセットの削除
+    pub fn delete_movement_preset(&self, name: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM movement_presets WHERE name = ?1",
+            params![name],
+        )?;
+        Ok(())
+    }
+
+    // プリセットを複数画像へ一括適用（単一トランザクション）
+    pub fn apply_movement_preset(
+        &self,
+        preset: &MovementPreset,
+        image_ids: &[String],
+    ) -> Result<()> {
+        let now = current_timestamp();
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO movement_settings
+                 (image_id, movement_type, movement_pattern, speed, size, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(image_id) DO UPDATE SET
+                    movement_type = excluded.movement_type,
+                    movement_pattern = excluded.movement_pattern,
+                    speed = excluded.speed,
+                    size = excluded.size,
+                    updated_at = excluded.updated_at",
+            )?;
+            for image_id in image_ids {
+                stmt.execute(params![
+                    image_id,
+                    preset.movement_type,
+                    preset.movement_pattern,
+                    preset.speed,
+                    preset.size,
+                    now,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // 背景スケジュールの保存
+    pub fn save_background_schedule_entry(&self, entry: &BackgroundScheduleEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO background_schedule
+             (id, background_id, start_time, enabled, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.id,
+                entry.background_id,
+                entry.start_time,
+                entry.enabled as i32,
+                entry.created_at,
+                entry.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 背景スケジュールの取得（開始時刻順）
+    pub fn get_background_schedule(&self) -> Result<Vec<BackgroundScheduleEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, background_id, start_time, enabled, created_at, updated_at
+             FROM background_schedule
+             ORDER BY start_time",
+        )?;
+
+        let entries = stmt.query_map([], |row| {
+            Ok(BackgroundScheduleEntry {
+                id: row.get(0)?,
+                background_id: row.get(1)?,
+                start_time: row.get(2)?,
+                enabled: row.get::<_, i32>(3)? != 0,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for entry in entries {
+            result.push(entry?);
         }
         Ok(result)
     }
 
+    // 背景スケジュールの有効/無効切り替え
+    pub fn set_background_schedule_enabled(&self, id: &str, enabled: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE background_schedule SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+            params![enabled as i32, current_timestamp(), id],
+        )?;
+        Ok(())
+    }
+
+    // 背景スケジュールの削除
+    pub fn delete_background_schedule_entry(&self, id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM background_schedule WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // 背景の表示設定の保存
+    pub fn save_background_settings(&self, settings: &BackgroundSettings) -> Result<()> {
+        let layers = serde_json::to_string(&settings.parallax_layers)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO background_settings
+             (background_id, fit_mode, focal_x, focal_y, parallax_layers, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                settings.background_id,
+                settings.fit_mode,
+                settings.focal_x,
+                settings.focal_y,
+                layers,
+                current_timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 背景の表示設定の取得（未設定ならNone）
+    pub fn get_background_settings(
+        &self,
+        background_id: &str,
+    ) -> Result<Option<BackgroundSettings>> {
+        match self.conn.query_row(
+            "SELECT background_id, fit_mode, focal_x, focal_y, parallax_layers, updated_at
+             FROM background_settings WHERE background_id = ?1",
+            params![background_id],
+            |row| {
+                let layers: String = row.get(4)?;
+                Ok(BackgroundSettings {
+                    background_id: row.get(0)?,
+                    fit_mode: row.get(1)?,
+                    focal_x: row.get(2)?,
+                    focal_y: row.get(3)?,
+                    parallax_layers: serde_json::from_str(&layers).unwrap_or_default(),
+                    updated_at: row.get(5)?,
+                })
+            },
+        ) {
+            Ok(settings) => Ok(Some(settings)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // テンプレートの保存（作成日時は既存行を引き継ぐ）
+    pub fn save_template(&self, template: &Template) -> Result<()> {
+        let name_box = template
+            .name_box
+            .map(|b| serde_json::to_string(&b))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let languages = serde_json::to_string(&template.ocr_languages)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let now = current_timestamp();
+        self.conn.execute(
+            "INSERT INTO templates (id, name, name_box, ocr_enabled, ocr_languages, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                name_box = excluded.name_box,
+                ocr_enabled = excluded.ocr_enabled,
+                ocr_languages = excluded.ocr_languages,
+                updated_at = excluded.updated_at",
+            params![
+                template.id,
+                template.name,
+                name_box,
+                template.ocr_enabled as i32,
+                languages,
+                now,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn row_to_template(row: &rusqlite::Row) -> Result<Template> {
+        let name_box: Option<String> = row.get(2)?;
+        let languages: String = row.get(4)?;
+        Ok(Template {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            name_box: name_box.and_then(|b| serde_json::from_str(&b).ok()),
+            ocr_enabled: row.get::<_, i32>(3)? != 0,
+            ocr_languages: serde_json::from_str(&languages).unwrap_or_default(),
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+
+    // テンプレートの取得（未登録ならNone）
+    pub fn get_template(&self, id: &str) -> Result<Option<Template>> {
+        match self.conn.query_row(
+            "SELECT id, name, name_box, ocr_enabled, ocr_languages, created_at, updated_at
+             FROM templates WHERE id = ?1",
+            params![id],
+            Self::row_to_template,
+        ) {
+            Ok(template) => Ok(Some(template)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn get_all_templates(&self) -> Result<Vec<Template>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, name_box, ocr_enabled, ocr_languages, created_at, updated_at
+             FROM templates ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], Self::row_to_template)?;
+        rows.collect()
+    }
+
+    pub fn delete_template(&self, id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM templates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
     // アプリケーション設定の保存
     pub fn save_app_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.save_app_setting_from(key, value, "system")
+    }
+
+    // 変更元を記録して保存（値が変わった場合のみ履歴に残す）
+    pub fn save_app_setting_from(&self, key: &str, value: &str, source: &str) -> Result<()> {
+        self.write_app_setting(key, value, Some(source))
+    }
+
+    // 履歴に残さずに保存（時刻補正値や連番など、アプリが内部で更新する値）
+    pub fn save_internal_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.write_app_setting(key, value, None)
+    }
+
+    fn write_app_setting(&self, key: &str, value: &str, source: Option<&str>) -> Result<()> {
         let now = current_timestamp();
+        let version = self.settings_version()? + 1;
+        let old_value = self.get_app_setting(key)?;
+        let tx = self.conn.unchecked_transaction()?;
+        if let Some(source) = source.filter(|_| old_value.as_deref() != Some(value)) {
+            tx.execute(
+                "INSERT INTO settings_history (scope, key, old_value, new_value, changed_at, source)
+                 VALUES ('app', ?1, ?2, ?3, ?4, ?5)",
+                params![key, old_value, value, now, source],
+            )?;
+            prune_settings_history(&tx)?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value, created_at, updated_at, version)
+             VALUES (?1, ?2, ?3, ?3, ?4)",
+            params![key, value, now, version],
+        )?;
+        tx.execute(
+            "DELETE FROM app_settings_tombstones WHERE key = ?1",
+            params![key],
+        )?;
+        tx.commit()
+    }
+
+    // グローバル設定の変更を履歴に残す（値はファイル側に保存済み）
+    pub fn record_global_setting_change(
+        &self,
+        key: &str,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+        source: &str,
+    ) -> Result<()> {
+        if old_value == new_value {
+            return Ok(());
+        }
         self.conn.execute(
-            "INSERT OR REPLACE INTO app_settings (key, value, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?3)",
-            params![key, value, now],
+            "INSERT INTO settings_history (scope, key, old_value, new_value, changed_at, source)
+             VALUES ('global', ?1, ?2, ?3, ?4, ?5)",
+            params![key, old_value, new_value, current_timestamp(), source],
         )?;
+        prune_settings_history(&self.conn)?;
         Ok(())
     }
 
+    // 設定変更の履歴（新しい順）。key未指定なら全項目
+    pub fn get_settings_history(
+        &self,
+        key: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SettingsHistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, scope, key, old_value, new_value, changed_at, source
+             FROM settings_history
+             WHERE ?1 IS NULL OR key = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![key, limit], |row| {
+            Ok(SettingsHistoryEntry {
+                id: row.get(0)?,
+                scope: row.get(1)?,
+                key: row.get(2)?,
+                old_value: row.get(3)?,
+                new_value: row.get(4)?,
+                changed_at: row.get(5)?,
+                source: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // アプリケーション設定の削除
+    pub fn delete_app_setting(&self, key: &str) -> Result<()> {
+        let version = self.settings_version()? + 1;
+        let tx = self.conn.unchecked_transaction()?;
+        let deleted = tx.execute("DELETE FROM app_settings WHERE key = ?1", params![key])?;
+        if deleted > 0 {
+            tx.execute(
+                "INSERT OR REPLACE INTO app_settings_tombstones (key, version) VALUES (?1, ?2)",
+                params![key, version],
+            )?;
+        }
+        tx.commit()
+    }
+
+    // 設定のスナップショットバージョン（保存・削除のたびに増える）
+    pub fn settings_version(&self) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT MAX(
+                (SELECT COALESCE(MAX(version), 0) FROM app_settings),
+                (SELECT COALESCE(MAX(version), 0) FROM app_settings_tombstones)
+             )",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    // 指定バージョン以降に変わった設定（削除はNone）をバージョン順に取得
+    pub fn get_settings_delta(&self, since_version: i64) -> Result<Vec<(String, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key, value, version FROM app_settings WHERE version > ?1
+             UNION ALL
+             SELECT key, NULL, version FROM app_settings_tombstones WHERE version > ?1
+             ORDER BY 3",
+        )?;
+        let rows = stmt.query_map(params![since_version], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+        rows.collect()
+    }
+
     // アプリケーション設定の取得
     pub fn get_app_setting(&self, key: &str) -> Result<Option<String>> {
         match self.conn.query_row(
@@ -508,6 +1929,18 @@ impl Database {
         }
     }
 
+    // ワークスペースごとの時刻補正値（ミリ秒、未測定なら0）
+    pub fn clock_offset_ms(&self) -> Result<i64> {
+        Ok(self
+            .get_app_setting(CLOCK_OFFSET_KEY)?
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(0))
+    }
+
+    pub fn save_clock_offset_ms(&self, offset_ms: i64) -> Result<()> {
+        self.save_internal_setting(CLOCK_OFFSET_KEY, &offset_ms.to_string())
+    }
+
     // 複数のアプリケーション設定を一度に取得
     pub fn get_app_settings(
         &self,
@@ -533,6 +1966,342 @@ impl Database {
 
         Ok(result)
     }
+
+    pub fn get_workspace_info(&self) -> Result<WorkspaceInfo> {
+        self.conn.query_row(
+            "SELECT workspace_id, display_name, created_at, app_version, schema_version
+             FROM workspace_info WHERE id = 1",
+            [],
+            |row| {
+                Ok(WorkspaceInfo {
+                    workspace_id: row.get(0)?,
+                    display_name: row.get(1)?,
+                    created_at: row.get(2)?,
+                    app_version: row.get(3)?,
+                    schema_version: row.get(4)?,
+                    secret_backend: None,
+                })
+            },
+        )
+    }
+
+    pub fn set_workspace_display_name(&self, display_name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE workspace_info SET display_name = ?1 WHERE id = 1",
+            params![display_name],
+        )?;
+        Ok(())
+    }
+
+    // QRセッション（再起動後も印刷済みのQRを使えるよう保存する）
+    pub fn save_qr_session(
+        &self,
+        session_id: &str,
+        image_id: &str,
+        created_at: &str,
+        printed: bool,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO qr_sessions (session_id, image_id, created_at, used, printed)
+             VALUES (?1, ?2, ?3, 0, ?4)",
+            params![session_id, image_id, created_at, printed as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_qr_session_used(&self, session_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE qr_sessions SET used = 1 WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_qr_session(&self, session_id: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM qr_sessions WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    // スマホが参加した接続元と時刻を記録（再接続のたびに最新へ更新）
+    pub fn record_qr_session_join(
+        &self,
+        session_id: &str,
+        remote_ip: Option<&str>,
+        joined_at: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE qr_sessions SET remote_ip = ?2, joined_at = ?3 WHERE session_id = ?1",
+            params![session_id, remote_ip, joined_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_qr_sessions(&self) -> Result<Vec<QrSessionRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT session_id, image_id, created_at, used, remote_ip, joined_at, printed
+             FROM qr_sessions ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(QrSessionRow {
+                session_id: row.get(0)?,
+                image_id: row.get(1)?,
+                created_at: row.get(2)?,
+                used: row.get::<_, i64>(3)? != 0,
+                remote_ip: row.get(4)?,
+                joined_at: row.get(5)?,
+                printed: row.get::<_, i64>(6)? != 0,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // Relayへ送るメッセージを末尾に追加
+    pub fn enqueue_relay_message(&self, payload: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO relay_outbox (payload, created_at) VALUES (?1, ?2)",
+            params![payload, current_timestamp()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // 送信待ちを古い順に取得
+    pub fn peek_relay_messages(&self, limit: i64) -> Result<Vec<RelayOutboxEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, payload, attempts FROM relay_outbox ORDER BY id LIMIT ?1")?;
+        let rows = stmt.query_map(params![limit], |row| {
+            Ok(RelayOutboxEntry {
+                id: row.get(0)?,
+                payload: row.get(1)?,
+                attempts: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn delete_relay_message(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM relay_outbox WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // created_at が cutoff より前の送信待ちを削除
+    pub fn prune_relay_messages(&self, cutoff: &str) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM relay_outbox WHERE created_at < ?1",
+            params![cutoff],
+        )
+    }
+
+    pub fn mark_relay_message_failed(&self, id: i64, error: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE relay_outbox SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+            params![id, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn relay_outbox_stats(&self) -> Result<RelayOutboxStats> {
+        let (pending, oldest_created_at) = self.conn.query_row(
+            "SELECT COUNT(*), MIN(created_at) FROM relay_outbox",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let head = self.conn.query_row(
+            "SELECT attempts, last_error FROM relay_outbox ORDER BY id LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        let (head_attempts, last_error) = match head {
+            Ok(head) => head,
+            Err(rusqlite::Error::QueryReturnedNoRows) => (0, None),
+            Err(e) => return Err(e),
+        };
+        Ok(RelayOutboxStats {
+            pending,
+            oldest_created_at,
+            head_attempts,
+            last_error,
+        })
+    }
+
+    // シーン状態を追記
+    pub fn append_scene_state(&self, state: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO scene_state (state, saved_at) VALUES (?1, ?2)",
+            params![state, current_timestamp()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // 最新のシーン状態と保存時刻
+    pub fn latest_scene_state(&self) -> Result<Option<(String, String)>> {
+        match self.conn.query_row(
+            "SELECT state, saved_at FROM scene_state ORDER BY id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => Ok(Some(row)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // 新しいものをkeep件だけ残して削除
+    pub fn compact_scene_state(&self, keep: i64) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM scene_state WHERE id NOT IN
+             (SELECT id FROM scene_state ORDER BY id DESC LIMIT ?1)",
+            params![keep],
+        )
+    }
+
+    pub fn insert_share_link(&self, link: &ShareLink) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO share_links (id, image_id, created_at, expires_at, revoked_at, downloads)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                link.id,
+                link.image_id,
+                link.created_at,
+                link.expires_at,
+                link.revoked_at,
+                link.downloads
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_share_link(&self, id: &str) -> Result<Option<ShareLink>> {
+        match self.conn.query_row(
+            &format!(
+                "SELECT {} FROM share_links WHERE id = ?1",
+                SHARE_LINK_COLUMNS
+            ),
+            params![id],
+            share_link_from_row,
+        ) {
+            Ok(link) => Ok(Some(link)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // 画像ID指定ならその作品の分だけ（新しい順）
+    pub fn list_share_links(&self, image_id: Option<&str>) -> Result<Vec<ShareLink>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM share_links WHERE (?1 IS NULL OR image_id = ?1)
+             ORDER BY created_at DESC",
+            SHARE_LINK_COLUMNS
+        ))?;
+        let links = stmt.query_map(params![image_id], share_link_from_row)?;
+        links.collect()
+    }
+
+    // 取り消した場合は true（取り消し済み・存在しない場合は false）
+    pub fn revoke_share_link(&self, id: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE share_links SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            params![id, current_timestamp()],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn increment_share_link_downloads(&self, id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE share_links SET downloads = downloads + 1 WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    // 作品ごとに1件。リンクを発行し直した場合は置き換え、受け取り回数は引き継ぐ
+    pub fn upsert_takeaway(
+        &self,
+        image_id: &str,
+        share_link_id: &str,
+        file_path: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO takeaways (image_id, share_link_id, file_path, issued_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(image_id) DO UPDATE SET
+                share_link_id = excluded.share_link_id,
+                file_path = excluded.file_path,
+                issued_at = excluded.issued_at",
+            params![image_id, share_link_id, file_path, current_timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_takeaway(&self, image_id: &str) -> Result<Option<Takeaway>> {
+        match self.conn.query_row(
+            &format!(
+                "SELECT {} FROM takeaways WHERE image_id = ?1",
+                TAKEAWAY_COLUMNS
+            ),
+            params![image_id],
+            takeaway_from_row,
+        ) {
+            Ok(takeaway) => Ok(Some(takeaway)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn list_takeaways(&self) -> Result<Vec<Takeaway>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM takeaways", TAKEAWAY_COLUMNS))?;
+        let takeaways = stmt.query_map([], takeaway_from_row)?;
+        takeaways.collect()
+    }
+
+    pub fn get_takeaway_by_share_link(&self, share_link_id: &str) -> Result<Option<Takeaway>> {
+        match self.conn.query_row(
+            &format!(
+                "SELECT {} FROM takeaways WHERE share_link_id = ?1",
+                TAKEAWAY_COLUMNS
+            ),
+            params![share_link_id],
+            takeaway_from_row,
+        ) {
+            Ok(takeaway) => Ok(Some(takeaway)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // 持ち帰り用でないリンクなら何もしない
+    pub fn record_takeaway_redemption(&self, share_link_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE takeaways SET redemptions = redemptions + 1,
+                first_redeemed_at = COALESCE(first_redeemed_at, ?2)
+             WHERE share_link_id = ?1",
+            params![share_link_id, current_timestamp()],
+        )?;
+        Ok(())
+    }
+
+    pub fn takeaway_stats(&self) -> Result<TakeawayStats> {
+        self.conn.query_row(
+            "SELECT COUNT(*),
+                    COUNT(first_redeemed_at),
+                    COALESCE(SUM(redemptions), 0)
+             FROM takeaways",
+            [],
+            |row| {
+                Ok(TakeawayStats {
+                    issued: row.get(0)?,
+                    redeemed: row.get(1)?,
+                    downloads: row.get(2)?,
+                })
+            },
+        )
+    }
 }
 
 // ヘルパー関数
@@ -540,6 +2309,20 @@ pub fn generate_id() -> String {
     Uuid::new_v4().to_string()
 }
 
+// 新しいものを SETTINGS_HISTORY_LIMIT 件だけ残す
+fn prune_settings_history(conn: &Connection) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM settings_history
+         WHERE id <= (SELECT MAX(id) FROM settings_history) - ?1",
+        params![SETTINGS_HISTORY_LIMIT],
+    )
+}
+
 pub fn current_timestamp() -> String {
     Utc::now().to_rfc3339()
 }
+
+// 時刻補正値を適用した現在時刻（表示/削除判定の比較用）
+pub fn current_timestamp_with_offset(offset_ms: i64) -> String {
+    (Utc::now() + Duration::milliseconds(offset_ms)).to_rfc3339()
+}
@@ -0,0 +1,85 @@
+use tauri::{AppHandle, State};
+
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const REDACTED: &str = "[redacted]";
+
+/// IDの先頭8文字だけを残す（トークン生成に使われるため全体は出さない）
+pub fn redact_id(id: &str) -> String {
+    let head: String = id.chars().take(8).collect();
+    format!("{}…", head)
+}
+
+/// サポート・診断パネル用にメモリ上の状態をJSONで書き出す
+/// output_pathを指定した場合はファイルにも保存する
+#[tauri::command]
+pub async fn dump_runtime_state(
+    app_handle: AppHandle,
+    server_state: State<'_, ServerState>,
+    workspace: State<'_, WorkspaceState>,
+    output_path: Option<String>,
+) -> Result<serde_json::Value, String> {
+    // 出力先はワークスペースのロックより先に検査する（allowed_rootsがロックを取るため）
+    let output_path = output_path
+        .map(|p| crate::path_sandbox::check_write(&app_handle, &p))
+        .transpose()?;
+
+    let qr_sessions = server_state
+        .get_qr_manager()
+        .map(|manager| manager.sessions_snapshot())
+        .unwrap_or_default();
+
+    let workspace_info = crate::workspace::current_workspace_info(&app_handle);
+    let (workspace_connected, workspace_db) = {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        (
+            conn.connection.is_some(),
+            conn.current_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+        )
+    };
+
+    let (python_running, python_pending) = crate::python_runtime_status();
+    let watcher = crate::file_watcher::watcher_status();
+    let license_token_present = crate::load_license_token().ok().flatten().is_some();
+
+    let snapshot = serde_json::json!({
+        "generatedAt": crate::db::current_timestamp(),
+        "appVersion": app_handle.package_info().version.to_string(),
+        "uptimeSeconds": crate::heartbeat::uptime_secs(),
+        "server": {
+            "httpPort": server_state.get_server_port(),
+            "httpsPort": server_state.get_https_port(),
+            "starting": *server_state.is_starting.lock().unwrap(),
+            "authMode": server_state.web_auth.mode().as_str(),
+            "authSecret": REDACTED,
+        },
+        "qrSessions": qr_sessions,
+        "websocketConnections": crate::websocket::connections_snapshot(),
+        "queues": {
+            "pythonRunning": python_running,
+            "pythonPending": python_pending,
+            "autoImportInFlight": watcher.imports_in_flight,
+        },
+        "watcher": watcher,
+        "workspace": {
+            "connected": workspace_connected,
+            "dbPath": workspace_db,
+            "info": workspace_info,
+        },
+        "licenseToken": if license_token_present { Some(REDACTED) } else { None },
+    });
+
+    if let Some(path) = output_path {
+        let body = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| format!("状態のシリアライズに失敗しました: {}", e))?;
+        std::fs::write(&path, body)
+            .map_err(|e| format!("状態ファイルの書き込みに失敗しました: {}", e))?;
+    }
+
+    Ok(snapshot)
+}
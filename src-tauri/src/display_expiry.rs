@@ -0,0 +1,198 @@
+use crate::db::Database;
+use crate::workspace::WorkspaceState;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// 削除のこの時間前に display-expiring を通知する（フェードアウト用）
+const EXPIRING_WINDOW_MS: i64 = 10_000;
+// 期限チェックの間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DisplayTimeRemaining {
+    pub image_id: String,
+    pub display_started_at: Option<String>,
+    // 削除設定（分）。無制限ならNone
+    pub deletion_minutes: Option<i64>,
+    pub expires_at: Option<String>,
+    // 残りミリ秒。表示前または無制限ならNone
+    pub remaining_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct DisplayExpiringPayload {
+    image_id: String,
+    expires_at: String,
+    remaining_ms: i64,
+}
+
+// "unlimited" 以外の分数を返す
+fn parse_deletion_minutes(value: &str) -> Option<i64> {
+    value.trim().parse::<i64>().ok().filter(|m| *m > 0)
+}
+
+/// 現在の削除設定（分）。DBのapp_settingsを優先し、なければワークスペースのsettings.jsonを見る
+fn deletion_minutes(db: &Database, db_path: Option<&Path>) -> Option<i64> {
+    if let Ok(Some(value)) = db.get_app_setting("deletion_time") {
+        return parse_deletion_minutes(&value);
+    }
+
+    let settings_path = db_path?.parent()?.join("settings.json");
+    let content = std::fs::read_to_string(settings_path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("deletionTime")
+        .and_then(|v| v.as_str())
+        .and_then(parse_deletion_minutes)
+}
+
+fn compute_remaining(
+    image_id: &str,
+    display_started_at: Option<String>,
+    minutes: Option<i64>,
+    now: DateTime<Utc>,
+) -> DisplayTimeRemaining {
+    let expires_at = match (display_started_at.as_deref(), minutes) {
+        (Some(started), Some(minutes)) => DateTime::parse_from_rfc3339(started)
+            .ok()
+            .map(|t| t.with_timezone(&Utc) + ChronoDuration::minutes(minutes)),
+        _ => None,
+    };
+
+    DisplayTimeRemaining {
+        image_id: image_id.to_string(),
+        display_started_at,
+        deletion_minutes: minutes,
+        expires_at: expires_at.map(|t| t.to_rfc3339()),
+        remaining_ms: expires_at.map(|t| (t - now).num_milliseconds().max(0)),
+    }
+}
+
+// 時刻補正値を適用した現在時刻
+fn corrected_now(db: &Database) -> DateTime<Utc> {
+    Utc::now() + ChronoDuration::milliseconds(db.clock_offset_ms().unwrap_or(0))
+}
+
+/// 画像が削除されるまでの残り時間
+#[tauri::command]
+pub fn get_display_time_remaining(
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+) -> Result<DisplayTimeRemaining, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let image = db
+        .get_image(&id)
+        .map_err(|e| format!("Failed to get image: {}", e))?
+        .ok_or_else(|| format!("画像が見つかりません: {}", id))?;
+
+    let minutes = deletion_minutes(db, conn.current_path.as_deref());
+    Ok(compute_remaining(
+        &id,
+        image.display_started_at,
+        minutes,
+        corrected_now(db),
+    ))
+}
+
+// 期限が近い画像を集める
+fn collect_expiring(app_handle: &AppHandle) -> Vec<DisplayExpiringPayload> {
+    let workspace: State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = workspace.lock() else {
+        return Vec::new();
+    };
+    let Ok(db) = conn.get() else {
+        return Vec::new();
+    };
+    let Some(minutes) = deletion_minutes(db, conn.current_path.as_deref()) else {
+        return Vec::new();
+    };
+    let images = match db.get_displayed_images() {
+        Ok(images) => images,
+        Err(e) => {
+            eprintln!("[display_expiry] failed to load images: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let now = corrected_now(db);
+    images
+        .into_iter()
+        .filter_map(|(id, started)| {
+            let remaining = compute_remaining(&id, Some(started), Some(minutes), now);
+            let remaining_ms = remaining.remaining_ms?;
+            (remaining_ms <= EXPIRING_WINDOW_MS).then(|| DisplayExpiringPayload {
+                image_id: id,
+                expires_at: remaining.expires_at.unwrap_or_default(),
+                remaining_ms,
+            })
+        })
+        .collect()
+}
+
+// 期限を過ぎた画像を削除する（アニメーションウィンドウには削除を許可していないため、ここで行う）
+fn delete_expired(app_handle: &AppHandle) {
+    let workspace: State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = workspace.lock() else {
+        return;
+    };
+    let Ok(db) = conn.get() else {
+        return;
+    };
+    let Some(minutes) = deletion_minutes(db, conn.current_path.as_deref()) else {
+        return;
+    };
+    let Ok(images) = db.get_displayed_images() else {
+        return;
+    };
+    let now = corrected_now(db);
+    for (id, started) in images {
+        let remaining = compute_remaining(&id, Some(started), Some(minutes), now);
+        if remaining.remaining_ms != Some(0) {
+            continue;
+        }
+        println!("[display_expiry] deleting expired image {}", id);
+        if let Err(e) = crate::delete_image_and_notify(app_handle, db, &id) {
+            eprintln!("[display_expiry] failed to delete {}: {}", id, e);
+        }
+    }
+}
+
+/// 削除直前の画像を定期的に通知し、期限を過ぎたら削除するタスクを起動（アプリ起動時に一度だけ呼ぶ）
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // 同じ画像を二度通知しない
+        let mut notified: HashSet<String> = HashSet::new();
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+
+            let expiring = collect_expiring(&app_handle);
+            let current: HashSet<String> = expiring.iter().map(|p| p.image_id.clone()).collect();
+            for payload in expiring {
+                if notified.contains(&payload.image_id) {
+                    continue;
+                }
+                if let Err(e) =
+                    crate::events::emit_routed(&app_handle, "display-expiring", &payload)
+                {
+                    eprintln!("[display_expiry] failed to emit: {}", e);
+                    continue;
+                }
+                // 持ち帰りQRが有効なら消える前に大画面へ出す
+                if let Err(e) = crate::takeaway::show(&app_handle, &payload.image_id) {
+                    eprintln!("[display_expiry] failed to show takeaway QR: {}", e);
+                }
+                notified.insert(payload.image_id);
+            }
+            delete_expired(&app_handle);
+            // 削除済み・設定変更で対象外になったものは忘れる
+            notified.retain(|id| current.contains(id));
+        }
+    });
+}
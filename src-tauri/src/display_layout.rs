@@ -0,0 +1,233 @@
+// 複数ディスプレイでのウィンドウ配置（操作画面・アニメーション・QRをそれぞれ別のディスプレイに置く）
+// 配置はワークスペースの app_settings に保存し、接続時・ウィンドウを開いたとき・ディスプレイの抜き差し時に適用する
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, State, WebviewWindow};
+
+use crate::workspace::WorkspaceState;
+
+const DISPLAY_LAYOUT_KEY: &str = "display_layout";
+// ディスプレイの抜き差しを確認する間隔
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// ウィンドウ1つ分の配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowPlacement {
+    // ディスプレイ名で探し、見つからなければ番号で探す
+    #[serde(default)]
+    pub monitor_name: Option<String>,
+    #[serde(default)]
+    pub monitor_index: Option<usize>,
+    // ディスプレイ左上からの位置（物理ピクセル）
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub fullscreen: bool,
+    // キオスク表示（全画面・最前面・カーソル非表示）
+    #[serde(default)]
+    pub kiosk: bool,
+}
+
+/// ウィンドウラベルごとの配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayLayout {
+    #[serde(default)]
+    pub windows: HashMap<String, WindowPlacement>,
+}
+
+impl DisplayLayout {
+    fn validate(&self) -> Result<(), String> {
+        for (label, placement) in &self.windows {
+            if placement.width == Some(0) || placement.height == Some(0) {
+                return Err(format!("ウィンドウ '{}' の大きさが不正です", label));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_layout(app_handle: &AppHandle) -> DisplayLayout {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(DISPLAY_LAYOUT_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<DisplayLayout>(&value).ok())
+        .filter(|layout| layout.validate().is_ok())
+        .unwrap_or_default()
+}
+
+// 割り当て先のディスプレイ（番号と本体）
+fn find_monitor<'a>(
+    monitors: &'a [Monitor],
+    placement: &WindowPlacement,
+) -> Option<(usize, &'a Monitor)> {
+    placement
+        .monitor_name
+        .as_ref()
+        .and_then(|name| monitors.iter().position(|m| m.name() == Some(name)))
+        .or(placement
+            .monitor_index
+            .filter(|index| *index < monitors.len()))
+        .map(|index| (index, &monitors[index]))
+}
+
+fn place_window(
+    app_handle: &AppHandle,
+    window: &WebviewWindow,
+    placement: &WindowPlacement,
+) -> Result<(), String> {
+    let monitors = window
+        .available_monitors()
+        .map_err(|e| format!("ディスプレイ一覧の取得に失敗しました: {}", e))?;
+    // 割り当て先のディスプレイが外されている間はそのままにする
+    let Some((index, monitor)) = find_monitor(&monitors, placement) else {
+        return Ok(());
+    };
+    let origin = monitor.position();
+
+    if placement.kiosk {
+        return crate::kiosk::enter_kiosk_mode(
+            app_handle.clone(),
+            window.label().to_string(),
+            Some(index),
+        );
+    }
+
+    window
+        .set_fullscreen(false)
+        .map_err(|e| format!("全画面の解除に失敗しました: {}", e))?;
+    window
+        .set_position(PhysicalPosition::new(
+            origin.x + placement.x,
+            origin.y + placement.y,
+        ))
+        .map_err(|e| format!("ウィンドウの移動に失敗しました: {}", e))?;
+    if let (Some(width), Some(height)) = (placement.width, placement.height) {
+        window
+            .set_size(PhysicalSize::new(width, height))
+            .map_err(|e| format!("ウィンドウの大きさの変更に失敗しました: {}", e))?;
+    }
+    if placement.fullscreen {
+        window
+            .set_fullscreen(true)
+            .map_err(|e| format!("全画面表示に失敗しました: {}", e))?;
+    }
+    Ok(())
+}
+
+fn apply_layout(app_handle: &AppHandle, layout: &DisplayLayout, only: Option<&str>) -> Vec<String> {
+    let mut applied = Vec::new();
+    for (label, placement) in &layout.windows {
+        if only.is_some_and(|only| only != label) {
+            continue;
+        }
+        // まだ開いていないウィンドウは開いたときに適用する
+        let Some(window) = app_handle.get_webview_window(label) else {
+            continue;
+        };
+        match place_window(app_handle, &window, placement) {
+            Ok(()) => applied.push(label.clone()),
+            Err(e) => eprintln!("[display_layout] {}: {}", label, e),
+        }
+    }
+    applied
+}
+
+/// 開いたばかりのウィンドウに保存済みの配置を適用する
+pub fn apply_to_window(app_handle: &AppHandle, label: &str) {
+    let layout = read_layout(app_handle);
+    apply_layout(app_handle, &layout, Some(label));
+}
+
+/// ワークスペース接続後に適用する（呼び出し元が接続のロックを持っているため別スレッドで）
+pub fn schedule_apply(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        let layout = read_layout(&app_handle);
+        apply_layout(&app_handle, &layout, None);
+    });
+}
+
+fn monitor_signature(app_handle: &AppHandle) -> Option<Vec<String>> {
+    let monitors = app_handle.available_monitors().ok()?;
+    Some(
+        monitors
+            .iter()
+            .map(|m| {
+                format!(
+                    "{}@{},{} {}x{}",
+                    m.name().map(String::as_str).unwrap_or(""),
+                    m.position().x,
+                    m.position().y,
+                    m.size().width,
+                    m.size().height
+                )
+            })
+            .collect(),
+    )
+}
+
+/// 起動時に配置を適用し、ディスプレイの抜き差しを監視する
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        let layout = read_layout(&app_handle);
+        apply_layout(&app_handle, &layout, None);
+        // 直近に確認したディスプレイ構成（名前・位置・大きさ）
+        let mut last: Option<Vec<String>> = None;
+        loop {
+            if let Some(signature) = monitor_signature(&app_handle) {
+                let changed = last.as_ref().is_some_and(|last| *last != signature);
+                last = Some(signature);
+                if changed {
+                    println!("[display_layout] monitors changed; re-applying layout");
+                    let layout = read_layout(&app_handle);
+                    let applied = apply_layout(&app_handle, &layout, None);
+                    let _ = crate::events::emit_routed(
+                        &app_handle,
+                        "display-layout-applied",
+                        serde_json::json!({ "reason": "monitorsChanged", "windows": applied }),
+                    );
+                }
+            }
+            std::thread::sleep(MONITOR_POLL_INTERVAL);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_display_layout(app_handle: AppHandle) -> Result<DisplayLayout, String> {
+    Ok(read_layout(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_display_layout(
+    workspace: State<'_, WorkspaceState>,
+    layout: DisplayLayout,
+) -> Result<(), String> {
+    layout.validate()?;
+    let value = serde_json::to_string(&layout)
+        .map_err(|e| format!("ディスプレイ配置のシリアライズに失敗しました: {}", e))?;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(DISPLAY_LAYOUT_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+/// 保存済みの配置を開いているウィンドウに適用する（適用したウィンドウのラベルを返す）
+#[tauri::command]
+pub fn apply_display_layout(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    let layout = read_layout(&app_handle);
+    Ok(apply_layout(&app_handle, &layout, None))
+}
@@ -0,0 +1,180 @@
+// スマホから届くエモートの正規化と許可リスト
+// 古いコントローラーは任意の文字列を送れるため、許可したものだけをアニメーションへ流す
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::feature_flags;
+use crate::workspace::WorkspaceState;
+
+// 許可リストを保存する app_settings のキー
+const EMOTE_SETTINGS_KEY: &str = "emote_allowlist";
+// エモートは大勢から同時に届くため毎回DBを読まない
+const EMOTE_SETTINGS_TTL: Duration = Duration::from_secs(2);
+// 正規化前の文字列の上限（ログに残す長さも兼ねる）
+const MAX_EMOTE_CHARS: usize = 32;
+
+// 画面側（animationSettings.ts の textEmotes / svgEmotes とじゃんけん）で表示できるもの
+const DEFAULT_ALLOWED: [&str; 20] = [
+    "❤️", "⭐", "✨", "😊", "😂", "🥰", "😍", "🎵", "💖", "🌟", "🎈", "🌈", "💭", "✊", "✌️", "🖐",
+    "good", "Hello", "hi", "wow",
+];
+
+// コントローラーの別名（小文字で照合）
+const DEFAULT_ALIASES: [(&str, &str); 12] = [
+    ("happy", "😊"),
+    ("heart", "❤️"),
+    ("rock", "✊"),
+    ("gu", "✊"),
+    ("scissors", "✌️"),
+    ("choki", "✌️"),
+    ("✌", "✌️"),
+    ("paper", "🖐"),
+    ("hand", "🖐"),
+    ("pa", "🖐"),
+    ("hello", "Hello"),
+    ("star", "⭐"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmoteSettings {
+    // 別名 → 正規のエモート
+    pub aliases: BTreeMap<String, String>,
+    // 表示してよいエモート
+    pub allowed: Vec<String>,
+    // 許可されていないものの置き換え先（省略すると破棄）
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+impl Default for EmoteSettings {
+    fn default() -> Self {
+        Self {
+            aliases: DEFAULT_ALIASES
+                .iter()
+                .map(|(alias, emote)| (alias.to_string(), emote.to_string()))
+                .collect(),
+            allowed: DEFAULT_ALLOWED.iter().map(|e| e.to_string()).collect(),
+            fallback: None,
+        }
+    }
+}
+
+fn validate(settings: &EmoteSettings) -> Result<(), String> {
+    if settings.allowed.is_empty() {
+        return Err("許可するエモートを1つ以上指定してください".to_string());
+    }
+    if let Some(emote) = settings
+        .allowed
+        .iter()
+        .find(|e| e.trim().is_empty() || e.chars().count() > MAX_EMOTE_CHARS)
+    {
+        return Err(format!("エモートの指定が不正です: {:?}", emote));
+    }
+    if let Some((alias, emote)) = settings
+        .aliases
+        .iter()
+        .find(|(_, emote)| !settings.allowed.contains(emote))
+    {
+        return Err(format!(
+            "別名 {} の置き換え先 {} が許可リストにありません",
+            alias, emote
+        ));
+    }
+    if let Some(fallback) = &settings.fallback {
+        if !settings.allowed.contains(fallback) {
+            return Err(format!("置き換え先 {} が許可リストにありません", fallback));
+        }
+    }
+    Ok(())
+}
+
+static EMOTE_SETTINGS: Mutex<Option<(Instant, EmoteSettings)>> = Mutex::new(None);
+
+fn read_settings(app_handle: &AppHandle) -> EmoteSettings {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(EMOTE_SETTINGS_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<EmoteSettings>(&value).ok())
+        .filter(|settings| validate(settings).is_ok())
+        .unwrap_or_default()
+}
+
+fn settings(app_handle: &AppHandle) -> EmoteSettings {
+    if let Some((loaded_at, settings)) = EMOTE_SETTINGS.lock().unwrap().as_ref() {
+        if loaded_at.elapsed() < EMOTE_SETTINGS_TTL {
+            return settings.clone();
+        }
+    }
+    let settings = read_settings(app_handle);
+    *EMOTE_SETTINGS.lock().unwrap() = Some((Instant::now(), settings.clone()));
+    settings
+}
+
+/// 届いたエモートを表示用に正規化する。表示しないものは None（理由をログに残す）
+pub fn normalize(app_handle: &AppHandle, raw: &str, image_id: Option<&str>) -> Option<String> {
+    let trimmed = raw.trim();
+    let settings = settings(app_handle);
+    let emote = settings
+        .aliases
+        .get(&trimmed.to_lowercase())
+        .map(String::as_str)
+        .unwrap_or(trimmed);
+
+    // モデレーションを切っている会場では従来どおり別名の変換だけ行う
+    if !feature_flags::is_enabled(feature_flags::MODERATION) {
+        return (!emote.is_empty()).then(|| emote.to_string());
+    }
+    if settings.allowed.iter().any(|allowed| allowed == emote) {
+        return Some(emote.to_string());
+    }
+
+    let logged: String = trimmed.chars().take(MAX_EMOTE_CHARS).collect();
+    match &settings.fallback {
+        Some(fallback) => {
+            println!(
+                "[emote] mapped unknown emote {:?} to {:?} (imageId={:?})",
+                logged, fallback, image_id
+            );
+            Some(fallback.clone())
+        }
+        None => {
+            println!(
+                "[emote] rejected unknown emote {:?} (imageId={:?})",
+                logged, image_id
+            );
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_emote_allowlist(app_handle: AppHandle) -> Result<EmoteSettings, String> {
+    Ok(read_settings(&app_handle))
+}
+
+/// エモートの許可リストと別名を保存（すぐに反映される）
+#[tauri::command]
+pub fn set_emote_allowlist(
+    workspace: State<'_, WorkspaceState>,
+    settings: EmoteSettings,
+) -> Result<(), String> {
+    validate(&settings)?;
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("エモート設定のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(EMOTE_SETTINGS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    *EMOTE_SETTINGS.lock().unwrap() = Some((Instant::now(), settings));
+    Ok(())
+}
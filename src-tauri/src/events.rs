@@ -1,7 +1,12 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, Manager};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
 
-use crate::db::ImageMetadata;
+use crate::db::{BackgroundSettings, ControlSmoothing, Database, ImageMetadata, MovementSettings};
+use crate::ground_line::GroundLine;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImageUpsertedPayload {
@@ -12,6 +17,8 @@ pub struct ImageUpsertedPayload {
     pub created_at: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display_started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
 }
 
 impl From<&ImageMetadata> for ImageUpsertedPayload {
@@ -23,6 +30,7 @@ impl From<&ImageMetadata> for ImageUpsertedPayload {
             image_type: meta.image_type.clone(),
             created_at: meta.created_at.clone(),
             display_started_at: meta.display_started_at.clone(),
+            display_name: meta.display_name.clone(),
         }
     }
 }
@@ -37,9 +45,63 @@ pub struct AudioUpdatedPayload {
     pub audio_type: String,
 }
 
+// 変更された背景とその表示設定（削除時などは両方None）
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BackgroundChangedPayload {
+    pub background_id: Option<String>,
+    pub settings: Option<BackgroundSettings>,
+}
+
+impl BackgroundChangedPayload {
+    // 背景IDから保存済みの表示設定（未設定なら既定値）を添える
+    pub fn for_background(db: &Database, background_id: &str) -> Self {
+        let settings = db
+            .get_background_settings(background_id)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| BackgroundSettings::default_for(background_id));
+        Self {
+            background_id: Some(background_id.to_string()),
+            settings: Some(settings),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AnimationSettingsChangedPayload {
     pub image_id: String,
+    // 画像の動きタイプに対応する操作のなめらかさ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<ControlSmoothing>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnimationSettingsBatchChangedPayload {
+    pub image_ids: Vec<String>,
+    // なめらかさ設定の変更による通知の場合のみ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<ControlSmoothing>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageWithSettingsSavedPayload {
+    pub image: ImageUpsertedPayload,
+    pub movement_type: String,
+    pub movement_pattern: String,
+    pub speed: f32,
+    pub size: String,
+}
+
+impl ImageWithSettingsSavedPayload {
+    pub fn new(meta: &ImageMetadata, settings: &MovementSettings) -> Self {
+        Self {
+            image: ImageUpsertedPayload::from(meta),
+            movement_type: settings.movement_type.clone(),
+            movement_pattern: settings.movement_pattern.clone(),
+            speed: settings.speed,
+            size: settings.size.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -47,6 +109,14 @@ pub struct GroundPositionChangedPayload {
     pub position: i32,
 }
 
+// 地面ラインの変更（削除時はlineがNone）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroundLineChangedPayload {
+    pub monitor_id: String,
+    pub background_id: Option<String>,
+    pub line: Option<GroundLine>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeletionTimeChangedPayload {
     pub time: String,
@@ -56,6 +126,9 @@ pub struct DeletionTimeChangedPayload {
 pub struct AppSettingChangedPayload {
     pub key: String,
     pub value: String,
+    // 保存後の設定バージョン（飛びがあれば受信側が get_settings_delta で追いつく）
+    #[serde(default)]
+    pub version: i64,
 }
 
 // データ変更イベントの種類（serdeで type/payload 形式に）
@@ -69,24 +142,272 @@ pub enum DataChangeEvent {
     #[serde(rename = "audio-updated")]
     AudioUpdated(AudioUpdatedPayload),
     #[serde(rename = "background-changed")]
-    BackgroundChanged,
+    BackgroundChanged(BackgroundChangedPayload),
     #[serde(rename = "animation-settings-changed")]
     AnimationSettingsChanged(AnimationSettingsChangedPayload),
+    #[serde(rename = "animation-settings-batch-changed")]
+    AnimationSettingsBatchChanged(AnimationSettingsBatchChangedPayload),
+    #[serde(rename = "image-with-settings-saved")]
+    ImageWithSettingsSaved(ImageWithSettingsSavedPayload),
     #[serde(rename = "ground-position-changed")]
     GroundPositionChanged(GroundPositionChangedPayload),
+    #[serde(rename = "ground-line-changed")]
+    GroundLineChanged(GroundLineChangedPayload),
     #[serde(rename = "deletion-time-changed")]
     DeletionTimeChanged(DeletionTimeChangedPayload),
     #[serde(rename = "app-setting-changed")]
     AppSettingChanged(AppSettingChangedPayload),
 }
 
+impl DataChangeEvent {
+    // serde の type と同じ名前（ルーティング表のキー）
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DataChangeEvent::ImageUpserted(_) => "image-upserted",
+            DataChangeEvent::ImageDeleted(_) => "image-deleted",
+            DataChangeEvent::AudioUpdated(_) => "audio-updated",
+            DataChangeEvent::BackgroundChanged(_) => "background-changed",
+            DataChangeEvent::AnimationSettingsChanged(_) => "animation-settings-changed",
+            DataChangeEvent::AnimationSettingsBatchChanged(_) => "animation-settings-batch-changed",
+            DataChangeEvent::ImageWithSettingsSaved(_) => "image-with-settings-saved",
+            DataChangeEvent::GroundPositionChanged(_) => "ground-position-changed",
+            DataChangeEvent::GroundLineChanged(_) => "ground-line-changed",
+            DataChangeEvent::DeletionTimeChanged(_) => "deletion-time-changed",
+            DataChangeEvent::AppSettingChanged(_) => "app-setting-changed",
+        }
+    }
+}
+
+// イベントの種類ごとの送り先ウィンドウ（ラベル。"*" はすべて、"prefix-*" は前方一致）
+// 表に無いイベントはすべてのウィンドウへ送る
+const ALL_WINDOWS: &str = "*";
+const DISPLAY_WINDOWS: &[&str] = &["main", "animation", "animation-*"];
+const MAIN_ONLY: &[&str] = &["main"];
+const ANIMATION_WINDOWS: &[&str] = &["animation", "animation-*"];
+
+static ROUTES: Lazy<RwLock<HashMap<String, Vec<String>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// 既定の送り先を登録（アプリ起動時に一度だけ呼ぶ）
+pub fn register_default_routes() {
+    let defaults: &[(&str, &[&str])] = &[
+        // アニメーション表示にだけ関係する変更
+        ("ground-position-changed", DISPLAY_WINDOWS),
+        ("ground-line-changed", DISPLAY_WINDOWS),
+        ("deletion-time-changed", DISPLAY_WINDOWS),
+        ("animation-settings-changed", DISPLAY_WINDOWS),
+        ("animation-settings-batch-changed", DISPLAY_WINDOWS),
+        ("audio-updated", DISPLAY_WINDOWS),
+        ("background-changed", DISPLAY_WINDOWS),
+        ("display-expiring", DISPLAY_WINDOWS),
+        ("takeaway-qr", DISPLAY_WINDOWS),
+        ("pause-display", DISPLAY_WINDOWS),
+        ("blackout-display", DISPLAY_WINDOWS),
+        ("resume-display", DISPLAY_WINDOWS),
+        // 複製表示のウィンドウへ位置を揃えるための通知
+        ("scene-state-updated", ANIMATION_WINDOWS),
+        // 管理画面（メインウィンドウ）向けの通知
+        ("auto-import-started", MAIN_ONLY),
+        ("auto-import-complete", MAIN_ONLY),
+        ("auto-import-error", MAIN_ONLY),
+        ("duplicate-skipped", MAIN_ONLY),
+        ("folder-watch-error", MAIN_ONLY),
+        ("folder-watch-recovered", MAIN_ONLY),
+        ("image-processing-progress", MAIN_ONLY),
+        ("image-downscaled", MAIN_ONLY),
+        ("image-rejected", MAIN_ONLY),
+        ("upload-received", MAIN_ONLY),
+        ("storage-warning", MAIN_ONLY),
+        ("retention-applied", MAIN_ONLY),
+        ("resources-reaped", MAIN_ONLY),
+        ("workspace-sync-warning", MAIN_ONLY),
+        ("highlights-exported", MAIN_ONLY),
+        ("highlights-error", MAIN_ONLY),
+        ("recording-progress", MAIN_ONLY),
+        ("qr-batch-progress", MAIN_ONLY),
+        ("sidecar-status", MAIN_ONLY),
+        ("clock-skew-detected", MAIN_ONLY),
+        ("relay-client-status", MAIN_ONLY),
+        ("ws-metrics", MAIN_ONLY),
+        ("controller-latency", MAIN_ONLY),
+    ];
+    for (event, labels) in defaults {
+        register_route(event, labels);
+    }
+}
+
+/// イベントの送り先を登録（既存の登録は置き換える）
+pub fn register_route(event: &str, labels: &[&str]) {
+    if let Ok(mut routes) = ROUTES.write() {
+        routes.insert(
+            event.to_string(),
+            labels.iter().map(|label| label.to_string()).collect(),
+        );
+    }
+}
+
+fn label_matches(pattern: &str, label: &str) -> bool {
+    if pattern == ALL_WINDOWS {
+        return true;
+    }
+    match pattern.strip_suffix('*') {
+        Some(prefix) => label.starts_with(prefix),
+        None => pattern == label,
+    }
+}
+
+/// 指定したウィンドウがイベントを受け取るか
+pub fn routes_to(event: &str, label: &str) -> bool {
+    let Ok(routes) = ROUTES.read() else {
+        return true;
+    };
+    match routes.get(event) {
+        Some(patterns) => patterns.iter().any(|pattern| label_matches(pattern, label)),
+        None => true,
+    }
+}
+
+// サポート用に直近のイベント名を残す（内容は個人情報を含みうるため記録しない）
+const EVENT_LOG_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLogEntry {
+    pub timestamp: String,
+    pub event: String,
+}
+
+static EVENT_LOG: Lazy<Mutex<VecDeque<EventLogEntry>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(EVENT_LOG_CAPACITY)));
+
+fn record_event(event: &str) {
+    if let Ok(mut log) = EVENT_LOG.lock() {
+        if log.len() >= EVENT_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(EventLogEntry {
+            timestamp: crate::db::current_timestamp(),
+            event: event.to_string(),
+        });
+    }
+}
+
+/// 直近のイベント記録（新しい順）
+pub fn recent_events(limit: usize) -> Vec<EventLogEntry> {
+    EVENT_LOG
+        .lock()
+        .map(|log| log.iter().rev().take(limit).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// ルーティング表に従ってイベントを送る
+pub fn emit_routed<S: Serialize + Clone>(
+    app_handle: &AppHandle,
+    event: &str,
+    payload: S,
+) -> Result<(), String> {
+    record_event(event);
+    let mut errors = Vec::new();
+    // Window::emit は全ウィンドウへ送られるため、送り先のウィンドウを指定して送る
+    for label in app_handle.webview_windows().into_keys() {
+        if !routes_to(event, &label) {
+            continue;
+        }
+        if let Err(err) = app_handle.emit_to(
+            EventTarget::webview_window(label.as_str()),
+            event,
+            payload.clone(),
+        ) {
+            errors.push(format!("{}: {}", label, err));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to emit {}: {}", event, errors.join(", ")))
+    }
+}
+
+// 画像の追加・削除はまとめて取り込むと連続で届くため、この時間内のものを1回の通知にまとめる
+const COALESCE_WINDOW: Duration = Duration::from_millis(50);
+
+struct PendingBatch {
+    app_handle: AppHandle,
+    events: Vec<DataChangeEvent>,
+}
+
+static PENDING: Lazy<Mutex<Option<PendingBatch>>> = Lazy::new(|| Mutex::new(None));
+
+fn is_coalescable(event: &DataChangeEvent) -> bool {
+    matches!(
+        event,
+        DataChangeEvent::ImageUpserted(_) | DataChangeEvent::ImageDeleted(_)
+    )
+}
+
+// ウィンドウごとに送り先に含まれる変更だけを送る（1件なら従来どおり data-changed、複数なら data-changed-batch）
+fn broadcast(app_handle: &AppHandle, events: &[DataChangeEvent]) {
+    for event in events {
+        record_event(event.event_type());
+    }
+    for label in app_handle.webview_windows().into_keys() {
+        let targeted: Vec<&DataChangeEvent> = events
+            .iter()
+            .filter(|event| routes_to(event.event_type(), &label))
+            .collect();
+        let target = EventTarget::webview_window(label.as_str());
+        let result = match targeted.as_slice() {
+            [] => continue,
+            [event] => app_handle.emit_to(target, "data-changed", event),
+            _ => app_handle.emit_to(target, "data-changed-batch", &targeted),
+        };
+        if let Err(err) = result {
+            eprintln!("[Rust] Failed to emit event to window {}: {}", label, err);
+        }
+    }
+}
+
+// 溜まっている通知を送る
+fn flush_pending() {
+    let Some(batch) = PENDING.lock().ok().and_then(|mut pending| pending.take()) else {
+        return;
+    };
+    match batch.events.as_slice() {
+        [] => return,
+        [event] => println!("[Rust] Emitting event to windows: {:?}", event),
+        events => println!(
+            "[Rust] Emitting {} coalesced events to windows",
+            events.len()
+        ),
+    }
+    broadcast(&batch.app_handle, &batch.events);
+}
+
 // イベント発行関数（全ウィンドウへブロードキャスト）
 pub fn emit_data_change(app_handle: &AppHandle, event: DataChangeEvent) -> Result<(), String> {
-    println!("[Rust] Emitting event to all windows: {:?}", event);
+    if !is_coalescable(&event) {
+        // 先に溜まっている分を送り、イベントの順序を保つ
+        flush_pending();
+        println!("[Rust] Emitting event to windows: {:?}", event);
+        broadcast(app_handle, &[event]);
+        return Ok(());
+    }
 
-    for (label, window) in app_handle.webview_windows() {
-        if let Err(err) = window.emit("data-changed", &event) {
-            eprintln!("[Rust] Failed to emit event to window {}: {}", label, err);
+    let Ok(mut pending) = PENDING.lock() else {
+        broadcast(app_handle, &[event]);
+        return Ok(());
+    };
+    match pending.as_mut() {
+        Some(batch) => batch.events.push(event),
+        None => {
+            *pending = Some(PendingBatch {
+                app_handle: app_handle.clone(),
+                events: vec![event],
+            });
+            std::thread::spawn(|| {
+                std::thread::sleep(COALESCE_WINDOW);
+                flush_pending();
+            });
         }
     }
 
@@ -100,9 +421,13 @@ pub fn emit_to_window(
     window_label: &str,
     event: DataChangeEvent,
 ) -> Result<(), String> {
-    if let Some(window) = app_handle.get_webview_window(window_label) {
-        window
-            .emit("data-changed", &event)
+    if app_handle.get_webview_window(window_label).is_some() {
+        app_handle
+            .emit_to(
+                EventTarget::webview_window(window_label),
+                "data-changed",
+                &event,
+            )
             .map_err(|e| format!("Failed to emit event to window: {}", e))
     } else {
         Err(format!("Window '{}' not found", window_label))
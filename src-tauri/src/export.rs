@@ -0,0 +1,296 @@
+use crate::db::{ImageMetadata, MovementSettings, WorkspaceInfo};
+use crate::export_crypto::{self, ExportCipher};
+use crate::workspace::WorkspaceState;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+// 文字列フィールドの扱い
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldPolicy {
+    #[default]
+    Keep,
+    // 出力しない
+    Strip,
+    // エクスポートごとのランダムIDに置き換え（元の値とは紐付かない）
+    Pseudonymize,
+}
+
+// 日時フィールドの扱い
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampPolicy {
+    #[default]
+    Keep,
+    // 日付（YYYY-MM-DD）まで丸める
+    CoarseDate,
+    Strip,
+}
+
+/// フィールド単位の匿名化ポリシー（省略時はそのまま出力）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonymizationPolicy {
+    #[serde(default)]
+    pub image_id: FieldPolicy,
+    #[serde(default)]
+    pub original_file_name: FieldPolicy,
+    #[serde(default)]
+    pub timestamps: TimestampPolicy,
+}
+
+impl AnonymizationPolicy {
+    // スポンサー/研究者向けの既定値
+    pub fn anonymous() -> Self {
+        Self {
+            image_id: FieldPolicy::Pseudonymize,
+            original_file_name: FieldPolicy::Strip,
+            timestamps: TimestampPolicy::CoarseDate,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedImage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub file_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_started_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movement_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movement_pattern: Option<String>,
+}
+
+// manifest.json に書くワークスペースの情報（画像と同じ匿名化ポリシーを適用する）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedWorkspace {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    pub app_version: String,
+    pub schema_version: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub exported: usize,
+    pub skipped: usize,
+    pub manifest_path: String,
+    pub encrypted: bool,
+}
+
+fn apply_field_policy(policy: FieldPolicy, value: &str) -> Option<String> {
+    match policy {
+        FieldPolicy::Keep => Some(value.to_string()),
+        FieldPolicy::Strip => None,
+        FieldPolicy::Pseudonymize => Some(Uuid::new_v4().to_string()),
+    }
+}
+
+fn apply_timestamp_policy(policy: TimestampPolicy, value: Option<&str>) -> Option<String> {
+    let value = value?;
+    match policy {
+        TimestampPolicy::Keep => Some(value.to_string()),
+        TimestampPolicy::CoarseDate => Some(
+            DateTime::parse_from_rfc3339(value)
+                .map(|dt| dt.date_naive().to_string())
+                .unwrap_or_else(|_| value.chars().take(10).collect()),
+        ),
+        TimestampPolicy::Strip => None,
+    }
+}
+
+fn anonymize_entry(
+    meta: &ImageMetadata,
+    movement: Option<&MovementSettings>,
+    policy: &AnonymizationPolicy,
+) -> ExportedImage {
+    let id = apply_field_policy(policy.image_id, &meta.id);
+    let extension = Path::new(&meta.saved_file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png");
+    // 出力ファイル名はIDポリシーに従う（ID削除時もランダム名にして元IDを出さない）
+    let file_name = match &id {
+        Some(id) => format!("{}.{}", id, extension),
+        None => format!("{}.{}", Uuid::new_v4(), extension),
+    };
+
+    ExportedImage {
+        id,
+        file_name,
+        original_file_name: apply_field_policy(policy.original_file_name, &meta.original_file_name),
+        created_at: apply_timestamp_policy(policy.timestamps, Some(&meta.created_at)),
+        display_started_at: apply_timestamp_policy(
+            policy.timestamps,
+            meta.display_started_at.as_deref(),
+        ),
+        movement_type: movement.map(|m| m.movement_type.clone()),
+        movement_pattern: movement.map(|m| m.movement_pattern.clone()),
+    }
+}
+
+// ワークスペースIDは画像IDと、表示名は元ファイル名と同じ扱いにする
+fn anonymize_workspace(info: &WorkspaceInfo, policy: &AnonymizationPolicy) -> ExportedWorkspace {
+    ExportedWorkspace {
+        workspace_id: apply_field_policy(policy.image_id, &info.workspace_id),
+        display_name: info
+            .display_name
+            .as_deref()
+            .and_then(|name| apply_field_policy(policy.original_file_name, name)),
+        created_at: apply_timestamp_policy(policy.timestamps, Some(&info.created_at)),
+        app_version: info.app_version.clone(),
+        schema_version: info.schema_version,
+    }
+}
+
+/// 処理済み画像と manifest.json を書き出す（anonymize指定時は匿名化ポリシーを適用）
+/// passphrase を指定すると各ファイルを暗号化して `.enc` で書き出す
+#[tauri::command]
+pub fn export_images(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    output_dir: String,
+    include_hidden: Option<bool>,
+    anonymize: Option<bool>,
+    policy: Option<AnonymizationPolicy>,
+    passphrase: Option<String>,
+) -> Result<ExportSummary, String> {
+    let policy = match (policy, anonymize.unwrap_or(false)) {
+        (Some(p), _) => p,
+        (None, true) => AnonymizationPolicy::anonymous(),
+        (None, false) => AnonymizationPolicy::default(),
+    };
+    let include_hidden = include_hidden.unwrap_or(false);
+    // 書き出し先は許可範囲の中だけ（ワークスペースの接続をロックする前に確認する）
+    let out_dir = crate::path_sandbox::check_write(&app_handle, &output_dir)?;
+
+    let (images, movements, workspace_info) = {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        let workspace_info = db.get_workspace_info().ok();
+        let images = db
+            .get_all_images()
+            .map_err(|e| format!("Failed to get images: {}", e))?;
+        let movements: HashMap<String, MovementSettings> = db
+            .get_all_movement_settings()
+            .map_err(|e| format!("Failed to get movement settings: {}", e))?
+            .into_iter()
+            .map(|m| (m.image_id.clone(), m))
+            .collect();
+        (images, movements, workspace_info)
+    };
+
+    fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let cipher = passphrase
+        .filter(|p| !p.is_empty())
+        .map(|p| ExportCipher::create(&p, &out_dir))
+        .transpose()?;
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for meta in images
+        .iter()
+        .filter(|m| m.image_type == "processed")
+        .filter(|m| include_hidden || m.is_hidden == 0)
+    {
+        let entry = anonymize_entry(meta, movements.get(&meta.id), &policy);
+        let source = meta.resolve_file_path();
+        let dest = out_dir.join(&entry.file_name);
+        let copied = match &cipher {
+            Some(cipher) => cipher.encrypt_file(&source, &dest),
+            None => fs::copy(&source, &dest)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        };
+        if let Err(e) = copied {
+            eprintln!("[export] skip {}: {}", source.display(), e);
+            skipped += 1;
+            continue;
+        }
+        entries.push(entry);
+    }
+
+    let manifest_path = out_dir.join("manifest.json");
+    let manifest = serde_json::json!({
+        // どのワークスペースから書き出したか（匿名化ポリシーを適用）
+        "workspace": workspace_info.as_ref().map(|info| anonymize_workspace(info, &policy)),
+        "policy": policy,
+        "images": entries,
+    });
+    let manifest_json =
+        serde_json::to_string_pretty(&manifest).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    // 元ファイル名などを含むため、暗号化時はマニフェストも暗号化する
+    let manifest_path = match &cipher {
+        Some(cipher) => {
+            cipher.write(&manifest_path, manifest_json.as_bytes())?;
+            manifest_path.with_extension(format!("json.{}", export_crypto::ENCRYPTED_EXTENSION))
+        }
+        None => {
+            fs::write(&manifest_path, manifest_json)
+                .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+            manifest_path
+        }
+    };
+
+    Ok(ExportSummary {
+        exported: entries.len(),
+        skipped,
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+        encrypted: cipher.is_some(),
+    })
+}
+
+/// 暗号化したエクスポートを復号して別のフォルダへ書き出し、復号したファイル数を返す
+#[tauri::command]
+pub fn decrypt_export(
+    app_handle: AppHandle,
+    input_dir: String,
+    output_dir: String,
+    passphrase: String,
+) -> Result<usize, String> {
+    let in_dir = crate::path_sandbox::check_read(&app_handle, &input_dir)?;
+    let out_dir = crate::path_sandbox::check_write(&app_handle, &output_dir)?;
+    if in_dir == out_dir {
+        return Err("復号先には暗号化したフォルダと別のフォルダを指定してください".to_string());
+    }
+    let cipher = ExportCipher::open(&passphrase, &in_dir)?;
+    fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let entries = fs::read_dir(&in_dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let mut decrypted = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(export_crypto::ENCRYPTED_EXTENSION) {
+            continue;
+        }
+        let Some(name) = path.file_stem() else {
+            continue;
+        };
+        let data = fs::read(&path).map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
+        let plain = cipher.decrypt(&data)?;
+        fs::write(out_dir.join(name), plain)
+            .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+        decrypted += 1;
+    }
+    Ok(decrypted)
+}
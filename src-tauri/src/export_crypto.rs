@@ -0,0 +1,142 @@
+// エクスポートのパスフレーズ暗号化（AES-256-GCM、鍵は PBKDF2-HMAC-SHA256 で導出）
+// 会場からUSBメモリ等で持ち出す作品を保護するためのもの
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::Path;
+
+// 暗号化したファイルの先頭に付ける識別子
+const MAGIC: &[u8] = b"NRMENC1";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 600_000;
+// 書き換えられた encryption.json で鍵導出が終わらなくならないよう、読み込み時の回数に上限を設ける
+const MAX_PBKDF2_ROUNDS: u32 = PBKDF2_ROUNDS * 10;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+// 暗号化の設定（鍵は含まない）を書き出すファイル名
+pub const PARAMS_FILE_NAME: &str = "encryption.json";
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptionParams {
+    cipher: String,
+    kdf: String,
+    rounds: u32,
+    // base64
+    salt: String,
+}
+
+pub struct ExportCipher {
+    cipher: Aes256Gcm,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], rounds: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+impl ExportCipher {
+    /// 新しいソルトで鍵を作り、出力先に encryption.json を書き出す
+    pub fn create(passphrase: &str, out_dir: &Path) -> Result<Self, String> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+            return Err(format!(
+                "パスフレーズは{}文字以上で指定してください",
+                MIN_PASSPHRASE_CHARS
+            ));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let params = EncryptionParams {
+            cipher: "aes-256-gcm".to_string(),
+            kdf: "pbkdf2-hmac-sha256".to_string(),
+            rounds: PBKDF2_ROUNDS,
+            salt: STANDARD.encode(salt),
+        };
+        fs::write(
+            out_dir.join(PARAMS_FILE_NAME),
+            serde_json::to_string_pretty(&params).map_err(|e| format!("JSON変換エラー: {}", e))?,
+        )
+        .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+
+        Ok(Self {
+            cipher: derive_key(passphrase, &salt, PBKDF2_ROUNDS),
+        })
+    }
+
+    /// 暗号化済みエクスポートの encryption.json から鍵を復元する
+    pub fn open(passphrase: &str, export_dir: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(export_dir.join(PARAMS_FILE_NAME))
+            .map_err(|e| format!("暗号化設定の読み込みに失敗しました: {}", e))?;
+        let params: EncryptionParams = serde_json::from_str(&text)
+            .map_err(|e| format!("暗号化設定の形式が不正です: {}", e))?;
+        if params.cipher != "aes-256-gcm"
+            || params.kdf != "pbkdf2-hmac-sha256"
+            || params.rounds == 0
+        {
+            return Err(format!(
+                "対応していない暗号方式です: {} / {}",
+                params.cipher, params.kdf
+            ));
+        }
+        if params.rounds > MAX_PBKDF2_ROUNDS {
+            return Err(format!(
+                "鍵導出の回数が多すぎます: {}（上限 {}）",
+                params.rounds, MAX_PBKDF2_ROUNDS
+            ));
+        }
+        let salt = STANDARD
+            .decode(params.salt.trim())
+            .map_err(|e| format!("Failed to decode base64: {}", e))?;
+        Ok(Self {
+            cipher: derive_key(passphrase, &salt, params.rounds),
+        })
+    }
+
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let sealed = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_| "暗号化に失敗しました".to_string())?;
+
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let body = data
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() > NONCE_LEN)
+            .ok_or_else(|| "暗号化されたファイルではありません".to_string())?;
+        let (nonce, sealed) = body.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| {
+                "復号に失敗しました（パスフレーズが違うか、ファイルが壊れています）".to_string()
+            })
+    }
+
+    /// ファイルを読み込んで暗号化し、`<dest>.enc` として書き出す
+    pub fn encrypt_file(&self, source: &Path, dest: &Path) -> Result<(), String> {
+        let plain = fs::read(source).map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
+        self.write(dest, &plain)
+    }
+
+    pub fn write(&self, dest: &Path, plain: &[u8]) -> Result<(), String> {
+        let mut name = dest.as_os_str().to_owned();
+        name.push(".");
+        name.push(ENCRYPTED_EXTENSION);
+        fs::write(name, self.encrypt(plain)?).map_err(|e| format!("ファイル書き込みエラー: {}", e))
+    }
+}
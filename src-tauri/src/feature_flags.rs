@@ -0,0 +1,160 @@
+// 機能フラグ（会場ごとに危険な機能を段階的に有効化するためのもの）
+// 優先順位: Relayからの指定 > この端末の設定 > 既定値
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use tauri::AppHandle;
+
+use crate::workspace::{read_global_setting, write_global_setting};
+
+// この端末での上書き（グローバル設定、JSONの {name: bool}）
+const LOCAL_FLAGS_KEY: &str = "feature_flags";
+// Relayから最後に受け取った上書き（オフラインで再起動しても保つ）
+const REMOTE_FLAGS_KEY: &str = "feature_flags_remote";
+
+pub const RELAY_MODE: &str = "relay_mode";
+pub const UPLOAD_ENDPOINT: &str = "upload_endpoint";
+pub const MODERATION: &str = "moderation";
+
+// 既知のフラグと既定値
+const DEFAULTS: [(&str, bool); 3] = [
+    (RELAY_MODE, true),
+    (UPLOAD_ENDPOINT, true),
+    (MODERATION, true),
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Default,
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FeatureFlagState {
+    pub enabled: bool,
+    pub source: FlagSource,
+}
+
+#[derive(Default)]
+struct Overrides {
+    local: HashMap<String, bool>,
+    remote: HashMap<String, bool>,
+}
+
+static OVERRIDES: Lazy<RwLock<Overrides>> = Lazy::new(|| RwLock::new(Overrides::default()));
+
+fn is_known(name: &str) -> bool {
+    DEFAULTS.iter().any(|(known, _)| *known == name)
+}
+
+fn read_map(app_handle: &AppHandle, key: &str) -> HashMap<String, bool> {
+    read_global_setting(app_handle, key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str::<HashMap<String, bool>>(&value).ok())
+        .unwrap_or_default()
+}
+
+fn write_map(app_handle: &AppHandle, key: &str, map: &HashMap<String, bool>) -> Result<(), String> {
+    let value = serde_json::to_string(map)
+        .map_err(|e| format!("機能フラグのシリアライズに失敗しました: {}", e))?;
+    write_global_setting(app_handle, key, &value)
+}
+
+/// 起動時に保存済みの上書きを読み込む
+pub fn load(app_handle: &AppHandle) {
+    let mut overrides = OVERRIDES.write().unwrap();
+    overrides.local = read_map(app_handle, LOCAL_FLAGS_KEY);
+    overrides.remote = read_map(app_handle, REMOTE_FLAGS_KEY);
+}
+
+fn resolve(overrides: &Overrides, name: &str, default: bool) -> FeatureFlagState {
+    if let Some(enabled) = overrides.remote.get(name) {
+        return FeatureFlagState {
+            enabled: *enabled,
+            source: FlagSource::Remote,
+        };
+    }
+    if let Some(enabled) = overrides.local.get(name) {
+        return FeatureFlagState {
+            enabled: *enabled,
+            source: FlagSource::Local,
+        };
+    }
+    FeatureFlagState {
+        enabled: default,
+        source: FlagSource::Default,
+    }
+}
+
+/// 各機能から参照する（未知のフラグは無効）
+pub fn is_enabled(name: &str) -> bool {
+    let Some((_, default)) = DEFAULTS.iter().find(|(known, _)| *known == name) else {
+        return false;
+    };
+    resolve(&OVERRIDES.read().unwrap(), name, *default).enabled
+}
+
+pub fn snapshot() -> BTreeMap<String, FeatureFlagState> {
+    let overrides = OVERRIDES.read().unwrap();
+    DEFAULTS
+        .iter()
+        .map(|(name, default)| (name.to_string(), resolve(&overrides, name, *default)))
+        .collect()
+}
+
+fn notify(app_handle: &AppHandle) {
+    crate::relay_client::stop_if_disabled(app_handle);
+    let _ = crate::events::emit_routed(app_handle, "feature-flags-changed", snapshot());
+}
+
+/// Relayから届いた上書きを反映（前回と同じなら何もしない）
+pub fn apply_remote(app_handle: &AppHandle, flags: HashMap<String, bool>) {
+    let flags: HashMap<String, bool> = flags
+        .into_iter()
+        .filter(|(name, _)| is_known(name))
+        .collect();
+    {
+        let mut overrides = OVERRIDES.write().unwrap();
+        if overrides.remote == flags {
+            return;
+        }
+        overrides.remote = flags.clone();
+    }
+    println!("[feature_flags] remote overrides: {:?}", flags);
+    if let Err(e) = write_map(app_handle, REMOTE_FLAGS_KEY, &flags) {
+        eprintln!("[feature_flags] {}", e);
+    }
+    notify(app_handle);
+}
+
+#[tauri::command]
+pub fn get_feature_flags() -> Result<BTreeMap<String, FeatureFlagState>, String> {
+    Ok(snapshot())
+}
+
+/// この端末での上書きを設定（enabled を省略すると既定値に戻す）
+#[tauri::command]
+pub fn set_feature_flag(
+    app_handle: AppHandle,
+    name: String,
+    enabled: Option<bool>,
+) -> Result<BTreeMap<String, FeatureFlagState>, String> {
+    if !is_known(&name) {
+        return Err(format!("未知の機能フラグです: {}", name));
+    }
+    let local = {
+        let mut overrides = OVERRIDES.write().unwrap();
+        match enabled {
+            Some(enabled) => overrides.local.insert(name.clone(), enabled),
+            None => overrides.local.remove(&name),
+        };
+        overrides.local.clone()
+    };
+    write_map(&app_handle, LOCAL_FLAGS_KEY, &local)?;
+    notify(&app_handle);
+    Ok(snapshot())
+}
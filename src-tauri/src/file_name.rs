@@ -0,0 +1,243 @@
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::workspace::{read_global_setting, WorkspaceState};
+
+// 保存ファイル名の上限（バイト数、拡張子込み）
+const MAX_SAVED_NAME_BYTES: usize = 150;
+
+/// 表示用の元ファイル名（NFCに正規化するだけで文字は変えない）
+pub fn normalize(name: &str) -> String {
+    name.nfc().collect()
+}
+
+fn is_unsafe(c: char) -> bool {
+    c.is_control()
+        || c.is_whitespace()
+        || c == '\u{FFFD}'
+        || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+}
+
+// 拡張子を残したまま上限バイト数に収める
+fn truncate_keep_extension(name: &str) -> String {
+    if name.len() <= MAX_SAVED_NAME_BYTES {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos..]),
+        _ => (name, ""),
+    };
+    let budget = MAX_SAVED_NAME_BYTES.saturating_sub(ext.len());
+    let mut truncated = String::new();
+    for c in stem.chars() {
+        if truncated.len() + c.len_utf8() > budget {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push_str(ext);
+    truncated
+}
+
+/// 保存用のファイル名
+/// 全角英数字は半角へ（NFKC）、パスを壊す文字や空白は "_" に置き換える
+pub fn sanitize_for_storage(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    let mut last_replaced = false;
+    for c in name.nfkc() {
+        if is_unsafe(c) {
+            // 置換した文字が続く場合は1つにまとめる
+            if !last_replaced {
+                sanitized.push('_');
+            }
+            last_replaced = true;
+        } else {
+            sanitized.push(c);
+            last_replaced = false;
+        }
+    }
+
+    // 先頭のドット（隠しファイル化）と末尾のドット（Windowsで不可）を除く
+    let trimmed = sanitized.trim_start_matches('.').trim_end_matches('.');
+    if trimmed.is_empty() {
+        return "image".to_string();
+    }
+    truncate_keep_extension(trimmed)
+}
+
+/// 保存用ファイル名を生成（フロントエンドで保存前に呼ぶ）
+#[tauri::command]
+pub fn sanitize_file_name(name: String) -> String {
+    sanitize_for_storage(&name)
+}
+
+// 保存ファイル名のテンプレート（ワークスペースごと）
+const NAMING_TEMPLATE_KEY: &str = "file_naming_template";
+// 日付ごとの連番 "YYYYMMDD:N"
+const NAMING_SEQUENCE_KEY: &str = "file_naming_sequence";
+// 端末ID（未設定ならpcidを使う）
+const STATION_ID_KEY: &str = "station_id";
+// 従来どおりの `{uuid}.png`
+const DEFAULT_TEMPLATE: &str = "{uuid}";
+const TEMPLATE_TOKENS: [&str; 6] = [
+    "{date}",
+    "{time}",
+    "{seq}",
+    "{station}",
+    "{original}",
+    "{uuid}",
+];
+
+fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("ファイル名のテンプレートが空です".to_string());
+    }
+    // 置換後に残る { } は未知のトークン
+    let mut rest = template.to_string();
+    for token in TEMPLATE_TOKENS {
+        rest = rest.replace(token, "");
+    }
+    if rest.contains('{') || rest.contains('}') {
+        return Err(format!(
+            "使用できないトークンが含まれています（使用可: {}）",
+            TEMPLATE_TOKENS.join(" ")
+        ));
+    }
+    Ok(())
+}
+
+fn station_id(app_handle: &AppHandle) -> String {
+    [STATION_ID_KEY, "pcid"]
+        .iter()
+        .find_map(|key| {
+            read_global_setting(app_handle, key)
+                .ok()
+                .flatten()
+                .filter(|v| !v.trim().is_empty())
+        })
+        .unwrap_or_else(|| "station".to_string())
+}
+
+// テンプレートと当日の連番を取得（連番はテンプレートで使う場合のみ進める）
+fn template_and_sequence(app_handle: &AppHandle, date: &str) -> (String, u32) {
+    let state: State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = state.lock() else {
+        return (DEFAULT_TEMPLATE.to_string(), 0);
+    };
+    let Ok(db) = conn.get() else {
+        return (DEFAULT_TEMPLATE.to_string(), 0);
+    };
+    let template = db
+        .get_app_setting(NAMING_TEMPLATE_KEY)
+        .ok()
+        .flatten()
+        .filter(|t| validate_template(t).is_ok())
+        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+    if !template.contains("{seq}") {
+        return (template, 0);
+    }
+
+    let last = db
+        .get_app_setting(NAMING_SEQUENCE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| {
+            let (day, n) = value.split_once(':')?;
+            if day == date {
+                n.parse::<u32>().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(0);
+    let next = last + 1;
+    if let Err(e) = db.save_internal_setting(NAMING_SEQUENCE_KEY, &format!("{}:{}", date, next)) {
+        eprintln!("[file_name] failed to save sequence: {}", e);
+    }
+    (template, next)
+}
+
+/// 設定のテンプレートから保存ファイル名を生成する（dir内で重複しないよう連番を付ける）
+pub fn saved_name(
+    app_handle: &AppHandle,
+    dir: &Path,
+    image_id: &str,
+    original_file_name: &str,
+    extension: &str,
+) -> String {
+    let now = chrono::Local::now();
+    let date = now.format("%Y%m%d").to_string();
+    let (template, sequence) = template_and_sequence(app_handle, &date);
+
+    let original_stem = Path::new(original_file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = template
+        .replace("{date}", &date)
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{seq}", &format!("{:04}", sequence))
+        .replace("{station}", &station_id(app_handle))
+        .replace("{original}", &original_stem)
+        .replace("{uuid}", image_id);
+    let base = sanitize_for_storage(&format!("{}.{}", stem, extension));
+
+    if !dir.join(&base).exists() {
+        return base;
+    }
+    let (base_stem, ext) = base.rsplit_once('.').unwrap_or((base.as_str(), extension));
+    (2..)
+        .map(|n| sanitize_for_storage(&format!("{}-{}.{}", base_stem, n, ext)))
+        .find(|name| !dir.join(name).exists())
+        .unwrap_or(base)
+}
+
+/// 保存ファイル名を生成（フロントエンドでの保存用、dirは保存先ディレクトリ）
+#[tauri::command]
+pub fn generate_saved_file_name(
+    app_handle: AppHandle,
+    dir: String,
+    image_id: String,
+    original_file_name: String,
+    extension: Option<String>,
+) -> String {
+    let extension = extension
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "png".to_string());
+    saved_name(
+        &app_handle,
+        Path::new(&dir),
+        &image_id,
+        &original_file_name,
+        &extension,
+    )
+}
+
+#[tauri::command]
+pub fn get_file_naming_template(workspace: State<'_, WorkspaceState>) -> Result<String, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    Ok(db
+        .get_app_setting(NAMING_TEMPLATE_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()))
+}
+
+/// 例: "{date}-{seq}-{station}-{original}"
+#[tauri::command]
+pub fn set_file_naming_template(
+    workspace: State<'_, WorkspaceState>,
+    template: String,
+) -> Result<(), String> {
+    let template = template.trim().to_string();
+    validate_template(&template)?;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(NAMING_TEMPLATE_KEY, &template)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
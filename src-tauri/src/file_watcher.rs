@@ -1,36 +1,145 @@
-use crate::db::{current_timestamp, ImageMetadata as DbImageMetadata};
-use crate::events::{emit_data_change, DataChangeEvent};
+use crate::db::{current_timestamp, ImageMetadata as DbImageMetadata, MovementSettings};
+use crate::events::{
+    emit_data_change, AudioUpdatedPayload, BackgroundChangedPayload, DataChangeEvent,
+    ImageUpsertedPayload, ImageWithSettingsSavedPayload,
+};
 use crate::workspace::WorkspaceState;
 use base64::{engine::general_purpose, Engine as _};
+use globset::{GlobSet, GlobSetBuilder};
+use notify::event::ModifyKind;
 use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{channel, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use tauri::{AppHandle, Emitter, Manager};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
 // グローバルなwatcher管理
 struct WatcherState {
     watcher_thread: Option<JoinHandle<()>>,
     stop_sender: Option<Sender<()>>,
+    watch_path: Option<String>,
+    recursive: bool,
 }
 
 static WATCHER_STATE: Lazy<Arc<Mutex<WatcherState>>> = Lazy::new(|| {
     Arc::new(Mutex::new(WatcherState {
         watcher_thread: None,
         stop_sender: None,
+        watch_path: None,
+        recursive: false,
     }))
 });
 
+// 処理中の自動取り込み件数
+static IMPORTS_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// 監視スレッドが更新する実行状態（WATCHER_STATE は停止時に join で握るため分ける）
+#[derive(Debug, Clone, Default)]
+struct WatchRuntime {
+    // "running" / "waiting"（フォルダが見つからず再接続待ち） / "stopped"
+    state: &'static str,
+    last_event_at: Option<String>,
+    processed_count: u64,
+    last_error: Option<String>,
+}
+
+static WATCH_RUNTIME: Lazy<Mutex<WatchRuntime>> = Lazy::new(|| {
+    Mutex::new(WatchRuntime {
+        state: "stopped",
+        ..Default::default()
+    })
+});
+
+fn update_runtime(f: impl FnOnce(&mut WatchRuntime)) {
+    if let Ok(mut runtime) = WATCH_RUNTIME.lock() {
+        f(&mut runtime);
+    }
+}
+
+// 監視フォルダが見えなくなったときの確認間隔と、再接続を試す間隔
+const PATH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const RECOVER_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WatcherStatus {
+    pub watching: bool,
+    pub watch_path: Option<String>,
+    pub recursive: bool,
+    pub imports_in_flight: usize,
+    pub state: String,
+    pub last_event_at: Option<String>,
+    pub processed_count: u64,
+    pub last_error: Option<String>,
+}
+
+pub fn watcher_status() -> WatcherStatus {
+    let runtime = WATCH_RUNTIME.lock().unwrap().clone();
+    let state = WATCHER_STATE.lock().unwrap();
+    WatcherStatus {
+        watching: state.watcher_thread.is_some(),
+        watch_path: state.watch_path.clone(),
+        recursive: state.recursive,
+        imports_in_flight: IMPORTS_IN_FLIGHT.load(Ordering::SeqCst),
+        state: runtime.state.to_string(),
+        last_event_at: runtime.last_event_at,
+        processed_count: runtime.processed_count,
+        last_error: runtime.last_error,
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderWatchError {
+    pub watch_path: String,
+    pub error: String,
+}
+
+// 監視できなくなったことを通知し、再接続待ちにする
+fn report_watch_error(app_handle: &AppHandle, watch_path: &str, error: String) {
+    eprintln!("[file_watcher] {}: {}", watch_path, error);
+    update_runtime(|runtime| {
+        runtime.state = "waiting";
+        runtime.last_error = Some(error.clone());
+    });
+    let _ = crate::events::emit_routed(
+        app_handle,
+        "folder-watch-error",
+        FolderWatchError {
+            watch_path: watch_path.to_string(),
+            error,
+        },
+    );
+}
+
+// フォルダが戻るまで待つ。停止を指示されたら false
+fn wait_for_path(root: &Path, stop_rx: &Receiver<()>) -> bool {
+    loop {
+        match stop_rx.recv_timeout(RECOVER_INTERVAL) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => return false,
+        }
+        if root.is_dir() {
+            return true;
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AutoImportStarted {
     pub image_id: String,
     pub original_path: String,
+    // サブフォルダのルーティングで付けたタグ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,12 +148,90 @@ pub struct AutoImportResult {
     pub original_path: String,
     pub processed_path: String,
     pub animation_settings: AnimationSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AutoImportError {
     pub image_id: String,
     pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+// 同じ内容のファイルが取り込み済み（または処理中）だったため飛ばした
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateSkipped {
+    pub original_path: String,
+    pub content_hash: String,
+    // 取り込み済みの画像（処理中の場合はNone）
+    pub existing_image_id: Option<String>,
+}
+
+// 処理中の取り込みの内容ハッシュ（DBへ登録されるまでの重複を防ぐ）
+static HASHES_IN_FLIGHT: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn content_hash(path: &Path) -> Result<String, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image file: {}", e))?;
+    let digest = Sha256::digest(&data);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn existing_image_for_hash(app_handle: &AppHandle, content_hash: &str) -> Option<String> {
+    let state = app_handle.try_state::<WorkspaceState>()?;
+    let conn = state.lock().ok()?;
+    let db = conn.get().ok()?;
+    db.find_image_by_content_hash(content_hash).ok().flatten()
+}
+
+// 取り込み済み・処理中の内容なら duplicate-skipped を通知して true を返す
+// false のときはハッシュを処理中として予約する（finish_hash で解除）
+fn skip_duplicate(app_handle: &AppHandle, image_path: &Path, content_hash: &str) -> bool {
+    let existing_image_id = existing_image_for_hash(app_handle, content_hash);
+    let in_flight = existing_image_id.is_none()
+        && !HASHES_IN_FLIGHT
+            .lock()
+            .unwrap()
+            .insert(content_hash.to_string());
+    if existing_image_id.is_none() && !in_flight {
+        return false;
+    }
+    println!(
+        "[file_watcher] duplicate skipped: {:?} (existing={:?})",
+        image_path, existing_image_id
+    );
+    let _ = crate::events::emit_routed(
+        app_handle,
+        "duplicate-skipped",
+        DuplicateSkipped {
+            original_path: image_path.to_string_lossy().to_string(),
+            content_hash: content_hash.to_string(),
+            existing_image_id,
+        },
+    );
+    true
+}
+
+// 登録できた画像にハッシュを記録し、処理中の予約を外す
+fn finish_hash(app_handle: &AppHandle, image_id: Option<&str>, content_hash: &str) {
+    if let Some(image_id) = image_id {
+        let recorded = app_handle
+            .try_state::<WorkspaceState>()
+            .ok_or("ワークスペースが選択されていません".to_string())
+            .and_then(|state| {
+                let conn = state
+                    .lock()
+                    .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+                let db = conn.get()?;
+                db.set_image_content_hash(image_id, content_hash)
+                    .map_err(|e| format!("Failed to save content hash: {}", e))
+            });
+        if let Err(e) = recorded {
+            eprintln!("[file_watcher] {}", e);
+        }
+    }
+    HASHES_IN_FLIGHT.lock().unwrap().remove(content_hash);
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,86 +239,715 @@ pub struct AnimationSettings {
     pub animation_type: String,
     pub speed: f32,
     pub size: f32,
+    // "fly" / "walk"（省略時は fly で登録）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movement_type: Option<String>,
+}
+
+// 取り込みプロファイルを保存する app_settings のキー
+const IMPORT_PROFILE_KEY: &str = "folder_watch_profile";
+
+const WALK_PATTERNS: [&str; 3] = ["normal", "slow", "fast"];
+const FLY_PATTERNS: [&str; 4] = ["float", "bounce", "rotate", "swim"];
+
+/// フォルダ監視ごとの取り込み方（ブースごとに動きや大きさを変える）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImportProfile {
+    // "fly" / "walk"（省略時は取り込みごとにランダム）
+    pub movement_type: Option<String>,
+    // 動きのパターン候補（空なら movement_type に合うものすべて）
+    pub patterns: Vec<String>,
+    pub speed_min: f32,
+    pub speed_max: f32,
+    pub size_min: f32,
+    pub size_max: f32,
+    // false なら背景除去をせずにそのまま登録する（完成済みのイラスト向け）
+    pub remove_background: bool,
+    // 取り込み通知に付けるタグ（サブフォルダの振り分けがあればそちらを優先）
+    pub tag: Option<String>,
+}
+
+impl Default for ImportProfile {
+    fn default() -> Self {
+        Self {
+            movement_type: None,
+            patterns: Vec::new(),
+            speed_min: 0.5,
+            speed_max: 1.5,
+            size_min: 0.8,
+            size_max: 1.2,
+            remove_background: true,
+            tag: None,
+        }
+    }
+}
+
+impl ImportProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        let allowed: Vec<&str> = match self.movement_type.as_deref() {
+            None => WALK_PATTERNS
+                .iter()
+                .chain(FLY_PATTERNS.iter())
+                .copied()
+                .collect(),
+            Some("walk") => WALK_PATTERNS.to_vec(),
+            Some("fly") => FLY_PATTERNS.to_vec(),
+            Some(other) => return Err(format!("動きの種類が不正です: {}", other)),
+        };
+        if let Some(pattern) = self
+            .patterns
+            .iter()
+            .find(|pattern| !allowed.contains(&pattern.as_str()))
+        {
+            return Err(format!("動きのパターンが不正です: {}", pattern));
+        }
+        if !(0.1..=5.0).contains(&self.speed_min)
+            || !(0.1..=5.0).contains(&self.speed_max)
+            || self.speed_min > self.speed_max
+        {
+            return Err("速さの範囲は0.1〜5.0で、最小値を最大値以下にしてください".to_string());
+        }
+        if !(0.1..=3.0).contains(&self.size_min)
+            || !(0.1..=3.0).contains(&self.size_max)
+            || self.size_min > self.size_max
+        {
+            return Err("大きさの範囲は0.1〜3.0で、最小値を最大値以下にしてください".to_string());
+        }
+        Ok(())
+    }
+
+    /// 保存済みのプロファイル（未設定・不正値なら既定）
+    pub fn load(app_handle: &AppHandle) -> Self {
+        let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+            let conn = state.lock().ok()?;
+            let db = conn.get().ok()?;
+            db.get_app_setting(IMPORT_PROFILE_KEY).ok().flatten()
+        });
+        stored
+            .and_then(|value| serde_json::from_str::<ImportProfile>(&value).ok())
+            .filter(|profile| profile.validate().is_ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let value = serde_json::to_string(self)
+            .map_err(|e| format!("取り込みプロファイルのシリアライズに失敗しました: {}", e))?;
+        let state = app_handle
+            .try_state::<WorkspaceState>()
+            .ok_or("ワークスペースが選択されていません".to_string())?;
+        let conn = state
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        db.save_app_setting(IMPORT_PROFILE_KEY, &value)
+            .map_err(|e| format!("Failed to save app setting: {}", e))
+    }
+}
+
+/// フォルダ監視の設定（スキャナーが日付ごとのサブフォルダへ保存する運用向け）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchOptions {
+    // サブフォルダも監視する
+    pub recursive: bool,
+    // 監視フォルダからの相対パスに対するglob（例: "**/*.jpg"）。空ならすべて
+    pub include: Vec<String>,
+    // 一致したものは取り込まない（例: "**/thumbs/**"）
+    pub exclude: Vec<String>,
+    // サブフォルダ（監視フォルダからの相対パス）→ 取り込み方
+    pub routes: BTreeMap<String, FolderRoute>,
+    // ファイルサイズがこの時間変わらなければ書き込み完了とみなす（ミリ秒、省略時1000）
+    pub stable_ms: Option<u64>,
+    // 取り込みに成功した元ファイルの扱い
+    pub after_import: AfterImport,
+    // 動きの範囲や背景除去の有無（省略時は前回保存したもの）
+    pub profile: Option<ImportProfile>,
+}
+
+/// 取り込み後の元ファイルの扱い（監視フォルダに原本が溜まり続けないように）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterImport {
+    // そのまま残す
+    #[default]
+    Leave,
+    // 監視フォルダ内の imported/ へ移動
+    Archive,
+    // 削除
+    Delete,
+}
+
+// 取り込み済みの元ファイルを移す先（監視フォルダ直下）
+const ARCHIVE_DIR: &str = "imported";
+
+/// 取り込み元ごとの指定（フォルダ監視のタグ・元ファイルの扱い）
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub tag: Option<String>,
+    pub after_import: AfterImport,
+    // アーカイブ先の基準（監視フォルダ）
+    pub watch_root: Option<PathBuf>,
+    pub profile: ImportProfile,
+}
+
+const DEFAULT_STABLE_MS: u64 = 1000;
+const MAX_STABLE_MS: u64 = 60_000;
+// これ以上書き込みが終わらないファイルは諦める
+const MAX_PENDING_WAIT: Duration = Duration::from_secs(10 * 60);
+
+struct PendingFile {
+    size: u64,
+    changed_at: Instant,
+    first_seen: Instant,
+}
+
+// スキャン中のファイルを取り込まないよう、サイズが一定時間変わらなくなるまで待つ
+struct StabilityTracker {
+    stable_for: Duration,
+    pending: HashMap<PathBuf, PendingFile>,
+}
+
+impl StabilityTracker {
+    fn new(stable_for: Duration) -> Self {
+        Self {
+            stable_for,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn watch(&mut self, path: PathBuf) {
+        let now = Instant::now();
+        self.pending.entry(path).or_insert(PendingFile {
+            size: 0,
+            changed_at: now,
+            first_seen: now,
+        });
+    }
+
+    // 書き込みイベントが来たら待ち時間を延長する
+    fn touch(&mut self, path: &Path) {
+        if let Some(file) = self.pending.get_mut(path) {
+            file.changed_at = Instant::now();
+        }
+    }
+
+    // 書き込みが終わったファイルを取り出す
+    fn take_ready(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let mut ready = Vec::new();
+        let stable_for = self.stable_for;
+        self.pending.retain(|path, file| {
+            let Ok(metadata) = fs::metadata(path) else {
+                // 一時ファイルのリネームなどで消えたもの
+                return false;
+            };
+            if now.duration_since(file.first_seen) >= MAX_PENDING_WAIT {
+                eprintln!("[file_watcher] gave up waiting for {:?}", path);
+                return false;
+            }
+            if metadata.len() != file.size {
+                file.size = metadata.len();
+                file.changed_at = now;
+                return true;
+            }
+            if file.size > 0 && now.duration_since(file.changed_at) >= stable_for {
+                ready.push(path.clone());
+                return false;
+            }
+            true
+        });
+        ready.sort();
+        ready
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FolderRoute {
+    // 取り込み通知に付けるタグ（画面側での振り分け用）
+    pub tag: Option<String>,
+    // 画像: "processed"（背景除去して登録、既定） / "background"（そのまま背景として登録）
+    // 音声: "bgm"（既定） / "soundEffect"
+    pub image_type: Option<String>,
+}
+
+// 設定がなくても使えるサブフォルダ（backgrounds/ に置いた画像は背景、sound_effects/ の音声は効果音）
+const BUILTIN_ROUTES: [(&str, &str); 2] = [
+    ("backgrounds", "background"),
+    ("sound_effects", "soundEffect"),
+];
+
+// 監視フォルダ内のどのファイルをどう取り込むか
+struct ImportFilter {
+    root: PathBuf,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    routes: BTreeMap<String, FolderRoute>,
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("パターンが不正です ({}): {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("パターンの登録に失敗しました: {}", e))
+}
+
+impl ImportFilter {
+    fn new(root: &Path, options: &WatchOptions) -> Result<Self, String> {
+        if options.stable_ms.is_some_and(|ms| ms > MAX_STABLE_MS) {
+            return Err("書き込み完了の待ち時間は60秒以内で指定してください".to_string());
+        }
+        for (folder, route) in &options.routes {
+            if folder.trim().is_empty() {
+                return Err("振り分けるサブフォルダ名が空です".to_string());
+            }
+            if let Some(image_type) = route.image_type.as_deref() {
+                if !matches!(
+                    image_type,
+                    "processed" | "background" | "bgm" | "soundEffect"
+                ) {
+                    return Err(format!("取り込み先の種類が不正です: {}", image_type));
+                }
+            }
+        }
+        let include = if options.include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(&options.include)?)
+        };
+        // 区切り文字を / にそろえて照合する
+        let mut routes: BTreeMap<String, FolderRoute> = options
+            .routes
+            .iter()
+            .map(|(folder, route)| {
+                (
+                    folder.replace('\\', "/").trim_matches('/').to_string(),
+                    route.clone(),
+                )
+            })
+            .collect();
+        for (folder, image_type) in BUILTIN_ROUTES {
+            routes
+                .entry(folder.to_string())
+                .or_insert_with(|| FolderRoute {
+                    tag: None,
+                    image_type: Some(image_type.to_string()),
+                });
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            include,
+            exclude: build_glob_set(&options.exclude)?,
+            routes,
+        })
+    }
+
+    // 監視フォルダからの相対パス（/ 区切り）
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        Some(parts.join("/"))
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        if !is_image_file(path) && !is_audio_file(path) {
+            return false;
+        }
+        let Some(relative) = self.relative(path) else {
+            return false;
+        };
+        // アーカイブへ移したものを取り込み直さない
+        if relative.starts_with(&format!("{}/", ARCHIVE_DIR)) {
+            return false;
+        }
+        if self.exclude.is_match(&relative) {
+            return false;
+        }
+        match &self.include {
+            Some(include) => include.is_match(&relative),
+            None => true,
+        }
+    }
+
+    // いちばん深いサブフォルダから順に振り分け設定を探す
+    fn route(&self, path: &Path) -> Option<&FolderRoute> {
+        let relative = self.relative(path)?;
+        let mut folder = relative.rsplit_once('/')?.0;
+        loop {
+            if let Some(route) = self.routes.get(folder) {
+                return Some(route);
+            }
+            folder = folder.rsplit_once('/')?.0;
+        }
+    }
 }
 
 pub fn start_folder_watching(
     app_handle: AppHandle,
     watch_path: String,
     workspace_path: String,
+    options: WatchOptions,
 ) -> Result<(), String> {
     if !Path::new(&watch_path).exists() {
         return Err("指定されたフォルダが存在しません".to_string());
     }
+    // 監視フォルダはダイアログで許可したフォルダの中だけ
+    crate::path_sandbox::check_read(&app_handle, &watch_path)?;
+    let filter = ImportFilter::new(Path::new(&watch_path), &options)?;
+    // 指定があれば保存し、次回以降の起動でも同じ取り込み方にする
+    let profile = match options.profile {
+        Some(profile) => {
+            profile.validate()?;
+            profile.save(&app_handle)?;
+            profile
+        }
+        None => ImportProfile::load(&app_handle),
+    };
 
     // 既存のwatcherを停止
     stop_folder_watching();
 
     let app_handle_clone = app_handle.clone();
     let (stop_tx, stop_rx) = channel::<()>();
+    let watch_path_for_state = watch_path.clone();
+    let recursive = options.recursive;
+    let after_import = options.after_import;
+    let stable_for = Duration::from_millis(options.stable_ms.unwrap_or(DEFAULT_STABLE_MS));
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
 
-    let thread_handle = thread::spawn(move || {
-        let (tx, rx) = channel();
-
-        let mut watcher =
-            RecommendedWatcher::new(tx, Config::default()).expect("Failed to create watcher");
+    update_runtime(|runtime| {
+        *runtime = WatchRuntime {
+            state: "running",
+            ..Default::default()
+        }
+    });
 
-        watcher
-            .watch(Path::new(&watch_path), RecursiveMode::NonRecursive)
-            .expect("Failed to watch path");
+    let thread_handle = thread::spawn(move || {
+        let root = PathBuf::from(&watch_path);
+        let mut tracker = StabilityTracker::new(stable_for);
+        let mut recovering = false;
 
-        println!("Watching folder: {}", watch_path);
+        // USBドライブの取り外しなどでフォルダが消えたら、戻るのを待って監視を作り直す
+        'watch: loop {
+            let (tx, rx) = channel();
+            let watcher = RecommendedWatcher::new(tx, Config::default())
+                .map_err(|e| format!("フォルダ監視の作成に失敗しました: {}", e))
+                .and_then(|mut watcher| {
+                    watcher
+                        .watch(&root, mode)
+                        .map_err(|e| format!("フォルダを監視できません: {}", e))?;
+                    Ok(watcher)
+                });
+            // 監視はこの変数が生きている間だけ有効
+            let _watcher = match watcher {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    report_watch_error(&app_handle_clone, &watch_path, e);
+                    recovering = true;
+                    if wait_for_path(&root, &stop_rx) {
+                        continue 'watch;
+                    }
+                    break 'watch;
+                }
+            };
 
-        loop {
-            // stop_rxをチェック
-            if stop_rx.try_recv().is_ok() {
-                println!("Stopping folder watcher for: {}", watch_path);
-                break;
+            println!("Watching folder: {} (recursive={})", watch_path, recursive);
+            update_runtime(|runtime| runtime.state = "running");
+            if recovering {
+                recovering = false;
+                let _ = crate::events::emit_routed(
+                    &app_handle_clone,
+                    "folder-watch-recovered",
+                    serde_json::json!({ "watch_path": watch_path }),
+                );
             }
+            let mut last_path_check = Instant::now();
+
+            loop {
+                // stop_rxをチェック
+                if stop_rx.try_recv().is_ok() {
+                    println!("Stopping folder watcher for: {}", watch_path);
+                    break 'watch;
+                }
 
-            // file eventsをチェック（タイムアウト付き）
-            match rx.recv_timeout(std::time::Duration::from_millis(100)) {
-                Ok(res) => match res {
-                    Ok(event) => {
-                        if let EventKind::Create(_) = event.kind {
-                            for path in event.paths {
-                                if is_image_file(&path) {
-                                    println!("New image detected: {:?}", path);
-
-                                    let result = process_new_image(
-                                        app_handle_clone.clone(),
-                                        path.clone(),
-                                        workspace_path.clone(),
-                                    );
-
-                                    match result {
-                                        Ok(_) => println!("Image processed successfully"),
-                                        Err(e) => eprintln!("Error processing image: {}", e),
+                // file eventsをチェック（タイムアウト付き）
+                match rx.recv_timeout(Duration::from_millis(100)) {
+                    Ok(res) => match res {
+                        Ok(event) => {
+                            update_runtime(|runtime| {
+                                runtime.last_event_at = Some(current_timestamp())
+                            });
+                            match event.kind {
+                                // 新規作成とリネーム（一時ファイル → .jpg など）で取り込み候補にする
+                                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                                    for path in event.paths {
+                                        // フォルダごと移動された場合は作成イベントがフォルダにしか届かない
+                                        let paths = if recursive && path.is_dir() {
+                                            collect_files(&path)
+                                        } else {
+                                            vec![path]
+                                        };
+                                        for path in paths {
+                                            if filter.accepts(&path) {
+                                                tracker.watch(path);
+                                            }
+                                        }
+                                    }
+                                }
+                                EventKind::Modify(_) => {
+                                    for path in &event.paths {
+                                        tracker.touch(path);
                                     }
                                 }
+                                _ => {}
                             }
                         }
+                        Err(e) => {
+                            eprintln!("Watch error: {:?}", e);
+                            update_runtime(|runtime| runtime.last_error = Some(e.to_string()));
+                        }
+                    },
+                    Err(RecvTimeoutError::Timeout) => {
+                        // タイムアウトは正常、ループを続ける
+                    }
+                    Err(e) => {
+                        eprintln!("Channel error: {:?}", e);
+                        report_watch_error(
+                            &app_handle_clone,
+                            &watch_path,
+                            format!("フォルダ監視が停止しました: {}", e),
+                        );
+                        break;
                     }
-                    Err(e) => eprintln!("Watch error: {:?}", e),
-                },
-                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                    // タイムアウトは正常、ループを続ける
                 }
-                Err(e) => {
-                    eprintln!("Channel error: {:?}", e);
-                    break;
+
+                // 監視中のフォルダが消えてもイベントが届かないOSがあるため定期的に確認する
+                if last_path_check.elapsed() >= PATH_CHECK_INTERVAL {
+                    last_path_check = Instant::now();
+                    if !root.is_dir() {
+                        report_watch_error(
+                            &app_handle_clone,
+                            &watch_path,
+                            "監視フォルダが見つかりません（ドライブが外れた可能性があります）"
+                                .to_string(),
+                        );
+                        break;
+                    }
                 }
+
+                for path in tracker.take_ready() {
+                    println!("New image detected: {:?}", path);
+
+                    let route = filter.route(&path);
+                    let result = import_routed(
+                        &app_handle_clone,
+                        path,
+                        &workspace_path,
+                        route,
+                        ImportOptions {
+                            tag: profile.tag.clone(),
+                            after_import,
+                            watch_root: Some(root.clone()),
+                            profile: profile.clone(),
+                        },
+                    );
+
+                    match result {
+                        Ok(_) => {
+                            println!("Image processed successfully");
+                            update_runtime(|runtime| runtime.processed_count += 1);
+                        }
+                        Err(e) => {
+                            eprintln!("Error processing image: {}", e);
+                            update_runtime(|runtime| runtime.last_error = Some(e));
+                        }
+                    }
+                }
+            }
+
+            recovering = true;
+            if !wait_for_path(&root, &stop_rx) {
+                break 'watch;
             }
         }
+        update_runtime(|runtime| runtime.state = "stopped");
     });
 
     // グローバル状態を更新
     let mut state = WATCHER_STATE.lock().unwrap();
     state.watcher_thread = Some(thread_handle);
     state.stop_sender = Some(stop_tx);
+    state.watch_path = Some(watch_path_for_state);
+    state.recursive = recursive;
+
+    Ok(())
+}
 
+// フォルダ以下のファイルを列挙（サブフォルダも含む）
+fn collect_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+// 振り分け設定に従って取り込む
+fn import_routed(
+    app_handle: &AppHandle,
+    path: PathBuf,
+    workspace_path: &str,
+    route: Option<&FolderRoute>,
+    options: ImportOptions,
+) -> Result<(), String> {
+    let options = ImportOptions {
+        tag: route.and_then(|route| route.tag.clone()).or(options.tag),
+        ..options
+    };
+    let routed_type = route.and_then(|route| route.image_type.as_deref());
+    // 背景除去をせずにそのまま登録するもの
+    let asset_type = if is_audio_file(&path) {
+        match routed_type {
+            Some("soundEffect") => "soundEffect",
+            _ => "bgm",
+        }
+    } else if routed_type == Some("background") {
+        "background"
+    } else {
+        return process_new_image_with(
+            app_handle.clone(),
+            path,
+            workspace_path.to_string(),
+            options,
+        );
+    };
+
+    let hash = content_hash(&path)?;
+    if skip_duplicate(app_handle, &path, &hash) {
+        return Ok(());
+    }
+    let result = import_asset(app_handle, &path, workspace_path, asset_type);
+    finish_hash(app_handle, result.as_deref().ok(), &hash);
+    let image_id = result?;
+    println!(
+        "[file_watcher] imported {} {} (tag={:?})",
+        asset_type, image_id, options.tag
+    );
+    handle_source(app_handle, &image_id, &path, &options);
     Ok(())
 }
 
+// 同名のファイルがあれば -1, -2 ... を付ける
+fn unique_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{}-{}{}", stem, index, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+// 元ファイルを監視フォルダの imported/ へ移動し、移動先を返す
+fn archive_source(path: &Path, watch_root: Option<&Path>) -> Result<PathBuf, String> {
+    // 監視フォルダからの相対パス（日付フォルダなど）を保ったまま移す
+    let (root, relative) =
+        match watch_root.and_then(|root| Some((root, path.strip_prefix(root).ok()?))) {
+            Some((root, relative)) => (root.to_path_buf(), relative.to_path_buf()),
+            None => (
+                path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                PathBuf::from(path.file_name().unwrap_or_default()),
+            ),
+        };
+    let destination = unique_path(root.join(ARCHIVE_DIR).join(relative));
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("アーカイブ先の作成に失敗しました: {}", e))?;
+    }
+    // 別ドライブへは rename できないためコピーしてから消す
+    if fs::rename(path, &destination).is_err() {
+        fs::copy(path, &destination)
+            .map_err(|e| format!("元ファイルの移動に失敗しました: {}", e))?;
+        fs::remove_file(path).map_err(|e| format!("元ファイルの削除に失敗しました: {}", e))?;
+    }
+    Ok(destination)
+}
+
+/// 取り込みに成功した元ファイルを設定どおりに処理し、結果を画像に記録する
+fn handle_source(app_handle: &AppHandle, image_id: &str, path: &Path, options: &ImportOptions) {
+    let result = match options.after_import {
+        AfterImport::Leave => Ok(("left", Some(path.to_path_buf()))),
+        AfterImport::Archive => {
+            archive_source(path, options.watch_root.as_deref()).map(|dest| ("archived", Some(dest)))
+        }
+        AfterImport::Delete => fs::remove_file(path)
+            .map(|_| ("deleted", None))
+            .map_err(|e| format!("元ファイルの削除に失敗しました: {}", e)),
+    };
+    // 移動・削除に失敗したら元の場所に残っている
+    let (action, source_path) = match result {
+        Ok(done) => done,
+        Err(e) => {
+            eprintln!("[file_watcher] {:?}: {}", path, e);
+            ("left", Some(path.to_path_buf()))
+        }
+    };
+    let source_path = source_path.map(|p| p.to_string_lossy().to_string());
+    let recorded = app_handle
+        .try_state::<WorkspaceState>()
+        .ok_or("ワークスペースが選択されていません".to_string())
+        .and_then(|state| {
+            let conn = state
+                .lock()
+                .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+            let db = conn.get()?;
+            db.set_image_source(image_id, action, source_path.as_deref())
+                .map_err(|e| format!("Failed to save image source: {}", e))
+        });
+    if let Err(e) = recorded {
+        eprintln!("[file_watcher] {}", e);
+    }
+}
+
 pub fn stop_folder_watching() {
     let mut state = WATCHER_STATE.lock().unwrap();
 
@@ -144,9 +960,12 @@ pub fn stop_folder_watching() {
     if let Some(thread) = state.watcher_thread.take() {
         let _ = thread.join();
     }
+    state.watch_path = None;
+    state.recursive = false;
+    update_runtime(|runtime| runtime.state = "stopped");
 }
 
-fn is_image_file(path: &Path) -> bool {
+pub fn is_image_file(path: &Path) -> bool {
     if let Some(extension) = path.extension() {
         let ext = extension.to_str().unwrap_or("").to_lowercase();
         matches!(
@@ -158,63 +977,113 @@ fn is_image_file(path: &Path) -> bool {
     }
 }
 
-fn process_new_image(
+pub fn is_audio_file(path: &Path) -> bool {
+    if let Some(extension) = path.extension() {
+        let ext = extension.to_str().unwrap_or("").to_lowercase();
+        matches!(ext.as_str(), "mp3" | "wav" | "ogg" | "m4a" | "aac" | "flac")
+    } else {
+        false
+    }
+}
+
+/// 画像ファイルを取り込みキューへ渡す（フォルダ監視/クラウド取り込みで共通）
+pub fn process_new_image(
+    app_handle: AppHandle,
+    image_path: PathBuf,
+    workspace_path: String,
+) -> Result<(), String> {
+    process_new_image_with(
+        app_handle,
+        image_path,
+        workspace_path,
+        ImportOptions::default(),
+    )
+}
+
+/// 取り込み元ごとの指定（タグ・元ファイルの扱い）付きで取り込む
+pub fn process_new_image_with(
     app_handle: AppHandle,
     image_path: PathBuf,
     workspace_path: String,
+    options: ImportOptions,
 ) -> Result<(), String> {
+    // 一時停止中は保留し、解除後に取り込む
+    if crate::maintenance::defer_import(&image_path, &workspace_path) {
+        return Ok(());
+    }
+
+    // スキャナーの二重書き込みやリネームで同じ画像を取り込まない
+    let hash = content_hash(&image_path)?;
+    if skip_duplicate(&app_handle, &image_path, &hash) {
+        return Ok(());
+    }
+
     // 画像IDを生成
     let image_id = Uuid::new_v4().to_string();
     let original_path = image_path.to_string_lossy().to_string();
 
     // 処理開始を通知
-    app_handle
-        .emit(
-            "auto-import-started",
-            AutoImportStarted {
-                image_id: image_id.clone(),
-                original_path: original_path.clone(),
-            },
-        )
-        .map_err(|e| format!("Failed to emit start event: {}", e))?;
+    if let Err(e) = crate::events::emit_routed(
+        &app_handle,
+        "auto-import-started",
+        AutoImportStarted {
+            image_id: image_id.clone(),
+            original_path: original_path.clone(),
+            tag: options.tag.clone(),
+        },
+    ) {
+        finish_hash(&app_handle, None, &hash);
+        return Err(format!("Failed to emit start event: {}", e));
+    }
 
     // 画像処理を実行
     let handle_clone = app_handle.clone();
     let image_id_clone = image_id.clone();
     let workspace_path_clone = workspace_path.clone();
 
+    IMPORTS_IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
     thread::spawn(move || {
+        // プロファイルの範囲でアニメーション設定を生成（画像と同一トランザクションで保存する）
+        let animation = generate_animation(&options.profile);
+
         match process_image_async(
             handle_clone.clone(),
-            image_path,
+            image_path.clone(),
             image_id_clone.clone(),
             workspace_path_clone,
+            &animation,
+            options.profile.remove_background,
         ) {
             Ok(processed_path) => {
-                // ランダムアニメーション設定を生成
-                let animation = generate_random_animation();
-
+                finish_hash(&handle_clone, Some(&image_id_clone), &hash);
+                handle_source(&handle_clone, &image_id_clone, &image_path, &options);
                 let result = AutoImportResult {
                     image_id: image_id_clone,
                     original_path,
                     processed_path,
                     animation_settings: animation,
+                    tag: options.tag,
                 };
 
                 // 処理完了を通知
-                let _ = handle_clone.emit("auto-import-complete", result);
+                let _ = crate::events::emit_routed(&handle_clone, "auto-import-complete", result);
             }
             Err(e) => {
+                finish_hash(&handle_clone, None, &hash);
+                crate::heartbeat::record_error(format!("auto-import: {}", e));
                 // エラーを通知
-                let _ = handle_clone.emit(
+                let _ = crate::events::emit_routed(
+                    &handle_clone,
                     "auto-import-error",
                     AutoImportError {
                         image_id: image_id_clone,
                         error: e,
+                        tag: options.tag,
                     },
                 );
             }
         }
+        IMPORTS_IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
     });
 
     Ok(())
@@ -225,14 +1094,13 @@ fn process_image_async(
     image_path: PathBuf,
     image_id: String,
     workspace_path: String,
+    animation: &AnimationSettings,
+    remove_background: bool,
 ) -> Result<String, String> {
     // 画像ファイルを読み込み
     let image_data =
         fs::read(&image_path).map_err(|e| format!("Failed to read image file: {}", e))?;
 
-    // Base64エンコード
-    let base64_data = general_purpose::STANDARD.encode(&image_data);
-
     // ファイル拡張子を取得
     let extension = image_path
         .extension()
@@ -249,9 +1117,72 @@ fn process_image_async(
         _ => "image/png",
     };
 
+    // 非UTF-8（Shift-JIS等）のファイル名も置換文字付きで残す
+    let original_file_name = Path::new(&image_path)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // 背景除去をしない場合はPNGに変換してそのまま登録する
+    if !remove_background {
+        let decoded = image::load_from_memory(&image_data)
+            .map_err(|e| format!("画像を読み込めませんでした: {}", e))?;
+        let mut png = std::io::Cursor::new(Vec::new());
+        decoded
+            .write_to(&mut png, image::ImageFormat::Png)
+            .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+        return save_processed_image(
+            &app_handle,
+            png.into_inner(),
+            original_file_name,
+            &image_id,
+            &workspace_path,
+            animation,
+            None,
+        );
+    }
+
+    import_image_data(
+        &app_handle,
+        &image_data,
+        mime_type,
+        original_file_name,
+        &image_id,
+        &workspace_path,
+        animation,
+    )
+}
+
+/// 画像データを背景除去して処理済み画像として保存する（自動取り込み/スマホ投稿で共通）
+/// 保存先のパスを返す
+pub fn import_image_data(
+    app_handle: &AppHandle,
+    image_data: &[u8],
+    mime_type: &str,
+    original_file_name: String,
+    image_id: &str,
+    workspace_path: &str,
+    animation: &AnimationSettings,
+) -> Result<String, String> {
+    // 大きすぎる画像はサイドカーへ渡す前に縮小・拒否する
+    let resized = crate::image_limits::preflight(app_handle, image_data, &original_file_name)?;
+    let (image_data, mime_type) = match &resized {
+        Some((data, mime)) => (data.as_slice(), *mime),
+        None => (image_data, mime_type),
+    };
+
+    // Base64エンコード
+    let base64_data = general_purpose::STANDARD.encode(image_data);
+
     // データURLを作成
     let data_url = format!("data:{};base64,{}", mime_type, base64_data);
 
+    // テンプレートに名前欄があれば、背景除去前の元画像から表示名を読み取る
+    let display_name =
+        crate::template::ocr_config_for_import(app_handle).and_then(|(name_box, languages)| {
+            crate::template::read_display_name(&data_url, &name_box, &languages)
+        });
+
     // Python処理を直接実行
     let result = crate::process_image_sync(data_url)?;
 
@@ -272,16 +1203,46 @@ fn process_image_async(
     let processed_data = general_purpose::STANDARD
         .decode(base64_str)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    // 設定に応じて背景を塗る（単色・クロマキー）
+    let processed_data = crate::output_background::apply(app_handle, processed_data)?;
 
+    save_processed_image(
+        app_handle,
+        processed_data,
+        original_file_name,
+        image_id,
+        workspace_path,
+        animation,
+        display_name,
+    )
+}
+
+/// 背景除去済みのPNGを保存し、動き設定と合わせてDBへ登録する
+/// 保存先のパスを返す
+pub fn save_processed_image(
+    app_handle: &AppHandle,
+    processed_data: Vec<u8>,
+    original_file_name: String,
+    image_id: &str,
+    workspace_path: &str,
+    animation: &AnimationSettings,
+    display_name: Option<String>,
+) -> Result<String, String> {
     // 保存先パスを生成（ワークスペースは既にフルパスなので、そのまま使用）
-    let workspace_dir = PathBuf::from(&workspace_path);
+    let workspace_dir = PathBuf::from(workspace_path);
     let processed_dir = workspace_dir.join("images").join("processed");
 
     // ディレクトリを作成
     fs::create_dir_all(&processed_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
 
-    // ファイル名を生成
-    let filename = format!("{}.png", image_id);
+    // ファイル名を生成（命名テンプレートの設定に従う）
+    let filename = crate::file_name::saved_name(
+        app_handle,
+        &processed_dir,
+        image_id,
+        &original_file_name,
+        "png",
+    );
     let save_path = processed_dir.join(&filename);
 
     // ファイルを保存
@@ -296,14 +1257,8 @@ fn process_image_async(
         .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
     let db = conn.get().map_err(|e| e)?;
 
-    let original_file_name = Path::new(&image_path)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-
     let metadata = DbImageMetadata {
-        id: image_id.clone(),
+        id: image_id.to_string(),
         original_file_name,
         saved_file_name: filename.clone(),
         image_type: "processed".to_string(),
@@ -311,46 +1266,163 @@ fn process_image_async(
         size: processed_data.len() as i64,
         width: None,
         height: None,
-        storage_location: workspace_path.clone(),
+        storage_location: workspace_path.to_string(),
         file_path: Some(save_path.to_string_lossy().to_string()),
         is_hidden: 0,
         display_started_at: None,
+        display_name,
     };
 
-    db.save_image_metadata(&metadata)
-        .map_err(|e| format!("Failed to save image metadata: {}", e))?;
+    // 取込時は「浮遊(fly)」で登録（フロントエンドの既定と揃える）。プロファイルで歩く動きを指定した場合のみ walk
+    let now = current_timestamp();
+    let settings = MovementSettings {
+        image_id: image_id.to_string(),
+        movement_type: animation
+            .movement_type
+            .clone()
+            .unwrap_or_else(|| "fly".to_string()),
+        movement_pattern: animation.animation_type.clone(),
+        speed: animation.speed,
+        size: animation.size.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+        gravity: None,
+        bounce_elasticity: None,
+        rotation_speed: None,
+        amplitude: None,
+        direction_bias: None,
+        z_order: None,
+    };
+
+    // 画像と動き設定を同時に保存（途中クラッシュで設定なしの画像が残らないように）
+    db.save_image_with_settings(&metadata, &settings)
+        .map_err(|e| format!("Failed to save image with settings: {}", e))?;
 
     // イベント発火（ギャラリー等へ反映）
     emit_data_change(
-        &app_handle,
-        DataChangeEvent::ImageUpserted(crate::events::ImageUpsertedPayload::from(&metadata)),
+        app_handle,
+        DataChangeEvent::ImageWithSettingsSaved(ImageWithSettingsSavedPayload::new(
+            &metadata, &settings,
+        )),
     )
     .map_err(|e| format!("Failed to emit data change: {}", e))?;
 
     Ok(save_path.to_string_lossy().to_string())
 }
 
-fn generate_random_animation() -> AnimationSettings {
+/// 背景・BGM・効果音をそのまま登録する（ホットフォルダの backgrounds/ や音声ファイル）
+/// 登録した画像IDを返す
+fn import_asset(
+    app_handle: &AppHandle,
+    source_path: &Path,
+    workspace_path: &str,
+    image_type: &str,
+) -> Result<String, String> {
+    let data = fs::read(source_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let extension = source_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bin")
+        .to_lowercase();
+    let original_file_name = source_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // 保存先は手動アップロードと同じ（ImageMetadata::resolve_file_path と対応）
+    let target_dir = match image_type {
+        "background" => PathBuf::from(workspace_path)
+            .join("images")
+            .join("backgrounds"),
+        _ => PathBuf::from(workspace_path).join("audio"),
+    };
+    fs::create_dir_all(&target_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let image_id = Uuid::new_v4().to_string();
+    let filename = crate::file_name::saved_name(
+        app_handle,
+        &target_dir,
+        &image_id,
+        &original_file_name,
+        &extension,
+    );
+    let save_path = target_dir.join(&filename);
+    fs::write(&save_path, &data).map_err(|e| format!("Failed to save file: {}", e))?;
+
+    let metadata = DbImageMetadata {
+        id: image_id.clone(),
+        original_file_name,
+        saved_file_name: filename,
+        image_type: image_type.to_string(),
+        created_at: current_timestamp(),
+        size: data.len() as i64,
+        width: None,
+        height: None,
+        storage_location: workspace_path.to_string(),
+        file_path: Some(save_path.to_string_lossy().to_string()),
+        is_hidden: 0,
+        display_started_at: None,
+        display_name: None,
+    };
+
+    let state: tauri::State<WorkspaceState> = app_handle.state();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_image_metadata(&metadata)
+        .map_err(|e| format!("Failed to save image metadata: {}", e))?;
+
+    emit_data_change(
+        app_handle,
+        DataChangeEvent::ImageUpserted(ImageUpsertedPayload::from(&metadata)),
+    )?;
+    let event = match image_type {
+        "background" => DataChangeEvent::BackgroundChanged(
+            BackgroundChangedPayload::for_background(db, &image_id),
+        ),
+        "soundEffect" => DataChangeEvent::AudioUpdated(AudioUpdatedPayload {
+            audio_type: "sound_effect".to_string(),
+        }),
+        _ => DataChangeEvent::AudioUpdated(AudioUpdatedPayload {
+            audio_type: "bgm".to_string(),
+        }),
+    };
+    emit_data_change(app_handle, event)?;
+    Ok(image_id)
+}
+
+pub fn generate_random_animation() -> AnimationSettings {
+    generate_animation(&ImportProfile::default())
+}
+
+/// プロファイルの範囲で動きをランダムに決める
+pub fn generate_animation(profile: &ImportProfile) -> AnimationSettings {
+    use rand::seq::SliceRandom;
     use rand::Rng;
 
     let mut rng = rand::thread_rng();
 
-    // 50%の確率で歩くタイプ、50%の確率で飛ぶタイプ
-    let is_walk = rng.gen_bool(0.5);
-
-    let animation_type = if is_walk {
-        // 歩くタイプの動き
-        let walk_types = vec!["normal", "slow", "fast"];
-        walk_types[rng.gen_range(0..walk_types.len())].to_string()
+    let candidates: Vec<String> = if !profile.patterns.is_empty() {
+        profile.patterns.clone()
     } else {
-        // 飛ぶタイプの動き
-        let fly_types = vec!["float", "bounce", "rotate", "swim"];
-        fly_types[rng.gen_range(0..fly_types.len())].to_string()
+        let patterns: &[&str] = match profile.movement_type.as_deref() {
+            Some("walk") => &WALK_PATTERNS,
+            Some("fly") => &FLY_PATTERNS,
+            // 50%の確率で歩くタイプ、50%の確率で飛ぶタイプ
+            _ if rng.gen_bool(0.5) => &WALK_PATTERNS,
+            _ => &FLY_PATTERNS,
+        };
+        patterns.iter().map(|p| p.to_string()).collect()
     };
+    let animation_type = candidates
+        .choose(&mut rng)
+        .cloned()
+        .unwrap_or_else(|| "float".to_string());
 
     AnimationSettings {
         animation_type,
-        speed: rng.gen_range(0.5..=1.5), // 0.5 ~ 1.5
-        size: rng.gen_range(0.8..=1.2),  // 0.8 ~ 1.2
+        speed: rng.gen_range(profile.speed_min..=profile.speed_max),
+        size: rng.gen_range(profile.size_min..=profile.size_max),
+        movement_type: profile.movement_type.clone(),
     }
 }
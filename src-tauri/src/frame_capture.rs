@@ -0,0 +1,135 @@
+// アニメーション画面の静止画保存（アニメーションのWebViewに描画を頼み、PNGをワークスペースの exports/ に保存する）
+use base64::Engine;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+use tokio::sync::oneshot;
+
+use crate::workspace::WorkspaceState;
+
+// WebViewからの返答を待つ時間
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(10);
+// 受け付ける画像の大きさ（base64を戻した後）
+const MAX_FRAME_BYTES: usize = 32 * 1024 * 1024;
+
+// 返答待ちの依頼（依頼ID → 受け取り口。描画に失敗したときは理由を受け取る）
+type FrameResult = Result<Vec<u8>, String>;
+static PENDING: Lazy<Mutex<HashMap<String, oneshot::Sender<FrameResult>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// ワークスペースの exports/ フォルダ
+pub(crate) fn exports_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let workspace = app_handle.state::<WorkspaceState>();
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    conn.workspace_root()
+        .map(|root| root.join("exports"))
+        .ok_or_else(|| "ワークスペースが選択されていません".to_string())
+}
+
+fn remove_pending(request_id: &str) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.remove(request_id);
+    }
+}
+
+fn take_pending(request_id: &str) -> Result<oneshot::Sender<FrameResult>, String> {
+    PENDING
+        .lock()
+        .map_err(|_| "撮影待ちのロックに失敗しました".to_string())?
+        .remove(request_id)
+        .ok_or_else(|| "撮影の依頼が見つかりません（時間切れの可能性があります）".to_string())
+}
+
+/// 指定したウィンドウに描画を頼み、PNGのバイト列を受け取る
+pub(crate) async fn request_frame(app_handle: &AppHandle, label: &str) -> Result<Vec<u8>, String> {
+    if app_handle.get_webview_window(label).is_none() {
+        return Err("アニメーションウィンドウが開いていません".to_string());
+    }
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = oneshot::channel();
+    PENDING
+        .lock()
+        .map_err(|_| "撮影待ちのロックに失敗しました".to_string())?
+        .insert(request_id.clone(), tx);
+
+    // 依頼したウィンドウだけに送る（複製表示のウィンドウが先に答えないように）
+    if let Err(e) = app_handle.emit_to(
+        EventTarget::webview_window(label),
+        "capture-frame-request",
+        serde_json::json!({ "requestId": request_id }),
+    ) {
+        remove_pending(&request_id);
+        return Err(format!("撮影の依頼に失敗しました: {}", e));
+    }
+
+    match tokio::time::timeout(CAPTURE_TIMEOUT, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("撮影が中断されました".to_string()),
+        Err(_) => {
+            remove_pending(&request_id);
+            Err("アニメーション画面から応答がありません".to_string())
+        }
+    }
+}
+
+/// 現在のアニメーション画面をPNGで保存し、保存先のパスを返す
+#[tauri::command]
+pub async fn capture_animation_frame(
+    app_handle: AppHandle,
+    window_label: Option<String>,
+) -> Result<String, String> {
+    let label = window_label.unwrap_or_else(|| "animation".to_string());
+    let out_dir = exports_dir(&app_handle)?;
+    let bytes = request_frame(&app_handle, &label).await?;
+
+    std::fs::create_dir_all(&out_dir)
+        .map_err(|e| format!("出力先フォルダの作成に失敗しました: {}", e))?;
+    let file_name = format!(
+        "capture-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+    );
+    let path = out_dir.join(file_name);
+    std::fs::write(&path, &bytes).map_err(|e| format!("画像の保存に失敗しました: {}", e))?;
+    println!("[frame_capture] saved {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// アニメーションのWebViewが描画したPNG（data URL または base64）を受け取る
+#[tauri::command]
+pub fn submit_animation_frame(request_id: String, data: String) -> Result<(), String> {
+    let sender = take_pending(&request_id)?;
+
+    let encoded = match data.split_once(',') {
+        Some((header, body)) if header.starts_with("data:") => body,
+        _ => data.as_str(),
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("画像データのデコードに失敗しました: {}", e))?;
+    if bytes.len() > MAX_FRAME_BYTES {
+        return Err("画像が大きすぎます".to_string());
+    }
+    if image::guess_format(&bytes).ok() != Some(image::ImageFormat::Png) {
+        return Err("PNG画像ではありません".to_string());
+    }
+    // 待っている側が時間切れで既にいない場合は何もしない
+    let _ = sender.send(Ok(bytes));
+    Ok(())
+}
+
+/// WebViewで描画できなかったことを知らせる（時間切れまで待たせず、理由を返す）
+#[tauri::command]
+pub fn report_animation_frame_error(request_id: String, message: String) -> Result<(), String> {
+    let sender = take_pending(&request_id)?;
+    let _ = sender.send(Err(format!(
+        "アニメーション画面を撮影できません: {}",
+        message
+    )));
+    Ok(())
+}
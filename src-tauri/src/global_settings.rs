@@ -0,0 +1,298 @@
+// アプリ全体の設定（app_data_dir/global_settings.json）
+// 以前は平坦な文字列キーで保存していたが、プロビジョニングと同じ relay/defaults/ui の入れ子構造と
+// 食い違っていたため、型付きの構造に揃える（未知のキーは extra にそのまま残す）
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+// 保存形式のバージョン（1 = 平坦なキーのみ）
+pub const GLOBAL_SETTINGS_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RelaySection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pc_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ws_protocol: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DefaultsSection {
+    // "auto" / "relay" / "local"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_mode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UiSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hide_relay_settings: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_relay_settings: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NetworkSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    // "8000-8100"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_range: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalSettings {
+    pub version: u32,
+    pub relay: RelaySection,
+    pub defaults: DefaultsSection,
+    pub ui: UiSection,
+    pub network: NetworkSection,
+    // 各機能が個別に保存する平坦なキー
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+// 平坦なキーのうち、セクションへ移したもの
+const LEGACY_RELAY_EVENT_ID: &str = "relay_event_id";
+const LEGACY_PC_ID: &str = "pcid";
+const LEGACY_PORT: &str = crate::web_server::PORT_KEY;
+const LEGACY_PORT_RANGE: &str = crate::web_server::PORT_RANGE_KEY;
+const LEGACY_TLS: &str = crate::tls::TLS_ENABLED_KEY;
+
+// 数値・真偽値で書かれていても文字列として扱う
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+// 型が合わないセクションは既定値にして、他の設定まで失わないようにする
+fn section<T: serde::de::DeserializeOwned + Default>(map: &mut Map<String, Value>, key: &str) -> T {
+    let Some(value) = map.remove(key) else {
+        return T::default();
+    };
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        eprintln!("[global_settings] ignoring invalid section {}: {}", key, e);
+        T::default()
+    })
+}
+
+impl GlobalSettings {
+    pub fn from_value(value: Value) -> Self {
+        let mut map = match value {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        let version = map
+            .get("version")
+            .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()))
+            .unwrap_or(1) as u32;
+        let mut settings = GlobalSettings {
+            version,
+            relay: section(&mut map, "relay"),
+            defaults: section(&mut map, "defaults"),
+            ui: section(&mut map, "ui"),
+            network: section(&mut map, "network"),
+            extra: Map::new(),
+        };
+        map.remove("version");
+        settings.extra = map;
+        settings.migrate();
+        settings
+    }
+
+    // 平坦なキーで保存されていた値をセクションへ移す（セクション側の値を優先）
+    fn migrate(&mut self) {
+        if self.version >= GLOBAL_SETTINGS_VERSION {
+            return;
+        }
+        for key in [
+            LEGACY_RELAY_EVENT_ID,
+            LEGACY_PC_ID,
+            LEGACY_PORT,
+            LEGACY_PORT_RANGE,
+            LEGACY_TLS,
+        ] {
+            let Some(value) = self.extra.get(key).and_then(value_as_string) else {
+                continue;
+            };
+            let already_set = self.get(key).is_some();
+            if already_set || self.set(key, &value).is_ok() {
+                self.extra.remove(key);
+            }
+        }
+        self.version = GLOBAL_SETTINGS_VERSION;
+    }
+
+    /// 平坦なキーで値を読む（セクションへ移したキーも同じ名前で読める）
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            LEGACY_RELAY_EVENT_ID => self.relay.event_id.clone(),
+            LEGACY_PC_ID => self.relay.pc_id.clone(),
+            LEGACY_PORT => self.network.port.map(|port| port.to_string()),
+            LEGACY_PORT_RANGE => self.network.port_range.clone(),
+            LEGACY_TLS => self.network.tls_enabled.map(|enabled| enabled.to_string()),
+            _ => self
+                .extra
+                .get(key)
+                .and_then(|value| value.as_str().map(str::to_string)),
+        }
+    }
+
+    /// 平坦なキーで値を書く（空文字はセクション側の値を消す）
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let trimmed = value.trim();
+        let text = (!trimmed.is_empty()).then(|| trimmed.to_string());
+        match key {
+            LEGACY_RELAY_EVENT_ID => self.relay.event_id = text,
+            LEGACY_PC_ID => self.relay.pc_id = text,
+            LEGACY_PORT_RANGE => self.network.port_range = text,
+            LEGACY_PORT => {
+                self.network.port = text
+                    .map(|text| text.parse::<u16>())
+                    .transpose()
+                    .map_err(|_| format!("ポート番号が不正です: {}", value))?
+            }
+            LEGACY_TLS => {
+                self.network.tls_enabled = text
+                    .map(|text| text.parse::<bool>())
+                    .transpose()
+                    .map_err(|_| format!("TLSの設定値が不正です: {}", value))?
+            }
+            _ => {
+                self.extra
+                    .insert(key.to_string(), Value::String(value.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// 部分的な変更を入れ子ごとマージする（nullはその項目を消す）
+    pub fn merge(&self, patch: &Value) -> Result<Self, String> {
+        if !patch.is_object() {
+            return Err("設定の変更はオブジェクトで指定してください".to_string());
+        }
+        let mut current =
+            serde_json::to_value(self).map_err(|e| format!("JSON変換エラー: {}", e))?;
+        merge_value(&mut current, patch);
+        // マージ後の値がセクションの型に合うかを確認する（読み込み時のように黙って既定値へ戻さない）
+        for key in ["relay", "defaults", "ui", "network"] {
+            let Some(value) = current.get(key).cloned() else {
+                continue;
+            };
+            let checked = match key {
+                "relay" => serde_json::from_value::<RelaySection>(value).err(),
+                "defaults" => serde_json::from_value::<DefaultsSection>(value).err(),
+                "ui" => serde_json::from_value::<UiSection>(value).err(),
+                _ => serde_json::from_value::<NetworkSection>(value).err(),
+            };
+            if let Some(e) = checked {
+                return Err(format!("{} の設定が不正です: {}", key, e));
+            }
+        }
+        let mut merged = Self::from_value(current);
+        merged.version = GLOBAL_SETTINGS_VERSION;
+        Ok(merged)
+    }
+}
+
+fn merge_value(base: &mut Value, patch: &Value) {
+    match (base, patch) {
+        (Value::Object(base_map), Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                if value.is_null() {
+                    base_map.remove(key);
+                    continue;
+                }
+                match base_map.get_mut(key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge_value(existing, value)
+                    }
+                    _ => {
+                        base_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+/// ファイルから読み込む（無い・壊れている場合は既定値）
+pub fn load_from(path: &Path) -> GlobalSettings {
+    let value = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .unwrap_or(Value::Null);
+    GlobalSettings::from_value(value)
+}
+
+pub fn save_to(path: &Path, settings: &GlobalSettings) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+    }
+    let content =
+        serde_json::to_string_pretty(settings).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    // 設定とプロビジョニングは壊れると起動に関わるため、必ず fsync してから置き換える
+    crate::atomic_file::write(path, content, true)
+        .map_err(|e| format!("ファイル書き込みエラー: {}", e))
+}
+
+/// 読み込み→変更→保存
+pub fn update_file(
+    path: &Path,
+    f: impl FnOnce(&mut GlobalSettings) -> Result<(), String>,
+) -> Result<GlobalSettings, String> {
+    let mut settings = load_from(path);
+    f(&mut settings)?;
+    save_to(path, &settings)?;
+    Ok(settings)
+}
+
+/// アプリ全体の設定を型付きで取得
+#[tauri::command]
+pub fn get_global_settings(app_handle: tauri::AppHandle) -> Result<GlobalSettings, String> {
+    let path = crate::workspace::global_settings_path(&app_handle)?;
+    Ok(load_from(&path))
+}
+
+/// 部分的な変更をマージして保存し、保存後の設定を返す
+#[tauri::command]
+pub fn update_global_settings(
+    window: tauri::Window,
+    app_handle: tauri::AppHandle,
+    patch: Value,
+) -> Result<GlobalSettings, String> {
+    let path = crate::workspace::global_settings_path(&app_handle)?;
+    let before = serde_json::to_string(&load_from(&path)).ok();
+    let updated = update_file(&path, |settings| {
+        *settings = settings.merge(&patch)?;
+        Ok(())
+    })?;
+    // 入れ子の設定はファイル全体の前後を記録する
+    let after = serde_json::to_string(&updated).ok();
+    if before != after {
+        crate::workspace::record_global_setting_change(
+            &app_handle,
+            "global_settings",
+            before.as_deref(),
+            after.as_deref(),
+            window.label(),
+        );
+    }
+    Ok(updated)
+}
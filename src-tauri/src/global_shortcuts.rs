@@ -0,0 +1,202 @@
+// 運営スタッフ用のグローバルショートカット（アプリが前面になくても効く緊急キー）
+// 割り当てはワークスペースの app_settings に保存する。他のアプリのキーを奪わないよう、既定では何も割り当てない
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::workspace::WorkspaceState;
+
+const SHORTCUTS_KEY: &str = "global_shortcuts";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    // 表示画面を暗転して作品を隠す（作品自体は消さず、ResumeDisplay で元に戻る）
+    HideAll,
+    PauseDisplay,
+    ResumeDisplay,
+    // QRコードのウィンドウを開き直して前面に出す
+    ShowQr,
+}
+
+const ACTIONS: [ShortcutAction; 4] = [
+    ShortcutAction::HideAll,
+    ShortcutAction::PauseDisplay,
+    ShortcutAction::ResumeDisplay,
+    ShortcutAction::ShowQr,
+];
+
+/// 操作ごとのキー（null / 空文字はその操作を無効にする）
+pub type ShortcutBindings = BTreeMap<ShortcutAction, Option<String>>;
+
+// 登録中のキーと操作
+static REGISTERED: Lazy<Mutex<Vec<(Shortcut, ShortcutAction)>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator.trim())
+        .map_err(|e| format!("ショートカット '{}' を解釈できません: {}", accelerator, e))
+}
+
+// 保存済みの割り当てに、未割り当ての操作を補う（設定画面で全操作を並べるため）
+fn with_all_actions(stored: ShortcutBindings) -> ShortcutBindings {
+    let mut bindings: ShortcutBindings = ACTIONS.iter().map(|action| (*action, None)).collect();
+    bindings.extend(stored);
+    bindings
+}
+
+fn validate(bindings: &ShortcutBindings) -> Result<(), String> {
+    let mut seen: Vec<(Shortcut, ShortcutAction)> = Vec::new();
+    for (action, accelerator) in bindings {
+        let Some(accelerator) = accelerator.as_deref().filter(|a| !a.trim().is_empty()) else {
+            continue;
+        };
+        let shortcut = parse(accelerator)?;
+        if let Some((_, other)) = seen.iter().find(|(s, _)| *s == shortcut) {
+            return Err(format!(
+                "ショートカット '{}' が {:?} と {:?} に重複しています",
+                accelerator, other, action
+            ));
+        }
+        seen.push((shortcut, *action));
+    }
+    Ok(())
+}
+
+fn read_bindings(app_handle: &AppHandle) -> ShortcutBindings {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(SHORTCUTS_KEY).ok().flatten()
+    });
+    let bindings = with_all_actions(
+        stored
+            .and_then(|value| serde_json::from_str::<ShortcutBindings>(&value).ok())
+            .unwrap_or_default(),
+    );
+    if let Err(e) = validate(&bindings) {
+        eprintln!("[global_shortcuts] ignoring stored bindings: {}", e);
+        return with_all_actions(ShortcutBindings::new());
+    }
+    bindings
+}
+
+// 登録済みのキーをすべて外し、割り当てどおりに登録し直す
+fn register_all(app_handle: &AppHandle, bindings: &ShortcutBindings) {
+    let Ok(mut registered) = REGISTERED.lock() else {
+        return;
+    };
+    let manager = app_handle.global_shortcut();
+    for (shortcut, _) in registered.drain(..) {
+        if let Err(e) = manager.unregister(shortcut) {
+            eprintln!("[global_shortcuts] failed to unregister: {}", e);
+        }
+    }
+    for (action, accelerator) in bindings {
+        let Some(accelerator) = accelerator.as_deref().filter(|a| !a.trim().is_empty()) else {
+            continue;
+        };
+        let Ok(shortcut) = parse(accelerator) else {
+            continue;
+        };
+        // 他のアプリが使っているキーは登録できないことがある（他の操作は続けて登録する）
+        match manager.register(shortcut) {
+            Ok(()) => registered.push((shortcut, *action)),
+            Err(e) => eprintln!(
+                "[global_shortcuts] failed to register {} for {:?}: {}",
+                accelerator, action, e
+            ),
+        }
+    }
+}
+
+/// 保存済みの割り当てを読み込んで登録する（起動時・ワークスペース接続時）
+pub fn reload(app_handle: &AppHandle) {
+    let bindings = read_bindings(app_handle);
+    register_all(app_handle, &bindings);
+}
+
+/// ワークスペース接続後に登録し直す（呼び出し元が接続のロックを持っているため別スレッドで）
+pub fn schedule_reload(app_handle: AppHandle) {
+    std::thread::spawn(move || reload(&app_handle));
+}
+
+fn run_action(app_handle: &AppHandle, action: ShortcutAction) {
+    println!("[global_shortcuts] {:?}", action);
+    let result = match action {
+        ShortcutAction::HideAll => crate::events::emit_routed(
+            app_handle,
+            "blackout-display",
+            serde_json::json!({ "source": "shortcut" }),
+        ),
+        ShortcutAction::PauseDisplay => crate::events::emit_routed(
+            app_handle,
+            "pause-display",
+            serde_json::json!({ "source": "shortcut" }),
+        ),
+        ShortcutAction::ResumeDisplay => crate::events::emit_routed(
+            app_handle,
+            "resume-display",
+            serde_json::json!({ "source": "shortcut" }),
+        ),
+        ShortcutAction::ShowQr => {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = crate::open_qr_window(app_handle).await {
+                    eprintln!("[global_shortcuts] ShowQr: {}", e);
+                }
+            });
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("[global_shortcuts] {:?}: {}", action, e);
+    }
+}
+
+/// プラグインから呼ばれるキー入力の処理（押したときだけ反応する）
+pub fn handle(app_handle: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let action = REGISTERED.lock().ok().and_then(|registered| {
+        registered
+            .iter()
+            .find(|(registered, _)| registered == shortcut)
+            .map(|(_, action)| *action)
+    });
+    if let Some(action) = action {
+        run_action(app_handle, action);
+    }
+}
+
+#[tauri::command]
+pub fn get_global_shortcuts(app_handle: AppHandle) -> Result<ShortcutBindings, String> {
+    Ok(read_bindings(&app_handle))
+}
+
+/// 割り当てを保存してすぐに登録し直す（指定しなかった操作はキーなし）
+#[tauri::command]
+pub fn set_global_shortcuts(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    bindings: ShortcutBindings,
+) -> Result<ShortcutBindings, String> {
+    validate(&bindings)?;
+    let value = serde_json::to_string(&bindings)
+        .map_err(|e| format!("ショートカットのシリアライズに失敗しました: {}", e))?;
+    {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        db.save_app_setting(SHORTCUTS_KEY, &value)
+            .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    }
+    reload(&app_handle);
+    Ok(read_bindings(&app_handle))
+}
@@ -0,0 +1,151 @@
+use crate::db::current_timestamp;
+use crate::events::{emit_data_change, DataChangeEvent, GroundLineChangedPayload};
+use crate::workspace::WorkspaceState;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+// app_settings のキー: "ground_line:<monitor_id>:<background_id|default>"
+const KEY_PREFIX: &str = "ground_line";
+const DEFAULT_BACKGROUND: &str = "default";
+// 保存形式のバージョン（形式を変える場合に上げる）
+const SCHEMA_VERSION: u32 = 1;
+
+// 画面に対する割合（0.0〜1.0、左上原点）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct GroundPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroundLine {
+    pub version: u32,
+    pub monitor_id: String,
+    // Noneはモニター共通（背景ごとの設定がない場合に使う）
+    pub background_id: Option<String>,
+    // x昇順の折れ線
+    pub points: Vec<GroundPoint>,
+    pub updated_at: String,
+}
+
+fn setting_key(monitor_id: &str, background_id: Option<&str>) -> String {
+    format!(
+        "{}:{}:{}",
+        KEY_PREFIX,
+        monitor_id,
+        background_id.unwrap_or(DEFAULT_BACKGROUND)
+    )
+}
+
+fn validate_points(points: &mut [GroundPoint]) -> Result<(), String> {
+    if points.len() < 2 {
+        return Err("地面ラインには2点以上が必要です".to_string());
+    }
+    let in_range = |v: f32| (0.0..=1.0).contains(&v);
+    if points.iter().any(|p| !in_range(p.x) || !in_range(p.y)) {
+        return Err("地面ラインの座標は0〜1の範囲で指定してください".to_string());
+    }
+    points.sort_by(|a, b| a.x.total_cmp(&b.x));
+    Ok(())
+}
+
+/// 地面ラインを保存（モニター×背景ごと）
+#[tauri::command]
+pub fn save_ground_line(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    monitor_id: String,
+    background_id: Option<String>,
+    mut points: Vec<GroundPoint>,
+) -> Result<GroundLine, String> {
+    validate_points(&mut points)?;
+
+    let line = GroundLine {
+        version: SCHEMA_VERSION,
+        monitor_id,
+        background_id,
+        points,
+        updated_at: current_timestamp(),
+    };
+    let value = serde_json::to_string(&line)
+        .map_err(|e| format!("地面ラインのシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(
+        &setting_key(&line.monitor_id, line.background_id.as_deref()),
+        &value,
+    )
+    .map_err(|e| format!("Failed to save ground line: {}", e))?;
+
+    emit_data_change(
+        &app_handle,
+        DataChangeEvent::GroundLineChanged(GroundLineChangedPayload {
+            monitor_id: line.monitor_id.clone(),
+            background_id: line.background_id.clone(),
+            line: Some(line.clone()),
+        }),
+    )?;
+
+    Ok(line)
+}
+
+/// 地面ラインを取得（背景ごとの設定がなければモニター共通を返す）
+#[tauri::command]
+pub fn get_ground_line(
+    workspace: State<'_, WorkspaceState>,
+    monitor_id: String,
+    background_id: Option<String>,
+) -> Result<Option<GroundLine>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let mut keys = Vec::new();
+    if background_id.is_some() {
+        keys.push(setting_key(&monitor_id, background_id.as_deref()));
+    }
+    keys.push(setting_key(&monitor_id, None));
+
+    for key in keys {
+        let value = db
+            .get_app_setting(&key)
+            .map_err(|e| format!("Failed to get ground line: {}", e))?;
+        let Some(value) = value else {
+            continue;
+        };
+        match serde_json::from_str::<GroundLine>(&value) {
+            Ok(line) => return Ok(Some(line)),
+            Err(e) => eprintln!("[ground_line] invalid value for {}: {}", key, e),
+        }
+    }
+    Ok(None)
+}
+
+/// 地面ラインを削除（従来の ground_position に戻る）
+#[tauri::command]
+pub fn delete_ground_line(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    monitor_id: String,
+    background_id: Option<String>,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.delete_app_setting(&setting_key(&monitor_id, background_id.as_deref()))
+        .map_err(|e| format!("Failed to delete ground line: {}", e))?;
+
+    emit_data_change(
+        &app_handle,
+        DataChangeEvent::GroundLineChanged(GroundLineChangedPayload {
+            monitor_id,
+            background_id,
+            line: None,
+        }),
+    )
+}
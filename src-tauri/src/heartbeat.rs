@@ -0,0 +1,260 @@
+use crate::workspace::{read_global_setting, write_global_setting, WorkspaceState};
+use chrono::{Local, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+// 送信を止めたい会場向けのオプトアウト（グローバル設定に保存）
+const OPT_OUT_KEY: &str = "relay_heartbeat_opt_out";
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 15;
+const MAX_BACKOFF_SECS: u64 = 15 * 60;
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+static STARTED_AT: Lazy<Instant> = Lazy::new(Instant::now);
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+static HEARTBEAT_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatConfig {
+    pub base_url: String,
+    pub event_id: String,
+    pub pc_id: String,
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HeartbeatReport {
+    pcid: String,
+    app_version: String,
+    uptime_sec: u64,
+    images_processed_today: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+    sent_at: String,
+}
+
+/// 起動時刻を確定させる（setupで一度呼ぶ）
+pub fn mark_app_started() {
+    Lazy::force(&STARTED_AT);
+}
+
+/// 起動からの経過秒数
+pub fn uptime_secs() -> u64 {
+    STARTED_AT.elapsed().as_secs()
+}
+
+/// 直近のエラーを記録（次回のハートビートで送信）
+pub fn record_error(message: impl Into<String>) {
+    if let Ok(mut guard) = LAST_ERROR.lock() {
+        *guard = Some(message.into());
+    }
+}
+
+// 送信できたエラーを消す（送信中に記録された新しいエラーは残して次回送る）
+fn clear_reported_error(reported: Option<&str>) {
+    if let Ok(mut guard) = LAST_ERROR.lock() {
+        if reported.is_some() && guard.as_deref() == reported {
+            *guard = None;
+        }
+    }
+}
+
+fn is_opted_out(app_handle: &AppHandle) -> bool {
+    matches!(
+        read_global_setting(app_handle, OPT_OUT_KEY)
+            .ok()
+            .flatten()
+            .as_deref(),
+        Some("true")
+    )
+}
+
+// ローカル日付の0時以降に処理された画像数（ワークスペース未接続なら0）
+pub fn images_processed_today(app_handle: &AppHandle) -> i64 {
+    let Some(midnight) = Local::now().date_naive().and_hms_opt(0, 0, 0) else {
+        return 0;
+    };
+    let Some(since) = Local.from_local_datetime(&midnight).earliest() else {
+        return 0;
+    };
+    let since = since.with_timezone(&Utc).to_rfc3339();
+
+    let state: tauri::State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = state.lock() else {
+        return 0;
+    };
+    conn.get()
+        .ok()
+        .and_then(|db| db.count_processed_images_since(&since).ok())
+        .unwrap_or(0)
+}
+
+fn build_report(app_handle: &AppHandle, pc_id: &str) -> HeartbeatReport {
+    let last_error = LAST_ERROR.lock().ok().and_then(|guard| guard.clone());
+    HeartbeatReport {
+        pcid: pc_id.to_string(),
+        app_version: app_handle.package_info().version.to_string(),
+        uptime_sec: uptime_secs(),
+        images_processed_today: images_processed_today(app_handle),
+        last_error,
+        sent_at: Utc::now().to_rfc3339(),
+    }
+}
+
+// Relayからの応答（遠隔での一時停止指示・機能フラグ）
+#[derive(Debug, Deserialize, Default)]
+struct HeartbeatResponse {
+    #[serde(default)]
+    maintenance: Option<RemoteMaintenance>,
+    // 会場ごとの機能フラグの上書き
+    #[serde(default, rename = "featureFlags")]
+    feature_flags: Option<std::collections::HashMap<String, bool>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteMaintenance {
+    enabled: bool,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+async fn send_report(
+    app_handle: &AppHandle,
+    client: &reqwest::Client,
+    config: &HeartbeatConfig,
+    report: &HeartbeatReport,
+) -> Result<(), String> {
+    let url = format!(
+        "{}/e/{}/heartbeat",
+        config.base_url.trim_end_matches('/'),
+        config.event_id
+    );
+    let mut request = client
+        .post(&url)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .json(report);
+    // ライセンス済み端末ならデバイストークンを付与
+    if let Ok(Some(token)) = crate::load_license_token() {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("heartbeat request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("heartbeat rejected: HTTP {}", response.status()));
+    }
+    // 本文がない/形式が異なる応答は従来どおり無視する
+    let body: HeartbeatResponse = response.json().await.unwrap_or_default();
+    if let Some(maintenance) = body.maintenance {
+        crate::maintenance::apply_remote(app_handle, maintenance.enabled, maintenance.message);
+    }
+    if let Some(flags) = body.feature_flags {
+        crate::feature_flags::apply_remote(app_handle, flags);
+    }
+    Ok(())
+}
+
+// 連続失敗回数に応じた指数バックオフ（上限あり）
+fn next_delay(interval: Duration, failures: u32) -> Duration {
+    if failures == 0 {
+        return interval;
+    }
+    let factor = 2u64.saturating_pow(failures.min(16));
+    let secs = interval.as_secs().saturating_mul(factor);
+    Duration::from_secs(secs.min(MAX_BACKOFF_SECS))
+}
+
+fn stop_task() {
+    if let Ok(mut guard) = HEARTBEAT_TASK.lock() {
+        if let Some(handle) = guard.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// ハートビート送信を開始（オプトアウト中・未設定の場合は開始しない）
+#[tauri::command]
+pub async fn start_relay_heartbeat(
+    app_handle: AppHandle,
+    config: HeartbeatConfig,
+) -> Result<bool, String> {
+    if config.base_url.trim().is_empty()
+        || config.event_id.trim().is_empty()
+        || config.pc_id.trim().is_empty()
+    {
+        return Ok(false);
+    }
+    if is_opted_out(&app_handle) {
+        println!("[heartbeat] opted out; not starting");
+        return Ok(false);
+    }
+
+    stop_task();
+
+    let interval = Duration::from_secs(
+        config
+            .interval_secs
+            .unwrap_or(DEFAULT_INTERVAL_SECS)
+            .max(MIN_INTERVAL_SECS),
+    );
+    let client = reqwest::Client::new();
+
+    let handle = tauri::async_runtime::spawn(async move {
+        let mut failures: u32 = 0;
+        loop {
+            let report = build_report(&app_handle, &config.pc_id);
+            match send_report(&app_handle, &client, &config, &report).await {
+                Ok(()) => {
+                    failures = 0;
+                    clear_reported_error(report.last_error.as_deref());
+                }
+                Err(e) => {
+                    failures = failures.saturating_add(1);
+                    eprintln!("[heartbeat] {} (failures={})", e, failures);
+                }
+            }
+            tokio::time::sleep(next_delay(interval, failures)).await;
+        }
+    });
+
+    if let Ok(mut guard) = HEARTBEAT_TASK.lock() {
+        *guard = Some(handle);
+    }
+    Ok(true)
+}
+
+/// ハートビート送信を停止
+#[tauri::command]
+pub fn stop_relay_heartbeat() -> Result<(), String> {
+    stop_task();
+    Ok(())
+}
+
+/// オプトアウト設定を保存（有効化した場合は即座に停止）
+#[tauri::command]
+pub async fn set_relay_heartbeat_opt_out(
+    app_handle: AppHandle,
+    opt_out: bool,
+) -> Result<(), String> {
+    write_global_setting(
+        &app_handle,
+        OPT_OUT_KEY,
+        if opt_out { "true" } else { "false" },
+    )?;
+    if opt_out {
+        stop_task();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_relay_heartbeat_opt_out(app_handle: AppHandle) -> Result<bool, String> {
+    Ok(is_opted_out(&app_handle))
+}
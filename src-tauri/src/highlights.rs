@@ -0,0 +1,429 @@
+use chrono::{Local, NaiveTime};
+use image::{imageops, Rgba, RgbaImage};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::workspace::{read_global_setting, WorkspaceState};
+
+// app_settings のキー
+const CLOSING_TIME_KEY: &str = "highlights_closing_time";
+pub const EXPORT_DIR_KEY: &str = "highlights_export_dir";
+const LAST_GENERATED_KEY: &str = "highlights_last_date";
+// ffmpegの場所（グローバル設定。未設定ならPATHから探す）
+const FFMPEG_PATH_KEY: &str = "ffmpeg_path";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// 動画に入れるキャラクター数と1体あたりの表示秒数
+const TOP_CHARACTERS: i64 = 10;
+const SECONDS_PER_CHARACTER: f32 = 2.5;
+const FRAME_WIDTH: u32 = 1280;
+const FRAME_HEIGHT: u32 = 720;
+const BACKGROUND: Rgba<u8> = Rgba([24, 24, 40, 255]);
+const BAR_COLOR: Rgba<u8> = Rgba([255, 196, 0, 255]);
+
+// 操作回数はメモリで集計し、定期的にDBへ書き込む
+static PENDING_COUNTS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HighlightsSchedule {
+    // 閉場時刻 "HH:MM"（Noneなら自動生成しない）
+    pub closing_time: Option<String>,
+    pub export_dir: Option<String>,
+    pub last_generated: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ControlStat {
+    pub image_id: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct HighlightsExported {
+    path: String,
+    characters: usize,
+}
+
+/// スマホからの操作を1回記録
+pub fn record_control(image_id: &str) {
+    if let Ok(mut counts) = PENDING_COUNTS.lock() {
+        *counts.entry(image_id.to_string()).or_insert(0) += 1;
+    }
+}
+
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn flush_counts(app_handle: &AppHandle) {
+    let counts: Vec<(String, u64)> = match PENDING_COUNTS.lock() {
+        Ok(mut pending) => pending.drain().collect(),
+        Err(_) => return,
+    };
+    if counts.is_empty() {
+        return;
+    }
+
+    let state: State<WorkspaceState> = app_handle.state();
+    let result = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())
+        .and_then(|conn| {
+            conn.get()?
+                .add_control_counts(&today(), &counts)
+                .map_err(|e| format!("Failed to save control stats: {}", e))
+        });
+    if let Err(e) = result {
+        eprintln!("[highlights] {}", e);
+    }
+}
+
+fn read_schedule(app_handle: &AppHandle) -> Result<HighlightsSchedule, String> {
+    let state: State<WorkspaceState> = app_handle.state();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let get = |key: &str| {
+        db.get_app_setting(key)
+            .map_err(|e| format!("Failed to get app setting: {}", e))
+    };
+    Ok(HighlightsSchedule {
+        closing_time: get(CLOSING_TIME_KEY)?,
+        export_dir: get(EXPORT_DIR_KEY)?,
+        last_generated: get(LAST_GENERATED_KEY)?,
+    })
+}
+
+// 出力先（未設定ならワークスペースの exports/highlights）
+/// 動画の出力先（作業フォルダ .highlights-YYYYMMDD もここに作られる）
+pub fn current_export_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    export_dir(app_handle, &read_schedule(app_handle)?)
+}
+
+fn export_dir(app_handle: &AppHandle, schedule: &HighlightsSchedule) -> Result<PathBuf, String> {
+    if let Some(dir) = schedule
+        .export_dir
+        .as_ref()
+        .filter(|d| !d.trim().is_empty())
+    {
+        // 保存時に確認しているが、許可から外したフォルダには書かない
+        return crate::path_sandbox::check_write(app_handle, dir);
+    }
+    let state: State<WorkspaceState> = app_handle.state();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    conn.workspace_root()
+        .map(|root| root.join("exports").join("highlights"))
+        .ok_or_else(|| "ワークスペースが選択されていません".to_string())
+}
+
+// キャラクター画像を中央に配置し、下部に操作回数のバーを描いたフレーム
+fn render_frame(character: &Path, count: i64, max_count: i64) -> Result<RgbaImage, String> {
+    let mut frame = RgbaImage::from_pixel(FRAME_WIDTH, FRAME_HEIGHT, BACKGROUND);
+
+    let img = image::open(character).map_err(|e| {
+        format!(
+            "画像の読み込みに失敗しました({}): {}",
+            character.display(),
+            e
+        )
+    })?;
+    let fitted = img.resize(
+        FRAME_WIDTH - 200,
+        FRAME_HEIGHT - 200,
+        imageops::FilterType::Lanczos3,
+    );
+    let x = (FRAME_WIDTH - fitted.width()) / 2;
+    let y = (FRAME_HEIGHT - 120 - fitted.height()) / 2 + 20;
+    imageops::overlay(&mut frame, &fitted.to_rgba8(), x as i64, y as i64);
+
+    let ratio = if max_count > 0 {
+        count as f32 / max_count as f32
+    } else {
+        0.0
+    };
+    let bar_width = ((FRAME_WIDTH - 160) as f32 * ratio).max(8.0) as u32;
+    for bx in 80..80 + bar_width {
+        for by in FRAME_HEIGHT - 70..FRAME_HEIGHT - 50 {
+            frame.put_pixel(bx, by, BAR_COLOR);
+        }
+    }
+    Ok(frame)
+}
+
+// drawtextのテキスト内で特別な意味を持つ文字をエスケープ
+fn escape_drawtext(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(':', "\\:")
+        .replace('\'', "\\'")
+        .replace('%', "\\%")
+}
+
+/// 設定済みのffmpegの場所（未設定なら "ffmpeg"）
+pub(crate) fn ffmpeg_path(app_handle: &AppHandle) -> String {
+    read_global_setting(app_handle, FFMPEG_PATH_KEY)
+        .ok()
+        .flatten()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "ffmpeg".to_string())
+}
+
+pub(crate) fn run_ffmpeg(ffmpeg: &str, args: &[String]) -> Result<(), String> {
+    let output = Command::new(ffmpeg)
+        .args(args)
+        .output()
+        .map_err(|e| format!("ffmpegを起動できませんでした({}): {}", ffmpeg, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: String = stderr.lines().rev().take(3).collect::<Vec<_>>().join(" / ");
+        Err(format!("ffmpegが失敗しました: {}", tail))
+    }
+}
+
+/// 当日の操作回数上位のキャラクターでハイライト動画を作成し、出力パスを返す
+pub fn generate(app_handle: &AppHandle) -> Result<String, String> {
+    flush_counts(app_handle);
+    let day = today();
+    let schedule = read_schedule(app_handle)?;
+    let out_dir = export_dir(app_handle, &schedule)?;
+
+    let characters: Vec<(PathBuf, i64)> = {
+        let state: State<WorkspaceState> = app_handle.state();
+        let conn = state
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        let stats = db
+            .get_control_stats(&day, TOP_CHARACTERS)
+            .map_err(|e| format!("Failed to get control stats: {}", e))?;
+        stats
+            .into_iter()
+            .filter_map(|(id, count)| {
+                let meta = db.get_image(&id).ok().flatten()?;
+                let path = meta.resolve_file_path();
+                path.exists().then_some((path, count))
+            })
+            .collect()
+    };
+    if characters.is_empty() {
+        return Err("本日の操作記録がないためハイライト動画を作成できません".to_string());
+    }
+    let drawings_today = crate::heartbeat::images_processed_today(app_handle);
+    let controls_today: i64 = characters.iter().map(|(_, c)| c).sum();
+
+    let work_dir = out_dir.join(format!(".highlights-{}", day));
+    std::fs::create_dir_all(&work_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    // フレーム画像とconcat用のリスト（最後のファイルは仕様上もう一度書く）
+    let max_count = characters[0].1;
+    let mut list = String::new();
+    let mut last_frame = String::new();
+    for (index, (path, count)) in characters.iter().enumerate() {
+        let frame = render_frame(path, *count, max_count)?;
+        let frame_path = work_dir.join(format!("frame_{:02}.png", index));
+        frame
+            .save(&frame_path)
+            .map_err(|e| format!("フレームの保存に失敗しました: {}", e))?;
+        last_frame = frame_path.to_string_lossy().replace('\'', "'\\''");
+        list.push_str(&format!(
+            "file '{}'\nduration {}\n",
+            last_frame, SECONDS_PER_CHARACTER
+        ));
+    }
+    list.push_str(&format!("file '{}'\n", last_frame));
+    let list_path = work_dir.join("frames.txt");
+    std::fs::write(&list_path, list).map_err(|e| format!("Failed to write frame list: {}", e))?;
+
+    // 順位と回数、当日の合計をdrawtextで重ねる
+    let mut filters = vec![format!(
+        "drawtext=text='{}':fontsize=32:fontcolor=white:x=40:y=30",
+        escape_drawtext(&format!(
+            "{}  drawings {}  controls {}",
+            day, drawings_today, controls_today
+        ))
+    )];
+    for (index, (_, count)) in characters.iter().enumerate() {
+        let start = index as f32 * SECONDS_PER_CHARACTER;
+        filters.push(format!(
+            "drawtext=text='{}':fontsize=48:fontcolor=white:x=80:y=h-130:enable='between(t,{},{})'",
+            escape_drawtext(&format!("No.{}  x{}", index + 1, count)),
+            start,
+            start + SECONDS_PER_CHARACTER
+        ));
+    }
+
+    let output = out_dir.join(format!("highlights-{}.mp4", day.replace('-', "")));
+    let ffmpeg = ffmpeg_path(app_handle);
+    let args = |filter: &str| -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            "-y".into(),
+            "-f".into(),
+            "concat".into(),
+            "-safe".into(),
+            "0".into(),
+            "-i".into(),
+            list_path.to_string_lossy().to_string(),
+            "-vf".into(),
+            format!("{}format=yuv420p", filter),
+            "-r".into(),
+            "30".into(),
+        ];
+        args.push(output.to_string_lossy().to_string());
+        args
+    };
+
+    // drawtextが使えないffmpeg（フォント未設定など）では文字なしで作り直す
+    let overlay = format!("{},", filters.join(","));
+    if let Err(e) = run_ffmpeg(&ffmpeg, &args(&overlay)) {
+        eprintln!("[highlights] 文字なしで再試行します: {}", e);
+        run_ffmpeg(&ffmpeg, &args(""))?;
+    }
+
+    let _ = std::fs::remove_dir_all(&work_dir);
+    println!("[highlights] exported {}", output.display());
+    Ok(output.to_string_lossy().to_string())
+}
+
+fn closing_time_passed(schedule: &HighlightsSchedule) -> bool {
+    let Some(closing) = schedule
+        .closing_time
+        .as_deref()
+        .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+    else {
+        return false;
+    };
+    Local::now().time() >= closing && schedule.last_generated.as_deref() != Some(&today())
+}
+
+fn mark_generated(app_handle: &AppHandle) -> Result<(), String> {
+    let state: State<WorkspaceState> = app_handle.state();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    conn.get()?
+        .save_internal_setting(LAST_GENERATED_KEY, &today())
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+/// 操作回数の書き込みと閉場時刻の自動生成を行うスレッドを起動
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        flush_counts(&app_handle);
+
+        let Ok(schedule) = read_schedule(&app_handle) else {
+            continue;
+        };
+        if !closing_time_passed(&schedule) {
+            continue;
+        }
+        // 失敗しても同じ日に何度も再試行しないよう先に記録する
+        if let Err(e) = mark_generated(&app_handle) {
+            eprintln!("[highlights] {}", e);
+            continue;
+        }
+        match generate(&app_handle) {
+            Ok(path) => {
+                let _ = crate::events::emit_routed(
+                    &app_handle,
+                    "highlights-exported",
+                    HighlightsExported {
+                        path,
+                        characters: TOP_CHARACTERS as usize,
+                    },
+                );
+            }
+            Err(e) => {
+                eprintln!("[highlights] {}", e);
+                crate::heartbeat::record_error(format!("highlights: {}", e));
+                let _ = crate::events::emit_routed(
+                    &app_handle,
+                    "highlights-error",
+                    serde_json::json!({ "error": e }),
+                );
+            }
+        }
+    });
+}
+
+/// 閉場時刻と出力先を設定（closing_time が None なら自動生成しない）
+#[tauri::command]
+pub fn set_highlights_schedule(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    closing_time: Option<String>,
+    export_dir: Option<String>,
+) -> Result<(), String> {
+    if let Some(time) = closing_time.as_deref() {
+        NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("閉場時刻は HH:MM 形式で指定してください: {}", time))?;
+    }
+    // 出力先は許可範囲の中だけ（ワークスペースの接続をロックする前に確認する）
+    if let Some(dir) = export_dir.as_deref().filter(|d| !d.trim().is_empty()) {
+        crate::path_sandbox::check_write(&app_handle, dir)?;
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    for (key, value) in [
+        (CLOSING_TIME_KEY, closing_time),
+        (EXPORT_DIR_KEY, export_dir),
+    ] {
+        match value.filter(|v| !v.trim().is_empty()) {
+            Some(value) => db.save_app_setting(key, &value),
+            None => db.delete_app_setting(key),
+        }
+        .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_highlights_schedule(app_handle: AppHandle) -> Result<HighlightsSchedule, String> {
+    read_schedule(&app_handle)
+}
+
+/// ハイライト動画を今すぐ作成
+#[tauri::command]
+pub async fn generate_highlights_now(app_handle: AppHandle) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || generate(&app_handle))
+        .await
+        .map_err(|e| format!("ハイライト動画の作成に失敗しました: {}", e))?
+}
+
+/// 指定日（省略時は今日）の操作回数ランキング
+#[tauri::command]
+pub fn get_control_stats(
+    app_handle: AppHandle,
+    day: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<ControlStat>, String> {
+    flush_counts(&app_handle);
+    let state: State<WorkspaceState> = app_handle.state();
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let stats = db
+        .get_control_stats(
+            &day.unwrap_or_else(today),
+            limit.unwrap_or(TOP_CHARACTERS).clamp(1, 500),
+        )
+        .map_err(|e| format!("Failed to get control stats: {}", e))?;
+    Ok(stats
+        .into_iter()
+        .map(|(image_id, count)| ControlStat { image_id, count })
+        .collect())
+}
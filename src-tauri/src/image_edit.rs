@@ -0,0 +1,220 @@
+use crate::db::{Database, ImageMetadata};
+use crate::events::{emit_data_change, DataChangeEvent, ImageUpsertedPayload};
+use crate::workspace::WorkspaceState;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::DynamicImage;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+/// 画像IDから保存済みファイルを読み込む
+pub fn load_image(db: &Database, id: &str) -> Result<(ImageMetadata, DynamicImage), String> {
+    let meta = db
+        .get_image(id)
+        .map_err(|e| format!("Failed to get image: {}", e))?
+        .ok_or_else(|| format!("画像が見つかりません: {}", id))?;
+    let path = meta.resolve_file_path();
+    let img = image::open(&path)
+        .map_err(|e| format!("画像の読み込みに失敗しました({}): {}", path.display(), e))?;
+    Ok((meta, img))
+}
+
+/// 編集後の画像を元のファイルへ書き戻し、サイズ情報を更新してImageUpsertedを通知
+pub fn store_image(
+    app_handle: &AppHandle,
+    db: &Database,
+    meta: &ImageMetadata,
+    img: &DynamicImage,
+) -> Result<(), String> {
+    let path = meta.resolve_file_path();
+    save_to_path(img, &path)?;
+
+    let size = std::fs::metadata(&path)
+        .map(|m| m.len() as i64)
+        .unwrap_or(meta.size);
+    db.update_image_dimensions(&meta.id, img.width() as i32, img.height() as i32, size)
+        .map_err(|e| format!("Failed to update image dimensions: {}", e))?;
+
+    if let Some(saved) = db
+        .get_image(&meta.id)
+        .map_err(|e| format!("Failed to re-fetch image metadata: {}", e))?
+    {
+        emit_data_change(
+            app_handle,
+            DataChangeEvent::ImageUpserted(ImageUpsertedPayload::from(&saved)),
+        )?;
+    }
+    Ok(())
+}
+
+// 拡張子の形式で保存（JPEGは透過を持てないためRGBへ変換）
+fn save_to_path(img: &DynamicImage, path: &Path) -> Result<(), String> {
+    let format = image::ImageFormat::from_path(path).unwrap_or(image::ImageFormat::Png);
+    let result = if format == image::ImageFormat::Jpeg {
+        DynamicImage::ImageRgb8(img.to_rgb8()).save_with_format(path, format)
+    } else {
+        img.save_with_format(path, format)
+    };
+    result.map_err(|e| format!("画像の保存に失敗しました({}): {}", path.display(), e))
+}
+
+/// 画像を時計回りに回転（90/180/270度、負の値は反時計回り）
+#[tauri::command]
+pub async fn rotate_image(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+    degrees: i32,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let (meta, img) = load_image(db, &id)?;
+    let rotated = match degrees.rem_euclid(360) {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        0 => return Ok(()),
+        _ => return Err(format!("回転角度は90度単位で指定してください: {}", degrees)),
+    };
+
+    store_image(&app_handle, db, &meta, &rotated)
+}
+
+/// 画像を反転（"horizontal" / "vertical"）
+#[tauri::command]
+pub async fn flip_image(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+    axis: String,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let (meta, img) = load_image(db, &id)?;
+    let flipped = match axis.as_str() {
+        "horizontal" => img.fliph(),
+        "vertical" => img.flipv(),
+        _ => return Err(format!("反転方向が不正です: {}", axis)),
+    };
+
+    store_image(&app_handle, db, &meta, &flipped)
+}
+
+// タッチアップ編集用のデータ（画像とアルファマスクをPNGのデータURLで渡す）
+#[derive(Debug, serde::Serialize)]
+pub struct TouchupSession {
+    pub image_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub image: String,
+    // 白=不透明、黒=透明のグレースケール
+    pub mask: String,
+}
+
+fn encode_png_data_url(img: &DynamicImage) -> Result<String, String> {
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    Ok(format!("data:image/png;base64,{}", STANDARD.encode(buf)))
+}
+
+// データURL（またはBase64文字列）からPNGを読み込む
+fn decode_png(data: &str) -> Result<DynamicImage, String> {
+    let base64_str = data
+        .find("base64,")
+        .map(|pos| &data[pos + 7..])
+        .unwrap_or(data);
+    let bytes = STANDARD
+        .decode(base64_str.trim())
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+        .map_err(|e| format!("マスク画像の読み込みに失敗しました: {}", e))
+}
+
+// 上書き前の画像を versions/ に退避（タッチアップのやり直し用）
+fn backup_current_version(meta: &ImageMetadata) -> Result<(), String> {
+    let path = meta.resolve_file_path();
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+    let versions_dir = dir.join("versions");
+    std::fs::create_dir_all(&versions_dir)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let backup = versions_dir.join(format!(
+        "{}-{}.{}",
+        meta.id,
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        extension
+    ));
+    std::fs::copy(&path, &backup)
+        .map_err(|e| format!("旧バージョンの保存に失敗しました: {}", e))?;
+    Ok(())
+}
+
+/// 手動タッチアップを開始（処理済み画像と現在のアルファマスクを返す）
+#[tauri::command]
+pub async fn open_touchup_session(
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+) -> Result<TouchupSession, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let (meta, img) = load_image(db, &id)?;
+    let rgba = img.to_rgba8();
+    let mask = image::GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        image::Luma([rgba.get_pixel(x, y)[3]])
+    });
+
+    Ok(TouchupSession {
+        image_id: meta.id,
+        width: rgba.width(),
+        height: rgba.height(),
+        image: encode_png_data_url(&img)?,
+        mask: encode_png_data_url(&DynamicImage::ImageLuma8(mask))?,
+    })
+}
+
+/// 編集済みマスクをアルファとして合成し、新しいバージョンとして保存
+#[tauri::command]
+pub async fn apply_touchup(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+    mask_png: String,
+) -> Result<(), String> {
+    let mask = decode_png(&mask_png)?.to_luma8();
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let (meta, img) = load_image(db, &id)?;
+    let mut rgba = img.to_rgba8();
+    if mask.dimensions() != rgba.dimensions() {
+        return Err(format!(
+            "マスクのサイズが画像と一致しません: mask={}x{} image={}x{}",
+            mask.width(),
+            mask.height(),
+            rgba.width(),
+            rgba.height()
+        ));
+    }
+
+    for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+        pixel[3] = mask.get_pixel(x, y)[0];
+    }
+
+    backup_current_version(&meta)?;
+    store_image(&app_handle, db, &meta, &DynamicImage::ImageRgba8(rgba))
+}
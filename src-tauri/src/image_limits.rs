@@ -0,0 +1,197 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use tauri::{AppHandle, Manager, State};
+
+use crate::workspace::WorkspaceState;
+
+// 制限値を保存する app_settings のキー
+const IMAGE_LIMITS_KEY: &str = "image_size_limits";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ImageSizeLimits {
+    // これを超える画素数は背景除去の前に縮小する
+    pub downscale_pixels: u64,
+    // これを超える画素数は読み込まずに拒否する
+    pub reject_pixels: u64,
+}
+
+impl Default for ImageSizeLimits {
+    fn default() -> Self {
+        Self {
+            // 4000x3000 相当
+            downscale_pixels: 12_000_000,
+            // A3 600dpi 相当
+            reject_pixels: 80_000_000,
+        }
+    }
+}
+
+/// 縮小したときの通知（"image-downscaled"）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDownscaled {
+    pub file_name: String,
+    pub original_width: u32,
+    pub original_height: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 拒否したときの通知（"image-rejected"）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageRejected {
+    pub code: &'static str,
+    pub file_name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: u64,
+    pub limit: u64,
+}
+
+fn validate(limits: &ImageSizeLimits) -> Result<(), String> {
+    if limits.downscale_pixels == 0 || limits.reject_pixels < limits.downscale_pixels {
+        return Err(
+            "画像サイズの上限は 0 < 縮小する画素数 <= 拒否する画素数 で指定してください"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn read_limits(app_handle: &AppHandle) -> ImageSizeLimits {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(IMAGE_LIMITS_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<ImageSizeLimits>(&value).ok())
+        .filter(|limits| validate(limits).is_ok())
+        .unwrap_or_default()
+}
+
+fn output_format(format: ImageFormat) -> (ImageFormat, &'static str) {
+    match format {
+        ImageFormat::Jpeg => (ImageFormat::Jpeg, "image/jpeg"),
+        _ => (ImageFormat::Png, "image/png"),
+    }
+}
+
+/// 背景除去の前にヘッダーだけで寸法を確認し、大きすぎる画像は縮小・拒否する
+/// 縮小した場合は (画像データ, MIMEタイプ) を返す。上限内ならNone
+pub fn preflight(
+    app_handle: &AppHandle,
+    image_data: &[u8],
+    file_name: &str,
+) -> Result<Option<(Vec<u8>, &'static str)>, String> {
+    let reader = ImageReader::new(Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| format!("画像形式の判定に失敗しました: {}", e))?;
+    let Some(format) = reader.format() else {
+        // 判定できない形式はサイドカー側の判断に任せる
+        return Ok(None);
+    };
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|e| format!("画像サイズの取得に失敗しました: {}", e))?;
+
+    let limits = read_limits(app_handle);
+    let pixels = width as u64 * height as u64;
+    if pixels > limits.reject_pixels {
+        let rejected = ImageRejected {
+            code: "IMAGE_TOO_LARGE",
+            file_name: file_name.to_string(),
+            width,
+            height,
+            pixels,
+            limit: limits.reject_pixels,
+        };
+        let _ = crate::events::emit_routed(app_handle, "image-rejected", rejected);
+        return Err(format!(
+            "IMAGE_TOO_LARGE: {}x{} ({}画素) は上限 {}画素 を超えているため取り込めません",
+            width, height, pixels, limits.reject_pixels
+        ));
+    }
+    if pixels <= limits.downscale_pixels {
+        return Ok(None);
+    }
+
+    // 画素数が上限に収まる倍率で縮小
+    let scale = (limits.downscale_pixels as f64 / pixels as f64).sqrt();
+    let new_width = ((width as f64 * scale).floor() as u32).max(1);
+    let new_height = ((height as f64 * scale).floor() as u32).max(1);
+    let img = image::load_from_memory_with_format(image_data, format)
+        .map_err(|e| format!("画像の読み込みに失敗しました: {}", e))?;
+    let resized = img.resize_exact(new_width, new_height, FilterType::Lanczos3);
+
+    let (out_format, mime_type) = output_format(format);
+    let resized = if out_format == ImageFormat::Jpeg {
+        DynamicImage::ImageRgb8(resized.to_rgb8())
+    } else {
+        resized
+    };
+    let mut buf = Vec::new();
+    resized
+        .write_to(&mut Cursor::new(&mut buf), out_format)
+        .map_err(|e| format!("縮小画像の書き出しに失敗しました: {}", e))?;
+
+    println!(
+        "[image_limits] downscaled {} from {}x{} to {}x{}",
+        file_name, width, height, new_width, new_height
+    );
+    let _ = crate::events::emit_routed(
+        app_handle,
+        "image-downscaled",
+        ImageDownscaled {
+            file_name: file_name.to_string(),
+            original_width: width,
+            original_height: height,
+            width: new_width,
+            height: new_height,
+        },
+    );
+    Ok(Some((buf, mime_type)))
+}
+
+/// データURL版（フロントエンドから直接送られる画像用）
+pub fn preflight_data_url(app_handle: &AppHandle, data_url: String) -> Result<String, String> {
+    let Some(pos) = data_url.find("base64,") else {
+        return Ok(data_url);
+    };
+    let bytes = STANDARD
+        .decode(data_url[pos + 7..].trim())
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    match preflight(app_handle, &bytes, "upload")? {
+        Some((resized, mime_type)) => Ok(format!(
+            "data:{};base64,{}",
+            mime_type,
+            STANDARD.encode(resized)
+        )),
+        None => Ok(data_url),
+    }
+}
+
+#[tauri::command]
+pub fn get_image_size_limits(app_handle: AppHandle) -> Result<ImageSizeLimits, String> {
+    Ok(read_limits(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_image_size_limits(
+    workspace: State<'_, WorkspaceState>,
+    limits: ImageSizeLimits,
+) -> Result<(), String> {
+    validate(&limits)?;
+    let value = serde_json::to_string(&limits)
+        .map_err(|e| format!("画像サイズ上限のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(IMAGE_LIMITS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
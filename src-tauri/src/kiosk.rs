@@ -0,0 +1,166 @@
+// 展示用のキオスク表示（2台目のディスプレイで全画面・最前面・カーソル非表示・誤って閉じない）
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, WebviewWindow, WindowEvent};
+
+// キオスク表示中のウィンドウ
+static KIOSK_WINDOWS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// 閉じる操作の監視を登録済みのウィンドウ（登録は一度だけ）
+static CLOSE_GUARDED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    pub index: usize,
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+fn is_kiosk(label: &str) -> bool {
+    KIOSK_WINDOWS
+        .lock()
+        .map(|windows| windows.contains(label))
+        .unwrap_or(false)
+}
+
+fn get_window(app_handle: &AppHandle, label: &str) -> Result<WebviewWindow, String> {
+    app_handle
+        .get_webview_window(label)
+        .ok_or_else(|| format!("ウィンドウ '{}' が見つかりません", label))
+}
+
+// キオスク表示中は閉じる操作を無視する
+fn guard_close(window: &WebviewWindow) {
+    let label = window.label().to_string();
+    let Ok(mut guarded) = CLOSE_GUARDED.lock() else {
+        return;
+    };
+    if !guarded.insert(label.clone()) {
+        return;
+    }
+    window.on_window_event(move |event| match event {
+        WindowEvent::CloseRequested { api, .. } if is_kiosk(&label) => {
+            println!("[kiosk] close blocked for window: {}", label);
+            api.prevent_close();
+        }
+        // 同じラベルで作り直されたときに登録し直せるようにする
+        WindowEvent::Destroyed => {
+            if let Ok(mut windows) = KIOSK_WINDOWS.lock() {
+                windows.remove(&label);
+            }
+            if let Ok(mut guarded) = CLOSE_GUARDED.lock() {
+                guarded.remove(&label);
+            }
+        }
+        _ => {}
+    });
+}
+
+/// 接続されているディスプレイの一覧
+#[tauri::command]
+pub fn list_monitors(app_handle: AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app_handle
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| *monitor.position());
+    let monitors = app_handle
+        .available_monitors()
+        .map_err(|e| format!("ディスプレイ一覧の取得に失敗しました: {}", e))?;
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| MonitorInfo {
+            index,
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+            is_primary: primary == Some(*monitor.position()),
+        })
+        .collect())
+}
+
+/// 指定ディスプレイでキオスク表示にする
+#[tauri::command]
+pub fn enter_kiosk_mode(
+    app_handle: AppHandle,
+    window_label: String,
+    monitor_index: Option<usize>,
+) -> Result<(), String> {
+    let window = get_window(&app_handle, &window_label)?;
+    if let Some(index) = monitor_index {
+        let monitors = window
+            .available_monitors()
+            .map_err(|e| format!("ディスプレイ一覧の取得に失敗しました: {}", e))?;
+        let monitor = monitors
+            .get(index)
+            .ok_or_else(|| format!("ディスプレイ {} が見つかりません", index))?;
+        // 全画面は現在のディスプレイに対して行われるため、先に移動する
+        window
+            .set_fullscreen(false)
+            .map_err(|e| format!("全画面の解除に失敗しました: {}", e))?;
+        window
+            .set_position(*monitor.position())
+            .map_err(|e| format!("ウィンドウの移動に失敗しました: {}", e))?;
+    }
+    window
+        .set_fullscreen(true)
+        .map_err(|e| format!("全画面表示に失敗しました: {}", e))?;
+    window
+        .set_always_on_top(true)
+        .map_err(|e| format!("最前面表示の設定に失敗しました: {}", e))?;
+    // 対応していない環境もあるため失敗しても続ける
+    if let Err(e) = window.set_cursor_visible(false) {
+        eprintln!("[kiosk] failed to hide cursor: {}", e);
+    }
+
+    if let Ok(mut windows) = KIOSK_WINDOWS.lock() {
+        windows.insert(window_label.clone());
+    }
+    guard_close(&window);
+    let _ = window.set_focus();
+    Ok(())
+}
+
+/// キオスク表示を解除する（ウィンドウ未指定ならすべて）
+#[tauri::command]
+pub fn exit_kiosk_mode(app_handle: AppHandle, window_label: Option<String>) -> Result<(), String> {
+    // 解除に失敗したウィンドウはキオスク状態のまま残し、再試行できるようにする
+    let labels: Vec<String> = {
+        let windows = KIOSK_WINDOWS
+            .lock()
+            .map_err(|_| "キオスク状態のロックに失敗しました".to_string())?;
+        match window_label {
+            Some(label) => windows.get(&label).cloned().into_iter().collect(),
+            None => windows.iter().cloned().collect(),
+        }
+    };
+    for label in labels {
+        // 既に閉じられたウィンドウは解除済みとみなす
+        if let Some(window) = app_handle.get_webview_window(&label) {
+            window
+                .set_fullscreen(false)
+                .map_err(|e| format!("全画面の解除に失敗しました: {}", e))?;
+            window
+                .set_always_on_top(false)
+                .map_err(|e| format!("最前面表示の解除に失敗しました: {}", e))?;
+            if let Err(e) = window.set_cursor_visible(true) {
+                eprintln!("[kiosk] failed to show cursor: {}", e);
+            }
+        }
+        if let Ok(mut windows) = KIOSK_WINDOWS.lock() {
+            windows.remove(&label);
+        }
+    }
+    Ok(())
+}
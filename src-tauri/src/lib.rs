@@ -3,29 +3,81 @@ use std::fs;
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 #[cfg(debug_assertions)]
 use tauri::menu::{Menu, SubmenuBuilder};
-use tauri::{Emitter, LogicalPosition, LogicalSize, Manager, Position, Size, State};
-
+use tauri::{LogicalPosition, LogicalSize, Manager, Position, Size, State};
+
+mod access_log;
+mod admin_api;
+mod atomic_file;
+mod background_scheduler;
+mod character_gif;
+mod clock;
+mod cloud_intake;
+mod command_metrics;
+mod command_permissions;
 mod db;
+mod diagnostics;
+mod display_expiry;
+mod display_layout;
+mod emote_filter;
 mod events;
+mod export;
+mod export_crypto;
+mod feature_flags;
+mod file_name;
 mod file_watcher;
+mod frame_capture;
+mod global_settings;
+mod global_shortcuts;
+mod ground_line;
+mod heartbeat;
+mod highlights;
+mod image_edit;
+mod image_limits;
+mod kiosk;
+mod licensing;
+mod maintenance;
+mod output_background;
+mod path_sandbox;
+mod print;
+mod provisioning;
+mod qr_batch;
 mod qr_manager;
+mod rate_limit;
+mod reaper;
+mod recording;
+mod relay_client;
+mod retention;
+mod scene_state;
+mod secret_store;
 mod server_state;
+mod share_links;
+mod sidecar_idle;
+mod storage;
+mod support_bundle;
+mod sync_safety;
+mod takeaway;
+mod template;
+#[cfg(feature = "test-harness")]
+mod test_harness;
+mod tls;
+mod web_auth;
 mod web_server;
 mod websocket;
 mod workspace;
 use db::{
-    current_timestamp, generate_id, ImageMetadata, MovementSettings, ProcessedImagePreview,
-    UserSettings,
+    current_timestamp, generate_id, BackgroundSettings, ControlSmoothing, ImageMetadata,
+    MovementPreset, MovementSettings, ProcessedImagePreview, UserSettings,
 };
 use events::{
-    emit_data_change, AnimationSettingsChangedPayload, AppSettingChangedPayload,
-    AudioUpdatedPayload, DataChangeEvent, DeletionTimeChangedPayload, GroundPositionChangedPayload,
-    ImageDeletedPayload, ImageUpsertedPayload,
+    emit_data_change, AnimationSettingsBatchChangedPayload, AnimationSettingsChangedPayload,
+    AppSettingChangedPayload, AudioUpdatedPayload, BackgroundChangedPayload, DataChangeEvent,
+    DeletionTimeChangedPayload, GroundPositionChangedPayload, ImageDeletedPayload,
+    ImageUpsertedPayload, ImageWithSettingsSavedPayload,
 };
-use keyring::Entry;
 use once_cell::sync::Lazy;
 use qr_manager::QrManager;
 use server_state::ServerState;
@@ -38,6 +90,9 @@ pub struct ProcessResult {
     pub success: bool,
     pub image: Option<String>,
     pub error: Option<String>,
+    // OCRの読み取り結果（ocrコマンドのみ）
+    #[serde(default)]
+    pub text: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -62,6 +117,35 @@ struct PythonProcess {
 }
 
 static PYTHON_PROCESS: Mutex<Option<PythonProcess>> = Mutex::new(None);
+// Pythonプロセスへの送信待ち（処理中を含む）の件数
+static PYTHON_PENDING: AtomicUsize = AtomicUsize::new(0);
+
+// 送信待ち件数を増減するガード
+struct PendingPythonRequest;
+
+impl PendingPythonRequest {
+    fn enter() -> Self {
+        PYTHON_PENDING.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for PendingPythonRequest {
+    fn drop(&mut self) {
+        PYTHON_PENDING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Pythonプロセスの状態（起動済みか, 送信待ち件数）
+pub(crate) fn python_runtime_status() -> (bool, usize) {
+    let running = match PYTHON_PROCESS.try_lock() {
+        Ok(guard) => guard.is_some(),
+        // 処理中でロックされている場合は起動済み
+        Err(std::sync::TryLockError::WouldBlock) => true,
+        Err(_) => false,
+    };
+    (running, PYTHON_PENDING.load(Ordering::SeqCst))
+}
 
 // DevTools 開閉状態の簡易トラッカー（ウィンドウラベル単位）
 static DEVTOOLS_OPEN: Lazy<Mutex<HashMap<String, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
@@ -369,16 +453,46 @@ fn ensure_python_process() -> Result<(), String> {
         None => true,
     };
     if need_spawn {
+        sidecar_idle::cold_start_began();
         let proc = spawn_python_process()?;
         *guard = Some(proc);
     }
     Ok(())
 }
 
+/// 処理待ちがなければPythonプロセスを終了する（アイドル停止用）。停止したらtrue
+pub(crate) fn shutdown_python_process_if_idle() -> bool {
+    let Ok(mut guard) = PYTHON_PROCESS.try_lock() else {
+        return false;
+    };
+    if PYTHON_PENDING.load(Ordering::SeqCst) > 0 {
+        return false;
+    }
+    let Some(mut proc) = guard.take() else {
+        return false;
+    };
+
+    // まずは正常終了を依頼し、応じなければ強制終了
+    let _ = proc
+        .stdin
+        .write_all(b"{\"command\":\"shutdown\"}\n")
+        .and_then(|_| proc.stdin.flush());
+    for _ in 0..20 {
+        if let Ok(Some(_)) = proc.child.try_wait() {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let _ = proc.child.kill();
+    let _ = proc.child.wait();
+    true
+}
+
 fn python_send_and_wait(
     app_handle: Option<&tauri::AppHandle>,
     msg: serde_json::Value,
 ) -> Result<ProcessResult, String> {
+    let _pending = PendingPythonRequest::enter();
     ensure_python_process()?;
     let mut guard = PYTHON_PROCESS
         .lock()
@@ -420,12 +534,14 @@ fn python_send_and_wait(
             line.to_string()
         };
         println!("[Rust] python <= {}", log_line);
+        sidecar_idle::touch();
 
         if let Ok(output) = serde_json::from_str::<PythonOutput>(line) {
             match output {
                 PythonOutput::Progress { value } => {
                     if let Some(handle) = app_handle {
-                        let _ = handle.emit(
+                        let _ = events::emit_routed(
+                            handle,
                             "image-processing-progress",
                             ImageProcessingProgress { value },
                         );
@@ -436,8 +552,13 @@ fn python_send_and_wait(
                     break;
                 }
             }
+        } else if line.contains("\"status\"") && line.contains("ready") {
+            // health/warmup の応答（起動完了）
+            sidecar_idle::mark_ready();
         }
     }
+    // 起動直後の最初の処理が終わった時点でも準備完了とみなす
+    sidecar_idle::mark_ready();
 
     match final_result {
         Some(r) => Ok(r),
@@ -482,33 +603,58 @@ pub fn process_image_sync(image_data: String) -> Result<ProcessResult, String> {
     python_send_and_wait(None, command)
 }
 
+// 名前欄のOCR（内部使用向け）
+pub fn ocr_name_sync(
+    image_data: String,
+    name_box: &db::NameBox,
+    languages: &[String],
+) -> Result<ProcessResult, String> {
+    let command = serde_json::json!({
+        "command": "ocr",
+        "image": image_data,
+        "box": name_box,
+        "languages": languages.join("+"),
+    });
+    python_send_and_wait(None, command)
+}
+
 #[tauri::command]
 async fn process_image(
     app_handle: tauri::AppHandle,
     image_data: String,
 ) -> Result<ProcessResult, String> {
+    let image_data = image_limits::preflight_data_url(&app_handle, image_data)?;
     let command = serde_json::json!({
         "command": "process",
         "image": image_data,
     });
-    python_send_and_wait(Some(&app_handle), command)
+    let mut result = python_send_and_wait(Some(&app_handle), command)?;
+    if let Some(image) = result.image.take() {
+        result.image = Some(output_background::apply_data_url(&app_handle, image)?);
+    }
+    Ok(result)
 }
 
 // カスタムディレクトリへのファイル操作コマンド
 #[tauri::command]
-async fn ensure_directory(path: String) -> Result<(), String> {
-    let dir_path = Path::new(&path);
+async fn ensure_directory(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    let dir_path = path_sandbox::check_write(&app_handle, &path)?;
 
     if !dir_path.exists() {
-        fs::create_dir_all(&dir_path).map_err(|e| format!("Failed to create directory: {}", e))?;
+        fs::create_dir_all(dir_path).map_err(|e| format!("Failed to create directory: {}", e))?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn write_file_absolute(path: String, contents: Vec<u8>) -> Result<(), String> {
-    let file_path = Path::new(&path);
+async fn write_file_absolute(
+    app_handle: tauri::AppHandle,
+    path: String,
+    contents: Vec<u8>,
+    durable: Option<bool>,
+) -> Result<(), String> {
+    let file_path = path_sandbox::check_write(&app_handle, &path)?;
 
     // 親ディレクトリが存在しない場合は作成
     if let Some(parent) = file_path.parent() {
@@ -518,28 +664,31 @@ async fn write_file_absolute(path: String, contents: Vec<u8>) -> Result<(), Stri
         }
     }
 
-    fs::write(&file_path, contents).map_err(|e| format!("Failed to write file: {}", e))?;
+    // 途中で落ちても元のファイルが残るよう置き換えで書く（durable: false なら fsync を省く）
+    atomic_file::write(&file_path, contents, durable.unwrap_or(true))
+        .map_err(|e| format!("Failed to write file: {}", e))?;
 
     Ok(())
 }
 
 #[tauri::command]
-async fn read_file_absolute(path: String) -> Result<Vec<u8>, String> {
-    fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))
+async fn read_file_absolute(app_handle: tauri::AppHandle, path: String) -> Result<Vec<u8>, String> {
+    let file_path = path_sandbox::check_read(&app_handle, &path)?;
+    fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))
 }
 
 #[tauri::command]
-async fn file_exists_absolute(path: String) -> Result<bool, String> {
-    Ok(Path::new(&path).exists())
+async fn file_exists_absolute(app_handle: tauri::AppHandle, path: String) -> Result<bool, String> {
+    Ok(path_sandbox::check_exists(&app_handle, &path)?.exists())
 }
 
 #[tauri::command]
-async fn delete_file_absolute(path: String) -> Result<(), String> {
-    let file_path = Path::new(&path);
+async fn delete_file_absolute(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    let file_path = path_sandbox::check_write(&app_handle, &path)?;
 
     // ファイルが存在する場合のみ削除
     if file_path.exists() {
-        fs::remove_file(&file_path).map_err(|e| format!("Failed to delete file: {}", e))?;
+        fs::remove_file(file_path).map_err(|e| format!("Failed to delete file: {}", e))?;
         println!("[delete_file_absolute] deleted path={}", path);
     }
 
@@ -585,9 +734,12 @@ async fn save_image_metadata(
                     audio_type: "sound_effect".to_string(),
                 }),
             )?,
-            "background" => {
-                emit_data_change(&state.app_handle, DataChangeEvent::BackgroundChanged)?
-            }
+            "background" => emit_data_change(
+                &state.app_handle,
+                DataChangeEvent::BackgroundChanged(BackgroundChangedPayload::for_background(
+                    db, &image_id,
+                )),
+            )?,
             _ => {}
         }
     }
@@ -664,36 +816,48 @@ async fn delete_image(
         .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
     let db = conn.get()?;
 
+    delete_image_and_notify(&state.app_handle, db, &id)
+}
+
+// 画像行を削除して各ウィンドウへ通知（コマンド/管理APIで共通）
+pub(crate) fn delete_image_and_notify(
+    app_handle: &tauri::AppHandle,
+    db: &db::Database,
+    id: &str,
+) -> Result<(), String> {
     // 削除前に画像情報を取得してタイプを確認
     let image_type = db
-        .get_image(&id)
+        .get_image(id)
         .map_err(|e| format!("Failed to get image: {}", e))?
         .map(|img| img.image_type)
         .unwrap_or_else(|| "unknown".to_string());
 
     // 画像を削除
-    db.delete_image(&id)
+    db.delete_image(id)
         .map_err(|e| format!("Failed to delete image: {}", e))?;
 
     emit_data_change(
-        &state.app_handle,
-        DataChangeEvent::ImageDeleted(ImageDeletedPayload { id: id.clone() }),
+        app_handle,
+        DataChangeEvent::ImageDeleted(ImageDeletedPayload { id: id.to_string() }),
     )?;
 
     match image_type.as_str() {
         "bgm" => emit_data_change(
-            &state.app_handle,
+            app_handle,
             DataChangeEvent::AudioUpdated(AudioUpdatedPayload {
                 audio_type: "bgm".to_string(),
             }),
         )?,
         "sound_effect" => emit_data_change(
-            &state.app_handle,
+            app_handle,
             DataChangeEvent::AudioUpdated(AudioUpdatedPayload {
                 audio_type: "sound_effect".to_string(),
             }),
         )?,
-        "background" => emit_data_change(&state.app_handle, DataChangeEvent::BackgroundChanged)?,
+        "background" => emit_data_change(
+            app_handle,
+            DataChangeEvent::BackgroundChanged(BackgroundChangedPayload::default()),
+        )?,
         _ => {}
     }
 
@@ -779,11 +943,51 @@ fn save_movement_settings(
 
     db.save_movement_settings(&settings)
         .map_err(|e| format!("Failed to save movement settings: {}", e))?;
+    let smoothing = db
+        .get_control_smoothing(&settings.movement_type)
+        .map_err(|e| format!("Failed to get control smoothing: {}", e))?;
 
     // イベントを発行
     emit_data_change(
         &state.app_handle,
-        DataChangeEvent::AnimationSettingsChanged(AnimationSettingsChangedPayload { image_id }),
+        DataChangeEvent::AnimationSettingsChanged(AnimationSettingsChangedPayload {
+            image_id,
+            smoothing: Some(smoothing),
+        }),
+    )?;
+
+    Ok(())
+}
+
+// データベース操作: 画像メタデータと動き設定を同時に保存
+#[tauri::command]
+fn save_image_with_settings(
+    state: State<AppState>,
+    workspace: State<WorkspaceState>,
+    metadata: ImageMetadata,
+    mut settings: MovementSettings,
+) -> Result<(), String> {
+    settings.image_id = metadata.id.clone();
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.save_image_with_settings(&metadata, &settings)
+        .map_err(|e| format!("Failed to save image with settings: {}", e))?;
+
+    let saved = db
+        .get_image(&metadata.id)
+        .map_err(|e| format!("Failed to re-fetch image metadata: {}", e))?
+        .unwrap_or(metadata);
+
+    // 画像と動き設定をまとめた単一イベントを発行
+    emit_data_change(
+        &state.app_handle,
+        DataChangeEvent::ImageWithSettingsSaved(ImageWithSettingsSavedPayload::new(
+            &saved, &settings,
+        )),
     )?;
 
     Ok(())
@@ -817,40 +1021,214 @@ fn get_all_movement_settings(
         .map_err(|e| format!("Failed to get all movement settings: {}", e))
 }
 
+// 動き設定プリセットの作成（同名は上書き）
+#[tauri::command]
+fn create_movement_preset(
+    workspace: State<WorkspaceState>,
+    name: String,
+    movement_type: String,
+    movement_pattern: String,
+    speed: f32,
+    size: String,
+) -> Result<MovementPreset, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("プリセット名が空です".to_string());
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let now = current_timestamp();
+    let preset = MovementPreset {
+        id: generate_id(),
+        name: name.clone(),
+        movement_type,
+        movement_pattern,
+        speed,
+        size,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    db.save_movement_preset(&preset)
+        .map_err(|e| format!("Failed to save movement preset: {}", e))?;
+
+    db.get_movement_preset_by_name(&name)
+        .map_err(|e| format!("Failed to get movement preset: {}", e))?
+        .ok_or_else(|| "プリセットの保存に失敗しました".to_string())
+}
+
+// 動き設定プリセットの一覧
+#[tauri::command]
+fn list_movement_presets(workspace: State<WorkspaceState>) -> Result<Vec<MovementPreset>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.get_movement_presets()
+        .map_err(|e| format!("Failed to get movement presets: {}", e))
+}
+
+// 動き設定プリセットの削除
+#[tauri::command]
+fn delete_movement_preset(workspace: State<WorkspaceState>, name: String) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.delete_movement_preset(&name)
+        .map_err(|e| format!("Failed to delete movement preset: {}", e))
+}
+
+// 動き設定プリセットを複数画像へ一括適用
+#[tauri::command]
+fn apply_movement_preset(
+    state: State<AppState>,
+    workspace: State<WorkspaceState>,
+    name: String,
+    image_ids: Vec<String>,
+) -> Result<usize, String> {
+    if image_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let preset = db
+        .get_movement_preset_by_name(&name)
+        .map_err(|e| format!("Failed to get movement preset: {}", e))?
+        .ok_or_else(|| format!("プリセットが見つかりません: {}", name))?;
+
+    db.apply_movement_preset(&preset, &image_ids)
+        .map_err(|e| format!("Failed to apply movement preset: {}", e))?;
+
+    // 画像ごとではなく一括イベントで通知
+    let count = image_ids.len();
+    emit_data_change(
+        &state.app_handle,
+        DataChangeEvent::AnimationSettingsBatchChanged(AnimationSettingsBatchChangedPayload {
+            image_ids,
+            smoothing: None,
+        }),
+    )?;
+
+    Ok(count)
+}
+
+// 操作のなめらかさ設定の取得（未保存なら既定値）
+#[tauri::command]
+fn get_control_smoothing(
+    workspace: State<WorkspaceState>,
+    movement_type: String,
+) -> Result<ControlSmoothing, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.get_control_smoothing(&movement_type)
+        .map_err(|e| format!("Failed to get control smoothing: {}", e))
+}
+
+// 操作のなめらかさ設定の保存（該当する動きタイプの画像へ一括通知）
+#[tauri::command]
+fn save_control_smoothing(
+    state: State<AppState>,
+    workspace: State<WorkspaceState>,
+    smoothing: ControlSmoothing,
+) -> Result<ControlSmoothing, String> {
+    if smoothing.movement_type.trim().is_empty() {
+        return Err("動きタイプを指定してください".to_string());
+    }
+    let positive = |v: f32| v.is_finite() && v > 0.0;
+    if !positive(smoothing.acceleration) || !positive(smoothing.max_velocity) {
+        return Err("加速度と最大速度は0より大きい値を指定してください".to_string());
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    db.save_control_smoothing(&smoothing)
+        .map_err(|e| format!("Failed to save control smoothing: {}", e))?;
+    let saved = db
+        .get_control_smoothing(&smoothing.movement_type)
+        .map_err(|e| format!("Failed to get control smoothing: {}", e))?;
+    let image_ids = db
+        .get_image_ids_by_movement_type(&smoothing.movement_type)
+        .map_err(|e| format!("Failed to get movement settings: {}", e))?;
+
+    emit_data_change(
+        &state.app_handle,
+        DataChangeEvent::AnimationSettingsBatchChanged(AnimationSettingsBatchChangedPayload {
+            image_ids,
+            smoothing: Some(saved.clone()),
+        }),
+    )?;
+
+    Ok(saved)
+}
+
 // アプリケーション設定の保存
 #[tauri::command]
 fn save_app_setting(
+    window: tauri::Window,
     state: State<AppState>,
     workspace: State<WorkspaceState>,
     key: String,
     value: String,
 ) -> Result<(), String> {
+    // ハイライト動画の出力先は許可範囲の中だけ（ワークスペースの接続をロックする前に確認する）
+    if key == highlights::EXPORT_DIR_KEY && !value.trim().is_empty() {
+        path_sandbox::check_write(&state.app_handle, &value)?;
+    }
     let conn = workspace
         .lock()
         .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
     let db = conn.get()?;
 
-    db.save_app_setting(&key, &value)
+    db.save_app_setting_from(&key, &value, window.label())
         .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    let version = db
+        .settings_version()
+        .map_err(|e| format!("Failed to get settings version: {}", e))?;
 
-    // 特定の設定項目の場合、専用のイベントを発行
-    let event = match key.as_str() {
+    emit_data_change(&state.app_handle, app_setting_event(key, value, version))?;
+
+    Ok(())
+}
+
+// 設定変更の通知イベント（特定の設定項目は専用のイベント）
+pub(crate) fn app_setting_event(key: String, value: String, version: i64) -> DataChangeEvent {
+    match key.as_str() {
         "ground_position" => {
             if let Ok(position) = value.parse::<i32>() {
                 DataChangeEvent::GroundPositionChanged(GroundPositionChangedPayload { position })
             } else {
-                DataChangeEvent::AppSettingChanged(AppSettingChangedPayload { key, value })
+                DataChangeEvent::AppSettingChanged(AppSettingChangedPayload {
+                    key,
+                    value,
+                    version,
+                })
             }
         }
-        "deletion_time" => DataChangeEvent::DeletionTimeChanged(DeletionTimeChangedPayload {
-            time: value.clone(),
+        "deletion_time" => {
+            DataChangeEvent::DeletionTimeChanged(DeletionTimeChangedPayload { time: value })
+        }
+        _ => DataChangeEvent::AppSettingChanged(AppSettingChangedPayload {
+            key,
+            value,
+            version,
         }),
-        _ => DataChangeEvent::AppSettingChanged(AppSettingChangedPayload { key, value }),
-    };
-
-    emit_data_change(&state.app_handle, event)?;
-
-    Ok(())
+    }
 }
 
 // アプリケーション設定の取得
@@ -883,12 +1261,125 @@ fn get_app_settings(
         .map_err(|e| format!("Failed to get app settings: {}", e))
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingChange {
+    key: String,
+    // 削除された設定はnull
+    value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingsDelta {
+    version: i64,
+    // trueなら changes は全件（受信側はキャッシュを置き換える）
+    full: bool,
+    changes: Vec<SettingChange>,
+}
+
+// 指定バージョン以降の設定変更を取得（ウィンドウ間の差分同期用）
+#[tauri::command]
+fn get_settings_delta(
+    workspace: State<WorkspaceState>,
+    since_version: i64,
+) -> Result<SettingsDelta, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let version = db
+        .settings_version()
+        .map_err(|e| format!("Failed to get settings version: {}", e))?;
+    // ワークスペースが切り替わった等で手元のほうが新しい場合は全件を返す
+    let full = since_version <= 0 || since_version > version;
+    let changes = db
+        .get_settings_delta(if full { 0 } else { since_version })
+        .map_err(|e| format!("Failed to get settings delta: {}", e))?
+        .into_iter()
+        .filter(|(_, value)| !full || value.is_some())
+        .map(|(key, value)| SettingChange { key, value })
+        .collect();
+    Ok(SettingsDelta {
+        version,
+        full,
+        changes,
+    })
+}
+
+// 背景の表示設定の取得（未設定なら既定値）
+#[tauri::command]
+fn get_background_settings(
+    workspace: State<WorkspaceState>,
+    background_id: String,
+) -> Result<BackgroundSettings, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    Ok(db
+        .get_background_settings(&background_id)
+        .map_err(|e| format!("Failed to get background settings: {}", e))?
+        .unwrap_or_else(|| BackgroundSettings::default_for(&background_id)))
+}
+
+// 背景の表示設定の更新（フィット方法・注視点・パララックス）
+#[tauri::command]
+fn update_background_settings(
+    state: State<AppState>,
+    workspace: State<WorkspaceState>,
+    settings: BackgroundSettings,
+) -> Result<(), String> {
+    if !matches!(
+        settings.fit_mode.as_str(),
+        "cover" | "contain" | "fill" | "none"
+    ) {
+        return Err(format!("不明なフィット方法です: {}", settings.fit_mode));
+    }
+    if !(0.0..=1.0).contains(&settings.focal_x) || !(0.0..=1.0).contains(&settings.focal_y) {
+        return Err("注視点は0〜1の範囲で指定してください".to_string());
+    }
+    if settings.parallax_layers.iter().any(|l| l.depth < 0.0) {
+        return Err("パララックスの奥行きは0以上で指定してください".to_string());
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+
+    let is_background = db
+        .get_image(&settings.background_id)
+        .map_err(|e| format!("Failed to get image: {}", e))?
+        .map(|img| img.image_type == "background")
+        .unwrap_or(false);
+    if !is_background {
+        return Err(format!(
+            "背景画像が見つかりません: {}",
+            settings.background_id
+        ));
+    }
+
+    db.save_background_settings(&settings)
+        .map_err(|e| format!("Failed to save background settings: {}", e))?;
+
+    emit_data_change(
+        &state.app_handle,
+        DataChangeEvent::BackgroundChanged(BackgroundChangedPayload::for_background(
+            db,
+            &settings.background_id,
+        )),
+    )
+}
+
 // フォルダ監視の開始
 #[tauri::command]
 fn start_folder_watching(
     state: State<AppState>,
     workspace: State<WorkspaceState>,
     watch_path: String,
+    options: Option<file_watcher::WatchOptions>,
 ) -> Result<(), String> {
     // 現在のワークスペースパスを取得（絶対パス）
     let conn = workspace
@@ -900,15 +1391,16 @@ fn start_folder_watching(
         conn.current_path
     );
 
+    if conn.current_path.is_none() {
+        return Err("ワークスペースが選択されていません".to_string());
+    }
     let workspace_path = conn
-        .current_path
-        .as_ref()
-        .ok_or("ワークスペースが選択されていません".to_string())?
-        .parent() // .nuriemonディレクトリの親を取得
-        .and_then(|p| p.parent()) // nuriemon.dbの親の親
+        .workspace_root()
         .ok_or("ワークスペースパスの取得に失敗しました".to_string())?
         .to_string_lossy()
         .to_string();
+    // 監視フォルダの許可範囲の確認で接続をロックするため、先に手放す
+    drop(conn);
 
     println!("[Rust] start_folder_watching - watch_path: {}", watch_path);
     println!(
@@ -916,7 +1408,18 @@ fn start_folder_watching(
         workspace_path
     );
 
-    file_watcher::start_folder_watching(state.app_handle.clone(), watch_path, workspace_path)
+    file_watcher::start_folder_watching(
+        state.app_handle.clone(),
+        watch_path,
+        workspace_path,
+        options.unwrap_or_default(),
+    )
+}
+
+// フォルダ監視の状態（再接続待ち・取り込み件数・直近のエラー）
+#[tauri::command]
+fn get_watcher_status() -> Result<file_watcher::WatcherStatus, String> {
+    Ok(file_watcher::watcher_status())
 }
 
 // フォルダ監視の停止
@@ -926,12 +1429,17 @@ fn stop_folder_watching() -> Result<(), String> {
     Ok(())
 }
 
-// Webサーバーの起動
+// Webサーバーの起動（tlsを指定した場合は設定として保存する）
 #[tauri::command]
 async fn start_web_server(
     state: State<'_, AppState>,
     server_state: State<'_, ServerState>,
+    tls: Option<bool>,
 ) -> Result<u16, String> {
+    if let Some(enabled) = tls {
+        tls::set_enabled(&state.app_handle, enabled)?;
+    }
+
     // すでに起動済みの場合はポート番号を返す
     if let Some(port) = server_state.get_server_port() {
         return Ok(port);
@@ -950,37 +1458,117 @@ async fn start_web_server(
         return Err("Webサーバー起動中です。少し待って再試行してください".to_string());
     }
 
-    // Webサーバーを起動
-    let result = web_server::start_web_server(state.app_handle.clone()).await;
-
-    match result {
-        Ok(port) => {
-            // QRマネージャーを初期化
-            let qr_manager = Arc::new(QrManager::new(port));
-            server_state.set_qr_manager(qr_manager);
-            // ポート番号を保存
-            server_state.set_server_port(port);
-            server_state.finish_starting();
-            Ok(port)
-        }
-        Err(e) => {
-            server_state.finish_starting();
-            Err(format!("Webサーバーの起動に失敗しました: {}", e))
+    let result = launch_web_server(&state.app_handle, &server_state).await;
+    server_state.finish_starting();
+    result
+}
+
+// 設定に従ってWebサーバーを起動し、ポートとQRマネージャーを登録する
+async fn launch_web_server(
+    app_handle: &tauri::AppHandle,
+    server_state: &ServerState,
+) -> Result<u16, String> {
+    let ports = web_server::candidate_ports(app_handle)?;
+    // レート制限値はワークスペースのapp_settingsから読み込む
+    rate_limit::reload(app_handle);
+
+    // HTTPSが有効なら証明書を用意（失敗時はHTTPのみで起動）
+    let tls_config = if tls::is_enabled(app_handle) {
+        match tls::load_server_config(app_handle) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("[tls] HTTPSを無効化して起動します: {}", e);
+                None
+            }
         }
+    } else {
+        None
+    };
+
+    // Webサーバーを起動
+    let running = web_server::start_web_server(app_handle.clone(), ports, tls_config)
+        .await
+        .map_err(|e| format!("Webサーバーの起動に失敗しました: {}", e))?;
+
+    // QRマネージャーを初期化
+    let qr_manager = Arc::new(QrManager::new(
+        app_handle.clone(),
+        running.http,
+        running.https,
+        server_state.web_auth.clone(),
+        crate::qr_manager::QrPolicy::load(app_handle),
+    ));
+    server_state.set_qr_manager(qr_manager);
+    // ポート番号と停止用ハンドルを保存
+    server_state.set_server_port(running.http);
+    server_state.set_https_port(running.https);
+    server_state.set_server_handle(running.handle);
+    Ok(running.http)
+}
+
+// Webサーバーの再起動（ポート/HTTPS設定の変更を反映。発行済みQRは再生成が必要）
+#[tauri::command]
+async fn restart_web_server(
+    state: State<'_, AppState>,
+    server_state: State<'_, ServerState>,
+) -> Result<u16, String> {
+    if !server_state.begin_starting() {
+        return Err("Webサーバー起動中です。少し待って再試行してください".to_string());
+    }
+
+    if let Some(handle) = server_state.take_running_server() {
+        // WebSocket接続の終了を待たずに即時停止する
+        handle.stop(false).await;
     }
+
+    let result = launch_web_server(&state.app_handle, &server_state).await;
+    server_state.finish_starting();
+    result
 }
 
-// QRコードの生成
+// Webサーバーの接続先（HTTP/HTTPS）を取得
+#[tauri::command]
+fn get_web_server_urls(server_state: State<'_, ServerState>) -> Result<serde_json::Value, String> {
+    let port = server_state
+        .get_server_port()
+        .ok_or("Webサーバーが起動していません".to_string())?;
+    let https_port = server_state.get_https_port();
+    // QRと同じ接続先を表示する（接続先の指定があればそれを使う）
+    let host = match server_state.get_qr_manager() {
+        Some(qr_manager) => qr_manager.preferred_host(),
+        None => local_ip_address::local_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| "localhost".to_string()),
+    };
+
+    Ok(serde_json::json!({
+        "httpPort": port,
+        "httpUrl": format!("http://{}:{}", host, port),
+        "httpsPort": https_port,
+        "httpsUrl": https_port.map(|p| format!("https://{}:{}", host, p)),
+    }))
+}
+
+// QRコードの生成（styleを省略した場合はQR表示設定に従う）
 #[tauri::command]
 fn generate_qr_code(
+    app_handle: tauri::AppHandle,
     image_id: String,
+    style: Option<crate::qr_manager::QrStyle>,
     server_state: State<'_, ServerState>,
 ) -> Result<serde_json::Value, String> {
     let qr_manager = server_state
         .get_qr_manager()
         .ok_or("Webサーバーが起動していません".to_string())?;
 
-    let (session_id, qr_code) = qr_manager.create_session(&image_id);
+    let style = match style {
+        Some(style) => {
+            style.validate()?;
+            style
+        }
+        None => crate::qr_manager::QrStyle::load(&app_handle),
+    };
+    let (session_id, qr_code) = qr_manager.create_session(&image_id, &style)?;
 
     Ok(serde_json::json!({
         "sessionId": session_id,
@@ -1009,62 +1597,205 @@ fn get_qr_session_status(
     }
 }
 
-// 任意文字列からQRコード（data URI）を生成（Relay用のURL等）
 #[tauri::command]
-fn generate_qr_from_text(text: String) -> Result<String, String> {
-    use base64::{engine::general_purpose, Engine as _};
-    use qrcode::{Color, QrCode};
+fn get_qr_policy(app_handle: tauri::AppHandle) -> Result<crate::qr_manager::QrPolicy, String> {
+    Ok(crate::qr_manager::QrPolicy::load(&app_handle))
+}
+
+// QRの有効期限・使い切り・同時接続数を設定（起動中のWebサーバーにもすぐ反映）
+#[tauri::command]
+fn configure_qr_policy(
+    workspace: State<'_, WorkspaceState>,
+    server_state: State<'_, ServerState>,
+    policy: crate::qr_manager::QrPolicy,
+) -> Result<(), String> {
+    policy.validate()?;
+    let value = serde_json::to_string(&policy)
+        .map_err(|e| format!("QRの設定のシリアライズに失敗しました: {}", e))?;
+    {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        db.save_app_setting(crate::qr_manager::QR_POLICY_KEY, &value)
+            .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    }
+    if let Some(qr_manager) = server_state.get_qr_manager() {
+        qr_manager.set_policy(policy);
+    }
+    Ok(())
+}
+
+// QRの接続先に選べるネットワークインターフェース
+#[tauri::command]
+fn list_network_interfaces() -> Result<Vec<crate::qr_manager::NetworkInterface>, String> {
+    Ok(crate::qr_manager::list_network_interfaces())
+}
+
+#[tauri::command]
+fn get_qr_host_settings(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::qr_manager::QrHostSettings, String> {
+    Ok(crate::qr_manager::QrHostSettings::load(&app_handle))
+}
+
+// QRのURLに使うインターフェース/ホスト/URLテンプレートを指定（次に発行するQRから反映）
+#[tauri::command]
+fn configure_qr_host(
+    workspace: State<'_, WorkspaceState>,
+    settings: crate::qr_manager::QrHostSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("接続先の設定のシリアライズに失敗しました: {}", e))?;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(crate::qr_manager::QR_HOST_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    Ok(())
+}
+
+// 発行済みのQRセッション一覧（スタッフが接続中のスマホを確認する）
+#[tauri::command]
+fn list_qr_sessions(
+    server_state: State<'_, ServerState>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let qr_manager = server_state
+        .get_qr_manager()
+        .ok_or("Webサーバーが起動していません".to_string())?;
+    let peers = websocket::session_peers();
+    Ok(qr_manager
+        .list_sessions()
+        .into_iter()
+        .map(|session| {
+            serde_json::json!({
+                "sessionId": session.session_id,
+                "imageId": session.image_id,
+                "connected": session.connected,
+                "ageSeconds": session.created_at.elapsed().as_secs(),
+                // 切断後も最後に参加した端末のIPを表示する
+                "remoteIp": peers.get(&session.session_id).or(session.remote_ip.as_ref()),
+                "joinedAt": session.joined_at,
+            })
+        })
+        .collect())
+}
 
-    let code = QrCode::new(text).map_err(|e| format!("QR_ENCODE_ERROR: {}", e))?;
-    let size = code.width();
-    let mut svg = format!(
-        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
-        size, size
+// セッションを取り消し、接続中のスマホを切断する
+#[tauri::command]
+fn revoke_qr_session(
+    session_id: String,
+    server_state: State<'_, ServerState>,
+) -> Result<serde_json::Value, String> {
+    let qr_manager = server_state
+        .get_qr_manager()
+        .ok_or("Webサーバーが起動していません".to_string())?;
+    let removed = qr_manager.revoke_session(&session_id);
+    let closed = websocket::kick_session(&session_id);
+    if !removed && closed == 0 {
+        return Err("セッションが見つかりません".to_string());
+    }
+    println!(
+        "[qr] revoked session {} (closed {} connection(s))",
+        crate::diagnostics::redact_id(&session_id),
+        closed
     );
-    for y in 0..size {
-        for x in 0..size {
-            if code[(x, y)] == Color::Dark {
-                svg.push_str(&format!(
-                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#000\"/>",
-                    x, y
-                ));
-            }
-        }
+    Ok(serde_json::json!({
+        "sessionId": session_id,
+        "closedConnections": closed,
+    }))
+}
+
+// 印刷用のPNGでQRコードを生成（output_path を指定するとファイルにも書き出す）
+#[tauri::command]
+async fn generate_qr_image(
+    text: String,
+    options: Option<crate::qr_manager::QrImageOptions>,
+    output_path: Option<String>,
+) -> Result<Vec<u8>, String> {
+    let options = options.unwrap_or_default();
+    let png =
+        tauri::async_runtime::spawn_blocking(move || qr_manager::render_qr_png(&text, &options))
+            .await
+            .map_err(|e| format!("QRコードの生成に失敗しました: {}", e))??;
+    if let Some(path) = output_path {
+        std::fs::write(&path, &png).map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
     }
-    svg.push_str("</svg>");
-    let encoded = general_purpose::STANDARD.encode(svg);
-    Ok(format!("data:image/svg+xml;base64,{}", encoded))
+    Ok(png)
+}
+
+// 任意文字列からQRコード（data URI）を生成（Relay用のURL等）
+#[tauri::command]
+fn generate_qr_from_text(app_handle: tauri::AppHandle, text: String) -> Result<String, String> {
+    qr_manager::render_qr_svg(&text, &qr_manager::QrStyle::load(&app_handle))
 }
 
 // QRコード表示ウィンドウを開く
 #[tauri::command]
-async fn open_animation_window(app: tauri::AppHandle) -> Result<(), String> {
+async fn open_animation_window(
+    app: tauri::AppHandle,
+    suffix: Option<String>,
+    monitor_index: Option<usize>,
+) -> Result<String, String> {
     use tauri::webview::WebviewWindowBuilder;
     use tauri::WebviewUrl;
 
+    // 2台目以降のプロジェクター用は "animation-<suffix>"（英小文字・数字・ハイフンのみ）
+    let label = match suffix
+        .as_deref()
+        .map(str::trim)
+        .filter(|suffix| !suffix.is_empty())
+    {
+        None => "animation".to_string(),
+        Some(suffix) => {
+            let valid = suffix
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid || suffix.len() > 32 {
+                return Err("ウィンドウ名には英小文字・数字・ハイフンのみ使用できます".to_string());
+            }
+            format!("animation-{}", suffix)
+        }
+    };
+
     // すでにウィンドウが存在する場合は前面に表示
-    if let Some(window) = app.get_webview_window("animation") {
+    if let Some(window) = app.get_webview_window(&label) {
         window
             .show()
             .map_err(|e| format!("ウィンドウの表示に失敗しました: {}", e))?;
         window
             .set_focus()
             .map_err(|e| format!("ウィンドウのフォーカスに失敗しました: {}", e))?;
-        return Ok(());
+        return Ok(label);
     }
 
     // 新しいウィンドウを作成
-    let _window =
-        WebviewWindowBuilder::new(&app, "animation", WebviewUrl::App("#/animation".into()))
-            .inner_size(1024.0, 768.0)
-            .title("ぬりえもん - アニメーション")
-            .resizable(true)
-            .build()
-            .map_err(|e| format!("アニメーションウィンドウの作成に失敗しました: {}", e))?;
+    let window = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App("#/animation".into()))
+        .inner_size(1024.0, 768.0)
+        .title("ぬりえもん - アニメーション")
+        .resizable(true)
+        .build()
+        .map_err(|e| format!("アニメーションウィンドウの作成に失敗しました: {}", e))?;
+    display_layout::apply_to_window(&app, &label);
+
+    // 表示先のディスプレイが指定されていればその左上へ移動
+    if let Some(index) = monitor_index {
+        let monitors = window
+            .available_monitors()
+            .map_err(|e| format!("ディスプレイ一覧の取得に失敗しました: {}", e))?;
+        let monitor = monitors
+            .get(index)
+            .ok_or_else(|| format!("ディスプレイ {} が見つかりません", index))?;
+        window
+            .set_position(*monitor.position())
+            .map_err(|e| format!("ウィンドウの移動に失敗しました: {}", e))?;
+    }
 
     // DevTools はデフォルトで開かない（ショートカットで開閉）
 
-    Ok(())
+    Ok(label)
 }
 
 #[tauri::command]
@@ -1086,6 +1817,7 @@ async fn open_qr_window(app: tauri::AppHandle) -> Result<(), String> {
         .resizable(true)
         .build()
         .map_err(|e| format!("ウィンドウの作成に失敗しました: {}", e))?;
+    display_layout::apply_to_window(&app, "qr-display");
     #[cfg(debug_assertions)]
     {
         window.open_devtools();
@@ -1100,7 +1832,12 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_store::Builder::default().build());
+        .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(global_shortcuts::handle)
+                .build(),
+        );
 
     #[cfg(debug_assertions)]
     {
@@ -1142,13 +1879,68 @@ pub fn run() {
             // ワークスペース接続の初期化
             let workspace_connection = WorkspaceState::new(WorkspaceConnection::new());
 
-            // サーバー状態の初期化
+            // 署名鍵の復元より前に秘密情報の代替保存先を設定する
+            if let Ok(dir) = app.path().app_data_dir() {
+                secret_store::init(dir);
+            }
+
+            // サーバー状態の初期化（Web認証モードは前回の設定を引き継ぐ）
             let server_state = ServerState::new();
+            server_state
+                .web_auth
+                .set_mode(web_auth::load_persisted_mode(app.handle()));
+            // 印刷済みのQRを再起動後も使えるよう署名鍵を引き継ぐ
+            server_state.web_auth.restore_secret();
 
             app.manage(app_state);
             app.manage(workspace_connection);
             app.manage(server_state);
 
+            // 稼働時間の起点を記録（ハートビート用）
+            heartbeat::mark_app_started();
+            // イベントの送り先（ウィンドウ役割ごと）を登録
+            events::register_default_routes();
+            maintenance::load(app.handle());
+            feature_flags::load(app.handle());
+
+            // 背景の時間帯スケジューラ
+            background_scheduler::start(app.handle().clone());
+
+            // 削除直前の画像の通知（アニメーションのフェードアウト用）
+            display_expiry::start(app.handle().clone());
+
+            // 無処理が続いたらサイドカーを停止（次の処理で自動的に再起動）
+            sidecar_idle::start(app.handle().clone());
+
+            // Webサーバーのアクセスログ（ワークスペースの logs/ へ書き出し）
+            access_log::start(app.handle().clone());
+            highlights::start(app.handle().clone());
+
+            // 放置されたセッションや作業ファイルを定期的に片付ける
+            reaper::start(app.handle().clone());
+
+            // ディスクの空き容量とワークスペースの使用量を監視
+            storage::start(app.handle().clone());
+
+            // 保持ルールに従って古い作品を非表示・削除する
+            retention::start(app.handle().clone());
+            // ライセンスの検証と期限前の更新
+            licensing::start(app.handle().clone());
+            // プロビジョニング設定の変更を再起動なしで反映
+            provisioning::start(app.handle().clone());
+            // ウィンドウの配置とディスプレイの抜き差しの監視
+            display_layout::start(app.handle().clone());
+            // 運営スタッフ用の緊急キー（割り当てはワークスペースごとに保存）
+            global_shortcuts::reload(app.handle());
+
+            // スマホ操作の遅延をデバッグ表示向けに定期通知
+            websocket::start_latency_reporter(app.handle().clone());
+            // 管理画面向けに接続状況を定期通知
+            websocket::start_metrics_reporter(app.handle().clone());
+
+            // 時刻ずれの確認（Relayのサーバー時刻と比較、失敗しても起動は継続）
+            tauri::async_runtime::spawn(clock::check_at_startup(app.handle().clone()));
+
             // 小文字 `nuriemon` への設定移行（旧フォルダ/大文字からの移行）
             if let Err(e) = migrate_lowercase_app_dirs(app) {
                 eprintln!("[setup:migration] warn: {}", e);
@@ -1223,60 +2015,276 @@ pub fn run() {
 
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            greet,
-            process_image,
-            warmup_python,
-            ensure_directory,
-            write_file_absolute,
-            read_file_absolute,
-            file_exists_absolute,
-            delete_file_absolute,
-            save_image_metadata,
-            get_all_images,
-            get_processed_images_preview,
-            get_image_metadata,
-            mark_display_started,
-            delete_image,
-            update_image_file_path,
-            save_user_settings,
-            get_user_settings,
-            get_image_counts,
-            generate_unique_id,
-            get_current_timestamp,
-            save_movement_settings,
-            get_movement_settings,
-            get_all_movement_settings,
-            save_app_setting,
-            get_app_setting,
-            get_app_settings,
-            // ワークスペース関連
-            workspace::initialize_workspace_db,
-            workspace::connect_workspace_db,
-            workspace::close_workspace_db,
-            workspace::save_global_setting,
-            workspace::get_global_setting,
-            read_bundle_global_settings,
-            read_user_provisioning_settings,
-            set_user_event_id,
-            read_env_provisioning_settings,
-            read_env_overrides,
-            // フォルダ監視
-            start_folder_watching,
-            stop_folder_watching,
-            // Webサーバーとスマホ連携
-            start_web_server,
-            generate_qr_code,
-            generate_qr_from_text,
-            get_qr_session_status,
-            open_qr_window,
-            open_animation_window,
-            save_license_token,
-            load_license_token,
-            delete_license_token,
-            open_devtools,
-            toggle_devtools
-        ])
+        .invoke_handler({
+            let handler = tauri::generate_handler![
+                greet,
+                process_image,
+                warmup_python,
+                sidecar_idle::set_sidecar_idle_minutes,
+                sidecar_idle::get_sidecar_idle_minutes,
+                ensure_directory,
+                write_file_absolute,
+                read_file_absolute,
+                file_exists_absolute,
+                delete_file_absolute,
+                save_image_metadata,
+                get_all_images,
+                get_processed_images_preview,
+                get_image_metadata,
+                mark_display_started,
+                delete_image,
+                update_image_file_path,
+                save_user_settings,
+                get_user_settings,
+                get_image_counts,
+                generate_unique_id,
+                get_current_timestamp,
+                save_movement_settings,
+                save_image_with_settings,
+                get_movement_settings,
+                get_all_movement_settings,
+                create_movement_preset,
+                list_movement_presets,
+                delete_movement_preset,
+                apply_movement_preset,
+                get_control_smoothing,
+                save_control_smoothing,
+                save_app_setting,
+                get_app_setting,
+                get_app_settings,
+                get_settings_delta,
+                // ワークスペース関連
+                workspace::initialize_workspace_db,
+                workspace::suggest_workspace_locations,
+                workspace::create_workspace,
+                sync_safety::check_workspace_location,
+                workspace::connect_workspace_db,
+                workspace::close_workspace_db,
+                workspace::get_workspace_info,
+                workspace::get_current_workspace,
+                workspace::set_workspace_display_name,
+                workspace::list_recent_workspaces,
+                workspace::switch_workspace,
+                workspace::force_unlock_workspace,
+                workspace::save_global_setting,
+                workspace::get_global_setting,
+                workspace::get_settings_history,
+                global_settings::get_global_settings,
+                global_settings::update_global_settings,
+                read_bundle_global_settings,
+                read_user_provisioning_settings,
+                set_user_event_id,
+                read_env_provisioning_settings,
+                read_env_overrides,
+                // フォルダ監視
+                start_folder_watching,
+                get_watcher_status,
+                stop_folder_watching,
+                // Relayへの稼働状況レポート
+                heartbeat::start_relay_heartbeat,
+                heartbeat::stop_relay_heartbeat,
+                heartbeat::set_relay_heartbeat_opt_out,
+                heartbeat::get_relay_heartbeat_opt_out,
+                // エクスポート
+                export::export_images,
+                export::decrypt_export,
+                // 背景の時間帯スケジュール
+                background_scheduler::add_background_schedule_entry,
+                background_scheduler::list_background_schedule,
+                background_scheduler::set_background_schedule_enabled,
+                background_scheduler::delete_background_schedule_entry,
+                // 時刻ずれの検出と補正
+                clock::check_clock_skew,
+                clock::get_clock_offset,
+                // 診断用の状態ダンプ
+                diagnostics::dump_runtime_state,
+                support_bundle::export_support_bundle,
+                scene_state::persist_scene_state,
+                scene_state::load_scene_state,
+                frame_capture::capture_animation_frame,
+                frame_capture::submit_animation_frame,
+                frame_capture::report_animation_frame_error,
+                recording::start_recording,
+                recording::stop_recording,
+                recording::get_recording_status,
+                print::list_printers,
+                print::get_print_template,
+                print::set_print_template,
+                print::print_image,
+                character_gif::export_character_gif,
+                share_links::create_share_link,
+                share_links::revoke_share_link,
+                share_links::list_share_links,
+                takeaway::get_takeaway_settings,
+                takeaway::set_takeaway_settings,
+                takeaway::get_takeaway_report,
+                licensing::get_license_status,
+                kiosk::list_monitors,
+                kiosk::enter_kiosk_mode,
+                kiosk::exit_kiosk_mode,
+                display_layout::get_display_layout,
+                display_layout::set_display_layout,
+                display_layout::apply_display_layout,
+                global_shortcuts::get_global_shortcuts,
+                global_shortcuts::set_global_shortcuts,
+                // 画像の回転・反転
+                image_edit::rotate_image,
+                image_edit::flip_image,
+                // 手動タッチアップ
+                image_edit::open_touchup_session,
+                image_edit::apply_touchup,
+                // 地面ラインのキャリブレーション
+                ground_line::save_ground_line,
+                ground_line::get_ground_line,
+                ground_line::delete_ground_line,
+                // 用紙テンプレート・名前欄OCR
+                template::save_template,
+                template::list_templates,
+                template::delete_template,
+                template::set_active_template,
+                template::get_active_template,
+                template::update_display_name,
+                // Webサーバーのレート制限
+                rate_limit::get_rate_limits,
+                rate_limit::set_rate_limits,
+                access_log::get_recent_access_logs,
+                // クラウドフォルダからの取り込み
+                cloud_intake::save_cloud_intake_token,
+                cloud_intake::delete_cloud_intake_token,
+                cloud_intake::start_cloud_intake,
+                cloud_intake::stop_cloud_intake,
+                cloud_intake::get_cloud_intake_status,
+                // 管理API
+                admin_api::get_admin_api_token,
+                admin_api::regenerate_admin_api_token,
+                // 大きな画像の縮小・拒否
+                image_limits::get_image_size_limits,
+                image_limits::set_image_size_limits,
+                output_background::get_output_background,
+                output_background::set_output_background,
+                reaper::reap_now,
+                reaper::get_reaper_settings,
+                reaper::set_reaper_settings,
+                storage::get_storage_usage,
+                storage::get_storage_settings,
+                storage::set_storage_settings,
+                retention::apply_retention_now,
+                retention::get_retention_settings,
+                retention::set_retention_settings,
+                // ハイライト動画
+                highlights::set_highlights_schedule,
+                highlights::get_highlights_schedule,
+                highlights::generate_highlights_now,
+                highlights::get_control_stats,
+                // 背景の表示設定
+                get_background_settings,
+                update_background_settings,
+                // ファイル名の正規化
+                file_name::sanitize_file_name,
+                file_name::generate_saved_file_name,
+                file_name::get_file_naming_template,
+                file_name::set_file_naming_template,
+                // 表示の残り時間
+                display_expiry::get_display_time_remaining,
+                // Webサーバーとスマホ連携
+                start_web_server,
+                restart_web_server,
+                get_web_server_urls,
+                generate_qr_code,
+                generate_qr_from_text,
+                get_qr_session_status,
+                websocket::send_to_mobile,
+                websocket::notify_controller,
+                websocket::get_controller_latency_stats,
+                websocket::get_ws_metrics,
+                relay_client::start_relay_client,
+                relay_client::stop_relay_client,
+                relay_client::get_relay_client_status,
+                relay_client::queue_relay_message,
+                relay_client::get_relay_queue_status,
+                relay_client::relay_register_pc,
+                relay_client::relay_pending_sid,
+                get_qr_policy,
+                configure_qr_policy,
+                list_network_interfaces,
+                get_qr_host_settings,
+                configure_qr_host,
+                list_qr_sessions,
+                revoke_qr_session,
+                generate_qr_image,
+                qr_batch::generate_qr_batch,
+                emote_filter::get_emote_allowlist,
+                emote_filter::set_emote_allowlist,
+                websocket::broadcast_to_mobiles,
+                websocket::get_controller_policy,
+                websocket::set_controller_policy,
+                websocket::get_tilt_control,
+                websocket::set_tilt_control,
+                // 一時停止（メンテナンスモード）
+                maintenance::set_maintenance_mode,
+                maintenance::get_maintenance_mode,
+                feature_flags::get_feature_flags,
+                feature_flags::set_feature_flag,
+                web_auth::set_web_auth_mode,
+                web_auth::get_web_auth_mode,
+                open_qr_window,
+                open_animation_window,
+                save_license_token,
+                load_license_token,
+                delete_license_token,
+                command_metrics::get_command_metrics,
+                path_sandbox::get_file_access_roots,
+                path_sandbox::pick_workspace_folder,
+                path_sandbox::add_file_access_root,
+                path_sandbox::remove_file_access_root,
+                open_devtools,
+                toggle_devtools
+            ];
+            #[cfg(feature = "test-harness")]
+            let test_handler = tauri::generate_handler![
+                test_harness::test_seed_workspace,
+                test_harness::test_emit_event,
+                test_harness::test_fake_controller
+            ];
+            // ウィンドウごとの許可リストを検証してからディスパッチ（コマンドごとに計測する）
+            move |invoke| {
+                let label = invoke.message.webview().label().to_string();
+                let command = invoke.message.command().to_string();
+                let arg_bytes = command_metrics::payload_size(invoke.message.payload());
+                let started = std::time::Instant::now();
+                if !command_permissions::is_allowed(&label, &command) {
+                    eprintln!(
+                        "[permissions] denied command '{}' from window '{}'",
+                        command, label
+                    );
+                    invoke.resolver.reject(format!(
+                        "COMMAND_NOT_PERMITTED: {} is not allowed in window {}",
+                        command, label
+                    ));
+                    command_metrics::record(
+                        &command,
+                        arg_bytes,
+                        started.elapsed(),
+                        Some("COMMAND_NOT_PERMITTED"),
+                    );
+                    return true;
+                }
+                #[cfg(feature = "test-harness")]
+                if command.starts_with("test_") {
+                    let handled = test_handler(invoke);
+                    command_metrics::record(&command, arg_bytes, started.elapsed(), None);
+                    return handled;
+                }
+                let handled = handler(invoke);
+                command_metrics::record(
+                    &command,
+                    arg_bytes,
+                    started.elapsed(),
+                    (!handled).then_some("COMMAND_NOT_FOUND"),
+                );
+                handled
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1329,33 +2337,15 @@ fn read_user_provisioning_settings(app: tauri::AppHandle) -> Result<Option<Strin
     Ok(Some(s))
 }
 
-/// ユーザー設定（AppConfig配下）の global_settings.json に eventId を保存（マージ書き込み）
+/// アプリのグローバル設定（AppData配下の global_settings.json）に eventId を保存（マージ書き込み）
 #[tauri::command]
 fn set_user_event_id(app: tauri::AppHandle, event_id: String) -> Result<(), String> {
-    let dir = app
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("app_config_dir error: {}", e))?;
-    let path = dir.join("global_settings.json");
-    // 既存を読み込み
-    let mut root: serde_json::Value = if path.exists() {
-        let s = std::fs::read_to_string(&path).unwrap_or_else(|_| "{}".into());
-        serde_json::from_str(&s).unwrap_or(serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
-    // relay オブジェクトを確保し、eventId を設定
-    if !root.get("relay").is_some() || !root.get("relay").unwrap().is_object() {
-        root["relay"] = serde_json::json!({});
-    }
-    root["relay"]["eventId"] = serde_json::Value::String(event_id.trim().to_string());
-    // ディレクトリ作成
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("create_dir_all error: {}", e))?;
-    }
-    // 保存（pretty）
-    let pretty = serde_json::to_string_pretty(&root).map_err(|e| format!("json error: {}", e))?;
-    std::fs::write(&path, pretty).map_err(|e| format!("write error: {}", e))?;
+    let path = workspace::global_settings_path(&app)?;
+    // relay.eventId だけを書き換え、他の項目はそのまま残す
+    global_settings::update_file(&path, |settings| {
+        settings.relay.event_id = Some(event_id.trim().to_string());
+        Ok(())
+    })?;
     Ok(())
 }
 
@@ -1395,37 +2385,55 @@ fn read_env_overrides() -> Result<Option<String>, String> {
     Ok(Some(s))
 }
 
-// ===== License device token (OS Keychain) =====
+// プロビジョニング設定から relay.<field> を解決（env上書き > envファイル > ユーザー > バンドル）
+pub(crate) fn resolve_relay_setting(app: &tauri::AppHandle, field: &str) -> Option<String> {
+    resolve_provisioning_setting(app, "relay", field)
+}
+
+// プロビジョニング設定から <section>.<field> を解決（優先順位は resolve_relay_setting と同じ）
+pub(crate) fn resolve_provisioning_setting(
+    app: &tauri::AppHandle,
+    section: &str,
+    field: &str,
+) -> Option<String> {
+    let sources = [
+        read_env_overrides().ok().flatten(),
+        read_env_provisioning_settings().ok().flatten(),
+        read_user_provisioning_settings(app.clone()).ok().flatten(),
+        read_bundle_global_settings(app.clone()).ok().flatten(),
+    ];
+    sources.into_iter().flatten().find_map(|s| {
+        serde_json::from_str::<serde_json::Value>(&s)
+            .ok()?
+            .get(section)?
+            .get(field)?
+            .as_str()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+// ===== License device token (OS Keychain、使えない環境では暗号化ファイル) =====
 #[tauri::command]
-fn save_license_token(token: String) -> Result<(), String> {
+fn save_license_token(app_handle: tauri::AppHandle, token: String) -> Result<(), String> {
     let (service, account) = license_token_account();
-    Entry::new(&service, &account)
-        .map_err(|e| format!("KEYCHAIN_INIT_ERROR: {}", e))?
-        .set_password(&token)
-        .map_err(|e| format!("KEYCHAIN_WRITE_ERROR: {}", e))
+    secret_store::save(&service, &account, &token)?;
+    licensing::refresh_status(&app_handle);
+    Ok(())
 }
 
 #[tauri::command]
 fn load_license_token() -> Result<Option<String>, String> {
     let (service, account) = license_token_account();
-    let entry =
-        Entry::new(&service, &account).map_err(|e| format!("KEYCHAIN_INIT_ERROR: {}", e))?;
-    match entry.get_password() {
-        Ok(pw) => Ok(Some(pw)),
-        Err(keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(format!("KEYCHAIN_READ_ERROR: {}", e)),
-    }
+    secret_store::load(&service, &account)
 }
 
 #[tauri::command]
-fn delete_license_token() -> Result<(), String> {
+fn delete_license_token(app_handle: tauri::AppHandle) -> Result<(), String> {
     let (service, account) = license_token_account();
-    let entry =
-        Entry::new(&service, &account).map_err(|e| format!("KEYCHAIN_INIT_ERROR: {}", e))?;
-    match entry.delete_password() {
-        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
-        Err(e) => Err(format!("KEYCHAIN_DELETE_ERROR: {}", e)),
-    }
+    secret_store::delete(&service, &account)?;
+    licensing::refresh_status(&app_handle);
+    Ok(())
 }
 
 // ================== Migration: uppercase -> lowercase app dirs ==================
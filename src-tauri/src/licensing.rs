@@ -0,0 +1,348 @@
+// ライセンス（デバイストークン）の検証と更新
+// トークンは Ed25519 署名（alg: EdDSA）のJWT。公開鍵はビルド時に NURIEMON_LICENSE_PUBLIC_KEY（base64）で埋め込む
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use chrono::Utc;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const PUBLIC_KEY_B64: Option<&str> = option_env!("NURIEMON_LICENSE_PUBLIC_KEY");
+// 検証のたびに書き換えるため、監視対象のグローバル設定とは別のファイルに置く
+const CACHE_FILE_NAME: &str = "license_cache.json";
+const DEFAULT_ENDPOINT: &str = "https://license.nuriemon.jp";
+// 期限切れ後もオフラインで使い続けられる猶予
+const GRACE_PERIOD_SECS: i64 = 7 * 24 * 60 * 60;
+// 期限のこの時間前から更新を試みる
+const RENEW_BEFORE_SECS: i64 = 3 * 24 * 60 * 60;
+const CHECK_INTERVAL_SECS: i64 = 60 * 60;
+const RETRY_MIN_SECS: i64 = 5 * 60;
+const RETRY_MAX_SECS: i64 = 6 * 60 * 60;
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+// 時計を戻して期限を延ばせないよう、最後に確認した時刻よりこれ以上前は信用しない
+const CLOCK_ROLLBACK_TOLERANCE_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseState {
+    Unlicensed,
+    Valid,
+    // 期限切れだが猶予期間内（更新を試み続ける）
+    Grace,
+    Expired,
+    // 署名やトークンの形式が不正
+    Invalid,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseStatus {
+    pub state: LicenseState,
+    // いずれもUNIX秒
+    pub expires_at: Option<i64>,
+    pub grace_until: Option<i64>,
+    pub license_id: Option<String>,
+    pub reason: Option<String>,
+    pub last_renewed_at: Option<i64>,
+    pub next_renewal_at: Option<i64>,
+    pub last_renewal_error: Option<String>,
+}
+
+impl LicenseStatus {
+    fn new(state: LicenseState) -> Self {
+        Self {
+            state,
+            expires_at: None,
+            grace_until: None,
+            license_id: None,
+            reason: None,
+            last_renewed_at: None,
+            next_renewal_at: None,
+            last_renewal_error: None,
+        }
+    }
+
+    fn invalid(reason: String) -> Self {
+        Self {
+            reason: Some(reason),
+            ..Self::new(LicenseState::Invalid)
+        }
+    }
+
+    // 通知が必要な変化か（更新予定時刻の変化だけでは通知しない）
+    fn differs_from(&self, other: &LicenseStatus) -> bool {
+        self.state != other.state
+            || self.expires_at != other.expires_at
+            || self.reason != other.reason
+            || self.last_renewal_error != other.last_renewal_error
+    }
+}
+
+// キャッシュファイルに保存する検証の記録
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct LicenseCache {
+    last_seen_at: i64,
+    #[serde(default)]
+    last_renewed_at: Option<i64>,
+    #[serde(default)]
+    last_renewal_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    #[serde(default)]
+    exp: Option<i64>,
+    #[serde(default)]
+    sub: Option<String>,
+}
+
+static STATUS: Mutex<Option<LicenseStatus>> = Mutex::new(None);
+static NEXT_RENEWAL_AT: Mutex<Option<i64>> = Mutex::new(None);
+
+fn cache_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(CACHE_FILE_NAME))
+        .map_err(|e| format!("アプリデータディレクトリの取得に失敗: {}", e))
+}
+
+fn load_cache(app_handle: &AppHandle) -> LicenseCache {
+    cache_path(app_handle)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(app_handle: &AppHandle, cache: &LicenseCache) {
+    let result = cache_path(app_handle).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+        }
+        let value = serde_json::to_string(cache).map_err(|e| format!("JSON変換エラー: {}", e))?;
+        crate::atomic_file::write(&path, value, false)
+            .map_err(|e| format!("ファイル書き込みエラー: {}", e))
+    });
+    if let Err(e) = result {
+        eprintln!("[licensing] failed to save cache: {}", e);
+    }
+}
+
+fn public_key() -> Result<VerifyingKey, String> {
+    let encoded = PUBLIC_KEY_B64
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| "ライセンスの公開鍵が設定されていません".to_string())?;
+    let bytes = STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("ライセンスの公開鍵が不正です: {}", e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "ライセンスの公開鍵が不正です".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("ライセンスの公開鍵が不正です: {}", e))
+}
+
+fn decode_part<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part.trim_end_matches('='))
+        .map_err(|_| "トークンの形式が不正です".to_string())?;
+    serde_json::from_slice(&bytes).map_err(|_| "トークンの形式が不正です".to_string())
+}
+
+// 署名を検証してクレームを返す
+fn verify_token(token: &str) -> Result<Claims, String> {
+    let mut parts = token.trim().split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("トークンの形式が不正です".to_string());
+    };
+    let header_json: serde_json::Value = decode_part(header)?;
+    if header_json.get("alg").and_then(|alg| alg.as_str()) != Some("EdDSA") {
+        return Err("対応していない署名方式です".to_string());
+    }
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| "トークンの署名が不正です".to_string())?;
+    public_key()?
+        .verify_strict(format!("{}.{}", header, payload).as_bytes(), &signature)
+        .map_err(|_| "トークンの署名が一致しません".to_string())?;
+    decode_part(payload)
+}
+
+// 時計の巻き戻しを考慮した現在時刻（記録も更新する）
+fn trusted_now(cache: &mut LicenseCache) -> i64 {
+    let now = Utc::now().timestamp();
+    let trusted = now.max(cache.last_seen_at - CLOCK_ROLLBACK_TOLERANCE_SECS);
+    cache.last_seen_at = cache.last_seen_at.max(now);
+    trusted
+}
+
+fn evaluate(app_handle: &AppHandle) -> LicenseStatus {
+    let mut cache = load_cache(app_handle);
+    let now = trusted_now(&mut cache);
+    save_cache(app_handle, &cache);
+
+    let mut status = match crate::load_license_token() {
+        Ok(None) => LicenseStatus::new(LicenseState::Unlicensed),
+        Err(e) => LicenseStatus::invalid(e),
+        Ok(Some(token)) => match verify_token(&token) {
+            Err(reason) => LicenseStatus::invalid(reason),
+            Ok(claims) => {
+                let state = match claims.exp {
+                    None => LicenseState::Valid,
+                    Some(exp) if now < exp => LicenseState::Valid,
+                    Some(exp) if now < exp + GRACE_PERIOD_SECS => LicenseState::Grace,
+                    Some(_) => LicenseState::Expired,
+                };
+                LicenseStatus {
+                    expires_at: claims.exp,
+                    grace_until: claims.exp.map(|exp| exp + GRACE_PERIOD_SECS),
+                    license_id: claims.sub,
+                    ..LicenseStatus::new(state)
+                }
+            }
+        },
+    };
+    status.last_renewed_at = cache.last_renewed_at;
+    status.last_renewal_error = cache.last_renewal_error;
+    status.next_renewal_at = *NEXT_RENEWAL_AT.lock().unwrap();
+    status
+}
+
+/// 状態を検証し直し、変化していれば license-status-changed を送る
+pub fn refresh_status(app_handle: &AppHandle) -> LicenseStatus {
+    let status = evaluate(app_handle);
+    let changed = {
+        let mut current = STATUS.lock().unwrap();
+        let changed = match current.as_ref() {
+            Some(previous) => status.differs_from(previous),
+            None => true,
+        };
+        *current = Some(status.clone());
+        changed
+    };
+    if changed {
+        if let Err(e) =
+            crate::events::emit_routed(app_handle, "license-status-changed", status.clone())
+        {
+            eprintln!("[licensing] {}", e);
+        }
+    }
+    status
+}
+
+// 次に更新を試みる時刻（更新不要ならNone）
+fn renewal_due_at(status: &LicenseStatus, now: i64) -> Option<i64> {
+    match (status.state, status.expires_at) {
+        (LicenseState::Valid, Some(exp)) => Some(exp - RENEW_BEFORE_SECS),
+        (LicenseState::Grace | LicenseState::Expired, _) => Some(now),
+        _ => None,
+    }
+}
+
+fn license_endpoint(app_handle: &AppHandle) -> String {
+    crate::resolve_provisioning_setting(app_handle, "license", "endpoint")
+        .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshResponse {
+    device_token: Option<String>,
+}
+
+async fn renew(app_handle: &AppHandle, client: &reqwest::Client) -> Result<(), String> {
+    let Some(token) = crate::load_license_token()? else {
+        return Ok(());
+    };
+    let response = client
+        .post(format!("{}/token/refresh", license_endpoint(app_handle)))
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| format!("ライセンスサーバーに接続できません: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "ライセンスの更新が拒否されました: HTTP {}",
+            response.status()
+        ));
+    }
+    let body: RefreshResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("ライセンスサーバーの応答が不正です: {}", e))?;
+    let new_token = body
+        .device_token
+        .ok_or_else(|| "ライセンスサーバーの応答にトークンがありません".to_string())?;
+    // 署名を確認してから置き換える
+    verify_token(&new_token)?;
+    crate::save_license_token(app_handle.clone(), new_token)
+}
+
+fn record_renewal(app_handle: &AppHandle, result: &Result<(), String>) {
+    let mut cache = load_cache(app_handle);
+    match result {
+        Ok(()) => {
+            cache.last_renewed_at = Some(Utc::now().timestamp());
+            cache.last_renewal_error = None;
+        }
+        Err(e) => cache.last_renewal_error = Some(e.clone()),
+    }
+    save_cache(app_handle, &cache);
+}
+
+fn retry_delay(failures: u32) -> i64 {
+    let factor = 2i64.saturating_pow(failures.saturating_sub(1).min(16));
+    RETRY_MIN_SECS.saturating_mul(factor).min(RETRY_MAX_SECS)
+}
+
+/// 期限が近づいたら更新する（オフラインの間は間隔を空けて再試行する）
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut failures: u32 = 0;
+        loop {
+            let status = refresh_status(&app_handle);
+            let now = Utc::now().timestamp();
+            let mut wait = CHECK_INTERVAL_SECS;
+            if let Some(due) = renewal_due_at(&status, now) {
+                if due <= now {
+                    let result = renew(&app_handle, &client).await;
+                    record_renewal(&app_handle, &result);
+                    match result {
+                        Ok(()) => failures = 0,
+                        Err(e) => {
+                            failures = failures.saturating_add(1);
+                            eprintln!("[licensing] {} (failures={})", e, failures);
+                            wait = retry_delay(failures);
+                        }
+                    }
+                } else {
+                    wait = wait.min(due - now);
+                }
+                *NEXT_RENEWAL_AT.lock().unwrap() = Some(now + wait);
+            } else {
+                *NEXT_RENEWAL_AT.lock().unwrap() = None;
+            }
+            refresh_status(&app_handle);
+            tokio::time::sleep(Duration::from_secs(wait.max(1) as u64)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_license_status(app_handle: AppHandle) -> Result<LicenseStatus, String> {
+    Ok(refresh_status(&app_handle))
+}
@@ -0,0 +1,162 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use tauri::AppHandle;
+
+use crate::workspace::{read_global_setting, write_global_setting};
+
+// 再起動後も停止状態を保つためのグローバル設定キー
+const MAINTENANCE_KEY: &str = "maintenance_mode";
+const DEFAULT_MESSAGE: &str = "ただいま準備中です。しばらくお待ちください";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    pub message: Option<String>,
+    // 有効にした時刻（RFC3339）
+    pub since: Option<String>,
+}
+
+impl MaintenanceState {
+    pub fn display_message(&self) -> &str {
+        self.message
+            .as_deref()
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or(DEFAULT_MESSAGE)
+    }
+
+    /// スマホ・QRウィンドウ向けの通知（WS/イベント共通）
+    pub fn to_message(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "maintenance",
+            "enabled": self.enabled,
+            "message": self.display_message(),
+            "since": self.since,
+        })
+    }
+}
+
+static STATE: Lazy<RwLock<MaintenanceState>> =
+    Lazy::new(|| RwLock::new(MaintenanceState::default()));
+// 停止中に届いた取り込み（解除時に処理する）
+static PAUSED_IMPORTS: Lazy<Mutex<Vec<(PathBuf, String)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// Relayから最後に受け取った値（変化したときだけ反映し、手動操作を上書きしない）
+static LAST_REMOTE: Mutex<Option<bool>> = Mutex::new(None);
+
+pub fn is_enabled() -> bool {
+    STATE.read().unwrap().enabled
+}
+
+pub fn current() -> MaintenanceState {
+    STATE.read().unwrap().clone()
+}
+
+/// WSコマンドを拒否するときの応答
+pub fn rejection_body() -> serde_json::Value {
+    serde_json::json!({
+        "type": "error",
+        "error": "maintenance",
+        "message": current().display_message(),
+    })
+}
+
+/// 起動時に保存済みの状態を読み込む
+pub fn load(app_handle: &AppHandle) {
+    let stored = read_global_setting(app_handle, MAINTENANCE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str::<MaintenanceState>(&value).ok());
+    if let Some(state) = stored {
+        if state.enabled {
+            println!("[maintenance] 一時停止中のまま起動しました");
+        }
+        *STATE.write().unwrap() = state;
+    }
+}
+
+/// 取り込みを一時停止中なら保留キューへ積んでtrueを返す
+pub fn defer_import(image_path: &std::path::Path, workspace_path: &str) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    println!(
+        "[maintenance] 一時停止中のため取り込みを保留します: {}",
+        image_path.display()
+    );
+    PAUSED_IMPORTS
+        .lock()
+        .unwrap()
+        .push((image_path.to_path_buf(), workspace_path.to_string()));
+    true
+}
+
+/// 保留キューに積まれているファイルか（解除時に取り込むので片付けの対象から外す）
+pub fn is_deferred(image_path: &std::path::Path) -> bool {
+    PAUSED_IMPORTS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(path, _)| path == image_path)
+}
+
+/// 一時停止の切り替え（手動/Relay共通）
+pub fn apply(app_handle: &AppHandle, enabled: bool, message: Option<String>) -> Result<(), String> {
+    let state = MaintenanceState {
+        enabled,
+        message,
+        since: enabled.then(|| chrono::Utc::now().to_rfc3339()),
+    };
+    let value = serde_json::to_string(&state)
+        .map_err(|e| format!("メンテナンス状態のシリアライズに失敗しました: {}", e))?;
+    write_global_setting(app_handle, MAINTENANCE_KEY, &value)?;
+    *STATE.write().unwrap() = state.clone();
+
+    println!("[maintenance] enabled={}", enabled);
+    let _ = crate::events::emit_routed(app_handle, "maintenance-mode-changed", state.to_message());
+    crate::websocket::broadcast(&state.to_message());
+
+    // 解除したら保留していた取り込みを再開
+    if !enabled {
+        let paused: Vec<(PathBuf, String)> = PAUSED_IMPORTS.lock().unwrap().drain(..).collect();
+        for (path, workspace_path) in paused {
+            if let Err(e) =
+                crate::file_watcher::process_new_image(app_handle.clone(), path, workspace_path)
+            {
+                eprintln!("[maintenance] 保留した取り込みの再開に失敗しました: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Relayから届いた指示を反映（前回と同じ値なら何もしない）
+pub fn apply_remote(app_handle: &AppHandle, enabled: bool, message: Option<String>) {
+    {
+        let mut last = LAST_REMOTE.lock().unwrap();
+        if *last == Some(enabled) {
+            return;
+        }
+        *last = Some(enabled);
+    }
+    if is_enabled() == enabled {
+        return;
+    }
+    if let Err(e) = apply(app_handle, enabled, message) {
+        eprintln!("[maintenance] {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn set_maintenance_mode(
+    app_handle: AppHandle,
+    enabled: bool,
+    message: Option<String>,
+) -> Result<(), String> {
+    apply(&app_handle, enabled, message)
+}
+
+#[tauri::command]
+pub fn get_maintenance_mode() -> Result<serde_json::Value, String> {
+    Ok(current().to_message())
+}
@@ -0,0 +1,133 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{ImageFormat, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use tauri::{AppHandle, Manager, State};
+
+use crate::workspace::WorkspaceState;
+
+// 出力背景を保存する app_settings のキー
+const OUTPUT_BACKGROUND_KEY: &str = "output_background";
+// 映像機器のクロマキーで一般的な緑
+const CHROMA_GREEN: [u8; 3] = [0, 177, 64];
+
+/// 背景除去後の画像の背景（外部の映像ツールで合成する会場向け）
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum OutputBackground {
+    #[default]
+    Transparent,
+    // "#rrggbb"
+    Solid {
+        color: String,
+    },
+    Chroma,
+}
+
+pub(crate) fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+impl OutputBackground {
+    fn color(&self) -> Option<[u8; 3]> {
+        match self {
+            Self::Transparent => None,
+            Self::Solid { color } => parse_hex_color(color),
+            Self::Chroma => Some(CHROMA_GREEN),
+        }
+    }
+}
+
+fn read_background(app_handle: &AppHandle) -> OutputBackground {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(OUTPUT_BACKGROUND_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<OutputBackground>(&value).ok())
+        .unwrap_or_default()
+}
+
+/// サイドカーの出力（透過PNG）を設定の背景色で塗りつぶす。透過のままなら何もしない
+pub fn apply(app_handle: &AppHandle, png_data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some([r, g, b]) = read_background(app_handle).color() else {
+        return Ok(png_data);
+    };
+    let img = image::load_from_memory(&png_data)
+        .map_err(|e| format!("画像の読み込みに失敗しました: {}", e))?
+        .to_rgba8();
+
+    // 半透明の縁も背景色と合成する
+    let blend = |fg: u8, bg: u8, alpha: u16| -> u8 {
+        ((fg as u16 * alpha + bg as u16 * (255 - alpha) + 127) / 255) as u8
+    };
+    let flattened = RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let [pr, pg, pb, pa] = img.get_pixel(x, y).0;
+        let alpha = pa as u16;
+        Rgb([
+            blend(pr, r, alpha),
+            blend(pg, g, alpha),
+            blend(pb, b, alpha),
+        ])
+    });
+
+    let mut buf = Vec::new();
+    flattened
+        .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    Ok(buf)
+}
+
+/// データURL版（process_image の結果用）
+pub fn apply_data_url(app_handle: &AppHandle, data_url: String) -> Result<String, String> {
+    if read_background(app_handle) == OutputBackground::Transparent {
+        return Ok(data_url);
+    }
+    let Some(pos) = data_url.find("base64,") else {
+        return Ok(data_url);
+    };
+    let bytes = STANDARD
+        .decode(data_url[pos + 7..].trim())
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    let flattened = apply(app_handle, bytes)?;
+    Ok(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(flattened)
+    ))
+}
+
+#[tauri::command]
+pub fn get_output_background(app_handle: AppHandle) -> Result<OutputBackground, String> {
+    Ok(read_background(&app_handle))
+}
+
+/// 以降に取り込む画像の背景を保存（保存済みの画像は変わらない）
+#[tauri::command]
+pub fn set_output_background(
+    workspace: State<'_, WorkspaceState>,
+    background: OutputBackground,
+) -> Result<(), String> {
+    if let OutputBackground::Solid { color } = &background {
+        if parse_hex_color(color).is_none() {
+            return Err(format!(
+                "背景色は #rrggbb の形式で指定してください: {}",
+                color
+            ));
+        }
+    }
+    let value = serde_json::to_string(&background)
+        .map_err(|e| format!("出力背景のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(OUTPUT_BACKGROUND_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
@@ -0,0 +1,340 @@
+// 絶対パスを受け取るファイル操作の許可範囲（侵害されたWebViewから任意のファイルを読み書きされないようにする）
+// 許可範囲はネイティブのダイアログで選んだフォルダとプロビジョニングで指定したフォルダだけから作る
+// （WebViewが渡すワークスペース・監視フォルダ・書き出し先は、保存や接続の前に許可範囲の中か確認する）
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::workspace::WorkspaceState;
+
+// ダイアログで追加したフォルダの保存先（グローバル設定はWebViewから書き換えられるため別ファイルにする）
+const ROOTS_FILE_NAME: &str = "file_access_roots.json";
+// 確認済みで接続したワークスペース（次回起動時に同じフォルダへ再接続するため）
+const WORKSPACES_FILE_NAME: &str = "workspace_roots.json";
+
+// このセッションでネイティブのダイアログから選ばれたワークスペース候補
+static PICKED_FOLDERS: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// このセッションで初期化したワークスペース（接続前に設定ファイルを書くため）
+static SESSION_ROOTS: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedRoot {
+    // "workspace" / "session" / "provisioned" / "custom"
+    pub kind: String,
+    pub path: String,
+}
+
+fn same_folder(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// ワークスペースDBの初期化に成功したときに呼ぶ。ダイアログで選ばれたフォルダなら接続前でも書けるようにする
+pub fn allow_initialized_workspace(root: &Path) {
+    let picked = PICKED_FOLDERS
+        .lock()
+        .map(|picked| picked.iter().any(|folder| same_folder(folder, root)))
+        .unwrap_or(false);
+    if !picked {
+        return;
+    }
+    if let Ok(mut roots) = SESSION_ROOTS.lock() {
+        if !roots.iter().any(|r| r == root) {
+            roots.push(root.to_path_buf());
+        }
+    }
+}
+
+// バンドルと環境変数で指定したプロビジョニングファイルの fileAccess.roots
+// （ユーザー設定の global_settings.json は環境によってアプリの設定と同じファイルになり、WebViewから書けるため使わない）
+fn provisioned_roots(app_handle: &AppHandle) -> Vec<String> {
+    let sources = [
+        crate::read_bundle_global_settings(app_handle.clone()),
+        crate::read_env_provisioning_settings(),
+    ];
+    sources
+        .into_iter()
+        .filter_map(|source| source.ok().flatten())
+        .filter_map(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        .filter_map(|value| {
+            value
+                .get("fileAccess")?
+                .get("roots")?
+                .as_array()
+                .map(|roots| {
+                    roots
+                        .iter()
+                        .filter_map(|root| root.as_str().map(str::to_string))
+                        .collect::<Vec<_>>()
+                })
+        })
+        .flatten()
+        .collect()
+}
+
+fn roots_file(app_handle: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("アプリデータディレクトリの取得に失敗: {}", e))
+}
+
+fn read_roots(app_handle: &AppHandle, name: &str) -> Vec<String> {
+    roots_file(app_handle, name)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_roots(app_handle: &AppHandle, name: &str, roots: &[String]) -> Result<(), String> {
+    let path = roots_file(app_handle, name)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+    }
+    let value =
+        serde_json::to_string_pretty(roots).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    std::fs::write(&path, value).map_err(|e| format!("ファイル書き込みエラー: {}", e))
+}
+
+fn custom_roots(app_handle: &AppHandle) -> Vec<String> {
+    read_roots(app_handle, ROOTS_FILE_NAME)
+}
+
+fn save_custom_roots(app_handle: &AppHandle, roots: &[String]) -> Result<(), String> {
+    save_roots(app_handle, ROOTS_FILE_NAME, roots)
+}
+
+fn allowed_roots(app_handle: &AppHandle) -> Vec<AllowedRoot> {
+    let mut roots = Vec::new();
+    let mut push = |kind: &str, path: PathBuf| {
+        roots.push(AllowedRoot {
+            kind: kind.to_string(),
+            path: path.to_string_lossy().to_string(),
+        })
+    };
+
+    let workspace_root = app_handle
+        .state::<WorkspaceState>()
+        .lock()
+        .ok()
+        .and_then(|conn| conn.workspace_root());
+    if let Some(root) = workspace_root {
+        push("workspace", root);
+    }
+    if let Ok(session) = SESSION_ROOTS.lock() {
+        for root in session.iter() {
+            push("session", root.clone());
+        }
+    }
+    for root in provisioned_roots(app_handle) {
+        push("provisioned", PathBuf::from(root));
+    }
+    for root in custom_roots(app_handle) {
+        push("custom", PathBuf::from(root));
+    }
+    roots
+}
+
+// ネイティブのフォルダ選択ダイアログ（キャンセルならNone）
+async fn pick_folder(app_handle: &AppHandle, title: &str) -> Option<PathBuf> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app_handle
+        .dialog()
+        .file()
+        .set_title(title)
+        .pick_folder(move |folder| {
+            let _ = tx.send(folder);
+        });
+    rx.await
+        .ok()
+        .flatten()
+        .and_then(|folder| folder.into_path().ok())
+}
+
+/// 実在する祖先までを正規化し、まだ無い末尾（これから作るファイル）を付け直す
+fn canonicalize_lenient(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            for part in missing.iter().rev() {
+                resolved.push(part);
+            }
+            return Some(resolved);
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+// まだどのワークスペースにも接続していない状態でフォルダを確認するため、
+// 各ワークスペースの .nuriemon/settings.json の読み取りと、DBの有無の確認だけは許可する
+fn is_workspace_file(path: &Path, names: &[&str]) -> bool {
+    path.file_name()
+        .is_some_and(|name| names.iter().any(|allowed| name == *allowed))
+        && path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .is_some_and(|name| name == ".nuriemon")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    Exists,
+    Read,
+    Write,
+}
+
+fn denied(path: &str) -> String {
+    eprintln!("[path_sandbox] denied access to {}", path);
+    format!("PERMISSION_DENIED: {} is outside the allowed folders", path)
+}
+
+fn check(app_handle: &AppHandle, path: &str, access: Access) -> Result<PathBuf, String> {
+    let requested = Path::new(path);
+    // 相対パスと ".." を含むパスは正規化前に拒否する
+    if !requested.is_absolute()
+        || requested
+            .components()
+            .any(|part| matches!(part, Component::ParentDir))
+    {
+        return Err(denied(path));
+    }
+    let resolved = canonicalize_lenient(requested).ok_or_else(|| denied(path))?;
+    let workspace_files: &[&str] = match access {
+        Access::Exists => &["settings.json", "nuriemon.db"],
+        Access::Read => &["settings.json"],
+        Access::Write => &[],
+    };
+    if is_workspace_file(&resolved, workspace_files) {
+        return Ok(resolved);
+    }
+    let allowed = allowed_roots(app_handle).into_iter().any(|root| {
+        canonicalize_lenient(Path::new(&root.path)).is_some_and(|root| resolved.starts_with(root))
+    });
+    if allowed {
+        Ok(resolved)
+    } else {
+        Err(denied(path))
+    }
+}
+
+/// 存在確認を許可するパスか確認し、正規化したパスを返す
+pub fn check_exists(app_handle: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    check(app_handle, path, Access::Exists)
+}
+
+/// 読み取りを許可するパスか確認し、正規化したパスを返す
+pub fn check_read(app_handle: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    check(app_handle, path, Access::Read)
+}
+
+/// 書き込み・削除を許可するパスか確認し、正規化したパスを返す
+pub fn check_write(app_handle: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    check(app_handle, path, Access::Write)
+}
+
+/// 接続するワークスペースを確認する（このセッションにダイアログで選んだフォルダ、確認済みで接続したことのあるフォルダ、許可範囲の中のフォルダだけ）
+/// ワークスペースの接続を保持したまま呼ばないこと（許可範囲の取得で接続をロックする）
+pub fn check_workspace_db(app_handle: &AppHandle, db_path: &Path) -> Result<PathBuf, String> {
+    let display = db_path.to_string_lossy().to_string();
+    let root = db_path
+        .is_absolute()
+        .then_some(db_path)
+        .filter(|path| is_workspace_file(path, &["nuriemon.db"]))
+        .and_then(|path| path.parent()?.parent())
+        .ok_or_else(|| denied(&display))?;
+    let in_list = |folders: &[PathBuf]| folders.iter().any(|folder| same_folder(folder, root));
+    let picked = PICKED_FOLDERS
+        .lock()
+        .map(|picked| in_list(&picked))
+        .unwrap_or(false);
+    let known = in_list(
+        &read_roots(app_handle, WORKSPACES_FILE_NAME)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>(),
+    );
+    if !picked && !known {
+        check(app_handle, &root.to_string_lossy(), Access::Write)?;
+    }
+    Ok(root.to_path_buf())
+}
+
+/// 確認済みのワークスペースとして覚える（次回起動時の再接続を許可する）
+pub fn remember_workspace(app_handle: &AppHandle, root: &Path) {
+    let root = root.to_string_lossy().to_string();
+    let mut known = read_roots(app_handle, WORKSPACES_FILE_NAME);
+    if known.contains(&root) {
+        return;
+    }
+    known.push(root);
+    if let Err(e) = save_roots(app_handle, WORKSPACES_FILE_NAME, &known) {
+        eprintln!("[path_sandbox] failed to remember workspace: {}", e);
+    }
+}
+
+/// 現在許可されているフォルダ
+#[tauri::command]
+pub fn get_file_access_roots(app_handle: AppHandle) -> Result<Vec<AllowedRoot>, String> {
+    Ok(allowed_roots(&app_handle))
+}
+
+/// ワークスペースにするフォルダをダイアログで選ぶ（選んだフォルダだけが初期化前に書き込める）
+#[tauri::command]
+pub async fn pick_workspace_folder(
+    app_handle: AppHandle,
+    title: Option<String>,
+) -> Result<Option<String>, String> {
+    let title = title.unwrap_or_else(|| "ワークスペースフォルダを選択".to_string());
+    let Some(folder) = pick_folder(&app_handle, &title).await else {
+        return Ok(None);
+    };
+    if let Ok(mut picked) = PICKED_FOLDERS.lock() {
+        picked.push(folder.clone());
+    }
+    Ok(Some(folder.to_string_lossy().to_string()))
+}
+
+/// 許可するフォルダをダイアログで選んで追加する（キャンセルならNone）
+/// 自動取り込みの監視フォルダなど、WebViewで使うフォルダもこのダイアログで選ぶ
+#[tauri::command]
+pub async fn add_file_access_root(
+    app_handle: AppHandle,
+    title: Option<String>,
+) -> Result<Option<String>, String> {
+    let title = title.unwrap_or_else(|| "アクセスを許可するフォルダを選択".to_string());
+    let Some(folder) = pick_folder(&app_handle, &title).await else {
+        return Ok(None);
+    };
+    let folder = folder.to_string_lossy().to_string();
+    let mut roots = custom_roots(&app_handle);
+    if !roots.contains(&folder) {
+        roots.push(folder.clone());
+        save_custom_roots(&app_handle, &roots)?;
+        println!("[path_sandbox] added root {}", folder);
+    }
+    Ok(Some(folder))
+}
+
+/// ダイアログで追加したフォルダを許可から外す
+#[tauri::command]
+pub fn remove_file_access_root(app_handle: AppHandle, path: String) -> Result<bool, String> {
+    let mut roots = custom_roots(&app_handle);
+    let before = roots.len();
+    roots.retain(|root| root != &path);
+    if roots.len() == before {
+        return Ok(false);
+    }
+    save_custom_roots(&app_handle, &roots)?;
+    Ok(true)
+}
@@ -0,0 +1,551 @@
+// 作品カードの印刷（処理済みの画像を台紙に重ね、名前とQRを入れてプリンターへ送るかPDFに書き出す）
+use ab_glyph::FontVec;
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::State;
+
+use crate::qr_manager::{render_qr_png, QrImageOptions};
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const PRINT_TEMPLATE_KEY: &str = "print_template";
+// PDFに書き出すときの解像度
+const PDF_DPI: f32 = 300.0;
+const MAX_COPIES: u32 = 20;
+
+/// 台紙上の範囲（用紙に対する0〜1の割合）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PrintBox {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        let in_range = |v: f32| (0.0..=1.0).contains(&v);
+        if !in_range(self.x)
+            || !in_range(self.y)
+            || self.width <= 0.0
+            || self.height <= 0.0
+            || self.x + self.width > 1.0
+            || self.y + self.height > 1.0
+        {
+            return Err(format!(
+                "{}の範囲は用紙内（0〜1の割合）で指定してください",
+                name
+            ));
+        }
+        Ok(())
+    }
+
+    fn to_pixels(self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        (
+            (self.x * width as f32).round() as u32,
+            (self.y * height as f32).round() as u32,
+            ((self.width * width as f32).round() as u32).max(1),
+            ((self.height * height as f32).round() as u32).max(1),
+        )
+    }
+}
+
+/// 印刷カードの台紙（ワークスペースの app_settings に保存）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintTemplate {
+    // 用紙の大きさ（ピクセル。既定はL判相当 300dpi）
+    pub width: u32,
+    pub height: u32,
+    pub background_color: String,
+    // 台紙の画像（用紙いっぱいに引き伸ばす）
+    pub background_path: Option<String>,
+    pub character: PrintBox,
+    // 名前を入れる位置（None なら入れない）
+    pub name: Option<PrintBox>,
+    pub font_path: Option<String>,
+    pub font_size: f32,
+    pub text_color: String,
+    // スマホ操作用QRの位置（None なら入れない）
+    pub qr: Option<PrintBox>,
+    pub qr_options: QrImageOptions,
+}
+
+impl Default for PrintTemplate {
+    fn default() -> Self {
+        Self {
+            width: 1051,
+            height: 1500,
+            background_color: "#ffffff".to_string(),
+            background_path: None,
+            character: PrintBox {
+                x: 0.08,
+                y: 0.06,
+                width: 0.84,
+                height: 0.66,
+            },
+            // 名前欄はフォントを指定してから使う
+            name: None,
+            font_path: None,
+            font_size: 64.0,
+            text_color: "#333333".to_string(),
+            qr: None,
+            qr_options: QrImageOptions::default(),
+        }
+    }
+}
+
+impl PrintTemplate {
+    fn validate(&self) -> Result<(), String> {
+        if !(100..=10000).contains(&self.width) || !(100..=10000).contains(&self.height) {
+            return Err("用紙の大きさは100〜10000ピクセルで指定してください".to_string());
+        }
+        if !(8.0..=512.0).contains(&self.font_size) {
+            return Err("文字サイズは8〜512で指定してください".to_string());
+        }
+        for color in [&self.background_color, &self.text_color] {
+            if crate::output_background::parse_hex_color(color).is_none() {
+                return Err(format!("色は #rrggbb の形式で指定してください: {}", color));
+            }
+        }
+        self.character.validate("キャラクター")?;
+        if let Some(name) = &self.name {
+            name.validate("名前欄")?;
+            let has_font = self
+                .font_path
+                .as_deref()
+                .is_some_and(|path| !path.trim().is_empty());
+            if !has_font {
+                return Err("名前を入れるにはフォントファイルを指定してください".to_string());
+            }
+        }
+        if let Some(qr) = &self.qr {
+            qr.validate("QR")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintOutput {
+    #[default]
+    Printer,
+    Pdf,
+}
+
+/// 1回の印刷の指定
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintOptions {
+    pub output: PrintOutput,
+    // 未指定ならOSの既定のプリンター
+    pub printer: Option<String>,
+    pub copies: u32,
+    // PDFの出力先（未指定ならワークスペースの exports/prints）
+    pub pdf_path: Option<String>,
+    // 保存済みの台紙の代わりに使う
+    pub template: Option<PrintTemplate>,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            output: PrintOutput::Printer,
+            printer: None,
+            copies: 1,
+            pdf_path: None,
+            template: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrinterInfo {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintResult {
+    // 印刷に使った（または書き出した）PDFのパス
+    pub pdf_path: String,
+    pub printer: Option<String>,
+}
+
+fn color(value: &str) -> Rgba<u8> {
+    let [r, g, b] = crate::output_background::parse_hex_color(value).unwrap_or([255, 255, 255]);
+    Rgba([r, g, b, 255])
+}
+
+fn read_template(workspace: &WorkspaceState) -> Result<PrintTemplate, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let stored = db
+        .get_app_setting(PRINT_TEMPLATE_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?;
+    Ok(stored
+        .and_then(|value| serde_json::from_str::<PrintTemplate>(&value).ok())
+        .unwrap_or_default())
+}
+
+// 範囲いっぱいに縦横比を保って中央に置く
+fn overlay_fitted(canvas: &mut RgbaImage, img: &RgbaImage, area: (u32, u32, u32, u32)) {
+    let (x, y, width, height) = area;
+    let fitted = image::DynamicImage::ImageRgba8(img.clone())
+        .resize(width, height, imageops::FilterType::Lanczos3)
+        .to_rgba8();
+    let left = x + (width - fitted.width()) / 2;
+    let top = y + (height - fitted.height()) / 2;
+    imageops::overlay(canvas, &fitted, left as i64, top as i64);
+}
+
+fn load_font(template: &PrintTemplate) -> Result<Option<FontVec>, String> {
+    if template.name.is_none() {
+        return Ok(None);
+    }
+    let Some(path) = &template.font_path else {
+        return Err("名前を入れるにはフォントファイルを指定してください".to_string());
+    };
+    let data = fs::read(path).map_err(|e| format!("フォントの読み込みに失敗しました: {}", e))?;
+    FontVec::try_from_vec(data)
+        .map(Some)
+        .map_err(|_| format!("フォントの形式が不正です: {}", path))
+}
+
+fn compose_card(
+    template: &PrintTemplate,
+    character: &Path,
+    name: Option<&str>,
+    qr_url: Option<&str>,
+) -> Result<RgbaImage, String> {
+    let (width, height) = (template.width, template.height);
+    let mut card = RgbaImage::from_pixel(width, height, color(&template.background_color));
+    if let Some(path) = &template.background_path {
+        let background = image::open(path)
+            .map_err(|e| format!("台紙の読み込みに失敗しました: {}", e))?
+            .resize_exact(width, height, imageops::FilterType::Lanczos3)
+            .to_rgba8();
+        imageops::overlay(&mut card, &background, 0, 0);
+    }
+
+    let img = image::open(character)
+        .map_err(|e| {
+            format!(
+                "画像の読み込みに失敗しました({}): {}",
+                character.display(),
+                e
+            )
+        })?
+        .to_rgba8();
+    overlay_fitted(&mut card, &img, template.character.to_pixels(width, height));
+
+    if let (Some(area), Some(name)) = (template.name, name.filter(|n| !n.trim().is_empty())) {
+        let font = load_font(template)?.ok_or("フォントが読み込めません")?;
+        let (x, y, w, h) = area.to_pixels(width, height);
+        let size = template.font_size.min(h as f32);
+        crate::qr_batch::draw_text_centered(
+            &mut card,
+            &font,
+            size,
+            name,
+            color(&template.text_color),
+            x as f32 + w as f32 / 2.0,
+            y as f32 + (h as f32 - size) / 2.0,
+        );
+    }
+
+    if let (Some(area), Some(url)) = (template.qr, qr_url) {
+        let png = render_qr_png(url, &template.qr_options)?;
+        let qr = image::load_from_memory(&png)
+            .map_err(|e| format!("画像の読み込みに失敗しました: {}", e))?
+            .to_rgba8();
+        overlay_fitted(&mut card, &qr, area.to_pixels(width, height));
+    }
+    Ok(card)
+}
+
+// 1ページに画像を1枚だけ置いたPDF（JPEGをそのまま埋め込む）
+fn write_pdf(card: &RgbaImage, path: &Path) -> Result<(), String> {
+    let rgb = image::DynamicImage::ImageRgba8(card.clone()).to_rgb8();
+    let mut jpeg = Vec::new();
+    rgb.write_to(&mut std::io::Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+        .map_err(|e| format!("JPEGへの変換に失敗しました: {}", e))?;
+
+    // 用紙の大きさ（pt = 1/72 inch）
+    let page_w = card.width() as f32 * 72.0 / PDF_DPI;
+    let page_h = card.height() as f32 * 72.0 / PDF_DPI;
+    let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", page_w, page_h);
+
+    let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(&mut pdf, b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>",
+            page_w, page_h
+        )
+        .as_bytes(),
+    );
+    let mut image_object = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+        rgb.width(),
+        rgb.height(),
+        jpeg.len()
+    )
+    .into_bytes();
+    image_object.extend_from_slice(&jpeg);
+    image_object.extend_from_slice(b"\nendstream");
+    object(&mut pdf, &image_object);
+    object(
+        &mut pdf,
+        format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        )
+        .as_bytes(),
+    );
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes(),
+    );
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    fs::write(path, pdf).map_err(|e| format!("PDFの書き込みに失敗しました: {}", e))
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("{}を起動できませんでした: {}", program, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!(
+            "{}が失敗しました: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn list_system_printers() -> Result<Vec<PrinterInfo>, String> {
+    let output = run_command(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_Printer | ForEach-Object { \"$($_.Default)`t$($_.Name)\" }",
+        ],
+    )?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(is_default, name)| PrinterInfo {
+            name: name.trim().to_string(),
+            is_default: is_default.trim().eq_ignore_ascii_case("true"),
+        })
+        .collect())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn list_system_printers() -> Result<Vec<PrinterInfo>, String> {
+    // 既定のプリンターが無い環境では lpstat -d が失敗するため無視する
+    let default = run_command("lpstat", &["-d"]).ok().and_then(|out| {
+        out.split_once(':')
+            .map(|(_, name)| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    });
+    let output = run_command("lpstat", &["-e"])?;
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| PrinterInfo {
+            name: name.to_string(),
+            is_default: default.as_deref() == Some(name),
+        })
+        .collect())
+}
+
+#[cfg(target_os = "windows")]
+fn send_to_printer(pdf: &Path, printer: Option<&str>, copies: u32) -> Result<(), String> {
+    // PDFに関連付けられたアプリの印刷機能を使う
+    // （-Wait を付けると関連付けアプリが終了するまで戻らないため、送ったら待たない）
+    let file = pdf.to_string_lossy().replace('\'', "''");
+    let script = match printer {
+        Some(printer) => format!(
+            "Start-Process -FilePath '{}' -Verb PrintTo -ArgumentList '\"{}\"'",
+            file,
+            printer.replace('\'', "''")
+        ),
+        None => format!("Start-Process -FilePath '{}' -Verb Print", file),
+    };
+    for _ in 0..copies {
+        run_command("powershell", &["-NoProfile", "-Command", &script])?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn send_to_printer(pdf: &Path, printer: Option<&str>, copies: u32) -> Result<(), String> {
+    let copies = copies.to_string();
+    let file = pdf.to_string_lossy().to_string();
+    let mut args = vec!["-n", copies.as_str(), "-o", "fit-to-page"];
+    if let Some(printer) = printer {
+        args.extend(["-d", printer]);
+    }
+    args.push(file.as_str());
+    run_command("lp", &args).map(|_| ())
+}
+
+/// 使えるプリンターの一覧
+#[tauri::command]
+pub async fn list_printers() -> Result<Vec<PrinterInfo>, String> {
+    tauri::async_runtime::spawn_blocking(list_system_printers)
+        .await
+        .map_err(|e| format!("プリンター一覧の取得に失敗しました: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_print_template(workspace: State<'_, WorkspaceState>) -> Result<PrintTemplate, String> {
+    read_template(&workspace)
+}
+
+#[tauri::command]
+pub fn set_print_template(
+    workspace: State<'_, WorkspaceState>,
+    template: PrintTemplate,
+) -> Result<(), String> {
+    template.validate()?;
+    let value = serde_json::to_string(&template)
+        .map_err(|e| format!("台紙のシリアライズに失敗しました: {}", e))?;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(PRINT_TEMPLATE_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+/// 処理済みの画像をカードにしてプリンターへ送る（またはPDFに書き出す）
+#[tauri::command]
+pub async fn print_image(
+    workspace: State<'_, WorkspaceState>,
+    server_state: State<'_, ServerState>,
+    id: String,
+    options: Option<PrintOptions>,
+) -> Result<PrintResult, String> {
+    let options = options.unwrap_or_default();
+    if !(1..=MAX_COPIES).contains(&options.copies) {
+        return Err(format!("部数は1〜{}で指定してください", MAX_COPIES));
+    }
+    let template = match options.template.clone() {
+        Some(template) => template,
+        None => read_template(&workspace)?,
+    };
+    template.validate()?;
+
+    let (image, root) = {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        let image = db
+            .get_image(&id)
+            .map_err(|e| format!("Failed to get image: {}", e))?
+            .ok_or_else(|| format!("画像が見つかりません: {}", id))?;
+        (image, conn.workspace_root())
+    };
+    if image.image_type != "processed" {
+        return Err("印刷できるのは処理済みの画像だけです".to_string());
+    }
+    // QRはWebサーバーが起動しているときだけ入れる
+    let qr_url = template.qr.and_then(|_| {
+        server_state
+            .get_qr_manager()
+            .map(|qr_manager| qr_manager.create_printed_session_url(&image.id).1)
+    });
+
+    let pdf_path = match &options.pdf_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = root
+                .ok_or_else(|| "ワークスペースが選択されていません".to_string())?
+                .join("exports")
+                .join("prints");
+            dir.join(format!(
+                "print-{}-{}.pdf",
+                image.id,
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ))
+        }
+    };
+    if let Some(parent) = pdf_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("フォルダ作成エラー: {}", e))?;
+    }
+
+    let printer = options.printer.clone().filter(|p| !p.trim().is_empty());
+    let result_path = pdf_path.clone();
+    let send = options.output == PrintOutput::Printer;
+    let copies = options.copies;
+    let job_printer = printer.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let card = compose_card(
+            &template,
+            &image.resolve_file_path(),
+            image.display_name.as_deref(),
+            qr_url.as_deref(),
+        )?;
+        write_pdf(&card, &pdf_path)?;
+        if send {
+            send_to_printer(&pdf_path, job_printer.as_deref(), copies)?;
+        }
+        Ok::<(), String>(())
+    })
+    .await
+    .map_err(|e| format!("印刷に失敗しました: {}", e))??;
+
+    println!(
+        "[print] {} -> {}",
+        id,
+        if send {
+            printer.as_deref().unwrap_or("(default printer)")
+        } else {
+            "pdf"
+        }
+    );
+    Ok(PrintResult {
+        pdf_path: result_path.to_string_lossy().to_string(),
+        printer: if send { printer } else { None },
+    })
+}
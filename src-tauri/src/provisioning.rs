@@ -0,0 +1,151 @@
+// プロビジョニング設定（global_settings.json）の変更を監視し、再起動せずに反映する
+// 対象: バンドル（resource_dir）、ユーザー（app_config_dir）、NURIEMON_GLOBAL_SETTINGS_PATH
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const FILE_NAME: &str = "global_settings.json";
+// 保存時に複数回届く変更通知をまとめる
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+static LAST_EFFECTIVE: Mutex<Option<Value>> = Mutex::new(None);
+
+// 監視するファイル（優先順位の低い順）
+fn provisioning_paths(app_handle: &AppHandle) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(dir) = app_handle.path().resource_dir() {
+        paths.push(dir.join(FILE_NAME));
+    }
+    if let Ok(dir) = app_handle.path().app_config_dir() {
+        paths.push(dir.join(FILE_NAME));
+    }
+    if let Ok(path) = std::env::var("NURIEMON_GLOBAL_SETTINGS_PATH") {
+        paths.push(PathBuf::from(path));
+    }
+    paths
+}
+
+// オブジェクトは再帰的に、それ以外は上書きでマージ（フロントエンドの deepMerge と同じ規則）
+fn deep_merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        deep_merge(existing, value)
+                    }
+                    _ => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+fn parse_layer(source: Result<Option<String>, String>) -> Option<Value> {
+    let text = source.ok().flatten()?;
+    serde_json::from_str::<Value>(&text)
+        .ok()
+        .filter(|value| value.is_object())
+}
+
+/// プロビジョニング設定をマージした結果（バンドル < ユーザー < 環境変数のファイル < 環境変数の個別指定）
+pub fn effective_provisioning(app_handle: &AppHandle) -> Value {
+    let layers = [
+        parse_layer(crate::read_bundle_global_settings(app_handle.clone())),
+        parse_layer(crate::read_user_provisioning_settings(app_handle.clone())),
+        parse_layer(crate::read_env_provisioning_settings()),
+        parse_layer(crate::read_env_overrides()),
+    ];
+    let mut effective = serde_json::json!({});
+    for layer in layers.into_iter().flatten() {
+        deep_merge(&mut effective, layer);
+    }
+    effective
+}
+
+// 内容が変わっていれば provisioning-changed を送る
+fn reload(app_handle: &AppHandle) {
+    let effective = effective_provisioning(app_handle);
+    {
+        let Ok(mut last) = LAST_EFFECTIVE.lock() else {
+            return;
+        };
+        if last.as_ref() == Some(&effective) {
+            return;
+        }
+        *last = Some(effective.clone());
+    }
+    println!("[provisioning] settings changed; reloading");
+    if let Err(e) = crate::events::emit_routed(app_handle, "provisioning-changed", effective) {
+        eprintln!("[provisioning] {}", e);
+    }
+}
+
+// シンボリックリンク等で表記が異なる場合に備え、フォルダは正規化して比べる
+fn is_target(paths: &[PathBuf], changed: &Path) -> bool {
+    let canonical_dir = |path: &Path| path.parent().and_then(|dir| dir.canonicalize().ok());
+    paths.iter().any(|path| {
+        path == changed
+            || (path.file_name() == changed.file_name()
+                && canonical_dir(path) == canonical_dir(changed))
+    })
+}
+
+/// 設定ファイルの監視を開始する（ファイルがまだ無い場合に備えて親フォルダを監視する）
+pub fn start(app_handle: AppHandle) {
+    let paths = provisioning_paths(&app_handle);
+    if let Ok(mut last) = LAST_EFFECTIVE.lock() {
+        *last = Some(effective_provisioning(&app_handle));
+    }
+
+    thread::spawn(move || {
+        let (tx, rx) = channel();
+        let mut watcher = match RecommendedWatcher::new(tx, Config::default()) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("[provisioning] failed to create watcher: {}", e);
+                return;
+            }
+        };
+        let mut watching = 0;
+        for dir in paths.iter().filter_map(|path| path.parent()) {
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => watching += 1,
+                // 存在しないフォルダ（ユーザー設定未作成など）は監視しない
+                Err(e) => eprintln!("[provisioning] cannot watch {}: {}", dir.display(), e),
+            }
+        }
+        if watching == 0 {
+            return;
+        }
+
+        let mut pending_since: Option<Instant> = None;
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        continue;
+                    }
+                    if event.paths.iter().any(|path| is_target(&paths, path)) {
+                        pending_since.get_or_insert_with(Instant::now);
+                    }
+                }
+                Ok(Err(e)) => eprintln!("[provisioning] watch error: {}", e),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if pending_since.is_some_and(|since| since.elapsed() >= DEBOUNCE) {
+                pending_since = None;
+                reload(&app_handle);
+            }
+        }
+    });
+}
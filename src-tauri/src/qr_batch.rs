@@ -0,0 +1,489 @@
+// 印刷用QRカードの一括生成（座席ごとに事前に配るカードなど）
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use qrcode::{Color, QrCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::qr_manager::{render_qr_png, QrImageOptions, QrManager};
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const MAX_BATCH: usize = 1000;
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CardFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+/// カードの見た目（ラベルの {index} {imageId} {displayName} は置き換える）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrCardTemplate {
+    pub format: CardFormat,
+    pub label: Option<String>,
+    // 出力ファイル名（拡張子なし、{index} {imageId} を置き換える）
+    pub file_name: String,
+    // PNGにラベルを入れる場合のフォント（TTF/OTF）
+    pub font_path: Option<String>,
+    pub font_size: f32,
+    pub qr: QrImageOptions,
+}
+
+impl Default for QrCardTemplate {
+    fn default() -> Self {
+        Self {
+            format: CardFormat::Png,
+            label: None,
+            file_name: "card-{index}".to_string(),
+            font_path: None,
+            font_size: 32.0,
+            qr: QrImageOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QrCardEntry {
+    pub index: usize,
+    pub session_id: String,
+    pub image_id: String,
+    pub url: String,
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QrBatchManifest<'a> {
+    generated_at: String,
+    format: CardFormat,
+    cards: &'a [QrCardEntry],
+}
+
+struct Target {
+    image_id: String,
+    display_name: Option<String>,
+}
+
+// 指定がなければ新しい順に count 件の作品を対象にする
+fn resolve_targets(
+    workspace: &State<'_, WorkspaceState>,
+    image_ids: Option<Vec<String>>,
+    count: Option<usize>,
+) -> Result<Vec<Target>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let images = db
+        .get_all_images()
+        .map_err(|e| format!("Failed to get images: {}", e))?;
+
+    let targets: Vec<Target> = match (image_ids, count) {
+        (Some(ids), _) => ids
+            .into_iter()
+            .map(|id| {
+                let image = images.iter().find(|image| image.id == id);
+                match image {
+                    Some(image) => Ok(Target {
+                        image_id: id,
+                        display_name: image.display_name.clone(),
+                    }),
+                    None => Err(format!("画像が見つかりません: {}", id)),
+                }
+            })
+            .collect::<Result<_, String>>()?,
+        (None, Some(count)) => images
+            .iter()
+            .filter(|image| image.image_type == "processed" && image.is_hidden == 0)
+            .take(count)
+            .map(|image| Target {
+                image_id: image.id.clone(),
+                display_name: image.display_name.clone(),
+            })
+            .collect(),
+        (None, None) => return Err("image_ids か count を指定してください".to_string()),
+    };
+    if targets.is_empty() {
+        return Err("QRカードを作る画像がありません".to_string());
+    }
+    if targets.len() > MAX_BATCH {
+        return Err(format!("一度に作れるのは{}枚までです", MAX_BATCH));
+    }
+    Ok(targets)
+}
+
+fn fill_placeholders(pattern: &str, index: usize, target: &Target) -> String {
+    pattern
+        .replace("{index}", &format!("{:03}", index))
+        .replace("{imageId}", &target.image_id)
+        .replace(
+            "{displayName}",
+            target.display_name.as_deref().unwrap_or_default(),
+        )
+}
+
+// ファイル名に使えない文字を除く
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.');
+    if cleaned.is_empty() {
+        "card".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_svg_card(
+    url: &str,
+    template: &QrCardTemplate,
+    label: Option<&str>,
+) -> Result<String, String> {
+    let options = &template.qr;
+    if options.logo_path.is_some() {
+        return Err("SVGのカードにはロゴを入れられません。PNGを指定してください".to_string());
+    }
+    for color in [&options.foreground, &options.background] {
+        if crate::output_background::parse_hex_color(color).is_none() {
+            return Err(format!("色は #rrggbb の形式で指定してください: {}", color));
+        }
+    }
+    let code = QrCode::with_error_correction_level(url, options.ec_level()?)
+        .map_err(|e| format!("QR_ENCODE_ERROR: {}", e))?;
+    let size = code.width() as u32;
+    let total = size + options.quiet_zone * 2;
+    let module = options.module_size;
+    let label_height = if label.is_some() {
+        (template.font_size * 1.6).ceil() as u32
+    } else {
+        0
+    };
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges"><rect width="{w}" height="{h}" fill="{bg}"/>"#,
+        w = total * module,
+        h = total * module + label_height,
+        bg = options.background
+    );
+    for y in 0..size {
+        for x in 0..size {
+            if code[(x as usize, y as usize)] == Color::Dark {
+                svg.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{m}\" height=\"{m}\" fill=\"{}\"/>",
+                    (x + options.quiet_zone) * module,
+                    (y + options.quiet_zone) * module,
+                    options.foreground,
+                    m = module
+                ));
+            }
+        }
+    }
+    if let Some(label) = label {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\" fill=\"{}\">{}</text>",
+            total * module / 2,
+            total * module + label_height / 2,
+            template.font_size,
+            options.foreground,
+            escape_xml(label)
+        ));
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// 中央揃えで1行の文字を描く（top は文字の上端）
+pub(crate) fn draw_text_centered(
+    canvas: &mut RgbaImage,
+    font: &FontVec,
+    size: f32,
+    text: &str,
+    color: Rgba<u8>,
+    center_x: f32,
+    top: f32,
+) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0f32;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        glyphs.push(id.with_scale_and_position(scale, point(caret, scaled.ascent())));
+        caret += scaled.h_advance(id);
+    }
+    let offset_x = center_x - caret / 2.0;
+
+    for glyph in glyphs {
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let x = (offset_x + bounds.min.x) as i64 + gx as i64;
+            let y = (top + bounds.min.y) as i64 + gy as i64;
+            if x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+                return;
+            }
+            let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+            for i in 0..3 {
+                pixel.0[i] = (color.0[i] as f32 * coverage + pixel.0[i] as f32 * (1.0 - coverage))
+                    .round() as u8;
+            }
+        });
+    }
+}
+
+// PNGの下部にラベルを描く（フォントは呼び出し側で読み込み済み）
+fn draw_label(
+    img: &RgbaImage,
+    font: &FontVec,
+    size: f32,
+    text: &str,
+    color: Rgba<u8>,
+    background: Rgba<u8>,
+) -> RgbaImage {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let label_height = (size * 1.6).ceil() as u32;
+    let mut card = RgbaImage::from_pixel(img.width(), img.height() + label_height, background);
+    imageops::overlay(&mut card, img, 0, 0);
+
+    let top = img.height() as f32 + (label_height as f32 - scaled.height()) / 2.0;
+    draw_text_centered(
+        &mut card,
+        font,
+        size,
+        text,
+        color,
+        img.width() as f32 / 2.0,
+        top,
+    );
+    card
+}
+
+fn render_png_card(
+    url: &str,
+    template: &QrCardTemplate,
+    label: Option<&str>,
+    font: Option<&FontVec>,
+) -> Result<Vec<u8>, String> {
+    let png = render_qr_png(url, &template.qr)?;
+    let (Some(label), Some(font)) = (label, font) else {
+        return Ok(png);
+    };
+    let qr = image::load_from_memory(&png)
+        .map_err(|e| format!("画像の読み込みに失敗しました: {}", e))?
+        .to_rgba8();
+    let color = |value: &str, fallback: [u8; 3]| {
+        let [r, g, b] = crate::output_background::parse_hex_color(value).unwrap_or(fallback);
+        Rgba([r, g, b, 255])
+    };
+    let card = draw_label(
+        &qr,
+        font,
+        template.font_size,
+        label,
+        color(&template.qr.foreground, [0, 0, 0]),
+        color(&template.qr.background, [255, 255, 255]),
+    );
+    let mut buf = Vec::new();
+    card.write_to(&mut std::io::Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    Ok(buf)
+}
+
+fn load_font(template: &QrCardTemplate) -> Result<Option<FontVec>, String> {
+    if template.format != CardFormat::Png || template.label.is_none() {
+        return Ok(None);
+    }
+    let Some(path) = &template.font_path else {
+        return Err(
+            "PNGのカードにラベルを入れるにはフォントファイルを指定してください".to_string(),
+        );
+    };
+    let data = fs::read(path).map_err(|e| format!("フォントの読み込みに失敗しました: {}", e))?;
+    FontVec::try_from_vec(data)
+        .map(Some)
+        .map_err(|_| format!("フォントの形式が不正です: {}", path))
+}
+
+fn unique_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", stem, n, extension));
+        n += 1;
+    }
+    path
+}
+
+struct Batch {
+    cards: Vec<QrCardEntry>,
+    // 失敗したときに取り消す分（カードを書き出す前に発行したセッションも含む）
+    session_ids: Vec<String>,
+    files: Vec<PathBuf>,
+}
+
+fn write_cards(
+    app_handle: &AppHandle,
+    qr_manager: &QrManager,
+    targets: &[Target],
+    template: &QrCardTemplate,
+    font: Option<&FontVec>,
+    out_dir: &Path,
+    batch: &mut Batch,
+) -> Result<(), String> {
+    let extension = match template.format {
+        CardFormat::Png => "png",
+        CardFormat::Svg => "svg",
+    };
+    let total = targets.len();
+    for (i, target) in targets.iter().enumerate() {
+        let index = i + 1;
+        let label = template
+            .label
+            .as_deref()
+            .map(|pattern| fill_placeholders(pattern, index, target));
+        let (session_id, url) = qr_manager.create_printed_session_url(&target.image_id);
+        batch.session_ids.push(session_id.clone());
+
+        let stem = sanitize_file_name(&fill_placeholders(&template.file_name, index, target));
+        let path = unique_path(out_dir, &stem, extension);
+        let contents = match template.format {
+            CardFormat::Png => render_png_card(&url, template, label.as_deref(), font)?,
+            CardFormat::Svg => render_svg_card(&url, template, label.as_deref())?.into_bytes(),
+        };
+        fs::write(&path, contents).map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+        batch.files.push(path.clone());
+
+        batch.cards.push(QrCardEntry {
+            index,
+            session_id,
+            image_id: target.image_id.clone(),
+            url,
+            file: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            label,
+        });
+        let _ = crate::events::emit_routed(
+            app_handle,
+            "qr-batch-progress",
+            serde_json::json!({ "done": index, "total": total }),
+        );
+    }
+
+    let manifest = QrBatchManifest {
+        generated_at: crate::db::current_timestamp(),
+        format: template.format,
+        cards: &batch.cards,
+    };
+    let manifest_path = out_dir.join(MANIFEST_FILE_NAME);
+    fs::write(
+        &manifest_path,
+        serde_json::to_string_pretty(&manifest).map_err(|e| format!("JSON変換エラー: {}", e))?,
+    )
+    .map_err(|e| format!("ファイル書き込みエラー: {}", e))
+}
+
+fn generate(
+    app_handle: &AppHandle,
+    image_ids: Option<Vec<String>>,
+    count: Option<usize>,
+    output_dir: &str,
+    template: QrCardTemplate,
+) -> Result<Vec<QrCardEntry>, String> {
+    let qr_manager = app_handle
+        .state::<ServerState>()
+        .get_qr_manager()
+        .ok_or("Webサーバーが起動していません".to_string())?;
+    if !(8.0..=256.0).contains(&template.font_size) {
+        return Err("ラベルの文字サイズは8〜256で指定してください".to_string());
+    }
+    let targets = resolve_targets(&app_handle.state::<WorkspaceState>(), image_ids, count)?;
+    let font = load_font(&template)?;
+
+    let out_dir = PathBuf::from(output_dir);
+    fs::create_dir_all(&out_dir).map_err(|e| format!("フォルダ作成エラー: {}", e))?;
+
+    let mut batch = Batch {
+        cards: Vec::with_capacity(targets.len()),
+        session_ids: Vec::with_capacity(targets.len()),
+        files: Vec::with_capacity(targets.len()),
+    };
+    let result = write_cards(
+        app_handle,
+        &qr_manager,
+        &targets,
+        &template,
+        font.as_ref(),
+        &out_dir,
+        &mut batch,
+    );
+    // 途中で失敗したら、配られないカードのセッションと書きかけのファイルを残さない
+    if let Err(e) = result {
+        for session_id in &batch.session_ids {
+            qr_manager.revoke_session(session_id);
+        }
+        for file in &batch.files {
+            let _ = fs::remove_file(file);
+        }
+        eprintln!(
+            "[qr_batch] rolled back {} card(s): {}",
+            batch.session_ids.len(),
+            e
+        );
+        return Err(e);
+    }
+    println!(
+        "[qr_batch] wrote {} card(s) to {}",
+        batch.cards.len(),
+        output_dir
+    );
+    Ok(batch.cards)
+}
+
+/// 画像ごとにQRセッションを発行し、カードと manifest.json を書き出す（"qr-batch-progress" で進捗を通知）
+/// 途中で失敗した場合は発行したセッションと書き出したカードを取り消す
+#[tauri::command]
+pub async fn generate_qr_batch(
+    app_handle: AppHandle,
+    image_ids: Option<Vec<String>>,
+    count: Option<usize>,
+    output_dir: String,
+    template: Option<QrCardTemplate>,
+) -> Result<Vec<QrCardEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        generate(
+            &app_handle,
+            image_ids,
+            count,
+            &output_dir,
+            template.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("QRカードの作成に失敗しました: {}", e))?
+}
@@ -1,8 +1,12 @@
+use crate::web_auth::WebAuth;
+use crate::workspace::WorkspaceState;
 use local_ip_address::{list_afinet_netifas, local_ip};
-use qrcode::{Color, QrCode};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
 use uuid::Uuid;
 
 #[derive(Clone, Debug)]
@@ -11,68 +15,361 @@ pub struct QrSession {
     pub image_id: String,
     pub created_at: Instant,
     pub connected: bool,
+    // 一度でも接続したか（使い切りモードで2台目を拒否するため）
+    pub used: bool,
+    // 最後に参加した端末のIPと時刻（RFC3339）
+    pub remote_ip: Option<String>,
+    pub joined_at: Option<String>,
+    // 印刷して配ったQR（会期中は使えるよう期限を別に数える）
+    pub printed: bool,
+}
+
+// QRの運用ポリシーを保存する app_settings のキー
+pub const QR_POLICY_KEY: &str = "qr_policy";
+
+/// QRの有効期限と使い方（撮影会など有料の会場向けに制限をかけられる）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrPolicy {
+    // 発行からの有効期限（秒、未指定なら無期限）
+    pub expiry_secs: Option<u64>,
+    // true なら一度接続したQRは他の端末から使えない（再接続トークンでの復帰は可）
+    pub single_use: bool,
+    // 同時に接続できるセッション数の上限（未指定なら無制限）
+    pub max_concurrent_sessions: Option<usize>,
+}
+
+impl QrPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.expiry_secs == Some(0) {
+            return Err("有効期限は1秒以上で指定してください".to_string());
+        }
+        if self
+            .expiry_secs
+            .is_some_and(|secs| secs > SESSION_MAX_AGE.as_secs())
+        {
+            return Err("有効期限は24時間以内で指定してください".to_string());
+        }
+        if self.max_concurrent_sessions == Some(0) {
+            return Err("同時接続数は1以上で指定してください".to_string());
+        }
+        Ok(())
+    }
+
+    fn expiry(&self) -> Option<Duration> {
+        self.expiry_secs.map(Duration::from_secs)
+    }
+
+    /// 設定から読み込む（未設定・不正値なら制限なし）
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        use tauri::Manager;
+        let stored = app_handle
+            .try_state::<crate::workspace::WorkspaceState>()
+            .and_then(|state| {
+                let conn = state.lock().ok()?;
+                let db = conn.get().ok()?;
+                db.get_app_setting(QR_POLICY_KEY).ok().flatten()
+            });
+        stored
+            .and_then(|value| serde_json::from_str::<QrPolicy>(&value).ok())
+            .filter(|policy| policy.validate().is_ok())
+            .unwrap_or_default()
+    }
+}
+
+// QRに埋め込む接続先の指定を保存する app_settings のキー
+pub const QR_HOST_KEY: &str = "qr_host";
+
+/// QRのURLに使う接続先（自動選択が仮想アダプタなどを選んでしまう環境向け）
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrHostSettings {
+    // 使うネットワークインターフェース名（list_network_interfaces の name）
+    pub preferred_interface: Option<String>,
+    // ホスト名/IPを直接指定（preferred_interface より優先）
+    pub host_override: Option<String>,
+    // QRに埋め込むURLのテンプレート（Relay運用で公開URLを指す場合など）
+    // {scheme} {host} {port} {session} {image} {token} を置き換える
+    pub url_template: Option<String>,
+}
+
+// url_template 未指定時（LAN内のWebサーバーを直接指す）
+pub const DEFAULT_QR_URL_TEMPLATE: &str =
+    "{scheme}://{host}:{port}/app?session={session}&image={image}&token={token}";
+
+impl QrHostSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(host) = &self.host_override {
+            let host = host.trim();
+            if host.is_empty()
+                || host.contains("://")
+                || host.contains('/')
+                || host.chars().any(char::is_whitespace)
+            {
+                return Err("接続先はホスト名またはIPアドレスのみで指定してください".to_string());
+            }
+        }
+        if self
+            .preferred_interface
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err("インターフェース名が空です".to_string());
+        }
+        if let Some(template) = &self.url_template {
+            if !template.contains("{session}") {
+                return Err("URLテンプレートには {session} を含めてください".to_string());
+            }
+            if !(template.starts_with("http://") || template.starts_with("https://"))
+                && !template.starts_with("{scheme}://")
+            {
+                return Err("URLテンプレートは http:// か https:// で始めてください".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 設定から読み込む（未設定・不正値なら自動選択）
+    pub fn load(app_handle: &AppHandle) -> Self {
+        let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+            let conn = state.lock().ok()?;
+            let db = conn.get().ok()?;
+            db.get_app_setting(QR_HOST_KEY).ok().flatten()
+        });
+        stored
+            .and_then(|value| serde_json::from_str::<QrHostSettings>(&value).ok())
+            .filter(|settings| settings.validate().is_ok())
+            .unwrap_or_default()
+    }
+}
+
+/// 選択できるネットワークインターフェース（IPv4のみ）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub ipv4: String,
+    // 自動選択で使われるもの
+    pub auto_selected: bool,
+}
+
+// 利用可能なローカルIPから、スマホが到達しやすいホストの候補（優先度, 名前, IP）。小さいほど優先
+fn host_candidates() -> Vec<(i32, String, String)> {
+    let Ok(map) = list_afinet_netifas() else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<(i32, String, String)> = Vec::new();
+    for (name, ip) in map.into_iter() {
+        // IPv4のみ対象
+        let std::net::IpAddr::V4(v4) = ip else {
+            continue;
+        };
+        // ループバック/リンクローカルは除外
+        if v4.is_loopback() || v4.octets()[0] == 169 {
+            continue;
+        }
+
+        // 優先度（小さいほど優先）: Wi-Fi(en*) < 有線(eth*) < 無線(wl*) < それ以外
+        let mut score = 100;
+        let lower = name.to_lowercase();
+        if lower.starts_with("en") {
+            score = 10;
+        } else if lower.starts_with("eth") {
+            score = 20;
+        } else if lower.starts_with("wl") {
+            score = 30;
+        }
+
+        // 明示的に除外したい仮想/特殊IFはスコアを下げない（実質候補外）
+        if lower.starts_with("awdl")
+            || lower.starts_with("llw")
+            || lower.starts_with("utun")
+            || lower.contains("bridge")
+        {
+            continue;
+        }
+
+        candidates.push((score, name, v4.to_string()));
+    }
+    candidates
+}
+
+/// QRの接続先に選べるインターフェースの一覧
+pub fn list_network_interfaces() -> Vec<NetworkInterface> {
+    let mut candidates = host_candidates();
+    candidates.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, name, ipv4))| NetworkInterface {
+            name,
+            ipv4,
+            auto_selected: i == 0,
+        })
+        .collect()
+}
+
+/// セッションを受け付けなかった理由（スマホへそのまま返す）
+#[derive(Debug, Clone)]
+pub struct SessionRejection {
+    pub code: &'static str,
+    pub message: &'static str,
+}
+
+impl SessionRejection {
+    const NOT_FOUND: Self = Self {
+        code: "session_not_found",
+        message: "QRコードが無効です。画面のQRコードを読み取り直してください",
+    };
+    const EXPIRED: Self = Self {
+        code: "session_expired",
+        message: "QRコードの有効期限が切れました。新しいQRコードを読み取ってください",
+    };
+    const USED: Self = Self {
+        code: "session_used",
+        message: "このQRコードは使用済みです。新しいQRコードを読み取ってください",
+    };
+    const TOO_MANY: Self = Self {
+        code: "too_many_sessions",
+        message: "接続できる台数の上限に達しています。しばらく待ってからお試しください",
+    };
+}
+
+// これより古いセッションは削除する
+const SESSION_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// 印刷したQR（座席カード・名札など）は事前に配るため、複数日の会期でも使えるようにする
+const PRINTED_SESSION_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// 切断後、この時間内なら再接続トークンでQRを読み直さずに復帰できる
+const RECONNECT_GRACE: Duration = Duration::from_secs(2 * 60);
+
+struct ReconnectToken {
+    session_id: String,
+    // 切断した時点から期限を数える（接続中はNone）
+    expires_at: Option<Instant>,
 }
 
 pub struct QrManager {
+    // セッションはワークスペースDBにも保存し、メモリ上の一覧はそのキャッシュとして使う
+    app_handle: AppHandle,
     sessions: Arc<Mutex<HashMap<String, QrSession>>>,
+    reconnect_tokens: Mutex<HashMap<String, ReconnectToken>>,
+    policy: Mutex<QrPolicy>,
     server_port: u16,
+    https_port: Option<u16>,
+    auth: Arc<WebAuth>,
 }
 
 impl QrManager {
-    pub fn new(server_port: u16) -> Self {
+    pub fn new(
+        app_handle: AppHandle,
+        server_port: u16,
+        https_port: Option<u16>,
+        auth: Arc<WebAuth>,
+        policy: QrPolicy,
+    ) -> Self {
         let manager = Self {
+            app_handle,
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            reconnect_tokens: Mutex::new(HashMap::new()),
+            policy: Mutex::new(policy),
             server_port,
+            https_port,
+            auth,
         };
 
-        // 期限切れセッションのクリーンアップタスクを開始
-        // TODO: 定期的なクリーンアップを実装
-        // 現在は各セッション確認時にクリーンアップを実行
+        // 再起動前に発行したQR（印刷済みのカードなど）を読み込む
+        manager.load_persisted();
+        // 期限切れセッションは各セッション確認時と reaper の定期実行で削除する
 
         manager
     }
 
-    // 利用可能なローカルIPから、スマホが到達しやすいホストを選ぶ
-    fn choose_preferred_host() -> String {
-        // 候補を列挙（インターフェース名 -> IP）
-        if let Ok(map) = list_afinet_netifas() {
-            let mut candidates: Vec<(i32, String, String)> = Vec::new();
-            for (name, ip) in map.into_iter() {
-                // IPv4のみ対象
-                let std::net::IpAddr::V4(v4) = ip else {
-                    continue;
-                };
-                // ループバック/リンクローカルは除外
-                if v4.is_loopback() || v4.octets()[0] == 169 {
-                    continue;
-                }
-
-                // 優先度（小さいほど優先）: Wi-Fi(en*) < 有線(eth*) < 無線(wl*) < それ以外
-                let mut score = 100;
-                let lower = name.to_lowercase();
-                if lower.starts_with("en") {
-                    score = 10;
-                } else if lower.starts_with("eth") {
-                    score = 20;
-                } else if lower.starts_with("wl") {
-                    score = 30;
-                }
+    // DBへの保存は失敗してもメモリ上のセッションで動作を続ける
+    fn persist(&self, f: impl FnOnce(&crate::db::Database) -> rusqlite::Result<()>) {
+        let Some(state) = self.app_handle.try_state::<WorkspaceState>() else {
+            return;
+        };
+        let Ok(conn) = state.lock() else {
+            return;
+        };
+        let Ok(db) = conn.get() else {
+            return;
+        };
+        if let Err(e) = f(db) {
+            eprintln!("[qr] failed to persist session: {}", e);
+        }
+    }
 
-                // 明示的に除外したい仮想/特殊IFはスコアを下げない（実質候補外）
-                if lower.starts_with("awdl")
-                    || lower.starts_with("llw")
-                    || lower.starts_with("utun")
-                    || lower.contains("bridge")
-                {
-                    continue;
+    fn load_persisted(&self) {
+        let rows = {
+            let Some(state) = self.app_handle.try_state::<WorkspaceState>() else {
+                return;
+            };
+            let Ok(conn) = state.lock() else {
+                return;
+            };
+            let Ok(db) = conn.get() else {
+                return;
+            };
+            match db.get_qr_sessions() {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("[qr] failed to load sessions: {}", e);
+                    return;
                 }
-
-                candidates.push((score, name, v4.to_string()));
             }
+        };
+        let now = chrono::Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        for row in rows {
+            // 保存時刻から経過時間を求めて Instant に戻す
+            let age = chrono::DateTime::parse_from_rfc3339(&row.created_at)
+                .ok()
+                .and_then(|at| (now - at.with_timezone(&chrono::Utc)).to_std().ok())
+                .unwrap_or_default();
+            let Some(created_at) = Instant::now().checked_sub(age) else {
+                continue;
+            };
+            sessions.insert(
+                row.session_id.clone(),
+                QrSession {
+                    session_id: row.session_id,
+                    image_id: row.image_id,
+                    created_at,
+                    connected: false,
+                    used: row.used,
+                    remote_ip: row.remote_ip,
+                    joined_at: row.joined_at,
+                    printed: row.printed,
+                },
+            );
+        }
+        if !sessions.is_empty() {
+            println!("[qr] restored {} session(s)", sessions.len());
+        }
+    }
+
+    /// QRのURLに使うホスト（設定の指定 → 自動選択 → local_ip の順）
+    pub fn preferred_host(&self) -> String {
+        let settings = QrHostSettings::load(&self.app_handle);
+        if let Some(host) = settings.host_override {
+            return host.trim().to_string();
+        }
 
-            if let Some((_, _name, ip)) = candidates.into_iter().min_by_key(|c| c.0) {
-                return ip;
+        let mut candidates = host_candidates();
+        if let Some(name) = settings.preferred_interface {
+            if let Some((_, _, ip)) = candidates.iter().find(|c| c.1 == name) {
+                return ip.clone();
             }
+            eprintln!(
+                "[qr] preferred interface {} not found, falling back to auto selection",
+                name
+            );
+        }
+        candidates.sort_by_key(|c| c.0);
+        if let Some((_, _name, ip)) = candidates.into_iter().next() {
+            return ip;
         }
 
         // フォールバック: 既存の local_ip
@@ -81,78 +378,415 @@ impl QrManager {
             .unwrap_or_else(|_| "localhost".to_string())
     }
 
-    pub fn create_session(&self, image_id: &str) -> (String, String) {
+    // HTTPSが有効ならそちらを優先（カメラ等のAPIはHTTPでは使えない端末がある）
+    fn scheme_and_port(&self) -> (&'static str, u16) {
+        match self.https_port {
+            Some(port) => ("https", port),
+            None => ("http", self.server_port),
+        }
+    }
+
+    /// 作品のダウンロード用共有リンクのURL（LAN内のこのPCのサーバー）
+    pub fn share_url(&self, token: &str) -> String {
+        let (scheme, port) = self.scheme_and_port();
+        format!(
+            "{}://{}:{}/share/{}",
+            scheme,
+            self.preferred_host(),
+            port,
+            token
+        )
+    }
+
+    // LAN/Relay どちらもテンプレートから組み立てる
+    fn session_url(&self, session_id: &str, image_id: &str) -> String {
+        let settings = QrHostSettings::load(&self.app_handle);
+        let template = settings
+            .url_template
+            .as_deref()
+            .unwrap_or(DEFAULT_QR_URL_TEMPLATE);
+        let (scheme, port) = self.scheme_and_port();
+        // ホストは使うときだけ解決する（公開URLのテンプレートではNICを列挙しない）
+        let host = if template.contains("{host}") {
+            self.preferred_host()
+        } else {
+            String::new()
+        };
+        let token = self.auth.issue_token(session_id);
+        let mut url = template
+            .replace("{scheme}", scheme)
+            .replace("{host}", &host)
+            .replace("{port}", &port.to_string())
+            .replace("{session}", session_id)
+            .replace("{image}", image_id)
+            .replace("{token}", &token);
+        // トークンがないとスマホ側の認証が通らないため必ず付ける
+        if !template.contains("{token}") {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!("{}{}token={}", url, separator, token);
+        }
+        url
+    }
+
+    pub fn create_session(
+        &self,
+        image_id: &str,
+        style: &QrStyle,
+    ) -> Result<(String, String), String> {
+        let (session_id, url) = self.create_session_url(image_id);
+        // QRコードを生成
+        let qr_code = render_qr_svg(&url, style)?;
+        Ok((session_id, qr_code))
+    }
+
+    /// セッションを発行し、QRに埋め込むURLを返す（描画を呼び出し側で行う場合）
+    pub fn create_session_url(&self, image_id: &str) -> (String, String) {
+        self.issue_session(image_id, false)
+    }
+
+    /// 印刷して配るQRのセッションを発行し、URLを返す（24時間では削除しない）
+    pub fn create_printed_session_url(&self, image_id: &str) -> (String, String) {
+        self.issue_session(image_id, true)
+    }
+
+    fn issue_session(&self, image_id: &str, printed: bool) -> (String, String) {
         let session_id = Uuid::new_v4().to_string();
         let session = QrSession {
             session_id: session_id.clone(),
             image_id: image_id.to_string(),
             created_at: Instant::now(),
             connected: false,
+            used: false,
+            remote_ip: None,
+            joined_at: None,
+            printed,
         };
 
         self.sessions
             .lock()
             .unwrap()
             .insert(session_id.clone(), session);
+        self.persist(|db| {
+            db.save_qr_session(
+                &session_id,
+                image_id,
+                &crate::db::current_timestamp(),
+                printed,
+            )
+        });
 
-        // QRコード用のURLを生成
-        let host = Self::choose_preferred_host();
-        let url = format!(
-            "http://{}:{}/app?session={}&image={}",
-            host, self.server_port, session_id, image_id
-        );
+        // QRコード用のURLを生成（署名付きトークンを埋め込む）
+        let url = self.session_url(&session_id, image_id);
         println!("[qr] Generated URL: {}", url);
+        (session_id, url)
+    }
 
-        // QRコードを生成
-        let qr_code = generate_qr_code(&url);
+    pub fn policy(&self) -> QrPolicy {
+        self.policy.lock().unwrap().clone()
+    }
 
-        (session_id, qr_code)
+    /// 運用ポリシーを差し替える（発行済みのQRにも次の接続から適用）
+    pub fn set_policy(&self, policy: QrPolicy) {
+        *self.policy.lock().unwrap() = policy;
     }
 
-    pub fn validate_session(&self, session_id: &str) -> Option<String> {
+    // 長期間放置されたセッションと、有効期限を過ぎた未接続のセッションを削除し、削除した数を返す
+    // （印刷したQRは会期中に読まれるまで待つため、運用ポリシーの有効期限を適用しない）
+    pub fn reap_expired(&self) -> usize {
+        let expiry = self.policy().expiry();
+        let now = Instant::now();
+        let removed: Vec<String> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let expired: Vec<String> = sessions
+                .values()
+                .filter(|session| {
+                    let age = now.duration_since(session.created_at);
+                    if session.printed {
+                        return age >= PRINTED_SESSION_MAX_AGE;
+                    }
+                    age >= SESSION_MAX_AGE
+                        || (!session.connected && expiry.is_some_and(|expiry| age >= expiry))
+                })
+                .map(|session| session.session_id.clone())
+                .collect();
+            for session_id in &expired {
+                sessions.remove(session_id);
+            }
+            expired
+        };
+        self.reconnect_tokens
+            .lock()
+            .unwrap()
+            .retain(|_, token| !matches!(token.expires_at, Some(at) if at <= now));
+        if !removed.is_empty() {
+            self.persist(|db| {
+                removed
+                    .iter()
+                    .try_for_each(|session_id| db.delete_qr_session(session_id))
+            });
+        }
+        removed.len()
+    }
+
+    /// QRから接続してきたセッションを確認し、紐付く画像IDを返す
+    pub fn validate_session(&self, session_id: &str) -> Result<String, SessionRejection> {
+        self.check_session(session_id, false)
+    }
+
+    /// HTTPとWebSocketの認証用: セッションが残っていて期限内か（接続の状態は変えない）
+    /// 取り消したセッションは削除済みのため通らない。接続中のスマホは期限を過ぎても切断までは使える
+    pub fn is_session_active(&self, session_id: &str) -> bool {
+        let expiry = self.policy().expiry();
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(session_id).is_some_and(|session| {
+            let age = session.created_at.elapsed();
+            if session.printed {
+                return age < PRINTED_SESSION_MAX_AGE;
+            }
+            age < SESSION_MAX_AGE
+                && (session.connected || !expiry.is_some_and(|expiry| age >= expiry))
+        })
+    }
+
+    // rejoin は再接続トークンで本人確認済みのため、使い切りの制限をかけない
+    fn check_session(&self, session_id: &str, rejoin: bool) -> Result<String, SessionRejection> {
+        // セッションのクリーンアップ
+        self.reap_expired();
+        let policy = self.policy();
         let mut sessions = self.sessions.lock().unwrap();
 
-        // セッションのクリーンアップ（長期間放置のみ削除）
-        // 有効期限は撤廃するため、24時間以上経過したものだけを掃除
+        let session = sessions
+            .get(session_id)
+            .ok_or(SessionRejection::NOT_FOUND)?;
+        if !rejoin
+            && !session.printed
+            && policy
+                .expiry()
+                .is_some_and(|expiry| session.created_at.elapsed() >= expiry)
+        {
+            return Err(SessionRejection::EXPIRED);
+        }
+        if !rejoin && policy.single_use && session.used {
+            return Err(SessionRejection::USED);
+        }
+        if let Some(max) = policy.max_concurrent_sessions {
+            let others = sessions
+                .values()
+                .filter(|other| other.connected && other.session_id != session_id)
+                .count();
+            if others >= max {
+                return Err(SessionRejection::TOO_MANY);
+            }
+        }
+
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or(SessionRejection::NOT_FOUND)?;
+        session.connected = true;
+        let first_use = !session.used;
+        session.used = true;
+        let image_id = session.image_id.clone();
+        drop(sessions);
+        if first_use {
+            self.persist(|db| db.mark_qr_session_used(session_id));
+        }
+        Ok(image_id)
+    }
+
+    // スマホが切断したら接続待ちに戻す（QRはそのまま再利用できる）
+    pub fn mark_disconnected(&self, session_id: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.connected = false;
+        }
+        // 再接続トークンの期限を切断時点から数え始める
+        let expires_at = Instant::now() + RECONNECT_GRACE;
+        for token in self.reconnect_tokens.lock().unwrap().values_mut() {
+            if token.session_id == session_id && token.expires_at.is_none() {
+                token.expires_at = Some(expires_at);
+            }
+        }
+    }
+
+    /// 接続したスマホへ渡す再接続トークンを発行する
+    pub fn issue_reconnect_token(&self, session_id: &str) -> String {
+        let token = Uuid::new_v4().to_string();
+        let mut tokens = self.reconnect_tokens.lock().unwrap();
         let now = Instant::now();
-        sessions.retain(|_, session| {
-            now.duration_since(session.created_at) < Duration::from_secs(24 * 60 * 60)
-        });
+        tokens.retain(|_, t| !matches!(t.expires_at, Some(at) if at <= now));
+        tokens.insert(
+            token.clone(),
+            ReconnectToken {
+                session_id: session_id.to_string(),
+                expires_at: None,
+            },
+        );
+        token
+    }
+
+    /// 再接続トークンを消費し、元のセッション (sessionId, imageId) を返す
+    pub fn redeem_reconnect_token(&self, token: &str) -> Option<(String, String)> {
+        let entry = self.reconnect_tokens.lock().unwrap().remove(token)?;
+        if entry.expires_at.is_some_and(|at| at <= Instant::now()) {
+            return None;
+        }
+        let image_id = self.check_session(&entry.session_id, true).ok()?;
+        Some((entry.session_id, image_id))
+    }
 
-        if let Some(session) = sessions.get_mut(session_id) {
-            // 有効期限を設けず常に許可（固定QR仕様）
-            session.connected = true;
-            return Some(session.image_id.clone());
+    /// セッションを取り消す（QRも再接続トークンも使えなくなる）。存在しなければ false
+    pub fn revoke_session(&self, session_id: &str) -> bool {
+        self.reconnect_tokens
+            .lock()
+            .unwrap()
+            .retain(|_, token| token.session_id != session_id);
+        let removed = self.sessions.lock().unwrap().remove(session_id).is_some();
+        self.persist(|db| db.delete_qr_session(session_id));
+        removed
+    }
+
+    /// スマホが参加した接続元を記録する（list_qr_sessions と見守り用の記録に残す）
+    pub fn record_join(&self, session_id: &str, remote_ip: Option<&str>) {
+        let joined_at = crate::db::current_timestamp();
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            let Some(session) = sessions.get_mut(session_id) else {
+                return;
+            };
+            session.remote_ip = remote_ip.map(str::to_string);
+            session.joined_at = Some(joined_at.clone());
         }
+        self.persist(|db| db.record_qr_session_join(session_id, remote_ip, &joined_at));
+    }
 
-        None
+    /// スタッフ向けのセッション一覧（新しい順）
+    pub fn list_sessions(&self) -> Vec<QrSession> {
+        let mut sessions: Vec<QrSession> =
+            self.sessions.lock().unwrap().values().cloned().collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        sessions
+    }
+
+    /// 診断用のセッション一覧（セッションIDは先頭のみ）
+    pub fn sessions_snapshot(&self) -> Vec<serde_json::Value> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .values()
+            .map(|session| {
+                serde_json::json!({
+                    "sessionId": crate::diagnostics::redact_id(&session.session_id),
+                    "imageId": session.image_id,
+                    "ageSeconds": session.created_at.elapsed().as_secs(),
+                    "connected": session.connected,
+                })
+            })
+            .collect()
     }
 
     pub fn get_session_status(&self, session_id: &str) -> Option<(bool, Duration)> {
+        let expiry = self.policy().expiry();
         let sessions = self.sessions.lock().unwrap();
         sessions.get(session_id).map(|session| {
-            // タイマー表記は廃止するが、互換のため大きな残り時間を返す
-            // UI側でカウントダウンは表示しない
-            let remaining = Duration::from_secs(24 * 60 * 60);
+            // 無期限の場合は互換のため大きな残り時間を返す（UI側でカウントダウンは表示しない）
+            let remaining = match expiry {
+                _ if session.printed => {
+                    PRINTED_SESSION_MAX_AGE.saturating_sub(session.created_at.elapsed())
+                }
+                Some(expiry) => expiry.saturating_sub(session.created_at.elapsed()),
+                None => SESSION_MAX_AGE,
+            };
             (session.connected, remaining)
         })
     }
 }
 
-fn generate_qr_code(data: &str) -> String {
-    let code = QrCode::new(data).unwrap();
-    let size = code.width();
+// QR表示スタイルを保存するグローバル設定キー
+pub const QR_STYLE_KEY: &str = "qr_style";
+
+/// QRの描画スタイル（暗いプロジェクター向けに高コントラスト表示を選べる）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrStyle {
+    // 背景を塗りつぶし、余白を広く取る
+    pub high_contrast: bool,
+    // 白黒を反転（黒背景に白モジュール）
+    pub inverted: bool,
+    // 1モジュールのピクセル数
+    pub module_size: u32,
+    // 余白のモジュール数（未指定なら高コントラスト時8、通常時0）
+    pub quiet_zone: Option<u32>,
+}
+
+impl Default for QrStyle {
+    fn default() -> Self {
+        Self {
+            high_contrast: false,
+            inverted: false,
+            module_size: 8,
+            quiet_zone: None,
+        }
+    }
+}
+
+impl QrStyle {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=64).contains(&self.module_size) {
+            return Err("モジュールサイズは1〜64で指定してください".to_string());
+        }
+        if self.quiet_zone.is_some_and(|zone| zone > 32) {
+            return Err("余白は32モジュール以下で指定してください".to_string());
+        }
+        Ok(())
+    }
+
+    /// 設定から読み込む（未設定・不正値なら通常表示）
+    pub fn load(app_handle: &tauri::AppHandle) -> Self {
+        crate::workspace::read_global_setting(app_handle, QR_STYLE_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str::<QrStyle>(&value).ok())
+            .filter(|style| style.validate().is_ok())
+            .unwrap_or_default()
+    }
+
+    fn quiet_zone(&self) -> u32 {
+        self.quiet_zone
+            .unwrap_or(if self.high_contrast { 8 } else { 0 })
+    }
+}
+
+/// QRコードをSVGのデータURIとして描画
+pub fn render_qr_svg(data: &str, style: &QrStyle) -> Result<String, String> {
+    let code = QrCode::new(data).map_err(|e| format!("QR_ENCODE_ERROR: {}", e))?;
+    let size = code.width() as u32;
+    let quiet = style.quiet_zone();
+    let total = size + quiet * 2;
+    let (dark, light) = if style.inverted {
+        ("#fff", "#000")
+    } else {
+        ("#000", "#fff")
+    };
+
     let mut svg = format!(
-        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
-        size, size
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{px}" height="{px}" viewBox="0 0 {t} {t}" shape-rendering="crispEdges">"#,
+        px = total * style.module_size,
+        t = total
     );
+    // 通常表示は従来どおり背景なし（反転時は背景が必須）
+    if style.high_contrast || style.inverted {
+        svg.push_str(&format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            total, total, light
+        ));
+    }
 
     for y in 0..size {
         for x in 0..size {
-            if code[(x, y)] == Color::Dark {
+            if code[(x as usize, y as usize)] == Color::Dark {
                 svg.push_str(&format!(
-                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#000\"/>",
-                    x, y
+                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"/>",
+                    x + quiet,
+                    y + quiet,
+                    dark
                 ));
             }
         }
@@ -163,5 +797,124 @@ fn generate_qr_code(data: &str) -> String {
     // Base64エンコードしてデータURIとして返す
     use base64::{engine::general_purpose, Engine as _};
     let encoded = general_purpose::STANDARD.encode(svg);
-    format!("data:image/svg+xml;base64,{}", encoded)
+    Ok(format!("data:image/svg+xml;base64,{}", encoded))
+}
+
+// 印刷用PNGのロゴは中央のこの割合まで（誤り訂正で読める範囲に収める）
+const MAX_LOGO_SCALE: f32 = 0.3;
+
+/// 印刷・ブランディング向けのPNG出力の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QrImageOptions {
+    // 誤り訂正レベル "L" | "M" | "Q" | "H"（未指定ならロゴありでH、なしでM）
+    pub error_correction: Option<String>,
+    // 1モジュールのピクセル数
+    pub module_size: u32,
+    // 余白のモジュール数
+    pub quiet_zone: u32,
+    // "#rrggbb"
+    pub foreground: String,
+    pub background: String,
+    // 中央に重ねるロゴ画像のパス
+    pub logo_path: Option<String>,
+    // ロゴの幅（QR全体の幅に対する割合）
+    pub logo_scale: f32,
+}
+
+impl Default for QrImageOptions {
+    fn default() -> Self {
+        Self {
+            error_correction: None,
+            module_size: 8,
+            quiet_zone: 4,
+            foreground: "#000000".to_string(),
+            background: "#ffffff".to_string(),
+            logo_path: None,
+            logo_scale: 0.2,
+        }
+    }
+}
+
+impl QrImageOptions {
+    pub fn ec_level(&self) -> Result<EcLevel, String> {
+        match self.error_correction.as_deref() {
+            None if self.logo_path.is_some() => Ok(EcLevel::H),
+            None => Ok(EcLevel::M),
+            Some("L") | Some("l") => Ok(EcLevel::L),
+            Some("M") | Some("m") => Ok(EcLevel::M),
+            Some("Q") | Some("q") => Ok(EcLevel::Q),
+            Some("H") | Some("h") => Ok(EcLevel::H),
+            Some(other) => Err(format!(
+                "誤り訂正レベルは L / M / Q / H で指定してください: {}",
+                other
+            )),
+        }
+    }
+}
+
+/// QRコードをPNGで描画（ロゴを重ねる場合は誤り訂正レベルを上げること）
+pub fn render_qr_png(data: &str, options: &QrImageOptions) -> Result<Vec<u8>, String> {
+    use image::{imageops, ImageFormat, Rgba, RgbaImage};
+
+    if !(1..=64).contains(&options.module_size) {
+        return Err("モジュールサイズは1〜64で指定してください".to_string());
+    }
+    if options.quiet_zone > 32 {
+        return Err("余白は32モジュール以下で指定してください".to_string());
+    }
+    let color = |value: &str| {
+        crate::output_background::parse_hex_color(value)
+            .map(|[r, g, b]| Rgba([r, g, b, 255]))
+            .ok_or_else(|| format!("色は #rrggbb の形式で指定してください: {}", value))
+    };
+    let dark = color(&options.foreground)?;
+    let light = color(&options.background)?;
+
+    let code = QrCode::with_error_correction_level(data, options.ec_level()?)
+        .map_err(|e| format!("QR_ENCODE_ERROR: {}", e))?;
+    let size = code.width() as u32;
+    let total = (size + options.quiet_zone * 2) * options.module_size;
+    let mut img = RgbaImage::from_pixel(total, total, light);
+    for y in 0..size {
+        for x in 0..size {
+            if code[(x as usize, y as usize)] != Color::Dark {
+                continue;
+            }
+            let left = (x + options.quiet_zone) * options.module_size;
+            let top = (y + options.quiet_zone) * options.module_size;
+            for dy in 0..options.module_size {
+                for dx in 0..options.module_size {
+                    img.put_pixel(left + dx, top + dy, dark);
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &options.logo_path {
+        if !(options.logo_scale > 0.0 && options.logo_scale <= MAX_LOGO_SCALE) {
+            return Err(format!(
+                "ロゴの大きさは0より大きく{}以下で指定してください",
+                MAX_LOGO_SCALE
+            ));
+        }
+        let logo =
+            image::open(path).map_err(|e| format!("ロゴ画像の読み込みに失敗しました: {}", e))?;
+        let max_side = ((size * options.module_size) as f32 * options.logo_scale).max(1.0) as u32;
+        let logo = logo
+            .resize(max_side, max_side, imageops::FilterType::Lanczos3)
+            .to_rgba8();
+        // ロゴの周りに背景色の縁を付けてモジュールと見分けやすくする
+        let pad = options.module_size;
+        let (w, h) = (logo.width() + pad * 2, logo.height() + pad * 2);
+        let plate = RgbaImage::from_pixel(w, h, light);
+        let (px, py) = (((total - w) / 2) as i64, ((total - h) / 2) as i64);
+        imageops::overlay(&mut img, &plate, px, py);
+        imageops::overlay(&mut img, &logo, px + pad as i64, py + pad as i64);
+    }
+
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    Ok(buf)
 }
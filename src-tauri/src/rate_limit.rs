@@ -0,0 +1,184 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::workspace::WorkspaceState;
+
+// 制限値を保存する app_settings のキー
+const RATE_LIMITS_KEY: &str = "rate_limits";
+// 制限対象のパス（静的UIと画像配信は対象外）
+const LIMITED_PREFIXES: [&str; 2] = ["/api/", "/ws"];
+// この時間アクセスのないIPのバケットは破棄する
+const BUCKET_IDLE_TTL: Duration = Duration::from_secs(600);
+// バケット数がこれを超えたら古いものを掃除する
+const MAX_TRACKED_IPS: usize = 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RateLimits {
+    // HTTP: IPごとの1分あたりのリクエスト数と瞬間的な上限
+    pub http_per_minute: u32,
+    pub http_burst: u32,
+    // WebSocket: セッションごとの1秒あたりのメッセージ数と瞬間的な上限
+    pub ws_messages_per_second: u32,
+    pub ws_burst: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            http_per_minute: 120,
+            http_burst: 30,
+            ws_messages_per_second: 10,
+            ws_burst: 30,
+        }
+    }
+}
+
+static LIMITS: Lazy<RwLock<RateLimits>> = Lazy::new(|| RwLock::new(RateLimits::default()));
+static HTTP_BUCKETS: Lazy<Mutex<HashMap<IpAddr, TokenBucket>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// トークンバケット（rate_per_sec で補充、capacity まで貯まる）
+pub struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            tokens: capacity as f64,
+            last: Instant::now(),
+        }
+    }
+
+    // 1トークン消費できればOk、足りなければ次に使えるまでの秒数
+    pub fn try_take(&mut self, rate_per_sec: f64, capacity: u32) -> Result<(), u64> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * rate_per_sec).min(capacity as f64);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if rate_per_sec > 0.0 {
+            Err(((1.0 - self.tokens) / rate_per_sec).ceil().max(1.0) as u64)
+        } else {
+            Err(60)
+        }
+    }
+}
+
+pub fn current() -> RateLimits {
+    *LIMITS.read().unwrap()
+}
+
+fn validate(limits: &RateLimits) -> Result<(), String> {
+    if limits.http_per_minute == 0
+        || limits.http_burst == 0
+        || limits.ws_messages_per_second == 0
+        || limits.ws_burst == 0
+    {
+        return Err("レート制限の値は1以上で指定してください".to_string());
+    }
+    Ok(())
+}
+
+/// ワークスペースのapp_settingsから制限値を読み直す（未設定・不正値なら既定値）
+pub fn reload(app_handle: &AppHandle) {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(RATE_LIMITS_KEY).ok().flatten()
+    });
+    let limits = stored
+        .and_then(|value| serde_json::from_str::<RateLimits>(&value).ok())
+        .filter(|limits| validate(limits).is_ok())
+        .unwrap_or_default();
+    *LIMITS.write().unwrap() = limits;
+}
+
+fn is_limited(path: &str) -> bool {
+    LIMITED_PREFIXES
+        .iter()
+        .any(|prefix| path == prefix.trim_end_matches('/') || path.starts_with(prefix))
+}
+
+fn check_http(ip: IpAddr) -> Result<(), u64> {
+    let limits = current();
+    let mut buckets = HTTP_BUCKETS.lock().unwrap();
+    if buckets.len() > MAX_TRACKED_IPS {
+        buckets.retain(|_, bucket| bucket.last.elapsed() < BUCKET_IDLE_TTL);
+    }
+    buckets
+        .entry(ip)
+        .or_insert_with(|| TokenBucket::new(limits.http_burst))
+        .try_take(limits.http_per_minute as f64 / 60.0, limits.http_burst)
+}
+
+/// 429応答の本文（HTTP/WS共通の形式）
+pub fn rate_limited_body(retry_after_secs: u64) -> serde_json::Value {
+    serde_json::json!({
+        "type": "error",
+        "error": "rate_limited",
+        "message": "リクエストが多すぎます。しばらく待ってから再度お試しください",
+        "retryAfter": retry_after_secs,
+    })
+}
+
+/// /api と /ws へのリクエストをIPごとに制限するミドルウェア
+pub async fn rate_limit_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let ip = req.peer_addr().map(|addr| addr.ip());
+    if let (Some(ip), true) = (ip, is_limited(req.path())) {
+        if let Err(retry_after) = check_http(ip) {
+            println!("[rate_limit] rejected {} from {}", req.path(), ip);
+            let res = HttpResponse::TooManyRequests()
+                .insert_header((
+                    actix_web::http::header::RETRY_AFTER,
+                    retry_after.to_string(),
+                ))
+                .json(rate_limited_body(retry_after));
+            return Ok(req.into_response(res).map_into_boxed_body());
+        }
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[tauri::command]
+pub fn get_rate_limits() -> Result<RateLimits, String> {
+    Ok(current())
+}
+
+/// 制限値を保存して即時反映（再起動不要）
+#[tauri::command]
+pub fn set_rate_limits(
+    workspace: State<'_, WorkspaceState>,
+    limits: RateLimits,
+) -> Result<(), String> {
+    validate(&limits)?;
+    let value = serde_json::to_string(&limits)
+        .map_err(|e| format!("レート制限のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(RATE_LIMITS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))?;
+
+    *LIMITS.write().unwrap() = limits;
+    HTTP_BUCKETS.lock().unwrap().clear();
+    Ok(())
+}
@@ -0,0 +1,285 @@
+// 放置されたセッション・接続・作業ファイルをまとめて定期的に片付ける
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, State};
+
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+// 保持期間を保存する app_settings のキー
+const REAPER_SETTINGS_KEY: &str = "reaper_settings";
+const REAP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// ハイライト動画の作業フォルダは生成が終われば消えるため、これより古いものは中断の残り
+const WORK_DIR_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+// Relayに送れないまま残ったメッセージ（プレビューや通知は時間が経つと意味がない）
+const OUTBOX_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ReaperSettings {
+    // クラウド取り込みでダウンロードした元画像を残す時間
+    pub intake_retention_hours: u64,
+}
+
+impl Default for ReaperSettings {
+    fn default() -> Self {
+        Self {
+            intake_retention_hours: 7 * 24,
+        }
+    }
+}
+
+/// 片付けた内容（"resources-reaped"）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReapReport {
+    pub qr_sessions: usize,
+    pub ws_connections: usize,
+    pub mobile_sessions: usize,
+    pub work_dirs: usize,
+    pub intake_files: usize,
+    pub takeaway_files: usize,
+    pub queue_rows: usize,
+    pub bytes_freed: u64,
+}
+
+impl ReapReport {
+    fn is_empty(&self) -> bool {
+        self.qr_sessions == 0
+            && self.ws_connections == 0
+            && self.mobile_sessions == 0
+            && self.work_dirs == 0
+            && self.intake_files == 0
+            && self.takeaway_files == 0
+            && self.queue_rows == 0
+    }
+}
+
+fn read_settings(app_handle: &AppHandle) -> ReaperSettings {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(REAPER_SETTINGS_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<ReaperSettings>(&value).ok())
+        .filter(|settings| settings.intake_retention_hours > 0)
+        .unwrap_or_default()
+}
+
+fn older_than(path: &Path, max_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+// 中断したハイライト動画生成の作業フォルダ
+fn reap_work_dirs(app_handle: &AppHandle, report: &mut ReapReport) {
+    let Ok(export_dir) = crate::highlights::current_export_dir(app_handle) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&export_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_work_dir = path.is_dir()
+            && entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(".highlights-");
+        if !is_work_dir || !older_than(&path, WORK_DIR_MAX_AGE) {
+            continue;
+        }
+        let size = dir_size(&path);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => {
+                report.work_dirs += 1;
+                report.bytes_freed += size;
+            }
+            Err(e) => eprintln!("[reaper] failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+// 取り込み済みのクラウドのダウンロード（images/inbox/<provider>/）
+fn reap_intake_files(app_handle: &AppHandle, max_age: Duration, report: &mut ReapReport) {
+    let root = {
+        let state: State<WorkspaceState> = app_handle.state();
+        let Ok(conn) = state.lock() else {
+            return;
+        };
+        conn.workspace_root()
+    };
+    let Some(root) = root else {
+        return;
+    };
+    let Ok(providers) = fs::read_dir(root.join("images").join("inbox")) else {
+        return;
+    };
+    for provider in providers.flatten() {
+        let Ok(files) = fs::read_dir(provider.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            // 一時停止中に保留した取り込みは解除後に処理するので残す
+            if !path.is_file()
+                || !older_than(&path, max_age)
+                || crate::maintenance::is_deferred(&path)
+            {
+                continue;
+            }
+            let size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    report.intake_files += 1;
+                    report.bytes_freed += size;
+                }
+                Err(e) => eprintln!("[reaper] failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+// リンクの期限が切れた・取り消された持ち帰りの控え（exports/takeaways/）
+// 受け取り回数の集計に使うので takeaways の行は残す
+fn reap_takeaway_files(app_handle: &AppHandle, report: &mut ReapReport) {
+    let inactive: Vec<String> = {
+        let state: State<WorkspaceState> = app_handle.state();
+        let Ok(conn) = state.lock() else {
+            return;
+        };
+        let Ok(db) = conn.get() else {
+            return;
+        };
+        let Ok(takeaways) = db.list_takeaways() else {
+            return;
+        };
+        takeaways
+            .into_iter()
+            .filter(|takeaway| {
+                !db.get_share_link(&takeaway.share_link_id)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|link| crate::share_links::is_active(&link))
+            })
+            .map(|takeaway| takeaway.file_path)
+            .collect()
+    };
+    for file_path in inactive {
+        let path = Path::new(&file_path);
+        if !path.is_file() {
+            continue;
+        }
+        let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+        match fs::remove_file(path) {
+            Ok(()) => {
+                report.takeaway_files += 1;
+                report.bytes_freed += size;
+            }
+            Err(e) => eprintln!("[reaper] failed to remove {}: {}", path.display(), e),
+        }
+    }
+}
+
+// 期限を過ぎたRelayの送信待ち
+fn reap_outbox(app_handle: &AppHandle, report: &mut ReapReport) {
+    let state: State<WorkspaceState> = app_handle.state();
+    let Ok(conn) = state.lock() else {
+        return;
+    };
+    let Ok(db) = conn.get() else {
+        return;
+    };
+    let cutoff = chrono::Utc::now() - chrono::Duration::seconds(OUTBOX_MAX_AGE.as_secs() as i64);
+    match db.prune_relay_messages(&cutoff.to_rfc3339()) {
+        Ok(removed) => report.queue_rows += removed,
+        Err(e) => eprintln!("[reaper] failed to prune relay outbox: {}", e),
+    }
+}
+
+/// 一通り片付けて結果を返す
+pub fn reap(app_handle: &AppHandle) -> ReapReport {
+    let mut report = ReapReport::default();
+
+    let server_state: State<ServerState> = app_handle.state();
+    if let Some(qr_manager) = server_state.get_qr_manager() {
+        report.qr_sessions = qr_manager.reap_expired();
+    }
+    report.ws_connections = crate::websocket::reap_dead_connections(app_handle);
+    report.mobile_sessions = server_state.reap_closed_mobile_sessions();
+
+    reap_work_dirs(app_handle, &mut report);
+    reap_takeaway_files(app_handle, &mut report);
+    reap_outbox(app_handle, &mut report);
+    let settings = read_settings(app_handle);
+    reap_intake_files(
+        app_handle,
+        Duration::from_secs(settings.intake_retention_hours * 60 * 60),
+        &mut report,
+    );
+    report
+}
+
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(REAP_INTERVAL);
+        let report = reap(&app_handle);
+        if report.is_empty() {
+            continue;
+        }
+        println!("[reaper] reclaimed {:?}", report);
+        let _ = crate::events::emit_routed(&app_handle, "resources-reaped", report);
+    });
+}
+
+/// 定期実行を待たずに片付ける
+#[tauri::command]
+pub fn reap_now(app_handle: AppHandle) -> Result<ReapReport, String> {
+    let report = reap(&app_handle);
+    let _ = crate::events::emit_routed(&app_handle, "resources-reaped", report.clone());
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_reaper_settings(app_handle: AppHandle) -> Result<ReaperSettings, String> {
+    Ok(read_settings(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_reaper_settings(
+    workspace: State<'_, WorkspaceState>,
+    settings: ReaperSettings,
+) -> Result<(), String> {
+    if settings.intake_retention_hours == 0 {
+        return Err("保持時間は1時間以上で指定してください".to_string());
+    }
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("片付けの設定のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(REAPER_SETTINGS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
@@ -0,0 +1,333 @@
+// アニメーション画面の録画（一定間隔でフレームを撮影し、終了後にffmpegで動画にまとめる）
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const DEFAULT_FPS: u32 = 10;
+const MAX_FPS: u32 = 30;
+const DEFAULT_DURATION_SECS: u64 = 30;
+// 長時間の録画は作業フォルダの容量を圧迫するため上限を設ける
+const MAX_DURATION_SECS: u64 = 10 * 60;
+// 連続してこの回数撮影に失敗したら録画を打ち切る
+const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingState {
+    Idle,
+    Capturing,
+    Encoding,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingStatus {
+    pub state: RecordingState,
+    pub fps: u32,
+    pub total_frames: u32,
+    pub frames_captured: u32,
+    pub frames_dropped: u32,
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Default for RecordingStatus {
+    fn default() -> Self {
+        RecordingStatus {
+            state: RecordingState::Idle,
+            fps: 0,
+            total_frames: 0,
+            frames_captured: 0,
+            frames_dropped: 0,
+            path: None,
+            error: None,
+        }
+    }
+}
+
+static STATUS: Lazy<Mutex<RecordingStatus>> = Lazy::new(|| Mutex::new(RecordingStatus::default()));
+// 実行中の録画の停止フラグ
+static STOP: Lazy<Mutex<Option<Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(None));
+
+fn status() -> RecordingStatus {
+    STATUS
+        .lock()
+        .map(|status| status.clone())
+        .unwrap_or_default()
+}
+
+fn update(app_handle: &AppHandle, f: impl FnOnce(&mut RecordingStatus)) {
+    let snapshot = match STATUS.lock() {
+        Ok(mut status) => {
+            f(&mut status);
+            status.clone()
+        }
+        Err(_) => return,
+    };
+    let _ = crate::events::emit_routed(app_handle, "recording-progress", snapshot);
+}
+
+fn is_running() -> bool {
+    matches!(
+        status().state,
+        RecordingState::Capturing | RecordingState::Encoding
+    )
+}
+
+// 撮影した時刻どおりの長さで各フレームを並べる（ffmpeg の concat demuxer 用の一覧）
+fn write_frame_list(
+    work_dir: &Path,
+    timestamps: &[f64],
+    end: f64,
+    fps: u32,
+) -> Result<PathBuf, String> {
+    let min_duration = 1.0 / fps as f64;
+    let mut list = String::from("ffconcat version 1.0\n");
+    for (index, start) in timestamps.iter().enumerate() {
+        let next = timestamps.get(index + 1).copied().unwrap_or(end);
+        list.push_str(&format!(
+            "file 'frame_{:05}.png'\nduration {:.6}\n",
+            index,
+            (next - start).max(min_duration)
+        ));
+    }
+    // 最後のフレームの長さを反映させるため、もう一度同じファイルを並べる（concat demuxer の仕様）
+    if let Some(last) = timestamps.len().checked_sub(1) {
+        list.push_str(&format!("file 'frame_{:05}.png'\n", last));
+    }
+    let path = work_dir.join("frames.txt");
+    std::fs::write(&path, list).map_err(|e| format!("フレーム一覧の保存に失敗しました: {}", e))?;
+    Ok(path)
+}
+
+fn encode(
+    app_handle: &AppHandle,
+    work_dir: &Path,
+    fps: u32,
+    timestamps: &[f64],
+    end: f64,
+    output: &Path,
+) -> Result<(), String> {
+    let codec: &[&str] = match output.extension().and_then(|ext| ext.to_str()) {
+        Some("webm") => &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "32"],
+        _ => &["-c:v", "libx264", "-preset", "veryfast", "-crf", "23"],
+    };
+    let list = write_frame_list(work_dir, timestamps, end, fps)?;
+    let mut args: Vec<String> = vec![
+        "-y".into(),
+        "-f".into(),
+        "concat".into(),
+        "-safe".into(),
+        "0".into(),
+        "-i".into(),
+        list.to_string_lossy().to_string(),
+        // 撮影間隔のばらつきは fps フィルターで複製・間引きして一定のフレームレートにする
+        // 幅・高さが奇数だとエンコードできないため偶数に丸める
+        "-vf".into(),
+        format!(
+            "fps={},scale=trunc(iw/2)*2:trunc(ih/2)*2,format=yuv420p",
+            fps
+        ),
+    ];
+    args.extend(codec.iter().map(|arg| arg.to_string()));
+    args.push(output.to_string_lossy().to_string());
+    crate::highlights::run_ffmpeg(&crate::highlights::ffmpeg_path(app_handle), &args)
+}
+
+async fn run(
+    app_handle: AppHandle,
+    label: String,
+    fps: u32,
+    total_frames: u32,
+    work_dir: PathBuf,
+    output: PathBuf,
+    stop: Arc<AtomicBool>,
+) {
+    let interval = Duration::from_secs_f64(1.0 / fps as f64);
+    let length = interval * total_frames;
+    let started_at = Instant::now();
+    // 撮影できたフレームの録画開始からの秒数（撮影に時間がかかっても実時間どおりに再生するため）
+    let mut timestamps: Vec<f64> = Vec::new();
+    let mut failures = 0u32;
+    let mut error = None;
+    // 撮影の枠（1/fps 秒ごと）。撮影が遅れた分の枠は飛ばし、欠けたフレームとして数える
+    let mut slot = 0u32;
+
+    while slot < total_frames {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let requested_at = started_at.elapsed();
+        match crate::frame_capture::request_frame(&app_handle, &label).await {
+            Ok(bytes) => {
+                failures = 0;
+                // 欠けたフレームがあっても連番になるよう撮影できた数で名前を付ける
+                let path = work_dir.join(format!("frame_{:05}.png", timestamps.len()));
+                if let Err(e) = std::fs::write(&path, bytes) {
+                    error = Some(format!("フレームの保存に失敗しました: {}", e));
+                    break;
+                }
+                timestamps.push(requested_at.as_secs_f64());
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!("[recording] frame {} dropped: {}", slot, e);
+                if failures >= MAX_CONSECUTIVE_FAILURES {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        let previous_second = slot / fps;
+        let elapsed_slots = (started_at.elapsed().as_secs_f64() * fps as f64) as u32;
+        slot = (slot + 1).max(elapsed_slots + 1).min(total_frames);
+        let captured = timestamps.len() as u32;
+        if let Ok(mut status) = STATUS.lock() {
+            status.frames_captured = captured;
+            status.frames_dropped = slot.saturating_sub(captured);
+        }
+        // 1秒ごとに進捗を通知
+        if slot / fps != previous_second {
+            update(&app_handle, |_| {});
+        }
+        if let Some(wait) = (interval * slot).checked_sub(started_at.elapsed()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+    let end = started_at.elapsed().min(length).as_secs_f64();
+
+    let result = match error {
+        Some(e) => Err(e),
+        None if timestamps.is_empty() => Err("フレームを撮影できませんでした".to_string()),
+        None => {
+            update(&app_handle, |status| {
+                status.state = RecordingState::Encoding
+            });
+            let encode_handle = app_handle.clone();
+            let encode_dir = work_dir.clone();
+            let encode_output = output.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                encode(
+                    &encode_handle,
+                    &encode_dir,
+                    fps,
+                    &timestamps,
+                    end,
+                    &encode_output,
+                )
+            })
+            .await
+            .map_err(|e| format!("動画の作成に失敗しました: {}", e))
+            .and_then(|result| result)
+        }
+    };
+
+    let _ = std::fs::remove_dir_all(&work_dir);
+    if let Ok(mut current) = STOP.lock() {
+        *current = None;
+    }
+    match result {
+        Ok(()) => {
+            println!("[recording] exported {}", output.display());
+            update(&app_handle, |status| {
+                status.state = RecordingState::Finished;
+                status.path = Some(output.to_string_lossy().to_string());
+            });
+        }
+        Err(e) => {
+            eprintln!("[recording] {}", e);
+            crate::heartbeat::record_error(format!("recording: {}", e));
+            update(&app_handle, |status| {
+                status.state = RecordingState::Failed;
+                status.error = Some(e);
+            });
+        }
+    }
+}
+
+/// 録画を開始する（format は "mp4" または "webm"）
+#[tauri::command]
+pub fn start_recording(
+    app_handle: AppHandle,
+    fps: Option<u32>,
+    duration_secs: Option<u64>,
+    format: Option<String>,
+    window_label: Option<String>,
+) -> Result<RecordingStatus, String> {
+    if is_running() {
+        return Err("録画中です".to_string());
+    }
+    let fps = fps.unwrap_or(DEFAULT_FPS);
+    if fps == 0 || fps > MAX_FPS {
+        return Err(format!("フレームレートは1〜{}で指定してください", MAX_FPS));
+    }
+    let duration_secs = duration_secs.unwrap_or(DEFAULT_DURATION_SECS);
+    if duration_secs == 0 || duration_secs > MAX_DURATION_SECS {
+        return Err(format!(
+            "録画時間は1〜{}秒で指定してください",
+            MAX_DURATION_SECS
+        ));
+    }
+    let extension = match format.as_deref().unwrap_or("mp4") {
+        "mp4" => "mp4",
+        "webm" => "webm",
+        other => return Err(format!("未対応の形式です: {}", other)),
+    };
+    let label = window_label.unwrap_or_else(|| "animation".to_string());
+
+    let out_dir = crate::frame_capture::exports_dir(&app_handle)?.join("recordings");
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let work_dir = out_dir.join(format!(".recording-{}", stamp));
+    std::fs::create_dir_all(&work_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let output = out_dir.join(format!("recording-{}.{}", stamp, extension));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Ok(mut current) = STOP.lock() {
+        *current = Some(stop.clone());
+    }
+    let total_frames = (fps as u64 * duration_secs) as u32;
+    update(&app_handle, |status| {
+        *status = RecordingStatus {
+            state: RecordingState::Capturing,
+            fps,
+            total_frames,
+            ..RecordingStatus::default()
+        };
+    });
+    println!(
+        "[recording] started: {} fps, {} frames -> {}",
+        fps,
+        total_frames,
+        output.display()
+    );
+    tauri::async_runtime::spawn(run(
+        app_handle,
+        label,
+        fps,
+        total_frames,
+        work_dir,
+        output,
+        stop,
+    ));
+    Ok(status())
+}
+
+/// 録画を止める（撮影済みのフレームで動画を作成する）
+#[tauri::command]
+pub fn stop_recording() -> Result<RecordingStatus, String> {
+    if let Some(stop) = STOP.lock().ok().and_then(|current| current.clone()) {
+        stop.store(true, Ordering::SeqCst);
+    }
+    Ok(status())
+}
+
+#[tauri::command]
+pub fn get_recording_status() -> Result<RecordingStatus, String> {
+    Ok(status())
+}
@@ -0,0 +1,607 @@
+// Relayへの常時接続（会場外のスマホからの操作をバックエンドで受け取る）
+// 受け取ったコマンドはローカルのWebSocketと同じく mobile-control へ流す
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::db::{Database, RelayOutboxStats};
+use crate::workspace::WorkspaceState;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// pc-ack がこの時間内に届かなければ接続し直す
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_BACKOFF_SECS: u64 = 2;
+const MAX_BACKOFF_SECS: u64 = 60;
+// 送信待ちを一度に読み出す件数
+const OUTBOX_BATCH: i64 = 50;
+// スマホへ送るプレビューの長辺
+const PREVIEW_MAX_SIDE: u32 = 256;
+
+static RELAY_TASK: Mutex<Option<tauri::async_runtime::JoinHandle<()>>> = Mutex::new(None);
+static STATUS: Mutex<Option<RelayStatus>> = Mutex::new(None);
+// 送信待ちが追加されたことを接続中のタスクへ知らせる
+static OUTBOX_READY: Lazy<Notify> = Lazy::new(Notify::new);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayClientConfig {
+    pub base_url: String,
+    pub event_id: String,
+    pub pc_id: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayState {
+    Stopped,
+    Connecting,
+    Authenticating,
+    Connected,
+    TokenMissing,
+    Error,
+}
+
+/// 接続状態（"relay-client-status"）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatus {
+    pub state: RelayState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    // 接続し直した回数（起動からの累計）
+    pub reconnects: u32,
+}
+
+impl Default for RelayStatus {
+    fn default() -> Self {
+        Self {
+            state: RelayState::Stopped,
+            detail: None,
+            url: None,
+            reconnects: 0,
+        }
+    }
+}
+
+pub fn current_status() -> RelayStatus {
+    STATUS.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// 接続を開始済みか（切断中で再接続を待っている間も含む）
+pub fn is_running() -> bool {
+    RELAY_TASK
+        .lock()
+        .map(|guard| guard.is_some())
+        .unwrap_or(false)
+}
+
+fn set_status(app_handle: &AppHandle, state: RelayState, detail: Option<String>) {
+    let status = {
+        let mut guard = STATUS.lock().unwrap();
+        let status = guard.get_or_insert_with(RelayStatus::default);
+        if state == RelayState::Connecting && status.state != RelayState::Stopped {
+            status.reconnects += 1;
+        }
+        status.state = state;
+        status.detail = detail;
+        status.clone()
+    };
+    // 各ウィンドウの接続表示は従来の "pc-bridge-status" の状態名で受け取る
+    let bridge_state = match status.state {
+        RelayState::Stopped => "stopped",
+        RelayState::Connecting => "starting",
+        RelayState::Authenticating => "auth-sent",
+        RelayState::Connected => "ack",
+        RelayState::TokenMissing => "token-missing",
+        RelayState::Error => "error",
+    };
+    let _ = crate::events::emit_routed(
+        app_handle,
+        "pc-bridge-status",
+        serde_json::json!({ "state": bridge_state, "detail": status.detail }),
+    );
+    let _ = crate::events::emit_routed(app_handle, "relay-client-status", status);
+}
+
+fn ws_url(config: &RelayClientConfig) -> String {
+    let base = config.base_url.trim().trim_end_matches('/');
+    let base = match base.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some(("http", rest)) => format!("ws://{}", rest),
+        _ => base.to_string(),
+    };
+    format!("{}/e/{}/ws", base, config.event_id.trim())
+}
+
+// Relayからのコマンドをローカルと同じ経路で処理する（一時停止中はローカルのWSと同じく受け付けない）
+fn forward_cmd(app_handle: &AppHandle, msg: &serde_json::Value) {
+    if crate::maintenance::is_enabled() {
+        println!("[relay_client] maintenance mode; ignoring relay command");
+        return;
+    }
+    let payload = match msg.get("payload") {
+        Some(payload) => payload.clone(),
+        None => serde_json::json!({
+            "cmd": msg.get("cmd"),
+            "imageId": msg.get("imageId"),
+        }),
+    };
+    if let Some(cmd) = payload.get("cmd").and_then(|v| v.as_str()) {
+        // Relayはセッションの検証を済ませた上で imageId を付けて転送してくる
+        let image_id = payload.get("imageId").and_then(|v| v.as_str());
+        crate::websocket::dispatch_cmd(app_handle, cmd, image_id);
+    }
+}
+
+// 受信したメッセージを処理する。認証が完了したら true
+fn handle_message(app_handle: &AppHandle, text: &str) -> Result<bool, String> {
+    let Ok(msg) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Ok(false);
+    };
+    match msg.get("type").and_then(|v| v.as_str()) {
+        Some("pc-ack") => return Ok(true),
+        Some("pc-err") => return Err(format!("Relayが認証を拒否しました: {}", msg)),
+        Some("cmd") => forward_cmd(app_handle, &msg),
+        Some("evt") if msg.get("evt").and_then(|v| v.as_str()) == Some("mobile-connected") => {
+            let image_id = msg
+                .get("data")
+                .and_then(|d| d.get("imageId"))
+                .or_else(|| msg.get("imageId"));
+            let _ = crate::events::emit_routed(
+                app_handle,
+                "mobile-connected",
+                serde_json::json!({
+                    "sessionId": msg.get("sid"),
+                    "imageId": image_id,
+                }),
+            );
+        }
+        Some("evt") => {
+            if let Some(echo) = msg
+                .get("echo")
+                .filter(|echo| echo.get("type").and_then(|v| v.as_str()) == Some("cmd"))
+            {
+                forward_cmd(app_handle, echo);
+            }
+        }
+        Some("req") if msg.get("req").and_then(|v| v.as_str()) == Some("preview") => {
+            if let (Some(sid), Some(image_id)) = (
+                msg.get("sid").and_then(|v| v.as_str()),
+                msg.get("imageId").and_then(|v| v.as_str()),
+            ) {
+                let app_handle = app_handle.clone();
+                let (sid, image_id) = (sid.to_string(), image_id.to_string());
+                // 画像の縮小は重いので受信ループを止めない
+                tauri::async_runtime::spawn_blocking(move || {
+                    if let Err(e) = send_preview(&app_handle, &sid, &image_id) {
+                        eprintln!("[relay_client] preview for {} failed: {}", image_id, e);
+                    }
+                });
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+// スマホの操作画面に出すプレビュー（長辺256pxに縮小したPNG）を送る
+fn send_preview(app_handle: &AppHandle, sid: &str, image_id: &str) -> Result<(), String> {
+    let (_, img) = {
+        let state = app_handle.state::<WorkspaceState>();
+        let conn = state
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        crate::image_edit::load_image(conn.get()?, image_id)?
+    };
+    let thumb = img.thumbnail(PREVIEW_MAX_SIDE, PREVIEW_MAX_SIDE);
+    let mut buf = Vec::new();
+    thumb
+        .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    let payload = serde_json::json!({
+        "v": 1,
+        "type": "evt",
+        "sid": sid,
+        "evt": "preview",
+        "data": {
+            "imageId": image_id,
+            "thumb": format!("data:image/png;base64,{}", STANDARD.encode(buf)),
+        },
+    });
+    enqueue(app_handle, &payload).map(|_| ())
+}
+
+fn with_db<T>(
+    app_handle: &AppHandle,
+    f: impl FnOnce(&Database) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let state = app_handle
+        .try_state::<WorkspaceState>()
+        .ok_or_else(|| "ワークスペースが初期化されていません".to_string())?;
+    let conn = state
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    f(db).map_err(|e| format!("Failed to access relay outbox: {}", e))
+}
+
+/// Relayへ送るメッセージを送信待ちに積む（接続中ならすぐ、切断中なら再接続後に順番どおり送る）
+pub fn enqueue(app_handle: &AppHandle, payload: &serde_json::Value) -> Result<i64, String> {
+    let id = with_db(app_handle, |db| {
+        db.enqueue_relay_message(&payload.to_string())
+    })?;
+    OUTBOX_READY.notify_one();
+    Ok(id)
+}
+
+// 送信待ちを古い順に送る。送れなかったものは残し、接続をやり直す
+async fn flush_outbox<S>(app_handle: &AppHandle, sink: &mut S) -> Result<(), String>
+where
+    S: futures_util::Sink<Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    loop {
+        let entries = with_db(app_handle, |db| db.peek_relay_messages(OUTBOX_BATCH))?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        for entry in entries {
+            if let Err(e) = sink.send(Message::Text(entry.payload)).await {
+                let error = format!("Relayへの送信に失敗しました: {}", e);
+                with_db(app_handle, |db| {
+                    db.mark_relay_message_failed(entry.id, &error)
+                })?;
+                return Err(error);
+            }
+            with_db(app_handle, |db| db.delete_relay_message(entry.id))?;
+        }
+    }
+}
+
+// 1回分の接続。Relay側から正常に閉じられたら Ok
+async fn run_connection(
+    app_handle: &AppHandle,
+    config: &RelayClientConfig,
+    token: &str,
+) -> Result<(), String> {
+    let url = ws_url(config);
+    let mut request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| format!("RelayのURLが不正です: {}", e))?;
+    let protocols = HeaderValue::from_str(&format!("bearer.{}, v1", token))
+        .map_err(|e| format!("デバイストークンの形式が不正です: {}", e))?;
+    request
+        .headers_mut()
+        .insert("Sec-WebSocket-Protocol", protocols);
+
+    STATUS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(RelayStatus::default)
+        .url = Some(url);
+    set_status(app_handle, RelayState::Connecting, None);
+    let (stream, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(|e| format!("Relayへの接続に失敗しました: {}", e))?;
+    let (mut sink, mut stream) = stream.split();
+
+    let auth = serde_json::json!({
+        "v": 1,
+        "type": "pc-auth",
+        "op": "ws-auth-bearer",
+        "token": token,
+        "pcid": config.pc_id,
+        // 同じPCで会場ごとにワークスペースを切り替えても区別できるように
+        "workspaceId": crate::workspace::current_workspace_info(app_handle)
+            .map(|info| info.workspace_id),
+    });
+    sink.send(Message::Text(auth.to_string()))
+        .await
+        .map_err(|e| format!("認証メッセージの送信に失敗しました: {}", e))?;
+    set_status(app_handle, RelayState::Authenticating, None);
+
+    let mut authenticated = false;
+    let auth_deadline = tokio::time::sleep(AUTH_TIMEOUT);
+    tokio::pin!(auth_deadline);
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.tick().await;
+
+    loop {
+        tokio::select! {
+            frame = stream.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(format!("Relayとの通信が切れました: {}", e)),
+                };
+                if handle_message(app_handle, &text)? && !authenticated {
+                    authenticated = true;
+                    println!("[relay_client] connected as pcid={}", config.pc_id);
+                    set_status(app_handle, RelayState::Connected, None);
+                    // 切断中に溜まった分を先に送る
+                    flush_outbox(app_handle, &mut sink).await?;
+                }
+            }
+            _ = OUTBOX_READY.notified(), if authenticated => {
+                flush_outbox(app_handle, &mut sink).await?;
+            }
+            _ = &mut auth_deadline, if !authenticated => {
+                return Err("Relayからの認証応答がありません".to_string());
+            }
+            _ = heartbeat.tick() => {
+                let hb = serde_json::json!({ "v": 1, "type": "hb" });
+                sink.send(Message::Text(hb.to_string()))
+                    .await
+                    .map_err(|e| format!("Relayへの送信に失敗しました: {}", e))?;
+            }
+        }
+    }
+}
+
+// 連続失敗回数に応じた指数バックオフ（上限あり）
+fn next_delay(failures: u32) -> Duration {
+    let factor = 2u64.saturating_pow(failures.min(16));
+    Duration::from_secs(
+        MIN_BACKOFF_SECS
+            .saturating_mul(factor)
+            .min(MAX_BACKOFF_SECS),
+    )
+}
+
+fn stop_task() {
+    if let Ok(mut guard) = RELAY_TASK.lock() {
+        if let Some(handle) = guard.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// Relayモードが無効になったら接続を止める（機能フラグが変わったときに呼ぶ）
+pub fn stop_if_disabled(app_handle: &AppHandle) {
+    if is_running() && !crate::feature_flags::is_enabled(crate::feature_flags::RELAY_MODE) {
+        println!("[relay_client] relay mode disabled; stopping");
+        stop_task();
+        set_status(
+            app_handle,
+            RelayState::Stopped,
+            Some("Relayモードが無効になりました".to_string()),
+        );
+    }
+}
+
+/// Relayへの接続を開始（Relayモードが無効・未設定の場合は開始しない）
+#[tauri::command]
+pub async fn start_relay_client(
+    app_handle: AppHandle,
+    config: RelayClientConfig,
+) -> Result<bool, String> {
+    if config.base_url.trim().is_empty()
+        || config.event_id.trim().is_empty()
+        || config.pc_id.trim().is_empty()
+    {
+        return Ok(false);
+    }
+    if !crate::feature_flags::is_enabled(crate::feature_flags::RELAY_MODE) {
+        println!("[relay_client] relay mode disabled; not starting");
+        return Ok(false);
+    }
+    let Some(token) = crate::load_license_token()? else {
+        set_status(
+            &app_handle,
+            RelayState::TokenMissing,
+            Some("デバイストークンが登録されていません".to_string()),
+        );
+        return Ok(false);
+    };
+
+    stop_task();
+
+    let handle = tauri::async_runtime::spawn(async move {
+        let mut failures: u32 = 0;
+        loop {
+            let result = run_connection(&app_handle, &config, &token).await;
+            // 一度でも認証できていれば失敗回数を戻す
+            if current_status().state == RelayState::Connected {
+                failures = 0;
+            }
+            match result {
+                Ok(()) => println!("[relay_client] closed by relay"),
+                Err(e) => {
+                    failures = failures.saturating_add(1);
+                    eprintln!("[relay_client] {} (failures={})", e, failures);
+                    set_status(&app_handle, RelayState::Error, Some(e));
+                }
+            }
+            tokio::time::sleep(next_delay(failures)).await;
+        }
+    });
+
+    if let Ok(mut guard) = RELAY_TASK.lock() {
+        *guard = Some(handle);
+    }
+    Ok(true)
+}
+
+/// Relayへの接続を停止
+#[tauri::command]
+pub fn stop_relay_client(app_handle: AppHandle) -> Result<(), String> {
+    stop_task();
+    set_status(&app_handle, RelayState::Stopped, None);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_relay_client_status() -> Result<RelayStatus, String> {
+    Ok(current_status())
+}
+
+/// Relayへの送信を送信待ちに積む（画面側から）
+#[tauri::command]
+pub fn queue_relay_message(
+    app_handle: AppHandle,
+    payload: serde_json::Value,
+) -> Result<i64, String> {
+    if !payload.is_object() {
+        return Err("payload はJSONオブジェクトで指定してください".to_string());
+    }
+    enqueue(&app_handle, &payload)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayQueueStatus {
+    #[serde(flatten)]
+    pub outbox: RelayOutboxStats,
+    pub connected: bool,
+}
+
+/// 送信待ちの件数など（回線断の間にどれだけ溜まっているか）
+#[tauri::command]
+pub fn get_relay_queue_status(app_handle: AppHandle) -> Result<RelayQueueStatus, String> {
+    Ok(RelayQueueStatus {
+        outbox: with_db(&app_handle, |db| db.relay_outbox_stats())?,
+        connected: current_status().state == RelayState::Connected,
+    })
+}
+
+// QR画面からのRelayへの登録（デバイストークンはWebViewへ渡さず、ここで付ける）
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Relayへの登録の結果（画面側の RelayResponse と同じ形）
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayPostResult {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+}
+
+impl RelayPostResult {
+    fn failed(error: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.into()),
+            ..Self::default()
+        }
+    }
+}
+
+// Retry-After（秒またはHTTP日付）
+fn retry_after_ms(value: &str) -> Option<u64> {
+    if let Ok(secs) = value.trim().parse::<u64>() {
+        return Some(secs * 1000);
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some((at.timestamp_millis() - chrono::Utc::now().timestamp_millis()).max(0) as u64)
+}
+
+// <base_url>/e/<event_id>/<action>（イベントIDはパスの1要素としてエンコードする）
+fn event_url(base_url: &str, event_id: &str, action: &str) -> Result<reqwest::Url, String> {
+    let mut url = reqwest::Url::parse(base_url.trim_end_matches('/'))
+        .map_err(|e| format!("RelayのURLが不正です: {}", e))?;
+    url.path_segments_mut()
+        .map_err(|_| "RelayのURLが不正です".to_string())?
+        .pop_if_empty()
+        .extend(["e", event_id, action]);
+    Ok(url)
+}
+
+async fn post_with_device_token(
+    base_url: &str,
+    event_id: &str,
+    action: &str,
+    body: serde_json::Value,
+) -> RelayPostResult {
+    let url = match event_url(base_url, event_id, action) {
+        Ok(url) => url,
+        Err(e) => return RelayPostResult::failed(e),
+    };
+    let token = match crate::load_license_token() {
+        Ok(Some(token)) => token,
+        Ok(None) => return RelayPostResult::failed("E_MISSING_TOKEN"),
+        Err(e) => return RelayPostResult::failed(e),
+    };
+    let response = match reqwest::Client::new()
+        .post(url)
+        .timeout(REGISTER_TIMEOUT)
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return RelayPostResult::failed(e.to_string()),
+    };
+    let status = response.status();
+    if status.as_u16() == 429 || status.as_u16() == 503 {
+        return RelayPostResult {
+            status: Some(status.as_u16()),
+            retry_after_ms: response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(retry_after_ms),
+            ..RelayPostResult::default()
+        };
+    }
+    let data = response.json::<serde_json::Value>().await.ok();
+    if !status.is_success() {
+        return RelayPostResult {
+            status: Some(status.as_u16()),
+            code: data
+                .as_ref()
+                .and_then(|v| v.get("code"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+            ..RelayPostResult::default()
+        };
+    }
+    RelayPostResult {
+        ok: true,
+        data,
+        ..RelayPostResult::default()
+    }
+}
+
+/// PCをイベントに登録する（QR画面から）
+#[tauri::command]
+pub async fn relay_register_pc(
+    base_url: String,
+    event_id: String,
+    pcid: String,
+) -> Result<RelayPostResult, String> {
+    let body = serde_json::json!({ "pcid": pcid });
+    Ok(post_with_device_token(&base_url, &event_id, "register-pc", body).await)
+}
+
+/// QRに載せるセッションIDを事前登録する（QR画面から。ttl は30〜120秒）
+#[tauri::command]
+pub async fn relay_pending_sid(
+    base_url: String,
+    event_id: String,
+    pcid: String,
+    sid: String,
+    ttl: u64,
+) -> Result<RelayPostResult, String> {
+    let body = serde_json::json!({ "pcid": pcid, "sid": sid, "ttl": ttl.clamp(30, 120) });
+    Ok(post_with_device_token(&base_url, &event_id, "pending-sid", body).await)
+}
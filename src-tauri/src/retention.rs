@@ -0,0 +1,246 @@
+// 古くなった作品を設定に従って自動的に非表示・削除する
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{Database, ImageMetadata};
+use crate::events::{emit_data_change, DataChangeEvent, ImageDeletedPayload};
+use crate::workspace::WorkspaceState;
+
+// 保持ルールを保存する app_settings のキー
+const RETENTION_SETTINGS_KEY: &str = "retention_settings";
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// 保持期間の上限（10年。これより長い期間は日時の計算が溢れうるため受け付けない）
+const MAX_HIDE_AFTER_HOURS: u64 = 10 * 365 * 24;
+const MAX_DELETE_AFTER_DAYS: u64 = 10 * 365;
+
+/// 保持ルール（未設定のルールは適用しない）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionSettings {
+    // 取り込みからこの時間が経った作品を非表示にする
+    #[serde(default)]
+    pub hide_after_hours: Option<u64>,
+    // 取り込みからこの日数が経った元画像を削除する
+    #[serde(default)]
+    pub delete_originals_after_days: Option<u64>,
+    // 作品（処理済み画像）の上限。超えた分は古い順に削除する
+    #[serde(default)]
+    pub max_processed_images: Option<u64>,
+}
+
+impl RetentionSettings {
+    fn validate(&self) -> Result<(), String> {
+        let values = [
+            self.hide_after_hours,
+            self.delete_originals_after_days,
+            self.max_processed_images,
+        ];
+        if values.contains(&Some(0)) {
+            return Err("保持ルールの値は1以上で指定してください".to_string());
+        }
+        if self
+            .hide_after_hours
+            .is_some_and(|hours| hours > MAX_HIDE_AFTER_HOURS)
+        {
+            return Err(format!(
+                "非表示にするまでの時間は{}時間以内で指定してください",
+                MAX_HIDE_AFTER_HOURS
+            ));
+        }
+        if self
+            .delete_originals_after_days
+            .is_some_and(|days| days > MAX_DELETE_AFTER_DAYS)
+        {
+            return Err(format!(
+                "元画像を削除するまでの日数は{}日以内で指定してください",
+                MAX_DELETE_AFTER_DAYS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 適用した結果（"retention-applied"）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub hidden: usize,
+    pub originals_deleted: usize,
+    pub processed_deleted: usize,
+    pub bytes_freed: u64,
+}
+
+impl RetentionReport {
+    fn is_empty(&self) -> bool {
+        self.hidden == 0 && self.originals_deleted == 0 && self.processed_deleted == 0
+    }
+}
+
+fn load_settings(db: &Database) -> RetentionSettings {
+    db.get_app_setting(RETENTION_SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str::<RetentionSettings>(&value).ok())
+        .filter(|settings| settings.validate().is_ok())
+        .unwrap_or_default()
+}
+
+fn created_before(image: &ImageMetadata, cutoff: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(&image.created_at)
+        .map(|created| created.with_timezone(&Utc) < cutoff)
+        .unwrap_or(false)
+}
+
+// ファイルと行を削除（ファイルが既に無い場合も行は消す）
+fn delete_with_file(app_handle: &AppHandle, db: &Database, image: &ImageMetadata) -> Option<u64> {
+    let path = image.resolve_file_path();
+    let size = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            eprintln!("[retention] failed to remove {}: {}", path.display(), e);
+            return None;
+        }
+    }
+    if let Err(e) = crate::delete_image_and_notify(app_handle, db, &image.id) {
+        eprintln!("[retention] failed to delete image {}: {}", image.id, e);
+        return None;
+    }
+    Some(size)
+}
+
+fn apply(app_handle: &AppHandle, db: &Database, settings: &RetentionSettings) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    let images = match db.get_all_images() {
+        Ok(images) => images,
+        Err(e) => {
+            eprintln!("[retention] failed to load images: {}", e);
+            return report;
+        }
+    };
+    let now = Utc::now() + ChronoDuration::milliseconds(db.clock_offset_ms().unwrap_or(0));
+
+    // 上限は validate で確認済みだが、時計のずれなどで溢れた場合はそのルールを飛ばす
+    let cutoff_before = |duration: Option<ChronoDuration>| {
+        duration.and_then(|duration| now.checked_sub_signed(duration))
+    };
+
+    if let Some(cutoff) = cutoff_before(
+        settings
+            .hide_after_hours
+            .map(|hours| ChronoDuration::hours(hours as i64)),
+    ) {
+        for image in images.iter().filter(|image| {
+            image.image_type == "processed" && image.is_hidden == 0 && created_before(image, cutoff)
+        }) {
+            if let Err(e) = db.set_image_hidden(&image.id, true) {
+                eprintln!("[retention] failed to hide {}: {}", image.id, e);
+                continue;
+            }
+            // 表示中の画面からは削除と同じ扱いで取り除く
+            let _ = emit_data_change(
+                app_handle,
+                DataChangeEvent::ImageDeleted(ImageDeletedPayload {
+                    id: image.id.clone(),
+                }),
+            );
+            report.hidden += 1;
+        }
+    }
+
+    if let Some(cutoff) = cutoff_before(
+        settings
+            .delete_originals_after_days
+            .map(|days| ChronoDuration::days(days as i64)),
+    ) {
+        for image in images
+            .iter()
+            .filter(|image| image.image_type == "original" && created_before(image, cutoff))
+        {
+            if let Some(size) = delete_with_file(app_handle, db, image) {
+                report.originals_deleted += 1;
+                report.bytes_freed += size;
+            }
+        }
+    }
+
+    if let Some(max) = settings.max_processed_images {
+        // get_all_images は新しい順なので、上限より後ろが古い作品
+        for image in images
+            .iter()
+            .filter(|image| image.image_type == "processed")
+            .skip(max as usize)
+        {
+            if let Some(size) = delete_with_file(app_handle, db, image) {
+                report.processed_deleted += 1;
+                report.bytes_freed += size;
+            }
+        }
+    }
+
+    report
+}
+
+/// 現在の保持ルールを適用して結果を返す
+pub fn run(app_handle: &AppHandle) -> RetentionReport {
+    let Some(state) = app_handle.try_state::<WorkspaceState>() else {
+        return RetentionReport::default();
+    };
+    let Ok(conn) = state.lock() else {
+        return RetentionReport::default();
+    };
+    let Ok(db) = conn.get() else {
+        return RetentionReport::default();
+    };
+    let settings = load_settings(db);
+    apply(app_handle, db, &settings)
+}
+
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        let report = run(&app_handle);
+        if report.is_empty() {
+            continue;
+        }
+        println!("[retention] applied {:?}", report);
+        let _ = crate::events::emit_routed(&app_handle, "retention-applied", report);
+    });
+}
+
+/// 定期実行を待たずに保持ルールを適用する
+#[tauri::command]
+pub fn apply_retention_now(app_handle: AppHandle) -> Result<RetentionReport, String> {
+    let report = run(&app_handle);
+    let _ = crate::events::emit_routed(&app_handle, "retention-applied", report.clone());
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_retention_settings(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<RetentionSettings, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    Ok(load_settings(db))
+}
+
+#[tauri::command]
+pub fn set_retention_settings(
+    workspace: State<'_, WorkspaceState>,
+    settings: RetentionSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("保持ルールのシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(RETENTION_SETTINGS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
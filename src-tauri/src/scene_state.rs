@@ -0,0 +1,82 @@
+// アニメーション画面の状態（表示中のキャラクターと位置）をDBに残し、再起動後に復元する
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::workspace::WorkspaceState;
+
+// この件数ごとに古い行を削除する
+const COMPACT_EVERY: i64 = 50;
+// 最新行が壊れていた場合に備えて残す件数
+const KEEP_ROWS: i64 = 3;
+// 1回に保存できる大きさ
+const MAX_STATE_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneStateSnapshot {
+    pub state: serde_json::Value,
+    pub saved_at: String,
+}
+
+// 複製表示のウィンドウへ送る内容（送り元は自分の送った状態を無視する）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneStateUpdatedPayload {
+    pub source: String,
+    pub state: serde_json::Value,
+}
+
+#[tauri::command]
+pub async fn persist_scene_state(
+    window: tauri::Window,
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    json: String,
+) -> Result<(), String> {
+    if json.len() > MAX_STATE_BYTES {
+        return Err("シーン状態が大きすぎます".to_string());
+    }
+    let state = serde_json::from_str::<serde_json::Value>(&json)
+        .map_err(|e| format!("シーン状態のJSONが不正です: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let id = db
+        .append_scene_state(&json)
+        .map_err(|e| format!("Failed to persist scene state: {}", e))?;
+    if id % COMPACT_EVERY == 0 {
+        db.compact_scene_state(KEEP_ROWS)
+            .map_err(|e| format!("Failed to compact scene state: {}", e))?;
+    }
+    drop(conn);
+
+    let _ = crate::events::emit_routed(
+        &app_handle,
+        "scene-state-updated",
+        SceneStateUpdatedPayload {
+            source: window.label().to_string(),
+            state,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn load_scene_state(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<Option<SceneStateSnapshot>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let latest = db
+        .latest_scene_state()
+        .map_err(|e| format!("Failed to load scene state: {}", e))?;
+    Ok(latest.and_then(|(state, saved_at)| {
+        serde_json::from_str(&state)
+            .ok()
+            .map(|state| SceneStateSnapshot { state, saved_at })
+    }))
+}
@@ -0,0 +1,257 @@
+// 秘密情報の保存先（通常はOSのキーチェーン）
+// キーチェーンが使えない環境（ロックダウンされたWindowsやLinuxのキオスク等）では
+// 端末固有のIDから導出した鍵で暗号化したファイル（app_data_dir/secrets.enc）に保存する
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use keyring::Entry;
+use once_cell::sync::{Lazy, OnceCell};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const FALLBACK_FILE_NAME: &str = "secrets.enc";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    Keychain,
+    EncryptedFile,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FallbackFile {
+    version: u32,
+    // base64
+    salt: String,
+    // base64（nonce + 暗号文）
+    data: String,
+}
+
+static FALLBACK_PATH: OnceCell<PathBuf> = OnceCell::new();
+// 最後に使った保存先（キーチェーンが一度でも失敗したら暗号化ファイル）
+static ACTIVE_BACKEND: Lazy<Mutex<SecretBackend>> =
+    Lazy::new(|| Mutex::new(SecretBackend::Keychain));
+// ファイルの読み書きを直列化する
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 代替ファイルの置き場所を設定する（起動時に一度だけ）
+pub fn init(app_data_dir: PathBuf) {
+    let _ = FALLBACK_PATH.set(app_data_dir.join(FALLBACK_FILE_NAME));
+}
+
+/// 現在使っている保存先
+pub fn active_backend() -> SecretBackend {
+    *ACTIVE_BACKEND.lock().unwrap()
+}
+
+fn set_backend(backend: SecretBackend) {
+    *ACTIVE_BACKEND.lock().unwrap() = backend;
+}
+
+// キーチェーン自体が使えない（値が無いなどではない）エラーか
+fn keychain_unavailable(err: &keyring::Error) -> bool {
+    matches!(
+        err,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+fn open_entry(service: &str, account: &str) -> Option<Entry> {
+    match Entry::new(service, account) {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            eprintln!(
+                "[secret_store] keychain unavailable, using encrypted file: {}",
+                e
+            );
+            None
+        }
+    }
+}
+
+pub fn save(service: &str, account: &str, value: &str) -> Result<(), String> {
+    if let Some(entry) = open_entry(service, account) {
+        match entry.set_password(value) {
+            Ok(()) => {
+                set_backend(SecretBackend::Keychain);
+                return Ok(());
+            }
+            Err(e) if keychain_unavailable(&e) => {
+                eprintln!(
+                    "[secret_store] keychain write failed, using encrypted file: {}",
+                    e
+                );
+            }
+            Err(e) => return Err(format!("KEYCHAIN_WRITE_ERROR: {}", e)),
+        }
+    }
+    set_backend(SecretBackend::EncryptedFile);
+    update_fallback(|secrets| {
+        secrets.insert(fallback_key(service, account), value.to_string());
+    })
+}
+
+pub fn load(service: &str, account: &str) -> Result<Option<String>, String> {
+    if let Some(entry) = open_entry(service, account) {
+        match entry.get_password() {
+            Ok(value) => {
+                set_backend(SecretBackend::Keychain);
+                return Ok(Some(value));
+            }
+            // キーチェーンが使えなかった頃にファイルへ保存した値があれば使う
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) if keychain_unavailable(&e) => set_backend(SecretBackend::EncryptedFile),
+            Err(e) => return Err(format!("KEYCHAIN_READ_ERROR: {}", e)),
+        }
+    } else {
+        set_backend(SecretBackend::EncryptedFile);
+    }
+    let _guard = FILE_LOCK.lock().unwrap();
+    Ok(read_fallback()?.remove(&fallback_key(service, account)))
+}
+
+pub fn delete(service: &str, account: &str) -> Result<(), String> {
+    if let Some(entry) = open_entry(service, account) {
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) if keychain_unavailable(&e) => {}
+            Err(e) => return Err(format!("KEYCHAIN_DELETE_ERROR: {}", e)),
+        }
+    }
+    update_fallback(|secrets| {
+        secrets.remove(&fallback_key(service, account));
+    })
+}
+
+fn fallback_key(service: &str, account: &str) -> String {
+    format!("{}/{}", service, account)
+}
+
+// 端末固有のID（取れなければホスト名）
+fn machine_id() -> String {
+    #[cfg(target_os = "linux")]
+    let id = ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok());
+    #[cfg(target_os = "windows")]
+    let id = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .ok()
+        .and_then(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .find(|line| line.contains("MachineGuid"))
+                .and_then(|line| line.split_whitespace().last().map(str::to_string))
+        });
+    #[cfg(target_os = "macos")]
+    let id = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()
+        .and_then(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .find(|line| line.contains("IOPlatformUUID"))
+                .and_then(|line| line.split('"').nth(3).map(str::to_string))
+        });
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    let id: Option<String> = None;
+
+    id.map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(crate::workspace::host_name)
+}
+
+fn derive_cipher(salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(machine_id().as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+fn fallback_path() -> Result<&'static PathBuf, String> {
+    FALLBACK_PATH
+        .get()
+        .ok_or_else(|| "秘密情報の保存先が初期化されていません".to_string())
+}
+
+fn read_fallback() -> Result<HashMap<String, String>, String> {
+    let path = fallback_path()?;
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("SECRET_FILE_READ_ERROR: {}", e)),
+    };
+    let file: FallbackFile =
+        serde_json::from_str(&text).map_err(|e| format!("SECRET_FILE_READ_ERROR: {}", e))?;
+    let salt = STANDARD
+        .decode(file.salt.trim())
+        .map_err(|e| format!("SECRET_FILE_READ_ERROR: {}", e))?;
+    let data = STANDARD
+        .decode(file.data.trim())
+        .map_err(|e| format!("SECRET_FILE_READ_ERROR: {}", e))?;
+    if data.len() <= NONCE_LEN {
+        return Err("SECRET_FILE_READ_ERROR: ファイルが壊れています".to_string());
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    // 別の端末にコピーされた場合などは復号できない
+    let plain = derive_cipher(&salt)
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| "SECRET_FILE_READ_ERROR: 復号に失敗しました".to_string())?;
+    serde_json::from_slice(&plain).map_err(|e| format!("SECRET_FILE_READ_ERROR: {}", e))
+}
+
+fn write_fallback(secrets: &HashMap<String, String>) -> Result<(), String> {
+    let path = fallback_path()?;
+    if secrets.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("SECRET_FILE_WRITE_ERROR: {}", e))
+            }
+            _ => Ok(()),
+        };
+    }
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let plain = serde_json::to_vec(secrets).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    let sealed = derive_cipher(&salt)
+        .encrypt(Nonce::from_slice(&nonce), plain.as_slice())
+        .map_err(|_| "SECRET_FILE_WRITE_ERROR: 暗号化に失敗しました".to_string())?;
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&sealed);
+    let file = FallbackFile {
+        version: 1,
+        salt: STANDARD.encode(salt),
+        data: STANDARD.encode(data),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("SECRET_FILE_WRITE_ERROR: {}", e))?;
+    }
+    let body = serde_json::to_string(&file).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    // 書き込み途中で落ちても壊れないよう一時ファイルから置き換える
+    let tmp = path.with_extension("enc.tmp");
+    fs::write(&tmp, body).map_err(|e| format!("SECRET_FILE_WRITE_ERROR: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("SECRET_FILE_WRITE_ERROR: {}", e))
+}
+
+fn update_fallback(f: impl FnOnce(&mut HashMap<String, String>)) -> Result<(), String> {
+    let _guard = FILE_LOCK.lock().unwrap();
+    let mut secrets = read_fallback()?;
+    f(&mut secrets);
+    write_fallback(&secrets)
+}
@@ -1,19 +1,95 @@
 use crate::qr_manager::QrManager;
+use crate::web_auth::{WebAuth, WebAuthMode};
+use actix_web::dev::ServerHandle;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+
+// スマホへ送るメッセージの送信口（WebSocket接続ごと）
+pub type MobileSender = UnboundedSender<String>;
+
+// WebSocketの稼働状況（websocket.rs が更新する）
+pub struct WsMetrics {
+    pub total_connections: AtomicU64,
+    pub total_joins: AtomicU64,
+    pub messages_received: AtomicU64,
+    // メッセージ数制限・不正な形式で捨てたもの、送信できなかったもの
+    pub dropped_frames: AtomicU64,
+    // 直近の受信数の計測（計測時刻, その時点の受信数, 毎秒の受信数）
+    rate: Mutex<(Instant, u64, f64)>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WsMetricsSnapshot {
+    pub active_connections: usize,
+    pub active_sessions: usize,
+    pub total_connections: u64,
+    pub total_joins: u64,
+    pub messages_received: u64,
+    pub messages_per_second: f64,
+    pub dropped_frames: u64,
+}
+
+impl WsMetrics {
+    fn new() -> Self {
+        Self {
+            total_connections: AtomicU64::new(0),
+            total_joins: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            dropped_frames: AtomicU64::new(0),
+            rate: Mutex::new((Instant::now(), 0, 0.0)),
+        }
+    }
+
+    pub fn add(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // 前回の計測からの受信数で毎秒の受信数を更新する（定期通知から呼ぶ）
+    pub fn sample_rate(&self) -> f64 {
+        let received = self.messages_received.load(Ordering::Relaxed);
+        let mut rate = self.rate.lock().unwrap();
+        let elapsed = rate.0.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            rate.2 = received.saturating_sub(rate.1) as f64 / elapsed;
+        }
+        *rate = (Instant::now(), received, rate.2);
+        rate.2
+    }
+
+    pub fn last_rate(&self) -> f64 {
+        self.rate.lock().unwrap().2
+    }
+}
 
 // Webサーバーとスマホ連携関連の状態を管理
 pub struct ServerState {
     pub web_server_port: Arc<Mutex<Option<u16>>>,
+    pub https_port: Arc<Mutex<Option<u16>>>,
+    pub server_handle: Arc<Mutex<Option<ServerHandle>>>,
     pub qr_manager: Arc<Mutex<Option<Arc<QrManager>>>>,
     pub is_starting: Arc<Mutex<bool>>,
+    pub web_auth: Arc<WebAuth>,
+    // sessionId → (接続ID, 送信口)。同じセッションの再接続は新しい接続で上書きする
+    pub mobile_sessions: Arc<Mutex<HashMap<String, (u64, MobileSender)>>>,
+    pub ws_metrics: Arc<WsMetrics>,
 }
 
 impl ServerState {
     pub fn new() -> Self {
         Self {
             web_server_port: Arc::new(Mutex::new(None)),
+            https_port: Arc::new(Mutex::new(None)),
+            server_handle: Arc::new(Mutex::new(None)),
             qr_manager: Arc::new(Mutex::new(None)),
             is_starting: Arc::new(Mutex::new(false)),
+            web_auth: Arc::new(WebAuth::new(WebAuthMode::Locked)),
+            mobile_sessions: Arc::new(Mutex::new(HashMap::new())),
+            ws_metrics: Arc::new(WsMetrics::new()),
         }
     }
 
@@ -25,6 +101,26 @@ impl ServerState {
         *self.web_server_port.lock().unwrap()
     }
 
+    pub fn set_https_port(&self, port: Option<u16>) {
+        *self.https_port.lock().unwrap() = port;
+    }
+
+    pub fn get_https_port(&self) -> Option<u16> {
+        *self.https_port.lock().unwrap()
+    }
+
+    pub fn set_server_handle(&self, handle: ServerHandle) {
+        *self.server_handle.lock().unwrap() = Some(handle);
+    }
+
+    // 停止のためにハンドルを取り出し、ポート/QR情報もクリアする
+    pub fn take_running_server(&self) -> Option<ServerHandle> {
+        *self.web_server_port.lock().unwrap() = None;
+        *self.https_port.lock().unwrap() = None;
+        *self.qr_manager.lock().unwrap() = None;
+        self.server_handle.lock().unwrap().take()
+    }
+
     pub fn set_qr_manager(&self, manager: Arc<QrManager>) {
         *self.qr_manager.lock().unwrap() = Some(manager);
     }
@@ -46,4 +142,58 @@ impl ServerState {
     pub fn finish_starting(&self) {
         *self.is_starting.lock().unwrap() = false;
     }
+
+    pub fn register_mobile_session(&self, session_id: &str, conn_id: u64, sender: MobileSender) {
+        self.mobile_sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), (conn_id, sender));
+    }
+
+    // 切断した接続に紐付くセッションを削除し、そのsessionIdを返す
+    pub fn unregister_mobile_connection(&self, conn_id: u64) -> Vec<String> {
+        let mut sessions = self.mobile_sessions.lock().unwrap();
+        let removed: Vec<String> = sessions
+            .iter()
+            .filter(|(_, (id, _))| *id == conn_id)
+            .map(|(session_id, _)| session_id.clone())
+            .collect();
+        for session_id in &removed {
+            sessions.remove(session_id);
+        }
+        removed
+    }
+
+    // 受信側が終了している（切断処理が漏れた）セッションを削除し、削除した数を返す
+    pub fn reap_closed_mobile_sessions(&self) -> usize {
+        let mut sessions = self.mobile_sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, (_, sender)| !sender.is_closed());
+        before - sessions.len()
+    }
+
+    /// 指定セッションのスマホへテキストを送る
+    pub fn send_to_mobile(&self, session_id: &str, text: String) -> Result<(), String> {
+        let sessions = self.mobile_sessions.lock().unwrap();
+        let (_, sender) = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("スマホが接続されていません: {}", session_id))?;
+        sender
+            .send(text)
+            .map_err(|_| format!("スマホへの送信に失敗しました: {}", session_id))
+    }
+
+    /// WebSocketの稼働状況（接続数は websocket.rs から渡す）
+    pub fn ws_metrics_snapshot(&self, active_connections: usize) -> WsMetricsSnapshot {
+        let metrics = &self.ws_metrics;
+        WsMetricsSnapshot {
+            active_connections,
+            active_sessions: self.mobile_sessions.lock().unwrap().len(),
+            total_connections: metrics.total_connections.load(Ordering::Relaxed),
+            total_joins: metrics.total_joins.load(Ordering::Relaxed),
+            messages_received: metrics.messages_received.load(Ordering::Relaxed),
+            messages_per_second: metrics.last_rate(),
+            dropped_frames: metrics.dropped_frames.load(Ordering::Relaxed),
+        }
+    }
 }
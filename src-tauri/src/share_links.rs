@@ -0,0 +1,214 @@
+// 作品のダウンロード用共有リンク（イベント後に保護者が自分の子の作品を受け取るためのもの）
+// トークンは "<リンクID>.<署名>"。期限と取り消しはワークスペースDBで管理する
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::ShareLink;
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+// 署名の用途（QRセッションのトークンと区別する）
+const TOKEN_SCOPE: &str = "share";
+const DEFAULT_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+pub(crate) const MAX_TTL_SECS: i64 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedShareLink {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub token: String,
+    // Webサーバーが起動していなければ None
+    pub url: Option<String>,
+}
+
+/// 共有リンクが使えない理由
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareLinkError {
+    Invalid,
+    Expired,
+    Revoked,
+}
+
+fn is_expired(link: &ShareLink, now: DateTime<Utc>) -> bool {
+    // 期限が読めないリンクは使えないものとして扱う
+    DateTime::parse_from_rfc3339(&link.expires_at)
+        .map(|expires_at| expires_at.with_timezone(&Utc) <= now)
+        .unwrap_or(true)
+}
+
+/// 取り消されておらず期限内のリンクか
+pub(crate) fn is_active(link: &ShareLink) -> bool {
+    link.revoked_at.is_none() && !is_expired(link, Utc::now())
+}
+
+/// 保存済みのリンクにトークンとURLを付ける（署名はリンクIDから決まるので再発行しても同じ）
+pub(crate) fn with_token(app_handle: &AppHandle, link: ShareLink) -> CreatedShareLink {
+    let server_state = app_handle.state::<ServerState>();
+    let token = server_state
+        .web_auth
+        .issue_scoped_token(TOKEN_SCOPE, &link.id);
+    let url = server_state
+        .get_qr_manager()
+        .map(|qr_manager| qr_manager.share_url(&token));
+    CreatedShareLink { link, token, url }
+}
+
+/// トークンを検証し、使えるリンクならダウンロード回数を数えて返す（/share/{token} から呼ぶ）
+pub fn redeem(app_handle: &AppHandle, token: &str) -> Result<ShareLink, ShareLinkError> {
+    let id = app_handle
+        .state::<ServerState>()
+        .web_auth
+        .verify_scoped_token(TOKEN_SCOPE, token)
+        .ok_or(ShareLinkError::Invalid)?;
+    let workspace = app_handle.state::<WorkspaceState>();
+    let conn = workspace.lock().map_err(|_| ShareLinkError::Invalid)?;
+    let db = conn.get().map_err(|_| ShareLinkError::Invalid)?;
+    let link = db
+        .get_share_link(&id)
+        .ok()
+        .flatten()
+        .ok_or(ShareLinkError::Invalid)?;
+    if link.revoked_at.is_some() {
+        return Err(ShareLinkError::Revoked);
+    }
+    if is_expired(&link, Utc::now()) {
+        return Err(ShareLinkError::Expired);
+    }
+    if let Err(e) = db.increment_share_link_downloads(&link.id) {
+        eprintln!("[share_links] failed to count download: {}", e);
+    }
+    // 持ち帰りQRから発行したリンクなら受け取り済みとして記録（イベントの集計用）
+    if let Err(e) = db.record_takeaway_redemption(&link.id) {
+        eprintln!("[share_links] failed to record takeaway: {}", e);
+    }
+    Ok(link)
+}
+
+// Relay経由で会場外からも受け取れるようにする（送信待ちに積み、接続中に順番に送る）
+fn upload_to_relay(app_handle: &AppHandle, link: &ShareLink, token: &str, file: &Path) {
+    let bytes = match std::fs::read(file) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("[share_links] failed to read image for relay: {}", e);
+            return;
+        }
+    };
+    let payload = serde_json::json!({
+        "type": "share-link",
+        "token": token,
+        "imageId": link.image_id,
+        "expiresAt": link.expires_at,
+        "contentType": mime_guess::from_path(file).first_or_octet_stream().to_string(),
+        "data": base64::engine::general_purpose::STANDARD.encode(bytes),
+    });
+    if let Err(e) = crate::relay_client::enqueue(app_handle, &payload) {
+        eprintln!("[share_links] failed to queue relay upload: {}", e);
+    }
+}
+
+/// 共有リンクを発行し、リンクと作品ファイルのパスを返す（持ち帰りQRからも使う）
+pub(crate) fn issue(
+    app_handle: &AppHandle,
+    image_id: &str,
+    ttl_secs: i64,
+) -> Result<(CreatedShareLink, PathBuf), String> {
+    if !(60..=MAX_TTL_SECS).contains(&ttl_secs) {
+        return Err(format!(
+            "有効期限は60〜{}秒で指定してください",
+            MAX_TTL_SECS
+        ));
+    }
+    let now = Utc::now();
+    let link = ShareLink {
+        id: crate::db::generate_id(),
+        image_id: image_id.to_string(),
+        created_at: now.to_rfc3339(),
+        expires_at: (now + chrono::Duration::seconds(ttl_secs)).to_rfc3339(),
+        revoked_at: None,
+        downloads: 0,
+    };
+    let file = {
+        let workspace = app_handle.state::<WorkspaceState>();
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        let image = db
+            .get_image(image_id)
+            .map_err(|e| format!("Failed to get image: {}", e))?
+            .ok_or_else(|| format!("画像が見つかりません: {}", image_id))?;
+        if image.image_type != "processed" {
+            return Err("共有できるのは処理済みの画像だけです".to_string());
+        }
+        db.insert_share_link(&link)
+            .map_err(|e| format!("Failed to save share link: {}", e))?;
+        image.resolve_file_path()
+    };
+
+    println!(
+        "[share_links] created {} for {} (expires {})",
+        link.id, link.image_id, link.expires_at
+    );
+    Ok((with_token(app_handle, link), file))
+}
+
+/// 共有リンクを発行する（ttl_secs 省略時は7日。upload なら Relay にも送る）
+#[tauri::command]
+pub fn create_share_link(
+    app_handle: AppHandle,
+    image_id: String,
+    ttl_secs: Option<i64>,
+    upload: Option<bool>,
+) -> Result<CreatedShareLink, String> {
+    let upload = upload.unwrap_or(false);
+    // Relayへの接続を始めていなければ送信待ちが溜まるだけなので、発行前に断る
+    if upload && !crate::relay_client::is_running() {
+        return Err("Relayに接続していないため会場外向けのリンクは発行できません".to_string());
+    }
+    let (created, file) = issue(&app_handle, &image_id, ttl_secs.unwrap_or(DEFAULT_TTL_SECS))?;
+    if upload {
+        upload_to_relay(&app_handle, &created.link, &created.token, &file);
+    }
+    Ok(created)
+}
+
+/// 共有リンクを取り消す（リンクIDかトークンを指定。Relayに送った分は期限で消える）
+#[tauri::command]
+pub fn revoke_share_link(workspace: State<'_, WorkspaceState>, id: String) -> Result<bool, String> {
+    // トークンが渡された場合は先頭のリンクIDだけを使う
+    let id = id
+        .split_once('.')
+        .map(|(id, _)| id)
+        .unwrap_or(&id)
+        .to_string();
+    let revoked = {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        db.revoke_share_link(&id)
+            .map_err(|e| format!("Failed to revoke share link: {}", e))?
+    };
+    if revoked {
+        println!("[share_links] revoked {}", id);
+    }
+    Ok(revoked)
+}
+
+/// 発行済みの共有リンク（画像ID指定ならその作品の分だけ）
+#[tauri::command]
+pub fn list_share_links(
+    workspace: State<'_, WorkspaceState>,
+    image_id: Option<String>,
+) -> Result<Vec<ShareLink>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.list_share_links(image_id.as_deref())
+        .map_err(|e| format!("Failed to list share links: {}", e))
+}
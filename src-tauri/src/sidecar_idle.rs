@@ -0,0 +1,114 @@
+use crate::workspace::{read_global_setting, write_global_setting};
+use once_cell::sync::{Lazy, OnceCell};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+// 無処理のままこの分数が経過したらサイドカーを停止する（0で無効）
+const IDLE_MINUTES_KEY: &str = "sidecar_idle_minutes";
+const DEFAULT_IDLE_MINUTES: u64 = 30;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static APP_HANDLE: OnceCell<AppHandle> = OnceCell::new();
+static LAST_ACTIVITY: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+// 起動中（readyになるまで）の開始時刻
+static COLD_START: Mutex<Option<Instant>> = Mutex::new(None);
+// 前回の起動にかかった時間（UIの目安表示用、未計測なら0）
+static LAST_COLD_START_MS: AtomicU64 = AtomicU64::new(0);
+
+fn emit_status(payload: serde_json::Value) {
+    if let Some(app_handle) = APP_HANDLE.get() {
+        let _ = crate::events::emit_routed(app_handle, "sidecar-status", payload);
+    }
+}
+
+fn idle_minutes(app_handle: &AppHandle) -> u64 {
+    read_global_setting(app_handle, IDLE_MINUTES_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_IDLE_MINUTES)
+}
+
+/// ジョブの送受信があったことを記録
+pub fn touch() {
+    if let Ok(mut guard) = LAST_ACTIVITY.lock() {
+        *guard = Instant::now();
+    }
+}
+
+/// サイドカーを新たに起動した（モデル読み込みが終わるまで処理が遅れる）
+pub fn cold_start_began() {
+    touch();
+    if let Ok(mut guard) = COLD_START.lock() {
+        *guard = Some(Instant::now());
+    }
+    let expected = LAST_COLD_START_MS.load(Ordering::SeqCst);
+    emit_status(serde_json::json!({
+        "state": "starting",
+        "expectedDelayMs": if expected > 0 { Some(expected) } else { None },
+    }));
+}
+
+/// 起動中（モデル読み込みが終わっていない）か
+pub fn is_starting() -> bool {
+    COLD_START
+        .lock()
+        .map(|guard| guard.is_some())
+        .unwrap_or(false)
+}
+
+/// 起動後に最初の応答を受け取った
+pub fn mark_ready() {
+    let started = COLD_START.lock().ok().and_then(|mut guard| guard.take());
+    let Some(started) = started else {
+        return;
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    LAST_COLD_START_MS.store(elapsed_ms, Ordering::SeqCst);
+    emit_status(serde_json::json!({
+        "state": "ready",
+        "coldStartMs": elapsed_ms,
+    }));
+}
+
+/// アイドル監視を開始（アプリ起動時に一度だけ呼ぶ）
+pub fn start(app_handle: AppHandle) {
+    if APP_HANDLE.set(app_handle.clone()).is_err() {
+        return;
+    }
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        let minutes = idle_minutes(&app_handle);
+        if minutes == 0 {
+            continue;
+        }
+        let idle_for = LAST_ACTIVITY
+            .lock()
+            .map(|guard| guard.elapsed())
+            .unwrap_or_default();
+        if idle_for < Duration::from_secs(minutes * 60) {
+            continue;
+        }
+        if crate::shutdown_python_process_if_idle() {
+            println!(
+                "[sidecar] {}分間処理がないため停止しました（次の処理で再起動します）",
+                minutes
+            );
+            emit_status(serde_json::json!({ "state": "stopped", "reason": "idle" }));
+        }
+    });
+}
+
+/// アイドル停止までの分数を設定（0で常駐）
+#[tauri::command]
+pub async fn set_sidecar_idle_minutes(app_handle: AppHandle, minutes: u64) -> Result<(), String> {
+    write_global_setting(&app_handle, IDLE_MINUTES_KEY, &minutes.to_string())
+}
+
+#[tauri::command]
+pub async fn get_sidecar_idle_minutes(app_handle: AppHandle) -> Result<u64, String> {
+    Ok(idle_minutes(&app_handle))
+}
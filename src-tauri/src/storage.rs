@@ -0,0 +1,206 @@
+// ワークスペースの使用量とディスクの空き容量を監視する
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::reaper::dir_size;
+use crate::workspace::WorkspaceState;
+
+// 監視のしきい値を保存する app_settings のキー
+const STORAGE_SETTINGS_KEY: &str = "storage_settings";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageSettings {
+    // ワークスペースのあるドライブの空き容量がこれを下回ったら警告
+    pub min_free_bytes: u64,
+    // ワークスペース全体の上限（未設定なら見ない）
+    #[serde(default)]
+    pub max_workspace_bytes: Option<u64>,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            min_free_bytes: 2 * GIB,
+            max_workspace_bytes: None,
+        }
+    }
+}
+
+impl StorageSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.min_free_bytes == 0 {
+            return Err("空き容量のしきい値は0より大きくしてください".to_string());
+        }
+        if self.max_workspace_bytes == Some(0) {
+            return Err("ワークスペースの上限は0より大きくしてください".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// ワークスペースの使用量（バイト）
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub workspace_path: String,
+    pub originals: u64,
+    pub processed: u64,
+    pub backgrounds: u64,
+    pub audio: u64,
+    pub thumbnails: u64,
+    // 上記以外（ログ、DB、取り込み待ちなど）
+    pub other: u64,
+    pub total: u64,
+    // ドライブの空き容量（取得できない場合はNone）
+    pub free_bytes: Option<u64>,
+}
+
+/// 容量不足の警告（"storage-warning"）
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageWarning {
+    // "lowDiskSpace" / "workspaceQuota"
+    pub reason: String,
+    pub free_bytes: Option<u64>,
+    pub workspace_bytes: u64,
+    pub threshold: u64,
+}
+
+// 同じ警告を繰り返し通知しないよう、直近に通知した理由を覚えておく
+static LAST_WARNINGS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn read_settings(app_handle: &AppHandle) -> StorageSettings {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(STORAGE_SETTINGS_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<StorageSettings>(&value).ok())
+        .filter(|settings| settings.validate().is_ok())
+        .unwrap_or_default()
+}
+
+fn workspace_root(app_handle: &AppHandle) -> Option<PathBuf> {
+    let state = app_handle.try_state::<WorkspaceState>()?;
+    let conn = state.lock().ok()?;
+    conn.workspace_root()
+}
+
+fn measure(root: &Path) -> StorageUsage {
+    let images = root.join("images");
+    let originals = dir_size(&images.join("originals"));
+    let processed = dir_size(&images.join("processed"));
+    let backgrounds = dir_size(&images.join("backgrounds"));
+    let thumbnails = dir_size(&images.join("thumbnails"));
+    let audio = dir_size(&root.join("audio"));
+    let total = dir_size(root);
+    StorageUsage {
+        workspace_path: root.to_string_lossy().to_string(),
+        originals,
+        processed,
+        backgrounds,
+        audio,
+        thumbnails,
+        other: total.saturating_sub(originals + processed + backgrounds + thumbnails + audio),
+        total,
+        free_bytes: fs2::available_space(root).ok(),
+    }
+}
+
+fn warnings_for(usage: &StorageUsage, settings: &StorageSettings) -> Vec<StorageWarning> {
+    let mut warnings = Vec::new();
+    if let Some(free) = usage.free_bytes {
+        if free < settings.min_free_bytes {
+            warnings.push(StorageWarning {
+                reason: "lowDiskSpace".to_string(),
+                free_bytes: usage.free_bytes,
+                workspace_bytes: usage.total,
+                threshold: settings.min_free_bytes,
+            });
+        }
+    }
+    if let Some(max) = settings.max_workspace_bytes {
+        if usage.total > max {
+            warnings.push(StorageWarning {
+                reason: "workspaceQuota".to_string(),
+                free_bytes: usage.free_bytes,
+                workspace_bytes: usage.total,
+                threshold: max,
+            });
+        }
+    }
+    warnings
+}
+
+// しきい値を下回った時点で一度だけ通知し、回復したら再び通知できるようにする
+fn check(app_handle: &AppHandle) {
+    let Some(root) = workspace_root(app_handle) else {
+        return;
+    };
+    let usage = measure(&root);
+    let warnings = warnings_for(&usage, &read_settings(app_handle));
+
+    let Ok(mut last) = LAST_WARNINGS.lock() else {
+        return;
+    };
+    for warning in &warnings {
+        if last.contains(&warning.reason) {
+            continue;
+        }
+        eprintln!("[storage] warning: {:?}", warning);
+        let _ = crate::events::emit_routed(app_handle, "storage-warning", warning.clone());
+    }
+    *last = warnings.into_iter().map(|warning| warning.reason).collect();
+}
+
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        check(&app_handle);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+/// 種類別の使用量とドライブの空き容量
+#[tauri::command]
+pub async fn get_storage_usage(app_handle: AppHandle) -> Result<StorageUsage, String> {
+    let root = workspace_root(&app_handle).ok_or("データベースに接続されていません")?;
+    // 画像が多いとフォルダの走査に時間がかかるため別スレッドで
+    tauri::async_runtime::spawn_blocking(move || measure(&root))
+        .await
+        .map_err(|e| format!("使用量の取得に失敗しました: {}", e))
+}
+
+#[tauri::command]
+pub fn get_storage_settings(app_handle: AppHandle) -> Result<StorageSettings, String> {
+    Ok(read_settings(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_storage_settings(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    settings: StorageSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("容量監視の設定のシリアライズに失敗しました: {}", e))?;
+    {
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        db.save_app_setting(STORAGE_SETTINGS_KEY, &value)
+            .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    }
+    // 新しいしきい値ですぐに確認する
+    std::thread::spawn(move || check(&app_handle));
+    Ok(())
+}
@@ -0,0 +1,317 @@
+// 問い合わせ対応用のサポートバンドル（ログ・設定・DB統計などを1つのzipにまとめる）
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const REDACTED: &str = "[redacted]";
+// キー名にこれらを含む設定値は伏せる
+const SECRET_KEY_PARTS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "passwd",
+    "apikey",
+    "api_key",
+    "credential",
+];
+const DEFAULT_EVENT_LIMIT: usize = 200;
+// 直近のログのみ（古いものや大きすぎるものは含めない）
+const LOG_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const LOG_MAX_TOTAL_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportBundleSummary {
+    pub path: String,
+    pub files: Vec<String>,
+    pub skipped_logs: usize,
+    pub bytes: u64,
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let lower = key.to_lowercase();
+    SECRET_KEY_PARTS.iter().any(|part| lower.contains(part))
+}
+
+// JSONの中も含めて秘密情報らしいキーの値を伏せる
+fn redact_value(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    if is_secret_key(&key) {
+                        (key, Value::String(REDACTED.to_string()))
+                    } else {
+                        (key, redact_value(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact_value).collect()),
+        other => other,
+    }
+}
+
+fn redact_setting(key: &str, value: &str) -> Value {
+    if is_secret_key(key) {
+        return Value::String(REDACTED.to_string());
+    }
+    match serde_json::from_str::<Value>(value) {
+        Ok(json @ (Value::Object(_) | Value::Array(_))) => redact_value(json),
+        _ => Value::String(value.to_string()),
+    }
+}
+
+fn workspace_snapshot(
+    workspace: &WorkspaceState,
+) -> Result<(Value, Value, Option<PathBuf>), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let root = conn.workspace_root();
+    let Ok(db) = conn.get() else {
+        return Ok((Value::Null, Value::Null, root));
+    };
+
+    let settings: serde_json::Map<String, Value> = db
+        .get_settings_delta(0)
+        .map_err(|e| format!("Failed to get app settings: {}", e))?
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key.clone(), redact_setting(&key, &value))))
+        .collect();
+
+    let tables: serde_json::Map<String, Value> = db
+        .table_row_counts()
+        .map_err(|e| format!("Failed to count table rows: {}", e))?
+        .into_iter()
+        .map(|(table, count)| (table, Value::from(count)))
+        .collect();
+    let db_bytes = conn
+        .current_path
+        .as_ref()
+        .and_then(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len());
+    let stats = serde_json::json!({
+        "info": db.get_workspace_info().ok(),
+        "schemaVersion": crate::db::WORKSPACE_SCHEMA_VERSION,
+        "settingsVersion": db.settings_version().ok(),
+        "dbBytes": db_bytes,
+        "tables": tables,
+        "syncedLocation": root.as_deref().and_then(crate::sync_safety::detect),
+    });
+    Ok((Value::Object(settings), stats, root))
+}
+
+fn file_info(path: &Path) -> Value {
+    let meta = fs::metadata(path).ok();
+    serde_json::json!({
+        "path": path.to_string_lossy(),
+        "exists": meta.is_some(),
+        "bytes": meta.as_ref().map(|m| m.len()),
+        "modifiedAt": meta
+            .and_then(|m| m.modified().ok())
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+    })
+}
+
+// サイドカーの候補（起動時の探索順と同じ場所）
+fn sidecar_info() -> Value {
+    let mut candidates = Vec::new();
+    if let Ok(path) = std::env::var("NURIEMON_SIDECAR") {
+        candidates.push(file_info(Path::new(&path)));
+    }
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.to_path_buf()))
+    {
+        if let Ok(entries) = fs::read_dir(&exe_dir) {
+            for entry in entries.flatten() {
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("python-sidecar")
+                {
+                    candidates.push(file_info(&entry.path()));
+                }
+            }
+        }
+    }
+    let (running, pending) = crate::python_runtime_status();
+    serde_json::json!({
+        "running": running,
+        "pending": pending,
+        "candidates": candidates,
+    })
+}
+
+fn system_info(app_handle: &AppHandle) -> Value {
+    let interfaces: Vec<Value> = local_ip_address::list_afinet_netifas()
+        .map(|list| {
+            list.into_iter()
+                .map(|(name, ip)| serde_json::json!({ "name": name, "ip": ip.to_string() }))
+                .collect()
+        })
+        .unwrap_or_default();
+    serde_json::json!({
+        "appVersion": app_handle.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "localIp": local_ip_address::local_ip().ok().map(|ip| ip.to_string()),
+        "interfaces": interfaces,
+    })
+}
+
+// 直近に更新されたログ（新しい順、合計サイズの上限まで）
+fn recent_logs(root: Option<&Path>) -> (Vec<PathBuf>, usize) {
+    let Some(dir) = root.map(|root| root.join("logs")) else {
+        return (Vec::new(), 0);
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return (Vec::new(), 0);
+    };
+    let cutoff = SystemTime::now() - LOG_MAX_AGE;
+    let mut files: Vec<(PathBuf, SystemTime, u64)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().ok()?;
+            (meta.is_file() && modified >= cutoff).then(|| (entry.path(), modified, meta.len()))
+        })
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut total = 0;
+    let mut included = Vec::new();
+    let mut skipped = 0;
+    for (path, _, len) in files {
+        if total + len > LOG_MAX_TOTAL_BYTES {
+            skipped += 1;
+            continue;
+        }
+        total += len;
+        included.push(path);
+    }
+    (included, skipped)
+}
+
+fn write_json(
+    zip: &mut ZipWriter<File>,
+    options: SimpleFileOptions,
+    name: &str,
+    value: &Value,
+) -> Result<(), String> {
+    let body = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("{} のシリアライズに失敗しました: {}", name, e))?;
+    zip.start_file(name, options)
+        .map_err(|e| format!("{} の追加に失敗しました: {}", name, e))?;
+    zip.write_all(&body)
+        .map_err(|e| format!("{} の書き込みに失敗しました: {}", name, e))
+}
+
+/// サポート用のzipを書き出す（秘密情報は伏せる）
+#[tauri::command]
+pub async fn export_support_bundle(
+    app_handle: AppHandle,
+    server_state: State<'_, ServerState>,
+    workspace: State<'_, WorkspaceState>,
+    dest: String,
+    event_limit: Option<usize>,
+) -> Result<SupportBundleSummary, String> {
+    // ワークスペースのロックより先に出力先を検査する
+    let dest_path = crate::path_sandbox::check_write(&app_handle, &dest)?;
+    let (settings, database, root) = workspace_snapshot(&workspace)?;
+    let runtime =
+        crate::diagnostics::dump_runtime_state(app_handle.clone(), server_state, workspace, None)
+            .await?;
+    let global_settings = crate::workspace::global_settings_path(&app_handle)
+        .map(|path| crate::global_settings::load_from(&path))
+        .ok()
+        .and_then(|settings| serde_json::to_value(settings).ok())
+        .map(redact_value)
+        .unwrap_or(Value::Null);
+    let events = serde_json::to_value(crate::events::recent_events(
+        event_limit.unwrap_or(DEFAULT_EVENT_LIMIT),
+    ))
+    .unwrap_or(Value::Null);
+    let system = system_info(&app_handle);
+    let sidecar = sidecar_info();
+    let command_metrics =
+        serde_json::to_value(crate::command_metrics::snapshot()).unwrap_or(Value::Null);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("出力先フォルダの作成に失敗しました: {}", e))?;
+        }
+        let file = File::create(&dest_path)
+            .map_err(|e| format!("サポートバンドルの作成に失敗しました: {}", e))?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let entries = [
+            ("runtime_state.json", runtime),
+            ("settings/app_settings.json", settings),
+            ("settings/global_settings.json", global_settings),
+            ("database.json", database),
+            ("system.json", system),
+            ("sidecar.json", sidecar),
+            ("events.json", events),
+            ("command_metrics.json", command_metrics),
+        ];
+        let mut files = Vec::new();
+        for (name, value) in &entries {
+            write_json(&mut zip, options, name, value)?;
+            files.push(name.to_string());
+        }
+
+        let (logs, skipped_logs) = recent_logs(root.as_deref());
+        for path in logs {
+            let Some(file_name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let mut body = Vec::new();
+            // 書き込み中などで読めないログは飛ばす
+            if File::open(&path)
+                .and_then(|mut f| f.read_to_end(&mut body))
+                .is_err()
+            {
+                continue;
+            }
+            let name = format!("logs/{}", file_name);
+            zip.start_file(name.as_str(), options)
+                .map_err(|e| format!("{} の追加に失敗しました: {}", name, e))?;
+            zip.write_all(&body)
+                .map_err(|e| format!("{} の書き込みに失敗しました: {}", name, e))?;
+            files.push(name);
+        }
+
+        let manifest = serde_json::json!({
+            "generatedAt": crate::db::current_timestamp(),
+            "files": files,
+            "skippedLogs": skipped_logs,
+        });
+        write_json(&mut zip, options, "manifest.json", &manifest)?;
+        zip.finish()
+            .map_err(|e| format!("サポートバンドルの書き込みに失敗しました: {}", e))?;
+
+        let bytes = fs::metadata(&dest_path).map(|m| m.len()).unwrap_or(0);
+        Ok(SupportBundleSummary {
+            path: dest_path.to_string_lossy().to_string(),
+            files,
+            skipped_logs,
+            bytes,
+        })
+    })
+    .await
+    .map_err(|e| format!("サポートバンドルの作成に失敗しました: {}", e))?
+}
@@ -0,0 +1,111 @@
+// クラウド同期フォルダ・ネットワークドライブ上のワークスペースの検出
+// 同期クライアントがDBファイルを途中の状態で書き戻すと壊れることがあるため、
+// 検出した場合はジャーナル設定を安全側に切り替え、ローカルへの移動を勧める
+use serde::Serialize;
+use std::path::Path;
+
+// パスに含まれていれば同期フォルダとみなすフォルダ名（小文字で比較）
+const SYNC_FOLDER_MARKERS: [(&str, &str); 8] = [
+    ("dropbox", "Dropbox"),
+    ("onedrive", "OneDrive"),
+    ("google drive", "Google Drive"),
+    ("googledrive", "Google Drive"),
+    ("icloud drive", "iCloud Drive"),
+    ("iclouddrive", "iCloud Drive"),
+    ("mobile documents", "iCloud Drive"),
+    ("box sync", "Box"),
+];
+// Linuxでネットワーク越しとみなすファイルシステム
+const NETWORK_FILESYSTEMS: [&str; 6] = ["nfs", "nfs4", "cifs", "smbfs", "smb3", "fuse.sshfs"];
+
+/// 同期・共有された場所の情報（"workspace-sync-warning"）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncedLocation {
+    pub path: String,
+    // "cloud" / "network"
+    pub kind: String,
+    pub provider: String,
+    pub message: String,
+}
+
+fn cloud_provider(path: &Path) -> Option<&'static str> {
+    let components: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_lowercase())
+        .collect();
+    // macOS の File Provider 経由の同期フォルダ（~/Library/CloudStorage/<provider>）
+    if components
+        .windows(2)
+        .any(|pair| pair[0] == "library" && pair[1] == "cloudstorage")
+    {
+        return Some("CloudStorage");
+    }
+    // Box Drive はホームフォルダ直下の Box（Windows: C:\Users\<名前>\Box、旧macOS: /Users/<名前>/Box）
+    // "box" はありふれた名前なので、それ以外の場所は同期フォルダとみなさない
+    if components
+        .windows(3)
+        .any(|triple| (triple[0] == "users" || triple[0] == "home") && triple[2] == "box")
+    {
+        return Some("Box");
+    }
+    components.iter().find_map(|component| {
+        SYNC_FOLDER_MARKERS
+            .iter()
+            .find(|(marker, _)| {
+                component == marker
+                    || component.starts_with(&format!("{} ", marker))
+                    || component.starts_with(&format!("{}-", marker))
+            })
+            .map(|(_, provider)| *provider)
+    })
+}
+
+fn is_network_path(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    // Windows の UNC パス（\\server\share）
+    if text.starts_with("\\\\") || text.starts_with("//") {
+        return true;
+    }
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    // パスを含むもっとも深いマウントポイントのファイルシステムで判断する
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            path.starts_with(mount_point)
+                .then_some((mount_point.len(), fs_type))
+        })
+        .max_by_key(|(len, _)| *len)
+        .is_some_and(|(_, fs_type)| NETWORK_FILESYSTEMS.contains(&fs_type))
+}
+
+/// 同期フォルダ・ネットワークドライブ上ならその情報を返す
+pub fn detect(path: &Path) -> Option<SyncedLocation> {
+    let (kind, provider) = if let Some(provider) = cloud_provider(path) {
+        ("cloud", provider.to_string())
+    } else if is_network_path(path) {
+        ("network", "network".to_string())
+    } else {
+        return None;
+    };
+    Some(SyncedLocation {
+        path: path.to_string_lossy().to_string(),
+        kind: kind.to_string(),
+        message: format!(
+            "ワークスペースが{}上にあります。同期中にデータベースが壊れることがあるため、ローカルのフォルダへ移動することをおすすめします",
+            if kind == "cloud" { provider.as_str() } else { "ネットワークドライブ" }
+        ),
+        provider,
+    })
+}
+
+/// 指定したフォルダが同期・共有された場所かどうか（作成前の確認用）
+#[tauri::command]
+pub fn check_workspace_location(path: String) -> Option<SyncedLocation> {
+    detect(Path::new(&path))
+}
@@ -0,0 +1,177 @@
+// 持ち帰りQR（展示が終わるキャラクターの作品を、大画面に出したQRからスマホで受け取れるようにする）
+// リンクは共有リンク（share_links）をそのまま使い、受け取り回数は takeaways テーブルで集計する
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::TakeawayStats;
+use crate::qr_manager::{render_qr_svg, QrStyle};
+use crate::server_state::ServerState;
+use crate::workspace::WorkspaceState;
+
+const TAKEAWAY_SETTINGS_KEY: &str = "takeaway_settings";
+// 会場で受け取り損ねても帰宅後に落とせるよう、既定は3日
+const DEFAULT_TTL_SECS: i64 = 3 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TakeawaySettings {
+    pub enabled: bool,
+    // リンクの有効期限（秒）
+    pub ttl_secs: i64,
+}
+
+impl Default for TakeawaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: DEFAULT_TTL_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TakeawayQrPayload {
+    image_id: String,
+    url: String,
+    // SVGのデータURI
+    qr_code: String,
+    expires_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeawayReport {
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub stats: TakeawayStats,
+}
+
+fn load_settings(workspace: &WorkspaceState) -> Result<TakeawaySettings, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    Ok(db
+        .get_app_setting(TAKEAWAY_SETTINGS_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+// 展示から削除された後も配信できるよう作品ファイルの控えを取る
+fn archive_file(app_handle: &AppHandle, image_id: &str, source: &Path) -> Result<PathBuf, String> {
+    let extension = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("png");
+    let dir = crate::frame_capture::exports_dir(app_handle)?.join("takeaways");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("フォルダ作成エラー: {}", e))?;
+    let target = dir.join(format!("{}.{}", image_id, extension));
+    std::fs::copy(source, &target).map_err(|e| format!("作品の控えの保存に失敗しました: {}", e))?;
+    Ok(target)
+}
+
+/// 作品の持ち帰りリンクを用意して大画面にQRを出す（展示終了の直前に呼ぶ）
+pub fn show(app_handle: &AppHandle, image_id: &str) -> Result<(), String> {
+    let settings = load_settings(&app_handle.state::<WorkspaceState>())?;
+    if !settings.enabled {
+        return Ok(());
+    }
+    // Webサーバーが止まっているとスマホから開けないので出さない
+    if app_handle.state::<ServerState>().get_qr_manager().is_none() {
+        return Ok(());
+    }
+
+    // 同じ作品で使えるリンクが残っていればそれを出す
+    let existing = {
+        let workspace = app_handle.state::<WorkspaceState>();
+        let conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        let db = conn.get()?;
+        match db
+            .get_takeaway(image_id)
+            .map_err(|e| format!("Failed to get takeaway: {}", e))?
+        {
+            Some(takeaway) => db
+                .get_share_link(&takeaway.share_link_id)
+                .map_err(|e| format!("Failed to get share link: {}", e))?
+                .filter(crate::share_links::is_active),
+            None => None,
+        }
+    };
+    let created = match existing {
+        Some(link) => crate::share_links::with_token(app_handle, link),
+        None => {
+            let (created, file) =
+                crate::share_links::issue(app_handle, image_id, settings.ttl_secs)?;
+            let archived = archive_file(app_handle, image_id, &file)?;
+            let workspace = app_handle.state::<WorkspaceState>();
+            let conn = workspace
+                .lock()
+                .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+            let db = conn.get()?;
+            db.upsert_takeaway(image_id, &created.link.id, &archived.to_string_lossy())
+                .map_err(|e| format!("Failed to save takeaway: {}", e))?;
+            created
+        }
+    };
+    let Some(url) = created.url else {
+        return Ok(());
+    };
+
+    let payload = TakeawayQrPayload {
+        image_id: image_id.to_string(),
+        qr_code: render_qr_svg(&url, &QrStyle::load(app_handle))?,
+        url,
+        expires_at: created.link.expires_at,
+    };
+    crate::events::emit_routed(app_handle, "takeaway-qr", &payload)
+        .map_err(|e| format!("Failed to emit takeaway-qr: {}", e))?;
+    println!("[takeaway] showing QR for {}", image_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_takeaway_settings(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<TakeawaySettings, String> {
+    load_settings(&workspace)
+}
+
+#[tauri::command]
+pub fn set_takeaway_settings(
+    workspace: State<'_, WorkspaceState>,
+    settings: TakeawaySettings,
+) -> Result<(), String> {
+    if !(60..=crate::share_links::MAX_TTL_SECS).contains(&settings.ttl_secs) {
+        return Err(format!(
+            "有効期限は60〜{}秒で指定してください",
+            crate::share_links::MAX_TTL_SECS
+        ));
+    }
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("設定のシリアライズに失敗しました: {}", e))?;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(TAKEAWAY_SETTINGS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+/// イベントレポート用の持ち帰り件数
+#[tauri::command]
+pub fn get_takeaway_report(workspace: State<'_, WorkspaceState>) -> Result<TakeawayReport, String> {
+    let enabled = load_settings(&workspace)?.enabled;
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let stats = db
+        .takeaway_stats()
+        .map_err(|e| format!("Failed to get takeaway stats: {}", e))?;
+    Ok(TakeawayReport { enabled, stats })
+}
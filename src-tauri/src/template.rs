@@ -0,0 +1,230 @@
+use crate::db::{Database, NameBox, Template};
+use crate::events::{emit_data_change, DataChangeEvent, ImageUpsertedPayload};
+use crate::workspace::WorkspaceState;
+use tauri::{AppHandle, Manager, State};
+
+// 取り込みに使う用紙テンプレートのIDを保存する app_settings のキー
+const ACTIVE_TEMPLATE_KEY: &str = "active_template_id";
+// 言語未指定時のOCR言語（日本語の手書き名が中心なので jpn を先に）
+const DEFAULT_OCR_LANGUAGES: [&str; 2] = ["jpn", "eng"];
+// 表示名の最大文字数（誤認識で長文になった場合に切り詰める）
+const MAX_DISPLAY_NAME_CHARS: usize = 32;
+
+fn validate_name_box(name_box: &NameBox) -> Result<(), String> {
+    let in_range = |v: f32| (0.0..=1.0).contains(&v);
+    if !in_range(name_box.x)
+        || !in_range(name_box.y)
+        || name_box.width <= 0.0
+        || name_box.height <= 0.0
+        || name_box.x + name_box.width > 1.0
+        || name_box.y + name_box.height > 1.0
+    {
+        return Err("名前欄の範囲は用紙内（0〜1の割合）で指定してください".to_string());
+    }
+    Ok(())
+}
+
+fn normalize_languages(languages: &[String]) -> Result<Vec<String>, String> {
+    let mut result = Vec::new();
+    for lang in languages {
+        let lang = lang.trim();
+        if lang.is_empty() {
+            continue;
+        }
+        if !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("OCR言語コードが不正です: {}", lang));
+        }
+        if !result.iter().any(|l: &String| l == lang) {
+            result.push(lang.to_string());
+        }
+    }
+    if result.is_empty() {
+        result = DEFAULT_OCR_LANGUAGES
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+    }
+    Ok(result)
+}
+
+fn active_template(db: &Database) -> Result<Option<Template>, String> {
+    let Some(id) = db
+        .get_app_setting(ACTIVE_TEMPLATE_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+    else {
+        return Ok(None);
+    };
+    db.get_template(&id)
+        .map_err(|e| format!("Failed to get template: {}", e))
+}
+
+/// 取り込み時に名前欄OCRを行うか（有効なテンプレートがあれば名前欄と言語を返す）
+pub fn ocr_config_for_import(app_handle: &AppHandle) -> Option<(NameBox, Vec<String>)> {
+    let state = app_handle.try_state::<WorkspaceState>()?;
+    let conn = state.lock().ok()?;
+    let db = conn.get().ok()?;
+    let template = active_template(db).ok()??;
+    if !template.ocr_enabled {
+        return None;
+    }
+    Some((template.name_box?, template.ocr_languages))
+}
+
+// OCR結果を表示名として使える形に整える（空白の連続をまとめ、長すぎれば切り詰める）
+fn clean_display_name(text: &str) -> Option<String> {
+    let joined = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name: String = joined.chars().take(MAX_DISPLAY_NAME_CHARS).collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(crate::file_name::normalize(&name))
+    }
+}
+
+/// 取り込み前の元画像から名前欄を読み取る（失敗しても取り込みは続行するためNoneを返す）
+pub fn read_display_name(
+    data_url: &str,
+    name_box: &NameBox,
+    languages: &[String],
+) -> Option<String> {
+    match crate::ocr_name_sync(data_url.to_string(), name_box, languages) {
+        Ok(result) if result.success => result.text.as_deref().and_then(clean_display_name),
+        Ok(result) => {
+            eprintln!(
+                "[template] 名前欄のOCRに失敗しました: {}",
+                result.error.unwrap_or_default()
+            );
+            None
+        }
+        Err(e) => {
+            eprintln!("[template] 名前欄のOCRに失敗しました: {}", e);
+            None
+        }
+    }
+}
+
+/// テンプレートを保存（IDが空なら新規作成）
+#[tauri::command]
+pub fn save_template(
+    workspace: State<'_, WorkspaceState>,
+    mut template: Template,
+) -> Result<Template, String> {
+    if template.name.trim().is_empty() {
+        return Err("テンプレート名を入力してください".to_string());
+    }
+    if let Some(name_box) = &template.name_box {
+        validate_name_box(name_box)?;
+    } else if template.ocr_enabled {
+        return Err("OCRを有効にするには名前欄の範囲を指定してください".to_string());
+    }
+    template.ocr_languages = normalize_languages(&template.ocr_languages)?;
+    if template.id.trim().is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_template(&template)
+        .map_err(|e| format!("Failed to save template: {}", e))?;
+    db.get_template(&template.id)
+        .map_err(|e| format!("Failed to get template: {}", e))?
+        .ok_or_else(|| "テンプレートの保存に失敗しました".to_string())
+}
+
+#[tauri::command]
+pub fn list_templates(workspace: State<'_, WorkspaceState>) -> Result<Vec<Template>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.get_all_templates()
+        .map_err(|e| format!("Failed to get templates: {}", e))
+}
+
+/// テンプレートを削除（取り込み用に選択中なら選択も解除）
+#[tauri::command]
+pub fn delete_template(workspace: State<'_, WorkspaceState>, id: String) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.delete_template(&id)
+        .map_err(|e| format!("Failed to delete template: {}", e))?;
+    if db
+        .get_app_setting(ACTIVE_TEMPLATE_KEY)
+        .map_err(|e| format!("Failed to get app setting: {}", e))?
+        .as_deref()
+        == Some(id.as_str())
+    {
+        db.delete_app_setting(ACTIVE_TEMPLATE_KEY)
+            .map_err(|e| format!("Failed to delete app setting: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 取り込みに使うテンプレートを選択（Noneで解除）
+#[tauri::command]
+pub fn set_active_template(
+    workspace: State<'_, WorkspaceState>,
+    id: Option<String>,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    match id {
+        Some(id) => {
+            if db
+                .get_template(&id)
+                .map_err(|e| format!("Failed to get template: {}", e))?
+                .is_none()
+            {
+                return Err(format!("テンプレートが見つかりません: {}", id));
+            }
+            db.save_app_setting(ACTIVE_TEMPLATE_KEY, &id)
+                .map_err(|e| format!("Failed to save app setting: {}", e))
+        }
+        None => db
+            .delete_app_setting(ACTIVE_TEMPLATE_KEY)
+            .map_err(|e| format!("Failed to delete app setting: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn get_active_template(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<Option<Template>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    active_template(db)
+}
+
+/// 表示名を手動で修正（OCRの誤認識の訂正用。空文字でクリア）
+#[tauri::command]
+pub fn update_display_name(
+    app_handle: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    id: String,
+    display_name: String,
+) -> Result<(), String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let name = clean_display_name(&display_name);
+    db.update_image_display_name(&id, name.as_deref())
+        .map_err(|e| format!("Failed to update display name: {}", e))?;
+
+    let saved = db
+        .get_image(&id)
+        .map_err(|e| format!("Failed to get image: {}", e))?
+        .ok_or_else(|| format!("画像が見つかりません: {}", id))?;
+    emit_data_change(
+        &app_handle,
+        DataChangeEvent::ImageUpserted(ImageUpsertedPayload::from(&saved)),
+    )
+}
@@ -0,0 +1,103 @@
+// E2Eテスト用のコマンド（`test-harness` フィーチャー有効時のみ登録される）
+// スキャナー・サイドカー・スマホなしでイベント/WSの経路を動かすためのもの
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::Cursor;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::workspace::WorkspaceState;
+
+const SEED_IMAGE_SIZE: u32 = 256;
+const MAX_SEED_COUNT: u32 = 200;
+
+// 透明背景に色付きの円を描いた、背景除去済み相当のPNG
+fn seed_png(index: u32) -> Result<Vec<u8>, String> {
+    let hue = (index * 47) % 360;
+    let (r, g, b) = match hue / 120 {
+        0 => (255 - (hue * 2) as u8, (hue * 2) as u8, 64),
+        1 => (64, 255 - ((hue - 120) * 2) as u8, ((hue - 120) * 2) as u8),
+        _ => (((hue - 240) * 2) as u8, 64, 255 - ((hue - 240) * 2) as u8),
+    };
+    let center = SEED_IMAGE_SIZE as f32 / 2.0;
+    let radius = center * 0.8;
+    let img = RgbaImage::from_fn(SEED_IMAGE_SIZE, SEED_IMAGE_SIZE, |x, y| {
+        let dx = x as f32 - center;
+        let dy = y as f32 - center;
+        if dx * dx + dy * dy <= radius * radius {
+            Rgba([r, g, b, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+    let mut buf = Vec::new();
+    img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+        .map_err(|e| format!("PNGへの変換に失敗しました: {}", e))?;
+    Ok(buf)
+}
+
+/// 現在のワークスペースにテスト用の処理済み画像を登録し、IDを返す
+#[tauri::command]
+pub fn test_seed_workspace(
+    app_handle: AppHandle,
+    count: Option<u32>,
+) -> Result<Vec<String>, String> {
+    let workspace_path = {
+        let state: tauri::State<WorkspaceState> = app_handle.state();
+        let conn = state
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        conn.get()?;
+        conn.workspace_root()
+            .ok_or_else(|| "ワークスペースが選択されていません".to_string())?
+            .to_string_lossy()
+            .to_string()
+    };
+
+    let count = count.unwrap_or(3).clamp(1, MAX_SEED_COUNT);
+    let mut ids = Vec::new();
+    for index in 0..count {
+        let image_id = Uuid::new_v4().to_string();
+        crate::file_watcher::save_processed_image(
+            &app_handle,
+            seed_png(index)?,
+            format!("test-seed-{:03}.png", index + 1),
+            &image_id,
+            &workspace_path,
+            &crate::file_watcher::generate_random_animation(),
+            Some(format!("TEST{}", index + 1)),
+        )?;
+        ids.push(image_id);
+    }
+    Ok(ids)
+}
+
+/// 任意のTauriイベントを発火する
+#[tauri::command]
+pub fn test_emit_event(
+    app_handle: AppHandle,
+    event: String,
+    payload: serde_json::Value,
+) -> Result<(), String> {
+    app_handle
+        .emit(&event, payload)
+        .map_err(|e| format!("イベントの発火に失敗しました: {}", e))
+}
+
+/// 疑似コントローラーとしてWSメッセージを順に処理させ、返信を返す
+/// sessionを指定すると最初に connect を送る（generate_qr_code で発行したもの）
+#[tauri::command]
+pub async fn test_fake_controller(
+    app_handle: AppHandle,
+    session: Option<String>,
+    messages: Vec<serde_json::Value>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut all = Vec::new();
+    if let Some(session_id) = session {
+        all.push(serde_json::json!({
+            "type": "connect",
+            "payload": { "sessionId": session_id },
+        }));
+    }
+    all.extend(messages);
+    crate::websocket::drive_fake_controller(&app_handle, all).await
+}
@@ -0,0 +1,106 @@
+use local_ip_address::list_afinet_netifas;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::workspace::{read_global_setting, write_global_setting, WorkspaceState};
+
+// HTTPS有効/無効を保存するグローバル設定キー
+pub const TLS_ENABLED_KEY: &str = "web_server_tls";
+// HTTPSはHTTPポートにこの値を足したポートで待ち受ける（8080 -> 8443）
+pub const HTTPS_PORT_OFFSET: u16 = 363;
+
+const CERT_FILE: &str = "server-cert.pem";
+const KEY_FILE: &str = "server-key.pem";
+
+/// HTTPSが有効化されているか（未設定なら無効）
+pub fn is_enabled(app_handle: &AppHandle) -> bool {
+    read_global_setting(app_handle, TLS_ENABLED_KEY)
+        .ok()
+        .flatten()
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+pub fn set_enabled(app_handle: &AppHandle, enabled: bool) -> Result<(), String> {
+    write_global_setting(app_handle, TLS_ENABLED_KEY, &enabled.to_string())
+}
+
+/// 証明書の保存先（ワークスペース接続中は .nuriemon/tls、未接続ならアプリデータ）
+fn cert_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let workspace_dir = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        conn.current_path
+            .as_ref()
+            .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+    });
+
+    let base = match workspace_dir {
+        Some(dir) => dir,
+        None => app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("アプリデータディレクトリの取得に失敗: {}", e))?,
+    };
+
+    Ok(base.join("tls"))
+}
+
+/// スマホからアクセスされ得るホスト名（自己署名証明書のSANに入れる）
+fn subject_alt_names() -> Vec<String> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Ok(map) = list_afinet_netifas() {
+        for (_, ip) in map {
+            if let std::net::IpAddr::V4(v4) = ip {
+                let ip = v4.to_string();
+                if !names.contains(&ip) {
+                    names.push(ip);
+                }
+            }
+        }
+    }
+    names
+}
+
+fn generate_certificate(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("証明書ディレクトリの作成に失敗しました: {}", e))?;
+
+    let certified = rcgen::generate_simple_self_signed(subject_alt_names())
+        .map_err(|e| format!("自己署名証明書の生成に失敗しました: {}", e))?;
+
+    std::fs::write(dir.join(CERT_FILE), certified.cert.pem())
+        .map_err(|e| format!("証明書の保存に失敗しました: {}", e))?;
+    std::fs::write(dir.join(KEY_FILE), certified.key_pair.serialize_pem())
+        .map_err(|e| format!("秘密鍵の保存に失敗しました: {}", e))?;
+
+    println!("[tls] 自己署名証明書を生成しました: {}", dir.display());
+    Ok(())
+}
+
+/// 保存済みの証明書を読み込む（なければ生成）してrustls設定を作成
+pub fn load_server_config(app_handle: &AppHandle) -> Result<ServerConfig, String> {
+    let dir = cert_dir(app_handle)?;
+    let cert_path = dir.join(CERT_FILE);
+    let key_path = dir.join(KEY_FILE);
+
+    if !cert_path.exists() || !key_path.exists() {
+        generate_certificate(&dir)?;
+    }
+
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("証明書の読み込みに失敗しました: {}", e))?;
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .map_err(|e| format!("秘密鍵の読み込みに失敗しました: {}", e))?;
+
+    ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS設定の作成に失敗しました: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("TLS設定の作成に失敗しました: {}", e))
+}
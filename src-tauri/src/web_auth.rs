@@ -0,0 +1,256 @@
+use actix_web::body::MessageBody;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::server_state::ServerState;
+use crate::web_server::WebServerState;
+use crate::workspace::{read_global_setting, write_global_setting};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// QR経由で受け取ったトークンをブラウザに保持させるCookie名
+pub const TOKEN_COOKIE: &str = "nuriemon_token";
+// 認証モードを保存するグローバル設定キー
+const AUTH_MODE_KEY: &str = "web_auth_mode";
+// 認証が必要なパス（静的UIは誰でも取得可能）
+const PROTECTED_PREFIXES: [&str; 3] = ["/image/", "/api/", "/ws"];
+const ADMIN_PREFIX: &str = "/api/admin/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebAuthMode {
+    // リハーサル用: 認証なし
+    Open,
+    // 本番: 署名付きトークン必須
+    Locked,
+}
+
+impl WebAuthMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "open" => Some(Self::Open),
+            "locked" => Some(Self::Locked),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Locked => "locked",
+        }
+    }
+}
+
+// 署名鍵の保存先（キーチェーンか暗号化ファイル。再起動後も印刷済みQRのトークンを有効にするため）
+const SECRET_SERVICE: &str = "nuriemon";
+const SECRET_ACCOUNT: &str = "web_auth_secret";
+
+/// QRトークンの署名と検証（秘密鍵は起動時に生成し、保存済みのものがあれば差し替える）
+pub struct WebAuth {
+    secret: Mutex<[u8; 32]>,
+    mode: Mutex<WebAuthMode>,
+}
+
+impl WebAuth {
+    pub fn new(mode: WebAuthMode) -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            secret: Mutex::new(secret),
+            mode: Mutex::new(mode),
+        }
+    }
+
+    /// 保存済みの署名鍵を読み込む（なければ今の鍵を保存する）
+    pub fn restore_secret(&self) {
+        let stored = match crate::secret_store::load(SECRET_SERVICE, SECRET_ACCOUNT) {
+            Ok(value) => value.and_then(|value| {
+                URL_SAFE_NO_PAD
+                    .decode(value.trim())
+                    .ok()
+                    .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            }),
+            Err(e) => {
+                eprintln!("[web_auth] failed to read signing key: {}", e);
+                return;
+            }
+        };
+        let mut secret = self.secret.lock().unwrap();
+        match stored {
+            Some(stored) => *secret = stored,
+            None => {
+                let encoded = URL_SAFE_NO_PAD.encode(*secret);
+                if let Err(e) = crate::secret_store::save(SECRET_SERVICE, SECRET_ACCOUNT, &encoded)
+                {
+                    eprintln!("[web_auth] failed to save signing key: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn mode(&self) -> WebAuthMode {
+        *self.mode.lock().unwrap()
+    }
+
+    pub fn set_mode(&self, mode: WebAuthMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&*self.secret.lock().unwrap())
+            .expect("HMAC accepts any key length")
+    }
+
+    // トークン形式: "<session_id>.<base64url(HMAC-SHA256(session_id))>"
+    pub fn issue_token(&self, session_id: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(session_id.as_bytes());
+        let sig = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", session_id, sig)
+    }
+
+    // 署名が正しければセッションIDを返す
+    pub fn verify_token(&self, token: &str) -> Option<String> {
+        let (session_id, sig) = token.rsplit_once('.')?;
+        let sig = URL_SAFE_NO_PAD.decode(sig).ok()?;
+        let mut mac = self.mac();
+        mac.update(session_id.as_bytes());
+        mac.verify_slice(&sig).ok()?;
+        Some(session_id.to_string())
+    }
+
+    // 用途ごとのトークン（"<scope>:<id>" に署名するため、QRセッションのトークンとしては通らない）
+    pub fn issue_scoped_token(&self, scope: &str, id: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(format!("{}:{}", scope, id).as_bytes());
+        let sig = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", id, sig)
+    }
+
+    pub fn verify_scoped_token(&self, scope: &str, token: &str) -> Option<String> {
+        let (id, sig) = token.rsplit_once('.')?;
+        let sig = URL_SAFE_NO_PAD.decode(sig).ok()?;
+        let mut mac = self.mac();
+        mac.update(format!("{}:{}", scope, id).as_bytes());
+        mac.verify_slice(&sig).ok()?;
+        Some(id.to_string())
+    }
+}
+
+fn query_token(req: &ServiceRequest) -> Option<String> {
+    web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.get("token").cloned())
+}
+
+// クエリ > Authorizationヘッダー > Cookie の順でトークンを探す
+fn extract_token(req: &ServiceRequest) -> Option<String> {
+    if let Some(token) = query_token(req) {
+        return Some(token);
+    }
+    if let Some(token) = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(token.trim().to_string());
+    }
+    req.cookie(TOKEN_COOKIE).map(|c| c.value().to_string())
+}
+
+fn is_protected(path: &str) -> bool {
+    // 管理APIは専用の管理トークンで保護する（admin_api::admin_guard）
+    if path.starts_with(ADMIN_PREFIX) {
+        return false;
+    }
+    PROTECTED_PREFIXES
+        .iter()
+        .any(|prefix| path == prefix.trim_end_matches('/') || path.starts_with(prefix))
+}
+
+/// /image, /api, /ws（/api/upload を含む）を有効なQRセッションの署名付きトークンで保護するミドルウェア
+pub async fn auth_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let app_handle = req
+        .app_data::<web::Data<WebServerState>>()
+        .map(|data| data.app_handle.clone());
+    let Some(app_handle) = app_handle else {
+        return next.call(req).await;
+    };
+    let server_state = app_handle.state::<ServerState>();
+    let auth = server_state.web_auth.clone();
+
+    // 署名が正しくても、期限切れや取り消し済みのQRセッションのトークンは通さない
+    // （署名鍵は保存されて残るため、署名だけでは古いトークンがいつまでも使えてしまう）
+    let token = extract_token(&req);
+    let valid = token
+        .as_deref()
+        .and_then(|t| auth.verify_token(t))
+        .is_some_and(|session_id| {
+            server_state
+                .get_qr_manager()
+                .is_some_and(|qr_manager| qr_manager.is_session_active(&session_id))
+        });
+
+    if auth.mode() == WebAuthMode::Locked && is_protected(req.path()) && !valid {
+        println!(
+            "[web_auth] rejected {} from {:?}",
+            req.path(),
+            req.peer_addr()
+        );
+        return Err(actix_web::error::ErrorUnauthorized("認証が必要です"));
+    }
+
+    // QRのURLで渡されたトークンはCookieに保存し、以降の /image や /ws に自動付与させる
+    let cookie_token = query_token(&req).filter(|_| valid);
+    let mut res = next.call(req).await?;
+    if let Some(token) = cookie_token {
+        let cookie = Cookie::build(TOKEN_COOKIE, token)
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .finish();
+        let _ = res.response_mut().add_cookie(&cookie);
+    }
+    Ok(res)
+}
+
+/// 保存済みの認証モードを読み込む（未設定なら locked）
+pub fn load_persisted_mode(app_handle: &AppHandle) -> WebAuthMode {
+    read_global_setting(app_handle, AUTH_MODE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| WebAuthMode::parse(&v))
+        .unwrap_or(WebAuthMode::Locked)
+}
+
+/// Webサーバーの認証モードを切り替え（"open" / "locked"）
+#[tauri::command]
+pub async fn set_web_auth_mode(
+    app_handle: AppHandle,
+    server_state: State<'_, ServerState>,
+    mode: String,
+) -> Result<(), String> {
+    let mode =
+        WebAuthMode::parse(&mode).ok_or_else(|| format!("不明な認証モードです: {}", mode))?;
+    server_state.web_auth.set_mode(mode);
+    write_global_setting(&app_handle, AUTH_MODE_KEY, mode.as_str())
+}
+
+#[tauri::command]
+pub fn get_web_auth_mode(server_state: State<'_, ServerState>) -> Result<String, String> {
+    Ok(server_state.web_auth.mode().as_str().to_string())
+}
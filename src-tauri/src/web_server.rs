@@ -1,12 +1,19 @@
+use actix_multipart::Multipart;
+use actix_web::dev::ServerHandle;
 use actix_web::http::header;
 use actix_web::{middleware, web, App, Error, HttpRequest, HttpResponse, HttpServer};
+use futures_util::{StreamExt, TryStreamExt};
 use local_ip_address::local_ip;
+use once_cell::sync::Lazy;
 use rust_embed::RustEmbed;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
 
-use crate::workspace::WorkspaceState;
+use crate::workspace::{read_global_setting, WorkspaceState};
 
 #[derive(RustEmbed)]
 #[folder = "../mobile-ui/dist"]
@@ -17,15 +24,75 @@ pub struct WebServerState {
     pub port: u16,
 }
 
+// スマホからの投稿画像の上限サイズ
+const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+// 固定ポート / ポート範囲を保存するグローバル設定キー
+pub const PORT_KEY: &str = "web_server_port";
+pub const PORT_RANGE_KEY: &str = "web_server_port_range";
+// 未設定時に探すポート範囲
+const DEFAULT_PORT_RANGE: (u16, u16) = (8080, 8090);
+
+// 直近にサーバーを起動した時刻（/healthz の稼働時間）
+static SERVER_STARTED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+// 起動したサーバーの待ち受けポートと停止用ハンドル
+#[derive(Clone)]
+pub struct RunningWebServer {
+    pub http: u16,
+    pub https: Option<u16>,
+    pub handle: ServerHandle,
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value
+        .trim()
+        .parse::<u16>()
+        .ok()
+        .filter(|p| *p > 0)
+        .ok_or_else(|| format!("ポート番号が不正です: {}", value))
+}
+
+// "8080-8090" 形式（単一の "8080" も可）
+fn parse_port_range(value: &str) -> Result<(u16, u16), String> {
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+        None => {
+            let port = parse_port(value)?;
+            (port, port)
+        }
+    };
+    if start > end {
+        return Err(format!("ポート範囲が不正です: {}", value));
+    }
+    Ok((start, end))
+}
+
+/// 設定から試行するポートを決める（固定ポート > ポート範囲 > 既定範囲）
+pub fn candidate_ports(app_handle: &AppHandle) -> Result<Vec<u16>, String> {
+    if let Some(port) = read_global_setting(app_handle, PORT_KEY)?.filter(|v| !v.trim().is_empty())
+    {
+        return Ok(vec![parse_port(&port)?]);
+    }
+    let (start, end) =
+        match read_global_setting(app_handle, PORT_RANGE_KEY)?.filter(|v| !v.trim().is_empty()) {
+            Some(range) => parse_port_range(&range)?,
+            None => DEFAULT_PORT_RANGE,
+        };
+    Ok((start..=end).collect())
+}
+
 pub async fn start_web_server(
     app_handle: AppHandle,
-) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+    ports: Vec<u16>,
+    tls_config: Option<rustls::ServerConfig>,
+) -> Result<RunningWebServer, Box<dyn std::error::Error + Send + Sync>> {
     let app_handle = Arc::new(app_handle);
 
-    // ポートを自動選択（8080-8090の範囲で利用可能なポートを探す）
-    let mut last_error = None;
+    // 指定されたポートを順に試し、最初にbindできたものを使う
+    let mut failures: Vec<String> = Vec::new();
 
-    for port in 8080..=8090 {
+    for port in ports.iter().copied() {
         let app_handle_clone = app_handle.clone();
 
         let server = HttpServer::new(move || {
@@ -36,55 +103,288 @@ pub async fn start_web_server(
 
             App::new()
                 .app_data(web::Data::new(state))
+                .wrap(middleware::from_fn(crate::web_auth::auth_guard))
+                .wrap(middleware::from_fn(crate::rate_limit::rate_limit_guard))
                 .wrap(middleware::Logger::default())
+                .wrap(middleware::from_fn(crate::access_log::access_log))
+                .service(web::resource("/healthz").route(web::get().to(healthz)))
                 .service(web::resource("/").route(web::get().to(serve_index)))
                 .service(web::resource("/mobile").route(web::get().to(serve_mobile)))
                 .service(web::resource("/app").route(web::get().to(serve_mobile)))
                 .service(web::resource("/image/{id}").route(web::get().to(serve_image_by_id)))
+                .service(
+                    web::resource("/share/{token}.gif").route(web::get().to(serve_character_gif)),
+                )
+                .service(web::resource("/share/{token}").route(web::get().to(serve_share_link)))
                 .service(web::resource("/api/connect").route(web::post().to(handle_connect)))
+                .service(web::resource("/api/upload").route(web::post().to(handle_upload)))
+                .service(web::resource("/api/images").route(web::get().to(list_gallery_images)))
+                .service(
+                    web::resource("/api/images/{id}/meta")
+                        .route(web::get().to(get_gallery_image_meta)),
+                )
                 .service(
                     web::resource("/ws").route(web::get().to(crate::websocket::websocket_handler)),
                 )
+                .configure(crate::admin_api::configure)
                 .default_service(web::route().to(serve_static))
         })
         .bind(("0.0.0.0", port));
 
+        // HTTPS有効時は同じサーバーにTLSのポートも追加する
+        let https_port = tls_config
+            .as_ref()
+            .map(|_| port.saturating_add(crate::tls::HTTPS_PORT_OFFSET));
+        let server = match (server, tls_config.clone(), https_port) {
+            (Ok(server), Some(config), Some(https_port)) => {
+                server.bind_rustls_0_23(("0.0.0.0", https_port), config)
+            }
+            (server, _, _) => server,
+        };
+
         match server {
             Ok(server) => {
                 println!("Webサーバーを起動しました: http://{}:{}", local_ip()?, port);
+                if let Some(https_port) = https_port {
+                    println!(
+                        "Webサーバー(HTTPS)を起動しました: https://{}:{}",
+                        local_ip()?,
+                        https_port
+                    );
+                }
 
                 // Tauriのランタイム上でサーバーを起動
-                let server_handle = server.run();
-                tauri::async_runtime::spawn(server_handle);
+                let server = server.run();
+                let handle = server.handle();
+                tauri::async_runtime::spawn(server);
+                *SERVER_STARTED_AT.lock().unwrap() = Some(Instant::now());
 
-                return Ok(port);
+                return Ok(RunningWebServer {
+                    http: port,
+                    https: https_port,
+                    handle,
+                });
             }
             Err(e) => {
-                last_error = Some(e);
+                failures.push(match https_port {
+                    Some(https_port) => format!("{}(+{}): {}", port, https_port, e),
+                    None => format!("{}: {}", port, e),
+                });
                 continue;
             }
         }
     }
 
-    Err(format!("利用可能なポートが見つかりません: {:?}", last_error).into())
+    Err(format!(
+        "利用可能なポートが見つかりません（試行: {}）",
+        failures.join(", ")
+    )
+    .into())
+}
+
+/// 監視用のヘルスチェック（DBに接続できない場合は503）
+async fn healthz(data: web::Data<WebServerState>) -> HttpResponse {
+    let uptime = SERVER_STARTED_AT
+        .lock()
+        .unwrap()
+        .map(|started| started.elapsed().as_secs());
+
+    let (python_running, python_pending) = crate::python_runtime_status();
+    let sidecar_state = if crate::sidecar_idle::is_starting() {
+        "starting"
+    } else if python_running {
+        "ready"
+    } else {
+        // 停止中でも次の処理で自動的に起動する
+        "stopped"
+    };
+
+    // 処理中でロックが取れない場合は待たずに busy と返す
+    let state: tauri::State<WorkspaceState> = data.app_handle.state();
+    let (db_status, workspace_path) = match state.try_lock() {
+        Ok(conn) => {
+            let db_status = match conn
+                .get()
+                .and_then(|db| db.ping().map_err(|e| e.to_string()))
+            {
+                Ok(()) => "ok".to_string(),
+                Err(e) => format!("error: {}", e),
+            };
+            (
+                db_status,
+                conn.workspace_root()
+                    .map(|p| p.to_string_lossy().to_string()),
+            )
+        }
+        Err(std::sync::TryLockError::WouldBlock) => ("busy".to_string(), None),
+        Err(_) => ("error: lock poisoned".to_string(), None),
+    };
+
+    let healthy = db_status == "ok" || db_status == "busy";
+    let body = serde_json::json!({
+        "status": if healthy { "ok" } else { "degraded" },
+        "uptimeSeconds": uptime,
+        "appUptimeSeconds": crate::heartbeat::uptime_secs(),
+        "port": data.port,
+        "sidecar": {
+            "state": sidecar_state,
+            "pending": python_pending,
+        },
+        "database": db_status,
+        "workspacePath": workspace_path,
+    });
+    if healthy {
+        HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(body)
+    } else {
+        HttpResponse::ServiceUnavailable()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(body)
+    }
+}
+
+// 一時停止中にスマホの操作画面の代わりに出す案内（解除されると自動で操作画面に戻る）
+const MAINTENANCE_PAGE: &str = include_str!("../../static/maintenance.html");
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn maintenance_page() -> HttpResponse {
+    let message = crate::maintenance::current().display_message().to_string();
+    HttpResponse::ServiceUnavailable()
+        .insert_header((header::CONTENT_TYPE, "text/html; charset=utf-8"))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(MAINTENANCE_PAGE.replace("{{message}}", &escape_html(&message)))
 }
 
 async fn serve_index(req: HttpRequest) -> Result<HttpResponse, Error> {
     println!("[web_server] GET / from {:?}", req.peer_addr());
-    serve_embedded_file("index.html")
+    if crate::maintenance::is_enabled() {
+        return Ok(maintenance_page());
+    }
+    serve_embedded_file(&req, "index.html")
 }
 
 async fn serve_mobile(req: HttpRequest) -> Result<HttpResponse, Error> {
     println!("[web_server] GET /mobile from {:?}", req.peer_addr());
-    serve_embedded_file("mobile.html")
+    if crate::maintenance::is_enabled() {
+        return Ok(maintenance_page());
+    }
+    serve_embedded_file(&req, "mobile.html")
 }
 
 async fn serve_static(req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
     println!("[web_server] GET /{} from {:?}", path, req.peer_addr());
-    serve_embedded_file(&path.into_inner())
+    serve_embedded_file(&req, &path.into_inner())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AssetEncoding {
+    Brotli,
+    Gzip,
 }
 
-fn serve_embedded_file(path: &str) -> Result<HttpResponse, Error> {
+impl AssetEncoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+}
+
+// これより小さいファイルは圧縮しない
+const MIN_COMPRESS_BYTES: usize = 1024;
+
+// 圧縮済みの埋め込みファイル（初回リクエスト時に作成して保持）
+static COMPRESSED_ASSETS: Lazy<Mutex<HashMap<(String, AssetEncoding), web::Bytes>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Accept-Encoding から使う圧縮形式を選ぶ（brを優先、q=0は除外）
+fn preferred_encoding(req: &HttpRequest) -> Option<AssetEncoding> {
+    let accept = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())?;
+    let accepted: Vec<String> = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let name = parts.next()?.trim().to_lowercase();
+            let rejected = parts.any(|p| {
+                p.trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .map(|q| q <= 0.0)
+                    .unwrap_or(false)
+            });
+            (!rejected).then_some(name)
+        })
+        .collect();
+    if accepted.iter().any(|e| e == "br") {
+        Some(AssetEncoding::Brotli)
+    } else if accepted.iter().any(|e| e == "gzip") {
+        Some(AssetEncoding::Gzip)
+    } else {
+        None
+    }
+}
+
+// テキスト系のみ圧縮する（画像やフォントは既に圧縮済み）
+fn is_compressible(mime: &mime::Mime) -> bool {
+    mime.type_() == mime::TEXT
+        || matches!(
+            mime.subtype().as_str(),
+            "javascript" | "json" | "xml" | "wasm" | "manifest+json"
+        )
+        || mime.suffix().map(|s| s == mime::XML || s == mime::JSON) == Some(true)
+}
+
+fn compress(data: &[u8], encoding: AssetEncoding) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    match encoding {
+        AssetEncoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        AssetEncoding::Brotli => {
+            let mut output = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+                encoder.write_all(data)?;
+            }
+            Ok(output)
+        }
+    }
+}
+
+fn compressed_asset(path: &str, data: &[u8], encoding: AssetEncoding) -> Option<web::Bytes> {
+    let key = (path.to_string(), encoding);
+    if let Some(cached) = COMPRESSED_ASSETS.lock().unwrap().get(&key) {
+        return Some(cached.clone());
+    }
+    let compressed = match compress(data, encoding) {
+        Ok(compressed) => web::Bytes::from(compressed),
+        Err(e) => {
+            eprintln!("[web_server] failed to compress {}: {}", path, e);
+            return None;
+        }
+    };
+    COMPRESSED_ASSETS
+        .lock()
+        .unwrap()
+        .insert(key, compressed.clone());
+    Some(compressed)
+}
+
+fn serve_embedded_file(req: &HttpRequest, path: &str) -> Result<HttpResponse, Error> {
     let path = path.trim_start_matches('/');
 
     // プレフィックス有無の両方を試す（後方互換）
@@ -93,14 +393,28 @@ fn serve_embedded_file(path: &str) -> Result<HttpResponse, Error> {
     match asset {
         Some(content) => {
             let mime = mime_guess::from_path(path).first_or_octet_stream();
-            let body = content.data.into_owned();
+            let mut builder = HttpResponse::Ok();
             // HTMLは文字化け回避のためUTF-8を明示
             if mime.type_() == mime::TEXT && mime.subtype() == mime::HTML {
-                Ok(HttpResponse::Ok()
-                    .insert_header((header::CONTENT_TYPE, "text/html; charset=utf-8"))
-                    .body(body))
+                builder.insert_header((header::CONTENT_TYPE, "text/html; charset=utf-8"));
             } else {
-                Ok(HttpResponse::Ok().content_type(mime.to_string()).body(body))
+                builder.content_type(mime.to_string());
+            }
+
+            if !is_compressible(&mime) || content.data.len() < MIN_COMPRESS_BYTES {
+                return Ok(builder.body(content.data.into_owned()));
+            }
+
+            // 圧縮の有無で内容が変わるため、キャッシュ向けにVaryを付ける
+            builder.insert_header((header::VARY, "Accept-Encoding"));
+            let compressed = preferred_encoding(req).and_then(|encoding| {
+                compressed_asset(path, &content.data, encoding).map(|data| (encoding, data))
+            });
+            match compressed {
+                Some((encoding, data)) if data.len() < content.data.len() => Ok(builder
+                    .insert_header((header::CONTENT_ENCODING, encoding.as_str()))
+                    .body(data)),
+                _ => Ok(builder.body(content.data.into_owned())),
             }
         }
         None => Ok(HttpResponse::NotFound()
@@ -134,21 +448,8 @@ async fn serve_image_by_id(
         return Ok(HttpResponse::NotFound().body("画像が見つかりません"));
     };
 
-    // ファイルパスを決定
-    let file_path: PathBuf = if let Some(fp) = meta.file_path.clone() {
-        PathBuf::from(fp)
-    } else {
-        // 互換のため保存先とタイプから推測
-        let base = PathBuf::from(meta.storage_location.clone());
-        let subdir = match meta.image_type.as_str() {
-            "processed" => Path::new("images").join("processed"),
-            "original" => Path::new("images").join("originals"),
-            "background" => Path::new("images").join("backgrounds"),
-            "bgm" | "sound_effect" | "soundEffect" => Path::new("audio").to_path_buf(),
-            _ => Path::new("images").join("processed"),
-        };
-        base.join(subdir).join(meta.saved_file_name.clone())
-    };
+    // ファイルパスを決定（互換のため保存先とタイプから推測する場合あり）
+    let file_path = meta.resolve_file_path();
 
     // 読み込み
     let bytes = match std::fs::read(&file_path) {
@@ -163,6 +464,186 @@ async fn serve_image_by_id(
         .body(bytes))
 }
 
+// 書き出し済みの共有用GIF（署名付きトークンのみ受け付け、書き出していない作品は配信しない）
+async fn serve_character_gif(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let token = path.into_inner();
+    let Some(image_id) = crate::character_gif::verify_share_token(&data.app_handle, &token) else {
+        return Ok(HttpResponse::NotFound().body("GIFが見つかりません"));
+    };
+    let exports_dir = crate::frame_capture::exports_dir(&data.app_handle)
+        .map_err(actix_web::error::ErrorServiceUnavailable)?;
+    let file_path = crate::character_gif::gif_path(&exports_dir, &image_id);
+    let bytes = match std::fs::read(&file_path) {
+        Ok(b) => b,
+        Err(_) => return Ok(HttpResponse::NotFound().body("GIFが見つかりません")),
+    };
+    Ok(HttpResponse::Ok().content_type("image/gif").body(bytes))
+}
+
+// 共有リンクからの作品のダウンロード（期限切れ・取り消し済みは 410）
+async fn serve_share_link(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    use crate::share_links::ShareLinkError;
+
+    let token = path.into_inner();
+    let link = match crate::share_links::redeem(&data.app_handle, &token) {
+        Ok(link) => link,
+        Err(ShareLinkError::Invalid) => {
+            return Ok(HttpResponse::NotFound().body("リンクが見つかりません"))
+        }
+        Err(ShareLinkError::Expired) => {
+            return Ok(HttpResponse::Gone().body("このリンクは有効期限が切れています"))
+        }
+        Err(ShareLinkError::Revoked) => {
+            return Ok(HttpResponse::Gone().body("このリンクは取り消されました"))
+        }
+    };
+
+    let file_path = {
+        let state: tauri::State<WorkspaceState> = data.app_handle.state();
+        let conn = state.lock().map_err(|_| {
+            actix_web::error::ErrorInternalServerError("ワークスペース接続のロックに失敗")
+        })?;
+        let db = conn
+            .get()
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        match db
+            .get_image(&link.image_id)
+            .map_err(actix_web::error::ErrorInternalServerError)?
+        {
+            Some(meta) => meta.resolve_file_path(),
+            // 展示終了で削除された作品は持ち帰り用の控えから配信する
+            None => match db
+                .get_takeaway_by_share_link(&link.id)
+                .map_err(actix_web::error::ErrorInternalServerError)?
+            {
+                Some(takeaway) => PathBuf::from(takeaway.file_path),
+                None => return Ok(HttpResponse::Gone().body("作品が削除されています")),
+            },
+        }
+    };
+    let bytes = match std::fs::read(&file_path) {
+        Ok(b) => b,
+        Err(_) => return Ok(HttpResponse::Gone().body("作品が削除されています")),
+    };
+    let mime = mime_guess::from_path(&file_path).first_or_octet_stream();
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("png");
+    Ok(HttpResponse::Ok()
+        .content_type(mime.to_string())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"nuriemon-{}.{}\"",
+                link.id, extension
+            ),
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(bytes))
+}
+
+#[derive(Debug, Deserialize)]
+struct GalleryQuery {
+    cursor: Option<i64>,
+    limit: Option<i64>,
+}
+
+// スマホのギャラリー表示用（元ファイル名は個人名を含み得るため返さない）
+async fn list_gallery_images(
+    data: web::Data<WebServerState>,
+    query: web::Query<GalleryQuery>,
+) -> Result<HttpResponse, Error> {
+    let limit = query.limit.unwrap_or(30).clamp(1, 100);
+
+    let state: tauri::State<WorkspaceState> = data.app_handle.state();
+    let conn = state.lock().map_err(|_| {
+        actix_web::error::ErrorInternalServerError("ワークスペース接続のロックに失敗")
+    })?;
+    let db = conn
+        .get()
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+
+    let previews = db
+        .get_processed_images_preview(query.cursor, limit)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+
+    let next_cursor = previews.last().map(|p| p.cursor);
+    let has_more = previews.len() as i64 >= limit;
+    let items: Vec<serde_json::Value> = previews
+        .into_iter()
+        .map(|p| {
+            serde_json::json!({
+                "id": p.id,
+                "createdAt": p.created_at,
+                "displayStartedAt": p.display_started_at,
+                "imageUrl": format!("/image/{}", p.id),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "items": items,
+        "nextCursor": next_cursor,
+        "hasMore": has_more,
+    })))
+}
+
+async fn get_gallery_image_meta(
+    data: web::Data<WebServerState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let image_id = path.into_inner();
+
+    let state: tauri::State<WorkspaceState> = data.app_handle.state();
+    let conn = state.lock().map_err(|_| {
+        actix_web::error::ErrorInternalServerError("ワークスペース接続のロックに失敗")
+    })?;
+    let db = conn
+        .get()
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+
+    let meta = db
+        .get_image(&image_id)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?
+        .filter(|m| m.image_type == "processed" && m.is_hidden == 0);
+    let Some(meta) = meta else {
+        return Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "not_found",
+            "message": "画像が見つかりません"
+        })));
+    };
+
+    let movement = db
+        .get_movement_settings(&image_id)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?
+        .map(|m| {
+            serde_json::json!({
+                "type": m.movement_type,
+                "pattern": m.movement_pattern,
+                "speed": m.speed,
+                "size": m.size,
+            })
+        });
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "id": meta.id,
+        "createdAt": meta.created_at,
+        "displayStartedAt": meta.display_started_at,
+        "width": meta.width,
+        "height": meta.height,
+        "size": meta.size,
+        "imageUrl": format!("/image/{}", meta.id),
+        "movement": movement,
+    })))
+}
+
 async fn handle_connect(
     data: web::Data<WebServerState>,
     body: web::Json<serde_json::Value>,
@@ -180,18 +661,155 @@ async fn handle_connect(
         .ok_or_else(|| actix_web::error::ErrorBadRequest("imageIdが必要です"))?;
 
     // Tauriイベントを発行して接続を通知
-    data.app_handle
-        .emit(
-            "mobile-connected",
-            serde_json::json!({
-                "sessionId": session_id,
-                "imageId": image_id,
-            }),
-        )
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+    crate::events::emit_routed(
+        &data.app_handle,
+        "mobile-connected",
+        serde_json::json!({
+            "sessionId": session_id,
+            "imageId": image_id,
+        }),
+    )
+    .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "message": "接続されました"
     })))
 }
+
+// 受け付ける画像形式（中身から判定したものを使う）
+fn upload_mime(format: image::ImageFormat) -> Option<&'static str> {
+    match format {
+        image::ImageFormat::Png => Some("image/png"),
+        image::ImageFormat::Jpeg => Some("image/jpeg"),
+        image::ImageFormat::WebP => Some("image/webp"),
+        image::ImageFormat::Gif => Some("image/gif"),
+        image::ImageFormat::Bmp => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+// スマホで描いた画像の投稿（multipartの "image" フィールド）
+async fn handle_upload(
+    data: web::Data<WebServerState>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, Error> {
+    if !crate::feature_flags::is_enabled(crate::feature_flags::UPLOAD_ENDPOINT) {
+        return Ok(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "feature_disabled",
+            "message": "この会場ではスマホからの投稿を受け付けていません",
+        })));
+    }
+    if crate::maintenance::is_enabled() {
+        return Ok(HttpResponse::ServiceUnavailable().json(crate::maintenance::rejection_body()));
+    }
+
+    let mut upload: Option<(Vec<u8>, String)> = None;
+
+    while let Some(mut field) = payload.try_next().await? {
+        if field.name() != Some("image") {
+            continue;
+        }
+        if let Some(content_type) = field.content_type() {
+            if content_type.type_() != mime::IMAGE {
+                return Err(actix_web::error::ErrorUnsupportedMediaType(
+                    "画像ファイルを送信してください",
+                ));
+            }
+        }
+        let file_name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or("upload.png")
+            .to_string();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if bytes.len() + chunk.len() > MAX_UPLOAD_BYTES {
+                return Err(actix_web::error::ErrorPayloadTooLarge(
+                    "画像サイズが上限を超えています",
+                ));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        upload = Some((bytes, file_name));
+        break;
+    }
+
+    let Some((bytes, file_name)) = upload else {
+        return Err(actix_web::error::ErrorBadRequest(
+            "imageフィールドが必要です",
+        ));
+    };
+    let mime_type = image::guess_format(&bytes)
+        .ok()
+        .and_then(upload_mime)
+        .ok_or_else(|| actix_web::error::ErrorUnsupportedMediaType("対応していない画像形式です"))?;
+
+    println!(
+        "[web_server] POST /api/upload name={} size={}",
+        file_name,
+        bytes.len()
+    );
+
+    let workspace_path = {
+        let state: tauri::State<WorkspaceState> = data.app_handle.state();
+        let conn = state.lock().map_err(|_| {
+            actix_web::error::ErrorInternalServerError("ワークスペース接続のロックに失敗")
+        })?;
+        conn.workspace_root()
+            .ok_or_else(|| {
+                actix_web::error::ErrorServiceUnavailable("ワークスペースが選択されていません")
+            })?
+            .to_string_lossy()
+            .to_string()
+    };
+
+    // 背景除去は時間がかかるためブロッキングスレッドで実行
+    let app_handle = data.app_handle.clone();
+    let image_id = uuid::Uuid::new_v4().to_string();
+    let animation = crate::file_watcher::generate_random_animation();
+    let result = {
+        let image_id = image_id.clone();
+        let animation = animation.clone();
+        let file_name = file_name.clone();
+        web::block(move || {
+            crate::file_watcher::import_image_data(
+                &app_handle,
+                &bytes,
+                mime_type,
+                file_name,
+                &image_id,
+                &workspace_path,
+                &animation,
+            )
+        })
+        .await?
+    };
+
+    let processed_path = match result {
+        Ok(path) => path,
+        Err(e) => {
+            crate::heartbeat::record_error(format!("upload: {}", e));
+            return Err(actix_web::error::ErrorInternalServerError(e));
+        }
+    };
+
+    // デスクトップへ通知
+    let _ = crate::events::emit_routed(
+        &data.app_handle,
+        "upload-received",
+        serde_json::json!({
+            "imageId": image_id,
+            "originalFileName": file_name,
+            "processedPath": processed_path,
+            "animationSettings": animation,
+        }),
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "imageId": image_id,
+    })))
+}
@@ -1,10 +1,703 @@
-use crate::server_state::ServerState;
+use crate::rate_limit::{self, TokenBucket};
+use crate::server_state::{MobileSender, ServerState, WsMetrics, WsMetricsSnapshot};
 use crate::web_server::WebServerState;
+use crate::workspace::WorkspaceState;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use futures_util::StreamExt;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use tauri::{Emitter, Manager};
+use tauri::Manager;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// 接続中のWebSocketクライアント（診断用）
+struct WsConnection {
+    peer: Option<String>,
+    connected_at: Instant,
+    session_id: Option<String>,
+    image_id: Option<String>,
+    // デスクトップからのプッシュ用
+    sender: MobileSender,
+    // 傾き操作の平滑化状態
+    tilt: Option<TiltState>,
+    // 形式が不正だったメッセージの数
+    invalid_messages: u32,
+    // join/connect でバイナリ形式を取り決めたか
+    binary: bool,
+    // ping/pongの往復時間
+    latency: LatencyStats,
+    // スタッフが操作を取り消したときに受信ループを終わらせる
+    kick: std::sync::Arc<tokio::sync::Notify>,
+}
+
+// 遅延の集計に使う直近のサンプル数
+const LATENCY_SAMPLES: usize = 60;
+// ヒストグラムの区切り（ミリ秒、最後は上限なし）
+const LATENCY_BUCKETS_MS: [u32; 5] = [25, 50, 100, 200, 500];
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct LatencyStats {
+    // 応答待ちのping（連番, 送信時刻）
+    pending: Option<(u64, Instant)>,
+    next_seq: u64,
+    samples: std::collections::VecDeque<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    // このミリ秒未満（Noneは上限なし）
+    pub lt_ms: Option<u32>,
+    pub count: usize,
+}
+
+/// 接続ごとの遅延（"controller-latency" と get_controller_latency_stats）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerLatency {
+    pub conn_id: u64,
+    pub session_id: Option<String>,
+    pub image_id: Option<String>,
+    pub samples: usize,
+    pub last_ms: Option<u32>,
+    pub p50_ms: Option<u32>,
+    pub p95_ms: Option<u32>,
+    pub max_ms: Option<u32>,
+    pub histogram: Vec<LatencyBucket>,
+}
+
+impl LatencyStats {
+    fn percentile(sorted: &[u32], ratio: f64) -> Option<u32> {
+        if sorted.is_empty() {
+            return None;
+        }
+        let index = ((sorted.len() - 1) as f64 * ratio).round() as usize;
+        sorted.get(index).copied()
+    }
+
+    fn summary(
+        &self,
+    ) -> (
+        Option<u32>,
+        Option<u32>,
+        Option<u32>,
+        Option<u32>,
+        Vec<LatencyBucket>,
+    ) {
+        let mut sorted: Vec<u32> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let mut histogram: Vec<LatencyBucket> = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|lt| LatencyBucket {
+                lt_ms: Some(*lt),
+                count: 0,
+            })
+            .collect();
+        histogram.push(LatencyBucket {
+            lt_ms: None,
+            count: 0,
+        });
+        for sample in &sorted {
+            let index = LATENCY_BUCKETS_MS
+                .iter()
+                .position(|lt| sample < lt)
+                .unwrap_or(LATENCY_BUCKETS_MS.len());
+            histogram[index].count += 1;
+        }
+        (
+            self.samples.back().copied(),
+            Self::percentile(&sorted, 0.5),
+            Self::percentile(&sorted, 0.95),
+            sorted.last().copied(),
+            histogram,
+        )
+    }
+}
+
+// pingを送る直前に呼び、ペイロード（連番）を返す
+fn begin_ping(conn_id: u64) -> [u8; 8] {
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let Some(conn) = connections.get_mut(&conn_id) else {
+        return [0; 8];
+    };
+    let stats = &mut conn.latency;
+    stats.next_seq += 1;
+    stats.pending = Some((stats.next_seq, Instant::now()));
+    stats.next_seq.to_le_bytes()
+}
+
+// pongの連番が応答待ちのpingと一致すれば往復時間を記録する
+fn record_pong(conn_id: u64, payload: &[u8]) {
+    let Ok(seq) = <[u8; 8]>::try_from(payload).map(u64::from_le_bytes) else {
+        return;
+    };
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let Some(conn) = connections.get_mut(&conn_id) else {
+        return;
+    };
+    let stats = &mut conn.latency;
+    let Some((pending_seq, sent_at)) = stats.pending else {
+        return;
+    };
+    if pending_seq != seq {
+        return;
+    }
+    stats.pending = None;
+    if stats.samples.len() >= LATENCY_SAMPLES {
+        stats.samples.pop_front();
+    }
+    stats
+        .samples
+        .push_back(sent_at.elapsed().as_millis().min(u32::MAX as u128) as u32);
+}
+
+/// 接続中のスマホごとの遅延
+pub fn latency_snapshot() -> Vec<ControllerLatency> {
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    let mut stats: Vec<ControllerLatency> = connections
+        .iter()
+        .map(|(id, conn)| {
+            let (last_ms, p50_ms, p95_ms, max_ms, histogram) = conn.latency.summary();
+            ControllerLatency {
+                conn_id: *id,
+                session_id: conn
+                    .session_id
+                    .as_deref()
+                    .map(crate::diagnostics::redact_id),
+                image_id: conn.image_id.clone(),
+                samples: conn.latency.samples.len(),
+                last_ms,
+                p50_ms,
+                p95_ms,
+                max_ms,
+                histogram,
+            }
+        })
+        .collect();
+    stats.sort_by_key(|s| s.conn_id);
+    stats
+}
+
+const METRICS_REPORT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 現在の接続状況（管理APIの /status でも使う）
+pub fn ws_metrics(app_handle: &tauri::AppHandle) -> WsMetricsSnapshot {
+    let active = WS_CONNECTIONS.lock().unwrap().len();
+    app_handle
+        .state::<ServerState>()
+        .ws_metrics_snapshot(active)
+}
+
+/// 管理画面向けに接続状況を定期的に通知する（接続がなくなった直後の1回までは送る）
+pub fn start_metrics_reporter(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let mut was_active = false;
+        loop {
+            std::thread::sleep(METRICS_REPORT_INTERVAL);
+            app_handle.state::<ServerState>().ws_metrics.sample_rate();
+            let snapshot = ws_metrics(&app_handle);
+            let active = snapshot.active_connections > 0;
+            if active || was_active {
+                let _ = crate::events::emit_routed(&app_handle, "ws-metrics", snapshot);
+            }
+            was_active = active;
+        }
+    });
+}
+
+/// 画面上のデバッグ表示向けに定期的に遅延を通知する（接続がないときは送らない）
+pub fn start_latency_reporter(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(LATENCY_REPORT_INTERVAL);
+        let stats = latency_snapshot();
+        if stats.is_empty() {
+            continue;
+        }
+        let _ = crate::events::emit_routed(&app_handle, "controller-latency", stats);
+    });
+}
+
+static WS_CONNECTIONS: Lazy<Mutex<HashMap<u64, WsConnection>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_WS_ID: AtomicU64 = AtomicU64::new(1);
+
+// 1つのキャラクターを同時に操作できる人数の方針（app_settings）
+const CONTROLLER_POLICY_KEY: &str = "controller_policy";
+const MAX_COOP_CONTROLLERS: u32 = 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerMode {
+    // 先に接続した1人だけが操作できる
+    Exclusive,
+    // max_controllers 人まで一緒に操作できる
+    Coop,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerPolicy {
+    pub mode: ControllerMode,
+    pub max_controllers: u32,
+}
+
+impl Default for ControllerPolicy {
+    fn default() -> Self {
+        Self {
+            mode: ControllerMode::Exclusive,
+            max_controllers: 2,
+        }
+    }
+}
+
+impl ControllerPolicy {
+    fn limit(&self) -> usize {
+        match self.mode {
+            ControllerMode::Exclusive => 1,
+            ControllerMode::Coop => self.max_controllers.clamp(1, MAX_COOP_CONTROLLERS) as usize,
+        }
+    }
+}
+
+fn controller_policy(app_handle: &tauri::AppHandle) -> ControllerPolicy {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(CONTROLLER_POLICY_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<ControllerPolicy>(&value).ok())
+        .unwrap_or_default()
+}
+
+// スマホの傾きで操作する設定（app_settings）
+const TILT_SETTINGS_KEY: &str = "tilt_control";
+// 設定の再読み込み間隔（傾きは高頻度で届くため毎回DBを読まない）
+const TILT_SETTINGS_TTL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct TiltSettings {
+    pub enabled: bool,
+    // 指数移動平均の係数（小さいほどなめらかだが反応が遅い）
+    pub smoothing: f64,
+    // アニメーションへ送る最大頻度
+    pub max_rate_hz: u32,
+    // これより小さい傾き（度）は無視する
+    pub dead_zone: f64,
+    // この傾き（度）で最大速度になる
+    pub full_tilt: f64,
+    // 手に持ったときの前後の傾き（度）。ここを基準に上下へ動かす
+    pub neutral_beta: f64,
+}
+
+impl Default for TiltSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            smoothing: 0.3,
+            max_rate_hz: 20,
+            dead_zone: 6.0,
+            full_tilt: 30.0,
+            neutral_beta: 40.0,
+        }
+    }
+}
+
+impl TiltSettings {
+    // 傾き（度）を -1.0〜1.0 の操作量へ変換
+    fn axis(&self, degrees: f64) -> f64 {
+        let magnitude = degrees.abs();
+        if magnitude < self.dead_zone {
+            return 0.0;
+        }
+        let ratio = ((magnitude - self.dead_zone) / (self.full_tilt - self.dead_zone)).min(1.0);
+        ratio.copysign(degrees)
+    }
+}
+
+fn validate_tilt_settings(settings: &TiltSettings) -> Result<(), String> {
+    if !(settings.smoothing > 0.0 && settings.smoothing <= 1.0) {
+        return Err("平滑化の係数は0より大きく1以下で指定してください".to_string());
+    }
+    if !(1..=60).contains(&settings.max_rate_hz) {
+        return Err("傾きの送信頻度は1〜60回/秒で指定してください".to_string());
+    }
+    if !(settings.dead_zone >= 0.0
+        && settings.dead_zone < settings.full_tilt
+        && settings.full_tilt <= 90.0)
+    {
+        return Err("傾きの範囲は 0 <= 遊び < 最大 <= 90 度で指定してください".to_string());
+    }
+    if !(-90.0..=90.0).contains(&settings.neutral_beta) {
+        return Err("基準の傾きは-90〜90度で指定してください".to_string());
+    }
+    Ok(())
+}
+
+static TILT_SETTINGS: Mutex<Option<(Instant, TiltSettings)>> = Mutex::new(None);
+
+fn read_tilt_settings(app_handle: &tauri::AppHandle) -> TiltSettings {
+    let stored = app_handle.try_state::<WorkspaceState>().and_then(|state| {
+        let conn = state.lock().ok()?;
+        let db = conn.get().ok()?;
+        db.get_app_setting(TILT_SETTINGS_KEY).ok().flatten()
+    });
+    stored
+        .and_then(|value| serde_json::from_str::<TiltSettings>(&value).ok())
+        .filter(|settings| validate_tilt_settings(settings).is_ok())
+        .unwrap_or_default()
+}
+
+fn tilt_settings(app_handle: &tauri::AppHandle) -> TiltSettings {
+    if let Some((loaded_at, settings)) = *TILT_SETTINGS.lock().unwrap() {
+        if loaded_at.elapsed() < TILT_SETTINGS_TTL {
+            return settings;
+        }
+    }
+    let settings = read_tilt_settings(app_handle);
+    *TILT_SETTINGS.lock().unwrap() = Some((Instant::now(), settings));
+    settings
+}
+
+// 接続ごとの傾きの平滑化状態
+struct TiltState {
+    alpha: f64,
+    beta: f64,
+    gamma: f64,
+    last_emit: Option<Instant>,
+    // 最後に送った操作量（止まったことを一度だけ送るため）
+    last_axis: (f64, f64),
+    disabled_notified: bool,
+}
+
+// 角度の差（-180〜180度、方位角の0/360度またぎ用）
+fn angle_delta(from: f64, to: f64) -> f64 {
+    (to - from + 540.0).rem_euclid(360.0) - 180.0
+}
+
+// 傾きを平滑化し、送る時刻になっていれば (平滑化後の角度, 操作量) を返す
+fn smooth_tilt(
+    conn_id: u64,
+    settings: &TiltSettings,
+    (alpha, beta, gamma): (f64, f64, f64),
+) -> Option<((f64, f64, f64), (f64, f64))> {
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let conn = connections.get_mut(&conn_id)?;
+    let state = conn.tilt.get_or_insert(TiltState {
+        alpha,
+        beta,
+        gamma,
+        last_emit: None,
+        last_axis: (0.0, 0.0),
+        disabled_notified: false,
+    });
+    let k = settings.smoothing;
+    state.alpha = (state.alpha + angle_delta(state.alpha, alpha) * k).rem_euclid(360.0);
+    state.beta += (beta - state.beta) * k;
+    state.gamma += (gamma - state.gamma) * k;
+
+    let interval = Duration::from_secs_f64(1.0 / settings.max_rate_hz as f64);
+    if state
+        .last_emit
+        .is_some_and(|last| last.elapsed() < interval)
+    {
+        return None;
+    }
+    let axis = (
+        settings.axis(state.gamma),
+        settings.axis(state.beta - settings.neutral_beta),
+    );
+    // 止まったままなら送らない
+    if axis == (0.0, 0.0) && state.last_axis == (0.0, 0.0) && state.last_emit.is_some() {
+        return None;
+    }
+    state.last_emit = Some(Instant::now());
+    state.last_axis = axis;
+    Some(((state.alpha, state.beta, state.gamma), axis))
+}
+
+// 傾き操作が無効なことを接続ごとに一度だけ知らせる
+fn take_tilt_disabled_notice(conn_id: u64) -> bool {
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let Some(conn) = connections.get_mut(&conn_id) else {
+        return false;
+    };
+    let state = conn.tilt.get_or_insert(TiltState {
+        alpha: 0.0,
+        beta: 0.0,
+        gamma: 0.0,
+        last_emit: None,
+        last_axis: (0.0, 0.0),
+        disabled_notified: false,
+    });
+    !std::mem::replace(&mut state.disabled_notified, true)
+}
+
+async fn handle_tilt(
+    app_handle: &tauri::AppHandle,
+    conn_id: u64,
+    payload: &serde_json::Value,
+    session: &mut WsReply<'_>,
+) {
+    // 一時停止中は黙って捨てる（高頻度のため拒否応答は返さない）
+    if crate::maintenance::is_enabled() {
+        return;
+    }
+    // 操作の対象はセッションに紐付いた画像に限る（ペイロードの imageId は信用しない）
+    let Some(image_id) = bound_image_id(conn_id) else {
+        return;
+    };
+    let settings = tilt_settings(app_handle);
+    if !settings.enabled {
+        if take_tilt_disabled_notice(conn_id) {
+            let _ = session
+                .text(
+                    serde_json::json!({
+                        "type": "error",
+                        "error": "tilt_disabled",
+                        "message": "傾き操作はオフになっています。ボタンで操作してね",
+                    })
+                    .to_string(),
+                )
+                .await;
+        }
+        return;
+    }
+
+    let angle = |name: &str, range: f64| {
+        payload
+            .get(name)
+            .and_then(|v| v.as_f64())
+            .filter(|v| v.is_finite())
+            .map(|v| v.clamp(-range, range))
+    };
+    let (Some(beta), Some(gamma)) = (angle("beta", 180.0), angle("gamma", 90.0)) else {
+        return;
+    };
+    let alpha = angle("alpha", 360.0).unwrap_or(0.0).rem_euclid(360.0);
+
+    if let Some(((alpha, beta, gamma), (axis_x, axis_y))) =
+        smooth_tilt(conn_id, &settings, (alpha, beta, gamma))
+    {
+        let _ = crate::events::emit_routed(
+            app_handle,
+            "mobile-control",
+            serde_json::json!({
+                "type": "tilt",
+                "alpha": alpha,
+                "beta": beta,
+                "gamma": gamma,
+                "axisX": axis_x,
+                "axisY": axis_y,
+                "imageId": image_id,
+            }),
+        );
+    }
+}
+
+// 同じ端末（IPが同じ）からの再接続かどうか
+fn peer_host(peer: &Option<String>) -> Option<&str> {
+    let peer = peer.as_deref()?;
+    Some(peer.rsplit_once(':').map(|(host, _)| host).unwrap_or(peer))
+}
+
+// 操作の受け付けを断ったときのエラー（スマホに表示する）
+fn controller_rejection(policy: &ControllerPolicy) -> serde_json::Value {
+    match policy.mode {
+        ControllerMode::Exclusive => serde_json::json!({
+            "type": "error",
+            "error": "controller_busy",
+            "message": "このキャラクターはほかの人が操作中です。終わるまで待ってね",
+        }),
+        ControllerMode::Coop => serde_json::json!({
+            "type": "error",
+            "error": "controller_full",
+            "message": format!(
+                "このキャラクターは{}人まで一緒に操作できます。いまは満員です",
+                policy.limit()
+            ),
+        }),
+    }
+}
+
+// 接続をセッション/画像に紐付ける（操作人数の上限を超える場合はErr）
+// 同じ端末からの再接続は古い接続から操作を引き継ぐ
+fn bind_session(
+    state: &ServerState,
+    policy: &ControllerPolicy,
+    conn_id: u64,
+    session_id: &str,
+    image_id: &str,
+) -> Result<(), serde_json::Value> {
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let host = connections
+        .get(&conn_id)
+        .and_then(|conn| peer_host(&conn.peer).map(str::to_string));
+
+    let others: Vec<u64> = connections
+        .iter()
+        .filter(|(id, conn)| **id != conn_id && conn.image_id.as_deref() == Some(image_id))
+        .map(|(id, _)| *id)
+        .collect();
+    let (same_device, different_device): (Vec<u64>, Vec<u64>) =
+        others.into_iter().partition(|id| {
+            host.is_some()
+                && connections.get(id).and_then(|conn| peer_host(&conn.peer)) == host.as_deref()
+        });
+    if different_device.len() + 1 > policy.limit() {
+        return Err(controller_rejection(policy));
+    }
+
+    for id in same_device {
+        if let Some(old) = connections.get_mut(&id) {
+            old.session_id = None;
+            old.image_id = None;
+            let _ = old.sender.send(
+                serde_json::json!({
+                    "type": "error",
+                    "error": "controller_replaced",
+                    "message": "別の画面で操作を再開しました",
+                })
+                .to_string(),
+            );
+        }
+        state.unregister_mobile_connection(id);
+    }
+
+    if let Some(conn) = connections.get_mut(&conn_id) {
+        conn.session_id = Some(session_id.to_string());
+        conn.image_id = Some(image_id.to_string());
+        state.register_mobile_session(session_id, conn_id, conn.sender.clone());
+        WsMetrics::add(&state.ws_metrics.total_joins);
+    }
+    Ok(())
+}
+
+// 再接続トークンで復帰するとき、同じセッションに残っている古い接続を外す
+// （Wi-Fiが切れた側の接続はタイムアウトまで残っていることがある）
+fn release_session(state: &ServerState, conn_id: u64, session_id: &str) {
+    let mut connections = WS_CONNECTIONS.lock().unwrap();
+    let stale: Vec<u64> = connections
+        .iter()
+        .filter(|(id, conn)| **id != conn_id && conn.session_id.as_deref() == Some(session_id))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in stale {
+        if let Some(old) = connections.get_mut(&id) {
+            old.session_id = None;
+            old.image_id = None;
+        }
+        state.unregister_mobile_connection(id);
+    }
+}
+
+fn connection_host(conn_id: u64) -> Option<String> {
+    WS_CONNECTIONS
+        .lock()
+        .unwrap()
+        .get(&conn_id)
+        .and_then(|conn| peer_host(&conn.peer).map(str::to_string))
+}
+
+// セッションへの参加をQRセッションとアクセスログに残す
+fn record_join(qr_manager: &crate::qr_manager::QrManager, conn_id: u64, session_id: &str) {
+    let host = connection_host(conn_id);
+    qr_manager.record_join(session_id, host.as_deref());
+    crate::access_log::record_session_join(host, session_id);
+}
+
+fn bound_image_id(conn_id: u64) -> Option<String> {
+    WS_CONNECTIONS
+        .lock()
+        .unwrap()
+        .get(&conn_id)
+        .and_then(|conn| conn.image_id.clone())
+}
+
+/// 接続中のすべてのスマホへ同じメッセージを送り、送信できた接続数を返す
+pub fn broadcast(payload: &serde_json::Value) -> usize {
+    let text = payload.to_string();
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    connections
+        .values()
+        .filter(|conn| conn.sender.send(text.clone()).is_ok())
+        .count()
+}
+
+/// 指定した画像を操作中のスマホへ送り、送信できた接続数を返す
+pub fn send_to_image(image_id: &str, payload: &serde_json::Value) -> usize {
+    let text = payload.to_string();
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    connections
+        .values()
+        .filter(|conn| conn.image_id.as_deref() == Some(image_id))
+        .filter(|conn| conn.sender.send(text.clone()).is_ok())
+        .count()
+}
+
+/// セッションに紐付くスマホの接続元（sessionId → IPアドレス）
+pub fn session_peers() -> HashMap<String, String> {
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    connections
+        .values()
+        .filter_map(|conn| {
+            let session_id = conn.session_id.clone()?;
+            let host = peer_host(&conn.peer)?;
+            Some((session_id, host.to_string()))
+        })
+        .collect()
+}
+
+/// セッションの接続を強制的に閉じ、閉じた接続数を返す
+pub fn kick_session(session_id: &str) -> usize {
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    let mut closed = 0;
+    for conn in connections.values() {
+        if conn.session_id.as_deref() == Some(session_id) {
+            conn.kick.notify_one();
+            closed += 1;
+        }
+    }
+    closed
+}
+
+/// 受信ループが終了したのに残っている接続を片付け、片付けた数を返す
+pub fn reap_dead_connections(app_handle: &tauri::AppHandle) -> usize {
+    let dead: Vec<u64> = WS_CONNECTIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, conn)| conn.sender.is_closed())
+        .map(|(id, _)| *id)
+        .collect();
+    for conn_id in &dead {
+        close_connection(app_handle, *conn_id, "closed");
+    }
+    dead.len()
+}
+
+/// 診断用の接続一覧（セッションIDは先頭のみ）
+pub fn connections_snapshot() -> Vec<serde_json::Value> {
+    let connections = WS_CONNECTIONS.lock().unwrap();
+    connections
+        .iter()
+        .map(|(id, conn)| {
+            serde_json::json!({
+                "id": id,
+                "peer": conn.peer,
+                "connectedSeconds": conn.connected_at.elapsed().as_secs(),
+                "sessionId": conn.session_id.as_deref().map(crate::diagnostics::redact_id),
+                "imageId": conn.image_id,
+                "invalidMessages": conn.invalid_messages,
+            })
+        })
+        .collect()
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 struct WebSocketMessage {
@@ -17,6 +710,242 @@ struct WebSocketMessage {
     sid: Option<String>,
     #[serde(default, rename = "imageId")]
     image_id_top: Option<String>,
+    // join/connect で希望する通信形式（"binary-v1"）
+    #[serde(default)]
+    protocol: Option<String>,
+    // rejoin で送られる再接続トークン
+    #[serde(default)]
+    token: Option<String>,
+}
+
+// バイナリ形式（join/connect で protocol: "binary-v1" を取り決めた接続のみ）
+// 先頭1バイトが種類、以降はリトルエンディアンの固定長。imageId は接続に紐付いたものを使う
+//   0x01 move      [方向 u8][動作 u8]  方向: 0=left 1=right 2=up 3=down 4=all / 動作: 0=pulse 1=start 2=hold 3=stop
+//   0x02 action    [種類 u8]           0=jump 1=spin 2=shake 3=grow 4=shrink
+//   0x03 emote     [長さ u8][UTF-8]
+//   0x04 tilt      [alpha f32][beta f32][gamma f32]
+//   0x05 keepalive
+const BINARY_PROTOCOL: &str = "binary-v1";
+const BINARY_DIRECTIONS: [&str; 5] = ["left", "right", "up", "down", "all"];
+const BINARY_MOVE_ACTIONS: [&str; 4] = ["pulse", "start", "hold", "stop"];
+const BINARY_ACTIONS: [&str; 5] = ["jump", "spin", "shake", "grow", "shrink"];
+
+// バイナリフレームを同じ処理に流せるメッセージへ変換する
+fn decode_binary(conn_id: u64, bytes: &[u8]) -> Result<WebSocketMessage, InvalidMessage> {
+    let (negotiated, image_id) = WS_CONNECTIONS
+        .lock()
+        .unwrap()
+        .get(&conn_id)
+        .map(|conn| (conn.binary, conn.image_id.clone()))
+        .unwrap_or((false, None));
+    if !negotiated {
+        return Err(InvalidMessage::new(
+            "binary_not_negotiated",
+            "バイナリ形式は join で protocol を指定してから使ってください",
+        ));
+    }
+
+    let invalid = |what: &str| {
+        InvalidMessage::new(
+            "invalid_binary",
+            format!("{} のバイナリ形式が不正です（{}バイト）", what, bytes.len()),
+        )
+    };
+    let lookup = |index: usize, table: &[&'static str]| {
+        bytes
+            .get(index)
+            .and_then(|b| table.get(*b as usize))
+            .copied()
+    };
+    let f32_at = |index: usize| {
+        bytes
+            .get(index..index + 4)
+            .and_then(|b| <[u8; 4]>::try_from(b).ok())
+            .map(|b| f32::from_le_bytes(b) as f64)
+    };
+
+    let (msg_type, mut payload) = match bytes.first() {
+        Some(0x01) => {
+            let direction = lookup(1, &BINARY_DIRECTIONS).ok_or_else(|| invalid("move"))?;
+            let action = lookup(2, &BINARY_MOVE_ACTIONS).ok_or_else(|| invalid("move"))?;
+            (
+                "move",
+                serde_json::json!({ "direction": direction, "action": action }),
+            )
+        }
+        Some(0x02) => {
+            let action_type = lookup(1, &BINARY_ACTIONS).ok_or_else(|| invalid("action"))?;
+            ("action", serde_json::json!({ "actionType": action_type }))
+        }
+        Some(0x03) => {
+            let len = *bytes.get(1).ok_or_else(|| invalid("emote"))? as usize;
+            let emote_type = bytes
+                .get(2..2 + len)
+                .and_then(|b| std::str::from_utf8(b).ok())
+                .ok_or_else(|| invalid("emote"))?;
+            ("emote", serde_json::json!({ "emoteType": emote_type }))
+        }
+        Some(0x04) => {
+            let (Some(alpha), Some(beta), Some(gamma)) = (f32_at(1), f32_at(5), f32_at(9)) else {
+                return Err(invalid("tilt"));
+            };
+            (
+                "tilt",
+                serde_json::json!({ "alpha": alpha, "beta": beta, "gamma": gamma }),
+            )
+        }
+        Some(0x05) => ("keepalive", serde_json::json!({})),
+        Some(other) => {
+            return Err(InvalidMessage::new(
+                "unknown_type",
+                format!("未知のバイナリメッセージです: 0x{:02x}", other),
+            ))
+        }
+        None => return Err(invalid("空のフレーム")),
+    };
+    payload["imageId"] = serde_json::json!(image_id);
+    Ok(WebSocketMessage {
+        msg_type: msg_type.to_string(),
+        payload,
+        sid: None,
+        image_id_top: None,
+        protocol: None,
+        token: None,
+    })
+}
+
+// join/connect で希望された通信形式を接続に記録し、採用した形式を返す
+fn negotiate_protocol(conn_id: u64, msg: &WebSocketMessage) -> Option<&'static str> {
+    let requested = msg
+        .protocol
+        .as_deref()
+        .or_else(|| msg.payload.get("protocol").and_then(|v| v.as_str()));
+    let binary = requested == Some(BINARY_PROTOCOL);
+    if let Some(conn) = WS_CONNECTIONS.lock().unwrap().get_mut(&conn_id) {
+        conn.binary = binary;
+    }
+    binary.then_some(BINARY_PROTOCOL)
+}
+
+/// 不正なメッセージへの応答（コントローラー側の不具合調査用）
+#[derive(Debug)]
+struct InvalidMessage {
+    code: &'static str,
+    message: String,
+}
+
+impl InvalidMessage {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn to_frame(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "error",
+            "code": self.code,
+            "message": self.message,
+        })
+    }
+}
+
+// ペイロードの文字列フィールドを必須として確認する
+fn require_str(msg: &WebSocketMessage, key: &str) -> Result<(), InvalidMessage> {
+    if msg.payload.get(key).is_some_and(|v| v.is_string()) {
+        Ok(())
+    } else {
+        Err(InvalidMessage::new(
+            "invalid_payload",
+            format!("{} には payload.{} が必要です", msg.msg_type, key),
+        ))
+    }
+}
+
+// ペイロードの数値フィールドを必須として確認する
+fn require_number(msg: &WebSocketMessage, key: &str) -> Result<(), InvalidMessage> {
+    if msg.payload.get(key).is_some_and(|v| v.is_number()) {
+        Ok(())
+    } else {
+        Err(InvalidMessage::new(
+            "invalid_payload",
+            format!("{} には数値の payload.{} が必要です", msg.msg_type, key),
+        ))
+    }
+}
+
+// メッセージタイプごとに処理に必要な項目がそろっているか確認する
+fn validate_message(msg: &WebSocketMessage) -> Result<(), InvalidMessage> {
+    match msg.msg_type.as_str() {
+        "connect" => require_str(msg, "sessionId"),
+        "join" => {
+            let has_sid =
+                msg.sid.is_some() || msg.payload.get("sid").is_some_and(|v| v.is_string());
+            if has_sid {
+                Ok(())
+            } else {
+                Err(InvalidMessage::new(
+                    "invalid_payload",
+                    "join には sid が必要です",
+                ))
+            }
+        }
+        "rejoin" => {
+            let has_token =
+                msg.token.is_some() || msg.payload.get("token").is_some_and(|v| v.is_string());
+            if has_token {
+                Ok(())
+            } else {
+                Err(InvalidMessage::new(
+                    "invalid_payload",
+                    "rejoin には token が必要です",
+                ))
+            }
+        }
+        "cmd" => require_str(msg, "cmd"),
+        "move" => require_str(msg, "direction"),
+        "action" => require_str(msg, "actionType"),
+        "emote" => require_str(msg, "emoteType"),
+        "tilt" => require_number(msg, "beta").and_then(|_| require_number(msg, "gamma")),
+        // さらにレガシーな形式（中身は dispatch_cmd 側で解釈）
+        "evt" | "keepalive" => Ok(()),
+        other => Err(InvalidMessage::new(
+            "unknown_type",
+            format!("未知のメッセージタイプです: {}", other),
+        )),
+    }
+}
+
+// 受信したテキストを検証してメッセージに変換する
+fn parse_message(text: &str) -> Result<WebSocketMessage, InvalidMessage> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| InvalidMessage::new("invalid_json", format!("JSONとして読めません: {}", e)))?;
+    if !value.get("type").is_some_and(|v| v.is_string()) {
+        return Err(InvalidMessage::new("missing_type", "type がありません"));
+    }
+    let msg = serde_json::from_value::<WebSocketMessage>(value)
+        .map_err(|e| InvalidMessage::new("invalid_payload", e.to_string()))?;
+    validate_message(&msg)?;
+    Ok(msg)
+}
+
+// 不正なメッセージを接続ごとに数えてログに残す
+fn record_invalid(conn_id: u64, invalid: &InvalidMessage) {
+    let count = {
+        let mut connections = WS_CONNECTIONS.lock().unwrap();
+        connections.get_mut(&conn_id).map(|conn| {
+            conn.invalid_messages += 1;
+            conn.invalid_messages
+        })
+    };
+    println!(
+        "[websocket] invalid message conn={} code={} count={}: {}",
+        conn_id,
+        invalid.code,
+        count.unwrap_or(0),
+        invalid.message
+    );
 }
 
 pub async fn websocket_handler(
@@ -32,6 +961,18 @@ pub async fn websocket_handler(
         req.peer_addr()
     );
 
+    let (conn_id, mut push_rx, kicked) =
+        open_connection(req.peer_addr().map(|addr| addr.to_string()));
+    let metrics = app_handle.state::<ServerState>().ws_metrics.clone();
+    WsMetrics::add(&metrics.total_connections);
+
+    // 一時停止中に接続してきたスマホには待機メッセージを表示させる
+    if crate::maintenance::is_enabled() {
+        let _ = session
+            .text(crate::maintenance::current().to_message().to_string())
+            .await;
+    }
+
     actix_web::rt::spawn(async move {
         let mut stream = stream
             .aggregate_continuations()
@@ -39,6 +980,15 @@ pub async fn websocket_handler(
 
         let mut last_heartbeat = Instant::now();
         let heartbeat_interval = Duration::from_secs(5);
+        // 操作メッセージが続いてもpingが止まらないよう固定間隔で刻む（遅延測定を兼ねる）
+        let mut heartbeat = tokio::time::interval(heartbeat_interval);
+        heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        // セッションごとのメッセージ数制限（超過が続く場合は切断）
+        let mut message_bucket = TokenBucket::new(rate_limit::current().ws_burst);
+        let mut rejected_in_row: u32 = 0;
+        // 切断理由（mobile-disconnected で通知）
+        let mut close_reason = "closed";
 
         loop {
             tokio::select! {
@@ -46,13 +996,53 @@ pub async fn websocket_handler(
                     // Log approximate size/type to debug
                     // Note: avoid dumping large payloads in production
                     match msg {
-                        Ok(actix_ws::AggregatedMessage::Text(text)) => {
-                            println!("[websocket] Received text: {}", text);
+                        Ok(frame @ (actix_ws::AggregatedMessage::Text(_) | actix_ws::AggregatedMessage::Binary(_))) => {
                             last_heartbeat = Instant::now();
+                            WsMetrics::add(&metrics.messages_received);
+                            let parsed = match &frame {
+                                actix_ws::AggregatedMessage::Text(text) => parse_message(text),
+                                actix_ws::AggregatedMessage::Binary(bytes) => decode_binary(conn_id, bytes),
+                                _ => continue,
+                            };
+                            // 傾きは端末から高頻度で届くためログには出さない（メッセージ数制限は他の操作と同じ）
+                            let is_tilt = matches!(&parsed, Ok(m) if m.msg_type == "tilt");
+                            if let (false, actix_ws::AggregatedMessage::Text(text)) = (is_tilt, &frame) {
+                                println!("[websocket] Received text: {}", text);
+                            }
+
+                            let limits = rate_limit::current();
+                            let taken = message_bucket
+                                .try_take(limits.ws_messages_per_second as f64, limits.ws_burst);
+                            if let Err(retry_after) = taken {
+                                WsMetrics::add(&metrics.dropped_frames);
+                                rejected_in_row += 1;
+                                if rejected_in_row > limits.ws_burst {
+                                    println!("[websocket] メッセージ過多のため切断します: conn={}", conn_id);
+                                    close_reason = "rate_limited";
+                                    let _ = session
+                                        .close(Some(actix_ws::CloseReason {
+                                            code: actix_ws::CloseCode::Policy,
+                                            description: Some("rate_limited".to_string()),
+                                        }))
+                                        .await;
+                                    break;
+                                }
+                                let _ = session
+                                    .text(rate_limit::rate_limited_body(retry_after).to_string())
+                                    .await;
+                                continue;
+                            }
+                            rejected_in_row = 0;
 
-                            // メッセージをパース
-                            if let Ok(ws_msg) = serde_json::from_str::<WebSocketMessage>(&text) {
-                                handle_websocket_message(&app_handle, ws_msg, &mut session).await;
+                            match parsed {
+                                Ok(ws_msg) => {
+                                    handle_websocket_message(&app_handle, conn_id, ws_msg, &mut WsReply::Socket(&mut session)).await;
+                                }
+                                Err(invalid) => {
+                                    WsMetrics::add(&metrics.dropped_frames);
+                                    record_invalid(conn_id, &invalid);
+                                    let _ = session.text(invalid.to_frame().to_string()).await;
+                                }
                             }
                         }
                         Ok(actix_ws::AggregatedMessage::Ping(bytes)) => {
@@ -62,9 +1052,10 @@ pub async fn websocket_handler(
                                 break;
                             }
                         }
-                        Ok(actix_ws::AggregatedMessage::Pong(_)) => {
+                        Ok(actix_ws::AggregatedMessage::Pong(bytes)) => {
                             // debug: suppress noisy pong logs
                             last_heartbeat = Instant::now();
+                            record_pong(conn_id, &bytes);
                         }
                         Ok(actix_ws::AggregatedMessage::Close(reason)) => {
                             println!("[websocket] Close: {:?}", reason);
@@ -74,28 +1065,230 @@ pub async fn websocket_handler(
                         _ => {}
                     }
                 }
-                _ = tokio::time::sleep(heartbeat_interval) => {
+                Some(text) = push_rx.recv() => {
+                    // send_to_mobile からのプッシュ
+                    if session.text(text).await.is_err() {
+                        break;
+                    }
+                }
+                _ = kicked.notified() => {
+                    println!("[websocket] session revoked by staff: conn={}", conn_id);
+                    close_reason = "revoked";
+                    let _ = session
+                        .text(
+                            serde_json::json!({
+                                "type": "error",
+                                "error": "session_revoked",
+                                "message": "スタッフにより操作が終了されました",
+                            })
+                            .to_string(),
+                        )
+                        .await;
+                    let _ = session
+                        .close(Some(actix_ws::CloseReason {
+                            code: actix_ws::CloseCode::Policy,
+                            description: Some("revoked".to_string()),
+                        }))
+                        .await;
+                    break;
+                }
+                _ = heartbeat.tick() => {
                     if Instant::now().duration_since(last_heartbeat) > heartbeat_interval * 2 {
                         println!("WebSocketクライアントがタイムアウトしました");
+                        close_reason = "timeout";
                         break;
                     }
 
-                    if session.ping(b"ping").await.is_err() {
+                    if session.ping(&begin_ping(conn_id)).await.is_err() {
                         break;
                     }
                 }
             }
         }
+
+        close_connection(&app_handle, conn_id, close_reason);
     });
 
     Ok(res)
 }
 
+// 接続を登録し、プッシュ受信用のチャネルを返す
+fn open_connection(
+    peer: Option<String>,
+) -> (
+    u64,
+    UnboundedReceiver<String>,
+    std::sync::Arc<tokio::sync::Notify>,
+) {
+    let conn_id = NEXT_WS_ID.fetch_add(1, Ordering::SeqCst);
+    let (push_tx, push_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let kick = std::sync::Arc::new(tokio::sync::Notify::new());
+    WS_CONNECTIONS.lock().unwrap().insert(
+        conn_id,
+        WsConnection {
+            peer,
+            connected_at: Instant::now(),
+            session_id: None,
+            image_id: None,
+            sender: push_tx,
+            tilt: None,
+            invalid_messages: 0,
+            binary: false,
+            latency: LatencyStats::default(),
+            kick: kick.clone(),
+        },
+    );
+    (conn_id, push_rx, kick)
+}
+
+// 接続を破棄し、紐付いていたセッションの切断を通知する
+fn close_connection(app_handle: &tauri::AppHandle, conn_id: u64, reason: &str) {
+    let (image_id, tilting) = WS_CONNECTIONS
+        .lock()
+        .unwrap()
+        .remove(&conn_id)
+        .map(|conn| {
+            if conn.invalid_messages > 0 {
+                println!(
+                    "[websocket] conn={} closed with {} invalid message(s)",
+                    conn_id, conn.invalid_messages
+                );
+            }
+            let tilting = conn.tilt.is_some_and(|tilt| tilt.last_axis != (0.0, 0.0));
+            (conn.image_id, tilting)
+        })
+        .unwrap_or((None, false));
+    // 傾けたまま切断されたらキャラクターを止める
+    if tilting {
+        if let Some(image_id) = &image_id {
+            let _ = crate::events::emit_routed(
+                app_handle,
+                "mobile-control",
+                serde_json::json!({
+                    "type": "tilt",
+                    "axisX": 0.0,
+                    "axisY": 0.0,
+                    "imageId": image_id,
+                }),
+            );
+        }
+    }
+    let state: tauri::State<ServerState> = app_handle.state();
+    // 同じセッションで再接続済みの場合は通知しない
+    for session_id in state.unregister_mobile_connection(conn_id) {
+        if let Some(qr_manager) = state.get_qr_manager() {
+            qr_manager.mark_disconnected(&session_id);
+        }
+        let _ = crate::events::emit_routed(
+            app_handle,
+            "mobile-disconnected",
+            serde_json::json!({
+                "sessionId": session_id,
+                "imageId": image_id,
+                "reason": reason,
+            }),
+        );
+    }
+}
+
+// メッセージ処理の返信先（疑似コントローラーでは返信を記録する）
+enum WsReply<'a> {
+    Socket(&'a mut actix_ws::Session),
+    #[cfg_attr(not(feature = "test-harness"), allow(dead_code))]
+    Recorded(&'a mut Vec<String>),
+}
+
+impl WsReply<'_> {
+    async fn text(&mut self, text: String) -> Result<(), actix_ws::Closed> {
+        match self {
+            Self::Socket(session) => session.text(text).await,
+            Self::Recorded(replies) => {
+                replies.push(text);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 実機のスマホなしでコントローラーのメッセージを処理させる（E2Eテスト用）
+/// 返信とプッシュされたメッセージを順に返す
+#[cfg(feature = "test-harness")]
+pub async fn drive_fake_controller(
+    app_handle: &tauri::AppHandle,
+    messages: Vec<serde_json::Value>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let (conn_id, mut push_rx, _) = open_connection(Some("test-harness".to_string()));
+    let mut replies = Vec::new();
+    for message in messages {
+        // 実機と同じ検証を通す（不正な場合はエラー応答が返信に入る）
+        match parse_message(&message.to_string()) {
+            Ok(ws_msg) => {
+                handle_websocket_message(
+                    app_handle,
+                    conn_id,
+                    ws_msg,
+                    &mut WsReply::Recorded(&mut replies),
+                )
+                .await
+            }
+            Err(invalid) => {
+                record_invalid(conn_id, &invalid);
+                replies.push(invalid.to_frame().to_string());
+            }
+        }
+        while let Ok(pushed) = push_rx.try_recv() {
+            replies.push(pushed);
+        }
+    }
+    close_connection(app_handle, conn_id, "closed");
+    Ok(replies
+        .into_iter()
+        .map(|text| serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text)))
+        .collect())
+}
+
 async fn handle_websocket_message(
     app_handle: &tauri::AppHandle,
+    conn_id: u64,
     msg: WebSocketMessage,
-    session: &mut actix_ws::Session,
+    session: &mut WsReply<'_>,
 ) {
+    if msg.msg_type == "tilt" {
+        handle_tilt(app_handle, conn_id, &msg.payload, session).await;
+        return;
+    }
+
+    let is_control = matches!(
+        msg.msg_type.as_str(),
+        "cmd" | "evt" | "move" | "action" | "emote"
+    );
+    // 一時停止中は操作を受け付けない
+    if is_control && crate::maintenance::is_enabled() {
+        let _ = session
+            .text(crate::maintenance::rejection_body().to_string())
+            .await;
+        return;
+    }
+
+    // 操作の対象はセッションに紐付いた画像に限る（ペイロードの imageId は信用しない）
+    let bound_image = if is_control {
+        bound_image_id(conn_id)
+    } else {
+        None
+    };
+    if is_control && bound_image.is_none() {
+        println!(
+            "[websocket] セッション未接続の操作を無視しました: conn={}",
+            conn_id
+        );
+        return;
+    }
+
+    // ハイライト動画用に操作回数を記録
+    if let Some(image_id) = &bound_image {
+        crate::highlights::record_control(image_id);
+    }
+
     match msg.msg_type.as_str() {
         "connect" => {
             // モバイル接続のハンドシェイク
@@ -105,52 +1298,71 @@ async fn handle_websocket_message(
                 // QrManagerでセッション検証
                 let state: tauri::State<ServerState> = app_handle.state();
                 if let Some(qr_manager) = state.get_qr_manager() {
-                    if let Some(valid_image_id) = qr_manager.validate_session(session_id) {
-                        // imageId一致チェック（提供されている場合）
-                        if let Some(img) = provided_image_id {
-                            if img != valid_image_id {
-                                let _ = session
-                                    .text(
-                                        serde_json::json!({
-                                            "type": "error",
-                                            "message": "imageId mismatch"
-                                        })
-                                        .to_string(),
-                                    )
-                                    .await;
+                    match qr_manager.validate_session(session_id) {
+                        Ok(valid_image_id) => {
+                            // imageId一致チェック（提供されている場合）。拒否した接続はセッションに紐付けない
+                            if let Some(img) = provided_image_id {
+                                if img != valid_image_id {
+                                    let _ = session
+                                        .text(
+                                            serde_json::json!({
+                                                "type": "error",
+                                                "message": "imageId mismatch"
+                                            })
+                                            .to_string(),
+                                        )
+                                        .await;
+                                    return;
+                                }
+                            }
+
+                            let policy = controller_policy(app_handle);
+                            if let Err(rejection) =
+                                bind_session(&state, &policy, conn_id, session_id, &valid_image_id)
+                            {
+                                let _ = session.text(rejection.to_string()).await;
                                 return;
                             }
-                        }
 
-                        // 接続完了通知（レガシー互換: connected）
-                        let _ = session
-                            .text(
-                                serde_json::json!({
-                                    "type": "connected",
-                                    "imageId": valid_image_id
-                                })
-                                .to_string(),
-                            )
-                            .await;
+                            record_join(&qr_manager, conn_id, session_id);
 
-                        // Tauriイベントを発火（QRウィンドウ等へ通知）
-                        let _ = app_handle.emit(
-                            "mobile-connected",
-                            serde_json::json!({
-                                "sessionId": session_id,
-                                "imageId": valid_image_id,
-                            }),
-                        );
-                    } else {
-                        let _ = session
-                            .text(
+                            // 接続完了通知（レガシー互換: connected）
+                            let protocol = negotiate_protocol(conn_id, &msg);
+                            let reconnect_token = qr_manager.issue_reconnect_token(session_id);
+                            let _ = session
+                                .text(
+                                    serde_json::json!({
+                                        "type": "connected",
+                                        "imageId": valid_image_id,
+                                        "protocol": protocol,
+                                        "reconnectToken": reconnect_token,
+                                    })
+                                    .to_string(),
+                                )
+                                .await;
+
+                            // Tauriイベントを発火（QRウィンドウ等へ通知）
+                            let _ = crate::events::emit_routed(
+                                app_handle,
+                                "mobile-connected",
                                 serde_json::json!({
-                                    "type": "error",
-                                    "message": "invalid or expired session"
-                                })
-                                .to_string(),
-                            )
-                            .await;
+                                    "sessionId": session_id,
+                                    "imageId": valid_image_id,
+                                }),
+                            );
+                        }
+                        Err(rejection) => {
+                            let _ = session
+                                .text(
+                                    serde_json::json!({
+                                        "type": "error",
+                                        "error": rejection.code,
+                                        "message": rejection.message,
+                                    })
+                                    .to_string(),
+                                )
+                                .await;
+                        }
                     }
                 }
             }
@@ -168,42 +1380,82 @@ async fn handle_websocket_message(
                     .or_else(|| msg.payload.get("imageId").and_then(|v| v.as_str()));
                 let state: tauri::State<ServerState> = app_handle.state();
                 if let Some(qr_manager) = state.get_qr_manager() {
-                    if let Some(valid_image_id) = qr_manager.validate_session(sid) {
-                        if let Some(img) = provided_image_id {
-                            if img != valid_image_id {
-                                let _ = session
-                                    .text(
-                                        serde_json::json!({
-                                            "type": "ack",
-                                            "ok": false,
-                                            "error": "imageId mismatch"
-                                        })
-                                        .to_string(),
-                                    )
-                                    .await;
-                                return;
-                            }
+                    let valid_image_id = match qr_manager.validate_session(sid) {
+                        Ok(image_id) => image_id,
+                        Err(rejection) => {
+                            let _ = session
+                                .text(
+                                    serde_json::json!({
+                                        "type": "ack",
+                                        "ok": false,
+                                        "error": rejection.code,
+                                        "message": rejection.message,
+                                    })
+                                    .to_string(),
+                                )
+                                .await;
+                            return;
+                        }
+                    };
+                    // 拒否した接続はセッションに紐付けない
+                    if let Some(img) = provided_image_id {
+                        if img != valid_image_id {
+                            let _ = session
+                                .text(
+                                    serde_json::json!({
+                                        "type": "ack",
+                                        "ok": false,
+                                        "error": "imageId mismatch"
+                                    })
+                                    .to_string(),
+                                )
+                                .await;
+                            return;
                         }
-                        // ack
+                    }
+                    let policy = controller_policy(app_handle);
+                    if let Err(rejection) =
+                        bind_session(&state, &policy, conn_id, sid, &valid_image_id)
+                    {
                         let _ = session
                             .text(
                                 serde_json::json!({
                                     "type": "ack",
-                                    "ok": true
+                                    "ok": false,
+                                    "error": rejection["error"],
+                                    "message": rejection["message"],
                                 })
                                 .to_string(),
                             )
                             .await;
-                        // 通知
-                        let _ = app_handle.emit(
-                            "mobile-connected",
-                            serde_json::json!({
-                                "sessionId": sid,
-                                "imageId": valid_image_id,
-                            }),
-                        );
                         return;
                     }
+                    record_join(&qr_manager, conn_id, sid);
+
+                    // ack（バイナリ形式を取り決めた場合は protocol を返す）
+                    let protocol = negotiate_protocol(conn_id, &msg);
+                    let reconnect_token = qr_manager.issue_reconnect_token(sid);
+                    let _ = session
+                        .text(
+                            serde_json::json!({
+                                "type": "ack",
+                                "ok": true,
+                                "protocol": protocol,
+                                "reconnectToken": reconnect_token,
+                            })
+                            .to_string(),
+                        )
+                        .await;
+                    // 通知
+                    let _ = crate::events::emit_routed(
+                        app_handle,
+                        "mobile-connected",
+                        serde_json::json!({
+                            "sessionId": sid,
+                            "imageId": valid_image_id,
+                        }),
+                    );
+                    return;
                 }
                 let _ = session
                     .text(
@@ -217,10 +1469,81 @@ async fn handle_websocket_message(
                     .await;
             }
         }
+        "rejoin" => {
+            // 再接続トークンでQRを読み直さずにセッションへ復帰する
+            let token = msg
+                .token
+                .as_deref()
+                .or_else(|| msg.payload.get("token").and_then(|v| v.as_str()));
+            let state: tauri::State<ServerState> = app_handle.state();
+            let redeemed = token.and_then(|token| {
+                state
+                    .get_qr_manager()
+                    .and_then(|qr_manager| qr_manager.redeem_reconnect_token(token))
+            });
+            let Some((session_id, image_id)) = redeemed else {
+                // 期限切れ等はQRの読み直しを案内する
+                let _ = session
+                    .text(
+                        serde_json::json!({
+                            "type": "ack",
+                            "ok": false,
+                            "error": "reconnect_expired",
+                            "message": "接続が切れてから時間が経ったため、もう一度QRコードを読み取ってください",
+                        })
+                        .to_string(),
+                    )
+                    .await;
+                return;
+            };
+
+            release_session(&state, conn_id, &session_id);
+            let policy = controller_policy(app_handle);
+            if let Err(rejection) = bind_session(&state, &policy, conn_id, &session_id, &image_id) {
+                let _ = session
+                    .text(
+                        serde_json::json!({
+                            "type": "ack",
+                            "ok": false,
+                            "error": rejection["error"],
+                            "message": rejection["message"],
+                        })
+                        .to_string(),
+                    )
+                    .await;
+                return;
+            }
+            let protocol = negotiate_protocol(conn_id, &msg);
+            let reconnect_token = state.get_qr_manager().map(|qr_manager| {
+                record_join(&qr_manager, conn_id, &session_id);
+                qr_manager.issue_reconnect_token(&session_id)
+            });
+            let _ = session
+                .text(
+                    serde_json::json!({
+                        "type": "ack",
+                        "ok": true,
+                        "rejoined": true,
+                        "imageId": image_id,
+                        "protocol": protocol,
+                        "reconnectToken": reconnect_token,
+                    })
+                    .to_string(),
+                )
+                .await;
+            let _ = crate::events::emit_routed(
+                app_handle,
+                "mobile-reconnected",
+                serde_json::json!({
+                    "sessionId": session_id,
+                    "imageId": image_id,
+                }),
+            );
+        }
         "cmd" => {
             // レガシー/別UI互換: payload.cmd を action/move/emote に正規化
             if let Some(cmd) = msg.payload.get("cmd").and_then(|v| v.as_str()) {
-                handle_cmd_string(app_handle, session, cmd, msg.payload.get("imageId")).await;
+                dispatch_cmd(app_handle, cmd, bound_image.as_deref());
             }
         }
         "evt" => {
@@ -231,13 +1554,7 @@ async fn handle_websocket_message(
                     .and_then(|p| p.get("cmd"))
                     .and_then(|v| v.as_str());
                 if let Some(c) = cmd {
-                    handle_cmd_string(
-                        app_handle,
-                        session,
-                        c,
-                        echo.get("payload").and_then(|p| p.get("imageId")),
-                    )
-                    .await;
+                    dispatch_cmd(app_handle, c, bound_image.as_deref());
                 }
             }
         }
@@ -249,13 +1566,14 @@ async fn handle_websocket_message(
                     .get("action")
                     .and_then(|v| v.as_str())
                     .unwrap_or("pulse");
-                let _ = app_handle.emit(
+                let _ = crate::events::emit_routed(
+                    app_handle,
                     "mobile-control",
                     serde_json::json!({
                         "type": "move",
                         "direction": direction,
                         "action": action,
-                        "imageId": msg.payload.get("imageId"),
+                        "imageId": bound_image,
                     }),
                 );
             }
@@ -265,43 +1583,39 @@ async fn handle_websocket_message(
             if let Some(action_type) = msg.payload.get("actionType").and_then(|v| v.as_str()) {
                 println!(
                     "[websocket] action received: {:?} for imageId={:?}",
-                    action_type,
-                    msg.payload.get("imageId")
+                    action_type, bound_image
                 );
-                let _ = app_handle.emit(
+                let _ = crate::events::emit_routed(
+                    app_handle,
                     "mobile-control",
                     serde_json::json!({
                         "type": "action",
                         "actionType": action_type,
-                        "imageId": msg.payload.get("imageId"),
+                        "imageId": bound_image,
                     }),
                 );
             }
         }
         "emote" => {
-            // エモートコマンドの処理
-            if let Some(mut emote_type) = msg.payload.get("emoteType").and_then(|v| v.as_str()) {
-                // コントローラーの別名を絵文字へ正規化
-                let lower = emote_type.to_lowercase();
-                emote_type = match lower.as_str() {
-                    "happy" => "😊",
-                    "heart" => "❤️",
-                    "rock" | "gu" | "✊" => "✊",
-                    "scissors" | "choki" | "✌" | "✌️" => "✌️",
-                    "paper" | "hand" | "pa" | "🖐" => "🖐",
-                    _ => emote_type,
-                };
+            // エモートコマンドの処理（別名の正規化と許可リストの確認）
+            let image_id = bound_image.as_deref();
+            let emote_type = msg
+                .payload
+                .get("emoteType")
+                .and_then(|v| v.as_str())
+                .and_then(|raw| crate::emote_filter::normalize(app_handle, raw, image_id));
+            if let Some(emote_type) = emote_type {
                 println!(
                     "[websocket] emote received: {:?} for imageId={:?}",
-                    emote_type,
-                    msg.payload.get("imageId")
+                    emote_type, image_id
                 );
-                let _ = app_handle.emit(
+                let _ = crate::events::emit_routed(
+                    app_handle,
                     "mobile-control",
                     serde_json::json!({
                         "type": "emote",
                         "emoteType": emote_type,
-                        "imageId": msg.payload.get("imageId"),
+                        "imageId": image_id,
                     }),
                 );
             }
@@ -320,22 +1634,22 @@ async fn handle_websocket_message(
     }
 }
 
-async fn handle_cmd_string(
-    app_handle: &tauri::AppHandle,
-    session: &mut actix_ws::Session,
-    cmd: &str,
-    image_id_val: Option<&serde_json::Value>,
-) {
+/// 文字列コマンドを mobile-control へ変換する（Relay経由のコマンドも同じ経路を通す）
+/// image_id は接続に紐付いた画像（スマホが送ってきた値ではなく、呼び出し側で確認したもの）
+pub fn dispatch_cmd(app_handle: &tauri::AppHandle, cmd: &str, image_id: Option<&str>) {
     // cmd 例: 'jump', 'left', 'move/start/right', 'emote:happy'
     if let Some(rest) = cmd.strip_prefix("emote:") {
-        let _ = app_handle.emit(
-            "mobile-control",
-            serde_json::json!({
-                "type": "emote",
-                "emoteType": rest,
-                "imageId": image_id_val,
-            }),
-        );
+        if let Some(emote_type) = crate::emote_filter::normalize(app_handle, rest, image_id) {
+            let _ = crate::events::emit_routed(
+                app_handle,
+                "mobile-control",
+                serde_json::json!({
+                    "type": "emote",
+                    "emoteType": emote_type,
+                    "imageId": image_id,
+                }),
+            );
+        }
         return;
     }
 
@@ -349,13 +1663,14 @@ async fn handle_cmd_string(
                 "stop" | "end" => "stop",
                 other => other,
             };
-            let _ = app_handle.emit(
+            let _ = crate::events::emit_routed(
+                app_handle,
                 "mobile-control",
                 serde_json::json!({
                     "type": "move",
                     "direction": direction,
                     "action": normalized_action,
-                    "imageId": image_id_val,
+                    "imageId": image_id,
                 }),
             );
             return;
@@ -364,26 +1679,128 @@ async fn handle_cmd_string(
 
     match cmd {
         "left" | "right" | "up" | "down" => {
-            let _ = app_handle.emit(
+            let _ = crate::events::emit_routed(
+                app_handle,
                 "mobile-control",
                 serde_json::json!({
                     "type": "move",
                     "direction": cmd,
                     "action": "pulse",
-                    "imageId": image_id_val,
+                    "imageId": image_id,
                 }),
             );
         }
         // その他はアクション扱い
         other => {
-            let _ = app_handle.emit(
+            let _ = crate::events::emit_routed(
+                app_handle,
                 "mobile-control",
                 serde_json::json!({
                     "type": "action",
                     "actionType": other,
-                    "imageId": image_id_val,
+                    "imageId": image_id,
                 }),
             );
         }
     }
 }
+
+/// 指定セッションのスマホへ任意のJSONを送る（例: キャラクターが画面から退場した通知）
+#[tauri::command]
+pub fn send_to_mobile(
+    server_state: tauri::State<'_, ServerState>,
+    session_id: String,
+    payload: serde_json::Value,
+) -> Result<(), String> {
+    server_state.send_to_mobile(&session_id, payload.to_string())
+}
+
+#[tauri::command]
+pub fn get_ws_metrics(app_handle: tauri::AppHandle) -> Result<WsMetricsSnapshot, String> {
+    Ok(ws_metrics(&app_handle))
+}
+
+#[tauri::command]
+pub fn get_controller_latency_stats() -> Result<Vec<ControllerLatency>, String> {
+    Ok(latency_snapshot())
+}
+
+/// キャラクターに起きた出来事を操作中のスマホへ知らせる（例: 星を取った→振動・効果音）
+/// 送信できた接続数を返す（操作中のスマホがなければ0）
+#[tauri::command]
+pub fn notify_controller(image_id: String, event: serde_json::Value) -> Result<usize, String> {
+    if !event.is_object() {
+        return Err("event はJSONオブジェクトで指定してください".to_string());
+    }
+    Ok(send_to_image(
+        &image_id,
+        &serde_json::json!({
+            "type": "game-event",
+            "imageId": image_id,
+            "event": event,
+        }),
+    ))
+}
+
+/// 接続中のすべてのスマホへ任意のJSONを送る（例: 終演時のフィナーレ画面）
+#[tauri::command]
+pub fn broadcast_to_mobiles(payload: serde_json::Value) -> Result<usize, String> {
+    let sent = broadcast(&payload);
+    println!("[websocket] broadcast to {} connection(s)", sent);
+    Ok(sent)
+}
+
+#[tauri::command]
+pub fn get_controller_policy(app_handle: tauri::AppHandle) -> Result<ControllerPolicy, String> {
+    Ok(controller_policy(&app_handle))
+}
+
+/// 同じキャラクターを複数のスマホで操作するときの方針を保存（次の接続から反映）
+#[tauri::command]
+pub fn set_controller_policy(
+    workspace: tauri::State<'_, WorkspaceState>,
+    policy: ControllerPolicy,
+) -> Result<(), String> {
+    if policy.mode == ControllerMode::Coop
+        && !(1..=MAX_COOP_CONTROLLERS).contains(&policy.max_controllers)
+    {
+        return Err(format!(
+            "一緒に操作できる人数は1〜{}人で指定してください",
+            MAX_COOP_CONTROLLERS
+        ));
+    }
+    let value = serde_json::to_string(&policy)
+        .map_err(|e| format!("操作人数の設定のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(CONTROLLER_POLICY_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))
+}
+
+#[tauri::command]
+pub fn get_tilt_control(app_handle: tauri::AppHandle) -> Result<TiltSettings, String> {
+    Ok(read_tilt_settings(&app_handle))
+}
+
+/// スマホの傾きによる操作の設定を保存（オフにすると tilt メッセージを無視する）
+#[tauri::command]
+pub fn set_tilt_control(
+    workspace: tauri::State<'_, WorkspaceState>,
+    settings: TiltSettings,
+) -> Result<(), String> {
+    validate_tilt_settings(&settings)?;
+    let value = serde_json::to_string(&settings)
+        .map_err(|e| format!("傾き操作の設定のシリアライズに失敗しました: {}", e))?;
+
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.save_app_setting(TILT_SETTINGS_KEY, &value)
+        .map_err(|e| format!("Failed to save app setting: {}", e))?;
+    *TILT_SETTINGS.lock().unwrap() = Some((Instant::now(), settings));
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::db::Database;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::{Manager, State};
 
@@ -22,13 +23,32 @@ impl WorkspaceConnection {
         // 既存の接続をクローズ
         self.close();
 
-        // 新しい接続を作成
-        let db =
-            Database::new(db_path.clone()).map_err(|e| format!("データベース接続エラー: {}", e))?;
+        // 他のインスタンスが開いていないか確認してからロックを取る
+        let lock_path = lock_path_for(&db_path);
+        acquire_lock(&lock_path)?;
 
-        // テーブルを初期化
-        db.initialize()
-            .map_err(|e| format!("データベース初期化エラー: {}", e))?;
+        // 新しい接続を作成
+        let db = match Database::new(db_path.clone())
+            .map_err(|e| format!("データベース接続エラー: {}", e))
+            .and_then(|db| {
+                // 同期フォルダ上では壊れにくい設定に切り替える
+                // （書き込みは WorkspaceState のロック越しにこの1接続だけが行う）
+                if crate::sync_safety::detect(&db_path).is_some() {
+                    db.use_sync_safe_journal()
+                        .map_err(|e| format!("データベース設定エラー: {}", e))?;
+                }
+
+                // テーブルを初期化
+                db.initialize()
+                    .map_err(|e| format!("データベース初期化エラー: {}", e))?;
+                Ok(db)
+            }) {
+            Ok(db) => db,
+            Err(e) => {
+                release_lock(&lock_path);
+                return Err(e);
+            }
+        };
 
         self.connection = Some(db);
         self.current_path = Some(db_path);
@@ -39,7 +59,18 @@ impl WorkspaceConnection {
     /// 接続をクローズ
     pub fn close(&mut self) {
         self.connection = None;
-        self.current_path = None;
+        if let Some(path) = self.current_path.take() {
+            release_lock(&lock_path_for(&path));
+        }
+    }
+
+    /// ワークスペースのルート（<root>/.nuriemon/nuriemon.db の <root>）
+    pub fn workspace_root(&self) -> Option<PathBuf> {
+        self.current_path
+            .as_ref()?
+            .parent()
+            .and_then(|p| p.parent())
+            .map(|p| p.to_path_buf())
     }
 
     /// 現在の接続を取得
@@ -50,13 +81,222 @@ impl WorkspaceConnection {
     }
 }
 
+impl Drop for WorkspaceConnection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
 pub type WorkspaceState = Mutex<WorkspaceConnection>;
 
+// 同じワークスペースを複数のインスタンスで開かないためのロックファイル
+const LOCK_FILE_NAME: &str = "workspace.lock";
+// 保持中のロックを更新する間隔と、更新が止まったロックを古いとみなすまでの時間
+const LOCK_HEARTBEAT: std::time::Duration = std::time::Duration::from_secs(30);
+const LOCK_STALE_SECS: i64 = 120;
+
+static HELD_LOCK: once_cell::sync::Lazy<Mutex<Option<PathBuf>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+static LOCK_HEARTBEAT_STARTED: std::sync::Once = std::sync::Once::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceLock {
+    pid: u32,
+    hostname: String,
+    locked_at: String,
+    refreshed_at: String,
+}
+
+impl WorkspaceLock {
+    fn is_own(&self) -> bool {
+        self.pid == std::process::id() && self.hostname == host_name()
+    }
+
+    fn is_fresh(&self) -> bool {
+        match chrono::DateTime::parse_from_rfc3339(&self.refreshed_at) {
+            Ok(refreshed) => {
+                chrono::Utc::now()
+                    .signed_duration_since(refreshed)
+                    .num_seconds()
+                    < LOCK_STALE_SECS
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// <root>/.nuriemon/nuriemon.db と同じ場所に置く
+fn lock_path_for(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|dir| dir.join(LOCK_FILE_NAME))
+        .unwrap_or_else(|| PathBuf::from(LOCK_FILE_NAME))
+}
+
+pub(crate) fn host_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|s| s.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn read_lock(lock_path: &Path) -> Option<WorkspaceLock> {
+    let content = std::fs::read_to_string(lock_path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_lock(lock_path: &Path, lock: &WorkspaceLock) -> Result<(), String> {
+    let content =
+        serde_json::to_string_pretty(lock).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    std::fs::write(lock_path, content).map_err(|e| format!("ロックファイルの書き込みに失敗: {}", e))
+}
+
+fn new_lock() -> WorkspaceLock {
+    let now = crate::db::current_timestamp();
+    WorkspaceLock {
+        pid: std::process::id(),
+        hostname: host_name(),
+        locked_at: now.clone(),
+        refreshed_at: now,
+    }
+}
+
+// ロックファイルが無いときだけ作る（他のインスタンスと同時に開いても片方しか取れない）
+fn create_lock(lock_path: &Path, lock: &WorkspaceLock) -> std::io::Result<()> {
+    use std::io::Write;
+    let content = serde_json::to_string_pretty(lock)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(lock_path)?;
+    file.write_all(content.as_bytes())
+}
+
+// 中身を読めないロック（作成直後で書き込み中の可能性がある）は、更新が止まってからだけ古いとみなす
+fn unreadable_lock_is_stale(lock_path: &Path) -> bool {
+    std::fs::metadata(lock_path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age.as_secs() as i64 >= LOCK_STALE_SECS)
+}
+
+fn acquire_lock(lock_path: &Path) -> Result<(), String> {
+    // 古いロックを消した直後に別のインスタンスが取る場合があるため、作成は一度だけやり直す
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match create_lock(lock_path, &new_lock()) {
+            Ok(()) => break,
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                return Err(format!("ロックファイルの書き込みに失敗: {}", e));
+            }
+            Err(_) => {}
+        }
+
+        let busy = || {
+            "このワークスペースは別のアプリで使用中です。前回異常終了した場合はロックを解除してから開き直してください".to_string()
+        };
+        match read_lock(lock_path) {
+            // 自分のロックは取り直す
+            Some(existing) if existing.is_own() => {
+                write_lock(lock_path, &new_lock())?;
+                break;
+            }
+            Some(existing) if existing.is_fresh() => {
+                return Err(format!(
+                    "このワークスペースは別のアプリで使用中です（{} / PID {}、{}から）。前回異常終了した場合はロックを解除してから開き直してください",
+                    existing.hostname, existing.pid, existing.locked_at
+                ));
+            }
+            Some(existing) => println!(
+                "[workspace] replacing stale lock held by {} / PID {}",
+                existing.hostname, existing.pid
+            ),
+            // 読む前に解除された
+            None if !lock_path.exists() => {}
+            None if !unreadable_lock_is_stale(lock_path) => return Err(busy()),
+            None => println!("[workspace] replacing unreadable stale lock"),
+        }
+        if attempts >= 2 {
+            return Err(busy());
+        }
+        if let Err(e) = std::fs::remove_file(lock_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(format!("ロックファイルの削除に失敗しました: {}", e));
+            }
+        }
+    }
+
+    if let Ok(mut held) = HELD_LOCK.lock() {
+        *held = Some(lock_path.to_path_buf());
+    }
+    start_lock_heartbeat();
+    Ok(())
+}
+
+// 自分のロックだけを消す（解除後に別インスタンスが取ったロックは残す）
+fn release_lock(lock_path: &Path) {
+    if let Ok(mut held) = HELD_LOCK.lock() {
+        if held.as_deref() == Some(lock_path) {
+            *held = None;
+        }
+    }
+    if read_lock(lock_path).is_some_and(|lock| lock.is_own()) {
+        if let Err(e) = std::fs::remove_file(lock_path) {
+            eprintln!("[workspace] failed to remove lock file: {}", e);
+        }
+    }
+}
+
+// 接続中はロックの更新時刻を進め、異常終了したロックだけが古くなるようにする
+fn start_lock_heartbeat() {
+    LOCK_HEARTBEAT_STARTED.call_once(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(LOCK_HEARTBEAT);
+            let Some(lock_path) = HELD_LOCK.lock().ok().and_then(|held| held.clone()) else {
+                continue;
+            };
+            let Some(mut lock) = read_lock(&lock_path) else {
+                continue;
+            };
+            // 強制解除されて別インスタンスが取得した場合は触らない
+            if !lock.is_own() {
+                continue;
+            }
+            lock.refreshed_at = crate::db::current_timestamp();
+            if let Err(e) = write_lock(&lock_path, &lock) {
+                eprintln!("[workspace] failed to refresh lock: {}", e);
+            }
+        });
+    });
+}
+
 /// 新しいワークスペースDBを初期化
 #[tauri::command]
-pub async fn initialize_workspace_db(db_path: String) -> Result<(), String> {
-    let path = PathBuf::from(&db_path);
+pub async fn initialize_workspace_db(
+    app_handle: tauri::AppHandle,
+    db_path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(db_path);
+    crate::path_sandbox::check_workspace_db(&app_handle, &path)?;
+    init_workspace_db(&path)?;
+    // 接続前に設定ファイルを書けるよう、ダイアログで選ばれたフォルダなら許可範囲に加える
+    if let Some(root) = path.parent().and_then(|p| p.parent()) {
+        crate::path_sandbox::allow_initialized_workspace(root);
+    }
+    Ok(())
+}
 
+fn init_workspace_db(path: &Path) -> Result<(), String> {
     // 親ディレクトリが存在することを確認
     if let Some(parent) = path.parent() {
         if !parent.exists() {
@@ -66,25 +306,397 @@ pub async fn initialize_workspace_db(db_path: String) -> Result<(), String> {
     }
 
     // DBファイルを作成して初期化
-    let db = Database::new(path).map_err(|e| format!("データベース作成エラー: {}", e))?;
+    let db =
+        Database::new(path.to_path_buf()).map_err(|e| format!("データベース作成エラー: {}", e))?;
 
     db.initialize()
         .map_err(|e| format!("データベース初期化エラー: {}", e))?;
 
+    // 表示名の既定はワークスペースのフォルダ名（<root>/.nuriemon/nuriemon.db の <root>）
+    let root_name = path
+        .parent()
+        .and_then(|p| p.parent())
+        .and_then(|root| root.file_name())
+        .map(|name| name.to_string_lossy().to_string());
+    if let Some(name) = root_name {
+        let info = db
+            .get_workspace_info()
+            .map_err(|e| format!("Failed to get workspace info: {}", e))?;
+        if info.display_name.is_none() {
+            db.set_workspace_display_name(&name)
+                .map_err(|e| format!("Failed to save workspace info: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+// 新規ワークスペースの既定フォルダ名
+const DEFAULT_WORKSPACE_NAME: &str = "Nuriemon";
+// ワークスペースに用意しておくフォルダ
+const WORKSPACE_DIRS: [&str; 6] = [
+    ".nuriemon",
+    "images/originals",
+    "images/processed",
+    "images/backgrounds",
+    "audio",
+    "logs",
+];
+
+/// ワークスペースの置き場所の候補
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceLocation {
+    // "documents" / "pictures"
+    pub kind: String,
+    pub parent_dir: String,
+    pub suggested_path: String,
+    // 空き容量を取得できない場合はNone
+    pub free_bytes: Option<u64>,
+    // 候補の場所に既にワークスペースがある
+    pub already_exists: bool,
+}
+
+/// 書類・ピクチャフォルダをワークスペースの置き場所として提案する
+#[tauri::command]
+pub fn suggest_workspace_locations(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<WorkspaceLocation>, String> {
+    let resolver = app_handle.path();
+    let candidates = [
+        ("documents", resolver.document_dir()),
+        ("pictures", resolver.picture_dir()),
+    ];
+
+    Ok(candidates
+        .into_iter()
+        .filter_map(|(kind, dir)| dir.ok().map(|dir| (kind, dir)))
+        .filter(|(_, dir)| dir.is_dir())
+        .map(|(kind, dir)| {
+            let suggested = dir.join(DEFAULT_WORKSPACE_NAME);
+            WorkspaceLocation {
+                kind: kind.to_string(),
+                parent_dir: dir.to_string_lossy().to_string(),
+                suggested_path: suggested.to_string_lossy().to_string(),
+                free_bytes: fs2::available_space(&dir).ok(),
+                already_exists: db_path_for(&suggested).exists(),
+            }
+        })
+        .collect())
+}
+
+// フォルダ名として使えない名前を弾く
+fn validate_workspace_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("ワークスペース名を入力してください".to_string());
+    }
+    if name == "."
+        || name == ".."
+        || name.chars().any(|c| {
+            matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+        })
+    {
+        return Err("ワークスペース名に使えない文字が含まれています".to_string());
+    }
+    Ok(name)
+}
+
+/// フォルダ構成を作ってDBを初期化する（作成したワークスペースのパスを返す）
+#[tauri::command]
+pub async fn create_workspace(parent_dir: String, name: String) -> Result<String, String> {
+    let parent = PathBuf::from(&parent_dir);
+    if !parent.is_dir() {
+        return Err("作成先のフォルダが見つかりません".to_string());
+    }
+    let root = parent.join(validate_workspace_name(&name)?);
+    let db_path = db_path_for(&root);
+    if db_path.exists() {
+        return Err("このフォルダには既にワークスペースがあります".to_string());
+    }
+
+    for dir in WORKSPACE_DIRS {
+        std::fs::create_dir_all(root.join(dir))
+            .map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+    }
+    init_workspace_db(&db_path)?;
+
+    // フロントエンドが初期化済みと判断する既定の設定ファイル
+    let settings_path = root.join(".nuriemon").join("settings.json");
+    if !settings_path.exists() {
+        let settings = serde_json::json!({
+            "version": "1.0.0",
+            "groundPosition": 80,
+            "deletionTime": "unlimited",
+            "imageDisplaySize": 18,
+            "saveLocation": "workspace",
+            "customPath": root.to_string_lossy(),
+        });
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("JSON変換エラー: {}", e))?;
+        std::fs::write(&settings_path, content)
+            .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
+    }
+
+    println!("[workspace] created {}", root.display());
+    Ok(root.to_string_lossy().to_string())
+}
+
+// 接続して、ワークスペースごとの後処理を行う
+fn open_workspace(
+    app_handle: &tauri::AppHandle,
+    conn: &mut WorkspaceConnection,
+    db_path: PathBuf,
+) -> Result<(), String> {
+    conn.connect(db_path)?;
+
+    // 同期フォルダ上ならローカルへの移動を勧める
+    if let Some(location) = conn
+        .workspace_root()
+        .and_then(|root| crate::sync_safety::detect(&root))
+    {
+        eprintln!("[workspace] synced location detected: {:?}", location);
+        let _ = crate::events::emit_routed(app_handle, "workspace-sync-warning", location);
+    }
+
+    // 新しいワークスペースの背景スケジュールを評価
+    crate::background_scheduler::notify_schedule_changed();
+    // ワークスペースに保存されたウィンドウ配置を適用
+    crate::display_layout::schedule_apply(app_handle.clone());
+    // ワークスペースに保存されたショートカットを登録し直す
+    crate::global_shortcuts::schedule_reload(app_handle.clone());
+    // ワークスペースに保存されたクラウド取り込みを再開する
+    crate::cloud_intake::schedule_resume(app_handle.clone());
+
+    // 起動時に測定済みの時刻補正値をワークスペースへ反映
+    if let Some(offset_ms) = crate::clock::measured_offset_ms() {
+        if let Ok(db) = conn.get() {
+            let _ = db.save_clock_offset_ms(offset_ms);
+        }
+    }
     Ok(())
 }
 
 /// ワークスペースDBに接続
 #[tauri::command]
 pub async fn connect_workspace_db(
+    app_handle: tauri::AppHandle,
     workspace: State<'_, WorkspaceState>,
     db_path: String,
 ) -> Result<(), String> {
-    let mut conn = workspace
+    let db_path = PathBuf::from(db_path);
+    // ダイアログで選んだフォルダか確認してから接続する（接続先は許可範囲になるため）
+    let root = crate::path_sandbox::check_workspace_db(&app_handle, &db_path)?;
+    {
+        let mut conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        open_workspace(&app_handle, &mut conn, db_path)?;
+    }
+    crate::path_sandbox::remember_workspace(&app_handle, &root);
+
+    if let Err(e) = record_recent_workspace(&app_handle, &root) {
+        eprintln!("[workspace] failed to update recent list: {}", e);
+    }
+    Ok(())
+}
+
+// 最近使ったワークスペースを保存するグローバル設定のキー
+const RECENT_WORKSPACES_KEY: &str = "recentWorkspaces";
+const MAX_RECENT_WORKSPACES: usize = 10;
+
+/// 最近開いたワークスペース
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentWorkspace {
+    pub name: String,
+    pub path: String,
+    pub last_opened_at: String,
+    // フォルダが外付けドライブごと外れている場合など
+    #[serde(default)]
+    pub available: bool,
+}
+
+fn db_path_for(root: &Path) -> PathBuf {
+    root.join(".nuriemon").join("nuriemon.db")
+}
+
+fn read_recent_workspaces(app_handle: &tauri::AppHandle) -> Vec<RecentWorkspace> {
+    read_global_setting(app_handle, RECENT_WORKSPACES_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+// 開いたワークスペースを先頭へ（同じパスは1件にまとめる）
+fn record_recent_workspace(app_handle: &tauri::AppHandle, root: &Path) -> Result<(), String> {
+    let path = root.to_string_lossy().to_string();
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+    let mut recent = read_recent_workspaces(app_handle);
+    recent.retain(|entry| entry.path != path);
+    recent.insert(
+        0,
+        RecentWorkspace {
+            name,
+            path,
+            last_opened_at: crate::db::current_timestamp(),
+            available: true,
+        },
+    );
+    recent.truncate(MAX_RECENT_WORKSPACES);
+    let value = serde_json::to_string(&recent).map_err(|e| format!("JSON変換エラー: {}", e))?;
+    write_global_setting(app_handle, RECENT_WORKSPACES_KEY, &value)
+}
+
+/// 最近開いたワークスペース（新しい順）
+#[tauri::command]
+pub fn list_recent_workspaces(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<RecentWorkspace>, String> {
+    Ok(read_recent_workspaces(&app_handle)
+        .into_iter()
+        .map(|entry| RecentWorkspace {
+            available: db_path_for(Path::new(&entry.path)).exists(),
+            ..entry
+        })
+        .collect())
+}
+
+/// 初期化済みのワークスペースへ切り替え、全ウィンドウへ workspace-changed と workspace-data-loaded を通知する
+#[tauri::command]
+pub async fn switch_workspace(
+    app_handle: tauri::AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    path: String,
+) -> Result<(), String> {
+    let root = PathBuf::from(&path);
+    let db_path = db_path_for(&root);
+    if !db_path.exists() {
+        return Err("ワークスペースが見つからないか、初期化されていません".to_string());
+    }
+    crate::path_sandbox::check_workspace_db(&app_handle, &db_path)?;
+
+    {
+        let mut conn = workspace
+            .lock()
+            .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+        conn.close();
+        open_workspace(&app_handle, &mut conn, db_path.clone())?;
+    }
+    crate::path_sandbox::remember_workspace(&app_handle, &root);
+
+    record_recent_workspace(&app_handle, &root)?;
+    write_global_setting(&app_handle, "lastWorkspace", &path)?;
+    println!("[workspace] switched to {}", path);
+    crate::events::emit_routed(
+        &app_handle,
+        "workspace-changed",
+        serde_json::json!({
+            "path": path,
+            "dbPath": db_path.to_string_lossy(),
+        }),
+    )
+    .map_err(|e| format!("Failed to emit workspace-changed: {}", e))?;
+    // フロントエンドの切り替えと同じく、データの読み込み完了も知らせる（自動取り込みの監視を張り直すため）
+    crate::events::emit_routed(
+        &app_handle,
+        "workspace-data-loaded",
+        serde_json::json!({ "path": path }),
+    )
+    .map_err(|e| format!("Failed to emit workspace-data-loaded: {}", e))
+}
+
+/// 接続中のワークスペースの識別情報
+#[tauri::command]
+pub fn get_workspace_info(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<crate::db::WorkspaceInfo, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    let mut info = db
+        .get_workspace_info()
+        .map_err(|e| format!("Failed to get workspace info: {}", e))?;
+    info.secret_backend = Some(crate::secret_store::active_backend());
+    Ok(info)
+}
+
+/// ワークスペースの表示名を変更
+#[tauri::command]
+pub fn set_workspace_display_name(
+    workspace: State<'_, WorkspaceState>,
+    display_name: String,
+) -> Result<(), String> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Err("表示名を入力してください".to_string());
+    }
+    let conn = workspace
         .lock()
         .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.set_workspace_display_name(display_name)
+        .map_err(|e| format!("Failed to save workspace info: {}", e))
+}
+
+/// 接続中のワークスペース（表示専用ウィンドウ向け）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentWorkspace {
+    pub path: String,
+    // .nuriemon/settings.json の内容（読めなければNone）
+    pub settings: Option<serde_json::Value>,
+}
 
-    conn.connect(PathBuf::from(db_path))
+/// メインウィンドウが接続したワークスペースのパスと設定（表示専用ウィンドウは自分では接続しない）
+#[tauri::command]
+pub fn get_current_workspace(
+    workspace: State<'_, WorkspaceState>,
+) -> Result<Option<CurrentWorkspace>, String> {
+    let root = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?
+        .workspace_root();
+    let Some(root) = root else {
+        return Ok(None);
+    };
+    let settings = std::fs::read_to_string(root.join(".nuriemon").join("settings.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok());
+    Ok(Some(CurrentWorkspace {
+        path: root.to_string_lossy().to_string(),
+        settings,
+    }))
+}
+
+/// 識別情報（ワークスペース未接続ならNone）
+pub fn current_workspace_info(app_handle: &tauri::AppHandle) -> Option<crate::db::WorkspaceInfo> {
+    let state = app_handle.try_state::<WorkspaceState>()?;
+    let conn = state.lock().ok()?;
+    conn.get().ok()?.get_workspace_info().ok()
+}
+
+/// 異常終了などで残ったワークスペースのロックを解除する（解除したらtrue）
+#[tauri::command]
+pub fn force_unlock_workspace(path: String) -> Result<bool, String> {
+    let lock_path = lock_path_for(&db_path_for(Path::new(&path)));
+    if !lock_path.exists() {
+        return Ok(false);
+    }
+    if let Some(lock) = read_lock(&lock_path) {
+        println!(
+            "[workspace] force unlocking {} (held by {} / PID {})",
+            path, lock.hostname, lock.pid
+        );
+    }
+    std::fs::remove_file(&lock_path)
+        .map_err(|e| format!("ロックファイルの削除に失敗しました: {}", e))?;
+    Ok(true)
 }
 
 /// ワークスペースDBをクローズ
@@ -98,76 +710,98 @@ pub async fn close_workspace_db(workspace: State<'_, WorkspaceState>) -> Result<
     Ok(())
 }
 
-/// グローバル設定を保存（アプリケーションレベル）
-#[tauri::command]
-pub async fn save_global_setting(
-    app_handle: tauri::AppHandle,
-    key: String,
-    value: String,
-) -> Result<(), String> {
+/// グローバル設定ファイル（app_data_dir/global_settings.json）のパス
+pub(crate) fn global_settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
         .map_err(|e| format!("アプリデータディレクトリの取得に失敗: {}", e))?;
 
-    let settings_path = app_data_dir.join("global_settings.json");
+    Ok(app_data_dir.join("global_settings.json"))
+}
 
-    // 既存の設定を読み込む
-    let mut settings: serde_json::Value = if settings_path.exists() {
-        let content = std::fs::read_to_string(&settings_path).unwrap_or_else(|_| "{}".to_string());
-        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
+/// グローバル設定を書き込む（Rust内部からも利用）
+pub fn write_global_setting(
+    app_handle: &tauri::AppHandle,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    let settings_path = global_settings_path(app_handle)?;
+    crate::global_settings::update_file(&settings_path, |settings| settings.set(key, value))?;
+    Ok(())
+}
 
-    // 設定を更新
-    settings[key] = serde_json::Value::String(value);
+/// グローバル設定を読み込む（Rust内部からも利用）
+pub fn read_global_setting(
+    app_handle: &tauri::AppHandle,
+    key: &str,
+) -> Result<Option<String>, String> {
+    let settings_path = global_settings_path(app_handle)?;
+    Ok(crate::global_settings::load_from(&settings_path).get(key))
+}
 
-    // ディレクトリを作成
-    if let Some(parent) = settings_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成エラー: {}", e))?;
+// グローバル設定の変更を接続中のワークスペースの履歴に残す（未接続なら記録しない）
+pub(crate) fn record_global_setting_change(
+    app_handle: &tauri::AppHandle,
+    key: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+    source: &str,
+) {
+    let Some(state) = app_handle.try_state::<WorkspaceState>() else {
+        return;
+    };
+    let Ok(conn) = state.lock() else {
+        return;
+    };
+    let Ok(db) = conn.get() else {
+        return;
+    };
+    if let Err(e) = db.record_global_setting_change(key, old_value, new_value, source) {
+        eprintln!("[workspace] failed to record settings history: {}", e);
     }
+}
 
-    // ファイルに保存
-    std::fs::write(
-        settings_path,
-        serde_json::to_string_pretty(&settings).map_err(|e| format!("JSON変換エラー: {}", e))?,
-    )
-    .map_err(|e| format!("ファイル書き込みエラー: {}", e))?;
-
+/// グローバル設定を保存（アプリケーションレベル）
+#[tauri::command]
+pub async fn save_global_setting(
+    window: tauri::Window,
+    app_handle: tauri::AppHandle,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    let old_value = read_global_setting(&app_handle, &key).ok().flatten();
+    write_global_setting(&app_handle, &key, &value)?;
+    record_global_setting_change(
+        &app_handle,
+        &key,
+        old_value.as_deref(),
+        Some(&value),
+        window.label(),
+    );
     Ok(())
 }
 
+/// 設定変更の履歴（新しい順、既定100件）
+#[tauri::command]
+pub fn get_settings_history(
+    workspace: State<'_, WorkspaceState>,
+    key: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<crate::db::SettingsHistoryEntry>, String> {
+    let conn = workspace
+        .lock()
+        .map_err(|_| "ワークスペース接続のロックに失敗しました".to_string())?;
+    let db = conn.get()?;
+    db.get_settings_history(key.as_deref(), limit.unwrap_or(100).clamp(1, 1000))
+        .map_err(|e| format!("Failed to get settings history: {}", e))
+}
+
 /// グローバル設定を取得
 #[tauri::command]
 pub async fn get_global_setting(
     app_handle: tauri::AppHandle,
     key: String,
 ) -> Result<Option<String>, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("アプリデータディレクトリの取得に失敗: {}", e))?;
-
-    let settings_path = app_data_dir.join("global_settings.json");
-
-    if !settings_path.exists() {
-        return Ok(None);
-    }
-
-    let content = std::fs::read_to_string(settings_path)
-        .map_err(|e| format!("ファイル読み込みエラー: {}", e))?;
-
-    let settings: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("JSON解析エラー: {}", e))?;
-
-    if let Some(value) = settings.get(&key) {
-        if let Some(str_value) = value.as_str() {
-            Ok(Some(str_value.to_string()))
-        } else {
-            Ok(None)
-        }
-    } else {
-        Ok(None)
-    }
+    read_global_setting(&app_handle, &key)
 }
@@ -1,4 +1,4 @@
-import { useState, useEffect, useRef } from "react";
+import { useState, useEffect } from "react";
 import { UploadPage } from "./components/UploadPage";
 import { GalleryPage } from "./components/GalleryPage";
 import { SettingsPage } from "./components/SettingsPage";
@@ -6,17 +6,16 @@ import { Sidebar } from "./components/Sidebar/Sidebar";
 import { initializeStorage } from "./services/imageStorage";
 import { AppSettingsService } from "./services/database";
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from './events/windowListen';
 import { useWorkspace } from "./hooks/useWorkspace";
 import { WorkspaceSelector } from "./components/WorkspaceSelector";
 import { TauriEventListener } from "./events/tauriEventListener";
 import { rehydrateStore } from "./stores/workspaceStore";
 import styles from "./App.module.scss";
 import { InitialSetup } from "./components/InitialSetup";
-import { createPcWsClient } from "./services/pcWsClient";
 import { ControllerSettings } from "./components/ControllerSettings";
 import { checkRelayHealth } from "./services/connectivityProbe";
-import { resolveBaseUrl } from "./services/relayClient";
+import { resolveBaseUrl, registerPc, retryWithBackoff } from "./services/relayClient";
 import { checkForUpdatesOnStartup } from "./services/updater";
 
 console.log('[App.tsx] Module loaded');
@@ -38,7 +37,6 @@ function App() {
   const [activeTab, setActiveTab] = useState<'settings' | 'controller' | 'upload' | 'gallery' | 'animation'>('upload');
   const { isLoading, needsWorkspace, isReady, currentWorkspace } = useWorkspace();
   const [showSetup, setShowSetup] = useState(false);
-  const relayBridgeRef = useRef<ReturnType<typeof createPcWsClient> | null>(null);
   
   console.log('[App] State:', { isLoading, needsWorkspace, isReady, currentWorkspace });
 
@@ -152,6 +150,8 @@ function App() {
       try {
         const { GlobalSettingsService } = await import('./services/globalSettings');
         await GlobalSettingsService.loadEffective();
+        // イベントID・PCIDはメインウィンドウで発行して保存する（QR画面は読むだけ）
+        await GlobalSettingsService.ensureEventId();
         const mode = await AppSettingsService.getAppSetting('operation_mode');
         const eff = GlobalSettingsService.getEffective();
         const eid = eff?.relay?.eventId || null;
@@ -171,16 +171,30 @@ function App() {
             relayActive = !!health.ok;
           } catch { relayActive = false; }
         }
+        // 稼働状況の報告（遠隔での一時停止・機能フラグの受け取りも兼ねる）はモードによらず送る
+        if (eid && pcid) {
+          const baseUrl = await resolveBaseUrl();
+          await invoke('start_relay_heartbeat', { config: { baseUrl, eventId: eid, pcId: pcid } })
+            .catch((e) => console.warn('[App] heartbeat start failed:', e));
+        } else {
+          await invoke('stop_relay_heartbeat').catch(() => {});
+        }
         if (relayActive && eid && pcid) {
-          if (relayBridgeRef.current) { relayBridgeRef.current.stop(); relayBridgeRef.current = null; }
-          const client = createPcWsClient({ eventId: eid, pcid });
-          relayBridgeRef.current = client;
-          await client.start();
+          const baseUrl = await resolveBaseUrl();
+          // 事前にPCを登録（リージョンピン/整合のため）
+          try {
+            const res = await retryWithBackoff(() => registerPc({ eventId: eid, pcid }));
+            if (!res.ok) console.warn('[App] registerPc failed:', res);
+          } catch (e) {
+            console.warn('[App] registerPc exception:', e);
+          }
+          // 接続はバックエンドが持つ（共有リンクなどの送信待ちもこの接続で送る）
+          await invoke('start_relay_client', { config: { baseUrl, eventId: eid, pcId: pcid } });
         } else {
-          if (relayBridgeRef.current) { relayBridgeRef.current.stop(); relayBridgeRef.current = null; }
+          await invoke('stop_relay_client');
         }
       } catch (e) {
-        console.warn('[App] pcWsClient start failed:', e);
+        console.warn('[App] relay client start failed:', e);
       }
     };
     run();
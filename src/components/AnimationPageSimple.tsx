@@ -3,13 +3,15 @@ import AnimationView from './AnimationView';
 import { useAudio } from '../hooks/useAudio';
 import { useAnimationData } from '../hooks/useAnimationData';
 import { useWorkspaceStore } from '../stores/workspaceStore';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
+import { settingsSync } from '../services/settingsSync';
 import styles from './AnimationPage.module.scss';
 
 const AnimationPageSimple: React.FC = () => {
   // Zustandストアから状態を取得（setGroundPositionとsetBackgroundのみ使用）
   const { 
-    setBackground 
+    setBackground,
+    activeBackgroundId
   } = useWorkspaceStore();
   
   const [isInitialized, setIsInitialized] = useState(false);
@@ -34,7 +36,11 @@ const AnimationPageSimple: React.FC = () => {
     try {
       const { getAllMetadata, loadImage, getFilePathForMetadata, filePathToUrl } = await import('../services/imageStorage');
       const metadata = await getAllMetadata();
-      const background = metadata.find(m => (m as any).image_type === 'background');
+      const backgrounds = metadata.filter(m => (m as any).image_type === 'background');
+      // 背景スケジュールで有効になった背景（app_settings）を優先し、無ければ先頭の背景を使う
+      await settingsSync.sync();
+      const activeId = settingsSync.get('active_background_id');
+      const background = backgrounds.find(m => m.id === activeId) ?? backgrounds[0];
       if (background) {
         const isVideo = /\.(mp4|mov)$/i.test(background.originalFileName);
         if (isVideo) {
@@ -67,6 +73,15 @@ const AnimationPageSimple: React.FC = () => {
     initialize();
   }, [loadBackground, loadAudioFiles, refresh]);
 
+  // --- 背景スケジュールによる切り替え ---
+  useEffect(() => {
+    if (isInitialized && activeBackgroundId) {
+      void loadBackground();
+    }
+    // 初期化時は initialize 内で読み込むため、切り替えのときだけ読み直す
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [activeBackgroundId]);
+
   // --- イベントハンドラ ---
   // data-changedイベントリスナーは削除（Zustandストアへの統一のため）
   // 画像、オーディオ、背景の変更は必要に応じて別のTauriイベントで通知される
@@ -1,5 +1,6 @@
 import React, { useState, useEffect, useRef, useCallback, useMemo } from 'react';
-import { listen } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '../events/windowListen';
 import { createNoise2D } from 'simplex-noise';
 import {
   SPEED_SETTINGS,
@@ -14,9 +15,42 @@ import {
   loadControllerSettings,
 } from '../services/controllerSettings';
 import { useWorkspaceStore } from '../stores/workspaceStore';
+import {
+  isCloneWindow,
+  listenSceneState,
+  loadSceneState,
+  persistSceneState,
+  SceneCharacter,
+} from '../services/sceneState';
+import { listenCaptureRequests } from '../services/frameCapture';
+import { displayExpiresAt } from '../utils/displayExpiry';
 import styles from './AnimationView.module.scss';
 
 const noise2D = createNoise2D();
+// 画面状態の保存間隔
+const SCENE_PERSIST_INTERVAL_MS = 5000;
+// 持ち帰りQRを出しておく時間と、同時に出す数
+const TAKEAWAY_QR_VISIBLE_MS = 30_000;
+const MAX_TAKEAWAY_QRS = 3;
+
+// 削除前に薄くしていく時間（display-expiring はこれより前に届く）
+const EXPIRY_FADE_MS = 5000;
+
+// "display-expiring" の内容
+interface DisplayExpiring {
+  image_id: string;
+  expires_at: string;
+  remaining_ms: number;
+}
+
+// "takeaway-qr" の内容
+interface TakeawayQr {
+  imageId: string;
+  url: string;
+  // SVGのデータURI
+  qrCode: string;
+  expiresAt: string;
+}
 
 interface AnimationViewProps {
   images: Array<{
@@ -27,6 +61,7 @@ interface AnimationViewProps {
     movement: string;
     size: string;
     speed: number;
+    displayStartedAt?: string | null;
   }>;
   onImageClick?: (imageId: string) => void;
 }
@@ -60,6 +95,17 @@ const AnimationView: React.FC<AnimationViewProps> = ({
   const containerRefs = useRef<Map<string, HTMLDivElement>>(new Map());
   const imgRefs = useRef<Map<string, HTMLImageElement>>(new Map());
   const emoteRefs = useRef<Map<string, HTMLDivElement>>(new Map());
+  // 再起動前に保存された位置（該当する画像が現れたら適用して消す）
+  const savedSceneRef = useRef<Map<string, SceneCharacter>>(new Map());
+  // 運営スタッフの緊急キーで一時停止中
+  const pausedRef = useRef(false);
+  // 運営スタッフの緊急キーで暗転中（再開キーで戻る）
+  const [blackout, setBlackout] = useState(false);
+  // 展示終了前に出す持ち帰りQR（新しい順）
+  const [takeawayQrs, setTakeawayQrs] = useState<TakeawayQr[]>([]);
+  const takeawayTimersRef = useRef<Map<string, ReturnType<typeof setTimeout>>>(new Map());
+  // ワークスペースの時刻補正値（削除判定をRust側と同じ時計で行う）
+  const clockOffsetRef = useRef(0);
   // ブロードキャスト用エモートキュー
   const emoteBroadcastRef = useRef<null | { type: 'text'|'svg', content: string, pending: string[] }>(null);
   const [controllerSettings, setControllerSettings] = useState(DEFAULT_CONTROLLER_SETTINGS);
@@ -259,7 +305,71 @@ const AnimationView: React.FC<AnimationViewProps> = ({
     }
   }, []);
 
-  // モバイル操作の受信（move/action/emote）
+  // 緊急キーによる一時停止・暗転・再開
+  useEffect(() => {
+    const offPause = listen('pause-display', () => { pausedRef.current = true; });
+    const offBlackout = listen('blackout-display', () => { setBlackout(true); });
+    const offResume = listen('resume-display', () => {
+      pausedRef.current = false;
+      setBlackout(false);
+    });
+    return () => {
+      offPause.then(fn => fn());
+      offBlackout.then(fn => fn());
+      offResume.then(fn => fn());
+    };
+  }, []);
+
+  // 時刻補正値の取得
+  useEffect(() => {
+    invoke<number>('get_clock_offset')
+      .then((offset) => { clockOffsetRef.current = offset; })
+      .catch((error) => console.warn('[AnimationView] 時刻補正値の取得に失敗しました:', error));
+  }, []);
+
+  // 削除直前の通知（Rust側で計算した期限に合わせてフェードアウトする）
+  useEffect(() => {
+    const off = listen<DisplayExpiring>('display-expiring', (event) => {
+      const payload = event.payload;
+      const image = payload?.image_id ? animatedImagesRef.current[payload.image_id] : undefined;
+      if (!image) return;
+      image.expiresAt = Date.now() + clockOffsetRef.current + Math.max(0, payload.remaining_ms);
+    });
+    return () => {
+      off.then(fn => fn());
+    };
+  }, []);
+
+  // 持ち帰りQR（キャラクターが消えた後もしばらく残す）
+  useEffect(() => {
+    const timers = takeawayTimersRef.current;
+    const off = listen<TakeawayQr>('takeaway-qr', (event) => {
+      const qr = event.payload;
+      if (!qr?.imageId || !qr.qrCode) return;
+      setTakeawayQrs(prev => [qr, ...prev.filter(item => item.imageId !== qr.imageId)].slice(0, MAX_TAKEAWAY_QRS));
+      const previous = timers.get(qr.imageId);
+      if (previous) clearTimeout(previous);
+      timers.set(qr.imageId, setTimeout(() => {
+        timers.delete(qr.imageId);
+        setTakeawayQrs(prev => prev.filter(item => item.imageId !== qr.imageId));
+      }, TAKEAWAY_QR_VISIBLE_MS));
+    });
+    return () => {
+      off.then(fn => fn());
+      timers.forEach(timer => clearTimeout(timer));
+      timers.clear();
+    };
+  }, []);
+
+  // 静止画保存の依頼に応える
+  useEffect(() => {
+    const off = listenCaptureRequests(() => canvasRef.current);
+    return () => {
+      off.then(fn => fn());
+    };
+  }, []);
+
+  // モバイル操作の受信（move/tilt/action/emote）
   useEffect(() => {
     let disposed = false;
     let unlisten: (() => void) | null = null;
@@ -305,6 +415,29 @@ const AnimationView: React.FC<AnimationViewProps> = ({
             });
             break;
           }
+          case 'tilt': {
+            // サーバー側で平滑化・間引き済みの操作量（-1〜1）
+            const axisX = Number(payload.axisX) || 0;
+            const axisY = Number(payload.axisY) || 0;
+            apply((img) => {
+              img.manualAxisX = axisX;
+              img.manualAxisY = axisY;
+              if (axisX < 0) {
+                img.flipped = true;
+              } else if (axisX > 0) {
+                img.flipped = false;
+              }
+              if (axisX === 0 && axisY === 0) {
+                img.velocityX = 0;
+                img.velocityY = 0;
+                img.directionChangeTimer = Math.random() * 60 + 45;
+                img.nextMovementUpdate = Date.now() + 600;
+              }
+              img.lastMovementUpdate = Date.now();
+              img.isNewImage = false;
+            });
+            break;
+          }
           case 'action': {
             const action = payload.actionType as string | undefined;
             const effective = (action && ['jump','spin','shake','grow','shrink'].includes(action)) ? action : 'jump';
@@ -392,6 +525,62 @@ const AnimationView: React.FC<AnimationViewProps> = ({
     } catch {}
     return () => { cache.splice(0, cache.length); };
   }, []);
+  // 保存された位置があれば引き継ぐ（新規追加の演出は出さない）
+  const applySavedPosition = useCallback((image: AnimatedImage) => {
+    const saved = savedSceneRef.current.get(image.id);
+    if (!saved) return;
+    savedSceneRef.current.delete(image.id);
+    image.x = saved.x;
+    image.y = image.type === 'walk' ? image.y : saved.y;
+    image.velocityX = saved.velocityX;
+    image.velocityY = saved.velocityY;
+    image.flipped = saved.flipped;
+    image.isNewImage = false;
+    image.highlightEffect = null;
+    image.highlightScale = 1;
+    image.highlightGlow = 0;
+  }, []);
+
+  // 画面の状態を定期的に保存し、ウィンドウの再起動後はその位置から再開する
+  useEffect(() => {
+    let cancelled = false;
+    if (isCloneWindow()) {
+      // 複製表示は元のウィンドウが保存するたびに位置を揃える
+      const unlisten = listenSceneState(state => {
+        state.characters.forEach(c => {
+          savedSceneRef.current.set(c.id, c);
+          const image = animatedImagesRef.current[c.id];
+          if (image) applySavedPosition(image);
+        });
+      });
+      return () => {
+        unlisten.then(fn => fn());
+      };
+    }
+    loadSceneState().then(state => {
+      if (cancelled || !state) return;
+      state.characters.forEach(c => savedSceneRef.current.set(c.id, c));
+      Object.values(animatedImagesRef.current).forEach(applySavedPosition);
+    });
+    const timer = window.setInterval(() => {
+      const characters = Object.values(animatedImagesRef.current)
+        .filter(img => !img.pendingDeletion)
+        .map(img => ({
+          id: img.id,
+          x: img.x,
+          y: img.y,
+          velocityX: img.velocityX,
+          velocityY: img.velocityY,
+          flipped: img.flipped,
+        }));
+      persistSceneState({ characters });
+    }, SCENE_PERSIST_INTERVAL_MS);
+    return () => {
+      cancelled = true;
+      window.clearInterval(timer);
+    };
+  }, [applySavedPosition]);
+
   // 削除時間のログは毎フレームの再レンダでノイズになるため削除
   
   // Zustandストアが削除時間を管理しているため、読み込みとイベントリスナーは不要
@@ -472,6 +661,7 @@ const AnimationView: React.FC<AnimationViewProps> = ({
   // 画像を初期化
   const initializeImage = useCallback((data: any, markAsNew: boolean): AnimatedImage => {
     const now = Date.now();
+    const startedAt = data.displayStartedAt ? Date.parse(data.displayStartedAt) : NaN;
     // Y座標を動的に計算
     let initialY: number;
     if (data.type === 'walk') {
@@ -493,6 +683,8 @@ const AnimationView: React.FC<AnimationViewProps> = ({
       velocityY: data.type === 'walk' ? 0 : (Math.random() - 0.5) * 0.5,
       scale: 1,
       createdAt: now, // 画像が作成された時刻を記録
+      // 未記録ならこのPCで表示し始めた時刻（mark_display_started でDBにも記録する）
+      displayStartedAt: Number.isFinite(startedAt) ? startedAt : now + clockOffsetRef.current,
       deletionTime: deletionTime, // 現在の削除時間設定を適用
       rotation: 0,
       zRotation: 0,
@@ -672,6 +864,10 @@ const AnimationView: React.FC<AnimationViewProps> = ({
   // アニメーションループ
   useEffect(() => {
     function animate() {
+      if (pausedRef.current) {
+        animationRef.current = requestAnimationFrame(animate);
+        return;
+      }
       const frameStart = performance.now();
       const currentTime = Date.now();
       // エモートのブロードキャストを分割して適用
@@ -691,22 +887,22 @@ const AnimationView: React.FC<AnimationViewProps> = ({
       }
       const updatedImages: AnimatedImage[] = [];
       
-      const deletionTimeMs = (deletionTime !== 'unlimited')
-        ? parseInt(deletionTime) * 60 * 1000
-        : -1;
+      const correctedNow = currentTime + clockOffsetRef.current;
 
-      // 自動削除対象を積んで背後で物理削除
-      const toDelete: string[] = [];
+      // 表示期限を過ぎた画像（画面から消すだけで、削除は display_expiry が行う）
+      const expired: string[] = [];
 
       for (const image of Object.values(animatedImagesRef.current)) {
         if (image.pendingDeletion) {
           continue;
         }
-        // 削除チェック
-        if (deletionTimeMs > 0 && image.createdAt && currentTime - image.createdAt >= deletionTimeMs) {
-          console.log(`[AnimationView] 画像 ${image.id} を自動削除（時間経過）`);
+        // 削除チェック（Rust側の display_expiry と同じ基準）
+        const expiresAt = image.expiresAt
+          ?? (image.displayStartedAt !== undefined ? displayExpiresAt(image.displayStartedAt, deletionTime) : null);
+        if (expiresAt !== null && correctedNow >= expiresAt) {
+          console.log(`[AnimationView] 画像 ${image.id} の表示期限切れ（削除はRust側で行う）`);
           image.pendingDeletion = true;
-          toDelete.push(image.id);
+          expired.push(image.id);
           continue; // この画像は更新リストに追加しない（アニメ画面から消す）
         }
 
@@ -752,7 +948,8 @@ const AnimationView: React.FC<AnimationViewProps> = ({
           } else if (div.style.filter !== '') {
             div.style.filter = '';
           }
-          const opacity = moved.highlightOpacity ?? 1;
+          const fade = expiresAt !== null ? Math.min(1, Math.max(0, (expiresAt - correctedNow) / EXPIRY_FADE_MS)) : 1;
+          const opacity = (moved.highlightOpacity ?? 1) * fade;
           const opacityStr = opacity >= 0.999 ? '' : String(opacity);
           if (div.style.opacity !== opacityStr) div.style.opacity = opacityStr;
         }
@@ -801,18 +998,10 @@ const AnimationView: React.FC<AnimationViewProps> = ({
       animatedImagesRef.current = newImageMap;
       // 毎フレームの再レンダリングは行わない
 
-      // 背景で物理削除し、画面からは即時退場
-      if (toDelete.length > 0) {
-        (async () => {
-          const { DatabaseService } = await import('../services/database');
-          try {
-            for (const id of toDelete) {
-              await DatabaseService.deleteImage(id, 'auto');
-              const div = containerRefs.current.get(id);
-              if (div) div.style.display = 'none';
-            }
-          } catch (e) { console.warn('[AnimationView] auto delete failed', e); }
-        })();
+      // 画面からは即時退場（DBからの削除は display_expiry が行う）
+      for (const id of expired) {
+        const div = containerRefs.current.get(id);
+        if (div) div.style.display = 'none';
       }
 
       // パフォーマンス監視（EMA / ヒステリシス）
@@ -875,7 +1064,14 @@ const AnimationView: React.FC<AnimationViewProps> = ({
         return existing;
       }
       // 新しい画像を初期化
-      return initializeImage(img, true);
+      const created = initializeImage(img, true);
+      if (!img.displayStartedAt) {
+        invoke('mark_display_started', { id: img.id }).catch((error) => {
+          console.warn('[AnimationView] 表示開始時刻の記録に失敗しました:', error);
+        });
+      }
+      applySavedPosition(created);
+      return created;
     });
 
     // 削除された画像を除外
@@ -888,7 +1084,7 @@ const AnimationView: React.FC<AnimationViewProps> = ({
     }, {} as Record<string, AnimatedImage>);
     // レンダー用の配列を更新（DOMノードの生成/破棄のため）
     setAnimatedImages(newImages);
-  }, [inputImages, initializeImage]);
+  }, [inputImages, initializeImage, applySavedPosition]);
 
   // 地面位置が変更されたら歩くタイプの画像の位置を更新
   useEffect(() => {
@@ -928,6 +1124,8 @@ const AnimationView: React.FC<AnimationViewProps> = ({
         <video
           ref={backgroundVideoRef}
           src={backgroundUrl}
+          // 撮影・録画で canvas に描いても読み出せるよう、CORS で読み込む
+          crossOrigin="anonymous"
           autoPlay
           loop
           muted
@@ -985,6 +1183,17 @@ const AnimationView: React.FC<AnimationViewProps> = ({
         <span>お絵かきの数</span>
         <p>{animatedImages.length}</p>
       </div>
+      {takeawayQrs.length > 0 && (
+        <div className={styles.takeawayQrs}>
+          {takeawayQrs.map(qr => (
+            <div key={qr.imageId} className={styles.takeawayQr}>
+              <img src={qr.qrCode} alt="" />
+              <span>スマホで作品を持ち帰る</span>
+            </div>
+          ))}
+        </div>
+      )}
+      {blackout && <div className={styles.blackout} />}
     </div>
   );
 };
@@ -1,7 +1,7 @@
 import { useState, useEffect } from 'react';
 import { open, confirm as tauriConfirm } from '@tauri-apps/plugin-dialog';
 import { readFile } from '@tauri-apps/plugin-fs';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { saveAudioFile, getAllMetadata, deleteImage, loadImage } from '../services/imageStorage';
 import styles from './AudioSettings.module.scss';
 
@@ -1,6 +1,6 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { saveImage } from '../services/imageStorage';
 import styles from './BackgroundRemover.module.scss';
 
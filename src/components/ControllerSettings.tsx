@@ -1,5 +1,6 @@
 import { useEffect, useRef, useState } from 'react';
 import { emit } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
 import styles from './ControllerSettings.module.scss';
 import {
   DEFAULT_CONTROLLER_SETTINGS,
@@ -8,19 +9,46 @@ import {
 } from '../services/controllerSettings';
 import type { ControllerSettings as ControllerSettingsConfig } from '../services/controllerSettings';
 
+type TiltSettings = {
+  enabled: boolean;
+  smoothing: number;
+  maxRateHz: number;
+  deadZone: number;
+  fullTilt: number;
+  neutralBeta: number;
+};
+
 export function ControllerSettings() {
   const [settings, setSettings] = useState<ControllerSettingsConfig>(DEFAULT_CONTROLLER_SETTINGS);
   const [isSaving, setIsSaving] = useState(false);
   const saveTimer = useRef<number | null>(null);
+  const [tilt, setTilt] = useState<TiltSettings | null>(null);
+  const [tiltError, setTiltError] = useState<string | null>(null);
 
   useEffect(() => {
     let mounted = true;
     loadControllerSettings().then((loaded) => {
       if (mounted) setSettings(loaded);
     });
+    invoke<TiltSettings>('get_tilt_control')
+      .then((loaded) => { if (mounted) setTilt(loaded); })
+      .catch((e) => console.warn('[ControllerSettings] get_tilt_control failed:', e));
     return () => { mounted = false; };
   }, []);
 
+  const updateTilt = async (partial: Partial<TiltSettings>) => {
+    if (!tilt) return;
+    const next = { ...tilt, ...partial };
+    setTilt(next);
+    try {
+      await invoke('set_tilt_control', { settings: next });
+      setTiltError(null);
+    } catch (e) {
+      setTiltError(String(e));
+      setTilt(tilt);
+    }
+  };
+
   useEffect(() => {
     return () => {
       if (saveTimer.current) window.clearTimeout(saveTimer.current);
@@ -69,6 +97,45 @@ export function ControllerSettings() {
         </div>
       </section>
 
+      {tilt && (
+        <section className={styles.card}>
+          <div className={styles.header}>
+            <h2 className={styles.title}>傾き操作</h2>
+          </div>
+          <p className={styles.description}>
+            スマホを傾けてキャラクターを動かせるようにします。
+            オフにするとボタンでの操作だけになります。
+          </p>
+          <div className={styles.sliderRow}>
+            <label>
+              <input
+                type="checkbox"
+                checked={tilt.enabled}
+                onChange={(e) => updateTilt({ enabled: e.target.checked })}
+              />
+              傾き操作を有効にする
+            </label>
+          </div>
+          <div className={styles.sliderRow}>
+            <label htmlFor="tilt-full">最大速度になる傾き ({tilt.fullTilt}°)</label>
+            <input
+              id="tilt-full"
+              type="range"
+              min={Math.ceil(tilt.deadZone) + 5}
+              max={90}
+              step={5}
+              value={tilt.fullTilt}
+              disabled={!tilt.enabled}
+              onChange={(e) => updateTilt({ fullTilt: Number(e.target.value) })}
+            />
+            <p className={styles.valueNote}>
+              小さいほど少し傾けただけで速く動きます。
+            </p>
+          </div>
+          {tiltError && <p className={styles.valueNote}>{tiltError}</p>}
+        </section>
+      )}
+
       <section className={styles.futureNote}>
         <strong>今後の予定:</strong>
         <p>
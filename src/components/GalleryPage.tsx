@@ -5,6 +5,7 @@ import { MovementSettings } from './MovementSettings';
 import { getAllMovementSettings, updateMovementSettings } from '../services/movementStorage';
 import styles from './GalleryPage.module.scss';
 import { useWorkspaceStore } from '../stores/workspaceStore';
+import { displayExpiresAt, parseDeletionMinutes } from '../utils/displayExpiry';
 
 interface GalleryImage extends Omit<ImageMetadata, 'size'> {
   thumbnailUrl?: string;
@@ -53,12 +54,10 @@ const formatSpeed = (speed?: number) => {
 };
 
 function renderRemaining(image: any, deletionTime: string) {
-  if (deletionTime === 'unlimited') return '無制限';
-  const minutes = parseInt(deletionTime);
-  if (!minutes || isNaN(minutes)) return '無制限';
+  if (parseDeletionMinutes(deletionTime) === null) return '無制限';
   const started = (image as any).display_started_at ? Date.parse((image as any).display_started_at) : undefined;
   if (!started) return '未表示';
-  const deadline = started + minutes * 60 * 1000;
+  const deadline = displayExpiresAt(started, deletionTime) ?? started;
   const left = Math.max(0, deadline - Date.now());
   const mm = Math.floor(left / 60000);
   const ss = Math.floor((left % 60000) / 1000);
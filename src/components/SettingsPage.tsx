@@ -1,13 +1,14 @@
 import React, { useState, useEffect } from 'react';
 import { open, confirm } from '@tauri-apps/plugin-dialog';
-// import { invoke } from '@tauri-apps/api/core';
+import { invoke } from '@tauri-apps/api/core';
 import { readFile } from '@tauri-apps/plugin-fs';
 import { AudioSettings } from './AudioSettings';
 import { GroundSetting } from './GroundSetting';
 
 console.log('[SettingsPage] Starting imports...');
 
-import { emit, listen } from '@tauri-apps/api/event';
+import { emit } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { getAllMetadata, loadImage, deleteImage, saveBackgroundFile } from '../services/imageStorage';
 import { WorkspaceManager } from '../services/workspaceManager';
 import { useWorkspaceStore } from '../stores/workspaceStore';
@@ -15,10 +16,25 @@ import { AppSettingsService } from '../services/database';
 import { GlobalSettingsService } from '../services/globalSettings';
 import styles from './SettingsPage.module.scss';
 import { checkForUpdatesManually } from '../services/updater';
-import { activateDevice, deleteDeviceToken, loadDeviceToken, parseJwtExp } from '../services/licenseClient';
+import { activateDevice, deleteDeviceToken, getLicenseStatus, licenseStateLabel, LicenseStatus } from '../services/licenseClient';
 
 console.log('[SettingsPage] All imports completed');
 
+type QrStyle = {
+  highContrast: boolean;
+  inverted: boolean;
+  moduleSize: number;
+  quietZone?: number | null;
+};
+
+const DEFAULT_QR_STYLE: QrStyle = { highContrast: false, inverted: false, moduleSize: 8, quietZone: null };
+
+// 背景除去後の画像の背景（外部の映像ツールで合成する会場向け）
+type OutputBackground =
+  | { mode: 'transparent' }
+  | { mode: 'solid'; color: string }
+  | { mode: 'chroma' };
+
 export function SettingsPage() {
   // Zustandストアから状態を取得
   const {
@@ -49,11 +65,22 @@ export function SettingsPage() {
   const [licenseCode, setLicenseCode] = useState<string>('');
   const [licenseStatus, setLicenseStatus] = useState<string>('未有効化');
   const [licenseExp, setLicenseExp] = useState<number | null>(null);
+  // QRの表示スタイル（暗いプロジェクター向けの高コントラスト表示）
+  const [qrStyle, setQrStyle] = useState<QrStyle>(DEFAULT_QR_STYLE);
+  // 保存ファイル名のテンプレート
+  const [namingTemplate, setNamingTemplate] = useState<string>('{uuid}');
+  // 取り込み画像の背景
+  const [outputBackground, setOutputBackground] = useState<OutputBackground>({ mode: 'transparent' });
 
   // 背景アップロード関連のstate
   const [uploadingBackground, setUploadingBackground] = useState(false);
   const [backgroundProgress, setBackgroundProgress] = useState(0);
 
+  const applyLicenseStatus = (status: LicenseStatus | null) => {
+    setLicenseStatus(licenseStateLabel(status));
+    setLicenseExp(status?.expiresAt ?? null);
+  };
+
   const loadSettings = async () => {
     // Zustandストアがすでに設定を管理しているため、背景画像の読み込みのみ行う
     console.log('[SettingsPage] 現在の設定:', {
@@ -62,6 +89,28 @@ export function SettingsPage() {
       currentWorkspace
     });
 
+    // 保存ファイル名テンプレートの読み込み
+    try {
+      setNamingTemplate(await invoke<string>('get_file_naming_template'));
+    } catch (_) {
+      setNamingTemplate('{uuid}');
+    }
+
+    // 取り込み画像の背景の読み込み
+    try {
+      setOutputBackground(await invoke<OutputBackground>('get_output_background'));
+    } catch (_) {
+      setOutputBackground({ mode: 'transparent' });
+    }
+
+    // QR表示スタイルの読み込み
+    try {
+      const raw = await GlobalSettingsService.get('qr_style');
+      if (raw) setQrStyle({ ...DEFAULT_QR_STYLE, ...JSON.parse(raw) });
+    } catch (_) {
+      setQrStyle(DEFAULT_QR_STYLE);
+    }
+
     // 動作モードの読み込み（デフォルト: auto）
     try {
       const mode = await AppSettingsService.getAppSetting('operation_mode');
@@ -111,17 +160,8 @@ export function SettingsPage() {
         setPcId(generated);
         try { await GlobalSettingsService.save('pcid', generated); } catch {}
       }
-      // License status (device token)
-      try {
-        const tok = await loadDeviceToken();
-        if (tok) {
-          setLicenseStatus('有効化済み');
-          setLicenseExp(parseJwtExp(tok));
-        } else {
-          setLicenseStatus('未有効化');
-          setLicenseExp(null);
-        }
-      } catch {}
+      // License status（検証はRust側で行う）
+      applyLicenseStatus(await getLicenseStatus());
     } catch (_) {}
 
     // （生成は上で一度だけ行う）
@@ -197,6 +237,16 @@ export function SettingsPage() {
     };
   }, []);
 
+  // ライセンス状態の変化（期限切れ・自動更新）を反映
+  useEffect(() => {
+    const unlistenPromise = listen<LicenseStatus>('license-status-changed', (e) => {
+      applyLicenseStatus(e.payload);
+    });
+    return () => {
+      unlistenPromise.then(unlisten => { try { unlisten(); } catch (_) {} });
+    };
+  }, []);
+
   // ワークスペース変更を監視
   useEffect(() => {
     const unlistenPromise = listen('workspace-data-loaded', async () => {
@@ -273,6 +323,21 @@ export function SettingsPage() {
     }
   };
 
+  const handleQrStyleChange = async (next: QrStyle) => {
+    const normalized: QrStyle = {
+      ...next,
+      moduleSize: Math.max(1, Math.min(64, Math.round(next.moduleSize))),
+      quietZone: next.quietZone == null ? null : Math.max(0, Math.min(32, Math.round(next.quietZone))),
+    };
+    setQrStyle(normalized);
+
+    try {
+      await GlobalSettingsService.save('qr_style', JSON.stringify(normalized));
+    } catch (error) {
+      console.error('[SettingsPage] QR表示スタイルの保存エラー:', error);
+    }
+  };
+
   const handleBackgroundSelect = async () => {
     try {
       const selected = await open({
@@ -376,13 +441,12 @@ export function SettingsPage() {
   const handleChangeWorkspace = async () => {
     try {
       setIsChangingWorkspace(true);
-      const selected = await open({
-        directory: true,
-        multiple: false,
+      // Rust側のダイアログで選ぶ（選んだフォルダだけが初期化前に書き込みを許可される）
+      const selected = await invoke<string | null>('pick_workspace_folder', {
         title: '新しいワークスペースフォルダを選択'
       });
 
-      if (selected && typeof selected === 'string') {
+      if (selected) {
         // ワークスペースを切り替えるだけ
         // UIの更新はZustandストアとイベントリスナーが自動的に処理する
         await WorkspaceManager.getInstance().switchWorkspace(selected);
@@ -488,10 +552,13 @@ export function SettingsPage() {
                   if (!code) { alert('ライセンスコードを入力してください'); return; }
                   const res = await activateDevice({ licenseCode: code, pcId: pid });
                   if (res.ok) {
-                    setLicenseStatus('有効化済み');
-                    const tok = await loadDeviceToken();
-                    if (tok) setLicenseExp(parseJwtExp(tok));
-                    alert('有効化しました');
+                    const status = await getLicenseStatus();
+                    applyLicenseStatus(status);
+                    if (status?.state === 'valid') {
+                      alert('有効化しました');
+                    } else {
+                      alert('有効化に失敗しました: ' + (status?.reason || licenseStateLabel(status)));
+                    }
                   } else {
                     alert('有効化に失敗しました: ' + mapLicenseActivationError(res.error));
                   }
@@ -806,6 +873,124 @@ export function SettingsPage() {
         </div>
       </section>
 
+      {/* 保存ファイル名 */}
+      <section className={styles.section}>
+        <h2>保存ファイル名</h2>
+        <div style={{ display: 'flex', gap: 8, maxWidth: 640 }}>
+          <input
+            type="text"
+            value={namingTemplate}
+            onChange={(e) => setNamingTemplate(e.target.value)}
+            style={{ flexGrow: 1, padding: 6, borderRadius: 6, border: '1px solid #ccc' }}
+          />
+          <button
+            onClick={async () => {
+              try {
+                await invoke('set_file_naming_template', { template: namingTemplate });
+                alert('保存しました');
+              } catch (error) {
+                alert('保存に失敗しました: ' + error);
+              }
+            }}
+            className={styles.animationButton}
+          >保存</button>
+        </div>
+        <div className={styles.note}>
+          <p>使用できる項目：{'{date}'} 日付、{'{time}'} 時刻、{'{seq}'} 当日の連番、{'{station}'} 端末ID、{'{original}'} 元のファイル名、{'{uuid}'} 画像ID</p>
+          <p>例：{'{date}-{seq}-{station}-{original}'}（同名のファイルがある場合は末尾に番号が付きます）</p>
+        </div>
+      </section>
+
+      {/* 取り込み画像の背景 */}
+      <section className={styles.section}>
+        <h2>取り込み画像の背景</h2>
+        <div style={{ display: 'flex', gap: 8, alignItems: 'center', maxWidth: 640 }}>
+          <select
+            value={outputBackground.mode}
+            onChange={(e) => {
+              const mode = e.target.value as OutputBackground['mode'];
+              setOutputBackground(mode === 'solid' ? { mode, color: '#ffffff' } : { mode });
+            }}
+          >
+            <option value="transparent">透過（標準）</option>
+            <option value="solid">単色</option>
+            <option value="chroma">クロマキー（緑）</option>
+          </select>
+          {outputBackground.mode === 'solid' && (
+            <input
+              type="color"
+              value={outputBackground.color}
+              onChange={(e) => setOutputBackground({ mode: 'solid', color: e.target.value })}
+            />
+          )}
+          <button
+            onClick={async () => {
+              try {
+                await invoke('set_output_background', { background: outputBackground });
+                alert('保存しました');
+              } catch (error) {
+                alert('保存に失敗しました: ' + error);
+              }
+            }}
+            className={styles.animationButton}
+          >保存</button>
+        </div>
+        <div className={styles.note}>
+          <p>外部の映像ツールで合成する場合は、単色またはクロマキーを選んでください。これから取り込む画像に反映されます。</p>
+        </div>
+      </section>
+
+      {/* QRコードの表示 */}
+      <section className={styles.section}>
+        <h2>QRコードの表示</h2>
+        <div style={{ display: 'grid', gap: 12, maxWidth: 640 }}>
+          <label>
+            <input
+              type="checkbox"
+              checked={qrStyle.highContrast}
+              onChange={(e) => { void handleQrStyleChange({ ...qrStyle, highContrast: e.target.checked }); }}
+            />
+            高コントラスト表示（背景を塗りつぶし、余白を広く取る）
+          </label>
+          <label>
+            <input
+              type="checkbox"
+              checked={qrStyle.inverted}
+              onChange={(e) => { void handleQrStyleChange({ ...qrStyle, inverted: e.target.checked }); }}
+            />
+            白黒を反転する
+          </label>
+          <label>
+            モジュールサイズ（px）
+            <input
+              type="number"
+              min={1}
+              max={64}
+              value={qrStyle.moduleSize}
+              onChange={(e) => { void handleQrStyleChange({ ...qrStyle, moduleSize: Number(e.target.value) || 8 }); }}
+              style={{ marginLeft: 8, width: 80 }}
+            />
+          </label>
+          <label>
+            余白（モジュール数、空欄で自動）
+            <input
+              type="number"
+              min={0}
+              max={32}
+              value={qrStyle.quietZone ?? ''}
+              onChange={(e) => {
+                const v = e.target.value === '' ? null : Number(e.target.value);
+                void handleQrStyleChange({ ...qrStyle, quietZone: v });
+              }}
+              style={{ marginLeft: 8, width: 80 }}
+            />
+          </label>
+        </div>
+        <div className={styles.note}>
+          <p>暗いプロジェクターや明るい会場でQRが読み取りにくい場合にお試しください。次に表示するQRから反映されます。</p>
+        </div>
+      </section>
+
       {/* データベースメンテナンス */}
       <section className={styles.section}>
         <h2>データベース管理</h2>
@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import { open } from '@tauri-apps/plugin-dialog';
 import { readFile } from '@tauri-apps/plugin-fs';
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { saveImage } from '../services/imageStorage';
 import { AppSettingsService } from '../services/database';
 import { saveMovementSettings } from '../services/movementStorage';
@@ -285,13 +285,12 @@ export function UploadPage() {
                 className={styles.selectFolderButton}
                 onClick={async () => {
                   try {
-                    const selected = await open({
-                      directory: true,
-                      multiple: false,
+                    // Rust側のダイアログで選ぶ（選んだフォルダがファイル操作の許可範囲に加わる）
+                    const selected = await invoke<string | null>('add_file_access_root', {
                       title: '設定するフォルダを選択'
                     });
 
-                    if (selected && typeof selected === 'string') {
+                    if (selected) {
                       setAutoImportPath(selected);
                       await AppSettingsService.setAutoImportPath(selected);
                     }
@@ -1,5 +1,5 @@
 import { useState } from 'react';
-import { open } from '@tauri-apps/plugin-dialog';
+import { invoke } from '@tauri-apps/api/core';
 import { useWorkspace } from '../hooks/useWorkspace';
 import styles from './WorkspaceSelector.module.scss';
 
@@ -11,13 +11,12 @@ export function WorkspaceSelector() {
     try {
       setIsSelecting(true);
 
-      const selected = await open({
-        directory: true,
-        multiple: false,
+      // Rust側のダイアログで選ぶ（選んだフォルダだけが初期化前に書き込みを許可される）
+      const selected = await invoke<string | null>('pick_workspace_folder', {
         title: 'ワークスペースフォルダを選択'
       });
 
-      if (selected && typeof selected === 'string') {
+      if (selected) {
         await switchWorkspace(selected);
       }
     } catch (error) {
@@ -1,7 +1,10 @@
-import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import { listen } from './windowListen';
 import { useWorkspaceStore, WorkspaceImage } from '../stores/workspaceStore';
 import { WorkspaceManager, WorkspaceSettings } from '../services/workspaceManager';
 import { DatabaseService, ProcessedImagePreview } from '../services/database';
+import { settingsSync } from '../services/settingsSync';
+import { GlobalSettingsService } from '../services/globalSettings';
 
 type ImageUpsertedPayload = {
   id: string;
@@ -10,17 +13,45 @@ type ImageUpsertedPayload = {
   image_type: string;
   created_at: string;
   display_started_at?: string | null;
+  display_name?: string | null;
+};
+
+type BackgroundSettings = {
+  background_id: string;
+  fit_mode: 'cover' | 'contain' | 'fill' | 'none';
+  focal_x: number;
+  focal_y: number;
+  parallax_layers: Array<{ image_id: string; depth: number }>;
+  updated_at: string;
+};
+
+type GroundLine = {
+  version: number;
+  monitor_id: string;
+  background_id: string | null;
+  points: Array<{ x: number; y: number }>;
+  updated_at: string;
+};
+
+type ControlSmoothing = {
+  movement_type: string;
+  acceleration: number;
+  max_velocity: number;
+  updated_at: string;
 };
 
 type DataChangeEvent =
   | { type: 'image-upserted'; payload: ImageUpsertedPayload }
   | { type: 'image-deleted'; payload: { id: string } }
   | { type: 'audio-updated'; payload: { audio_type: string } }
-  | { type: 'background-changed' }
-  | { type: 'animation-settings-changed'; payload: { image_id: string } }
+  | { type: 'background-changed'; payload?: { background_id: string | null; settings: BackgroundSettings | null } }
+  | { type: 'animation-settings-changed'; payload: { image_id: string; smoothing?: ControlSmoothing } }
+  | { type: 'animation-settings-batch-changed'; payload: { image_ids: string[]; smoothing?: ControlSmoothing } }
+  | { type: 'image-with-settings-saved'; payload: { image: ImageUpsertedPayload } }
   | { type: 'ground-position-changed'; payload: { position: number } }
+  | { type: 'ground-line-changed'; payload: { monitor_id: string; background_id: string | null; line: GroundLine | null } }
   | { type: 'deletion-time-changed'; payload: { time: string } }
-  | { type: 'app-setting-changed'; payload: { key: string; value: string } };
+  | { type: 'app-setting-changed'; payload: { key: string; value: string; version?: number } };
 
 /**
  * Tauriイベントを受信してZustandストアを更新する中央リスナー
@@ -75,8 +106,10 @@ export class TauriEventListener {
         store.setDeletionTime(eventData.payload.time);
         break;
       case 'background-changed':
+        store.setActiveBackgroundId(eventData.payload?.background_id ?? null);
         break;
       case 'app-setting-changed':
+        void settingsSync.onChanged(eventData.payload.key, eventData.payload.value, eventData.payload.version);
         if (eventData.payload.key === 'groundPosition') {
           store.setGroundPosition(parseInt(eventData.payload.value));
         } else if (eventData.payload.key === 'deletionTime') {
@@ -90,23 +123,56 @@ export class TauriEventListener {
         }
         break;
       }
+      case 'image-with-settings-saved': {
+        const workspaceImage = this.convertUpsertedPayload(eventData.payload.image);
+        if (workspaceImage) {
+          store.upsertProcessedImage(workspaceImage);
+        }
+        break;
+      }
       case 'image-deleted':
         if (eventData.payload?.id) {
           store.removeProcessedImage(eventData.payload.id);
         }
         break;
       case 'animation-settings-changed':
+      case 'animation-settings-batch-changed':
+        break;
+      case 'ground-line-changed':
         break;
       case 'audio-updated':
         break;
     }
   }
 
+  // data-changed-batch: 画像の追加・削除はまとめて反映し、それ以外は順に処理する
+  private applyDataChanges(events: DataChangeEvent[]): void {
+    const upserts = new Map<string, WorkspaceImage>();
+    const removedIds = new Set<string>();
+    for (const eventData of events) {
+      if (eventData.type === 'image-upserted') {
+        const workspaceImage = this.convertUpsertedPayload(eventData.payload);
+        if (workspaceImage) {
+          upserts.set(workspaceImage.id, workspaceImage);
+          removedIds.delete(workspaceImage.id);
+        }
+      } else if (eventData.type === 'image-deleted') {
+        if (eventData.payload?.id) {
+          upserts.delete(eventData.payload.id);
+          removedIds.add(eventData.payload.id);
+        }
+      } else {
+        this.applyDataChange(eventData);
+      }
+    }
+    useWorkspaceStore.getState().applyProcessedImageChanges(Array.from(upserts.values()), Array.from(removedIds));
+  }
+
   private flushPendingEvents(): void {
     if (this.pendingEvents.length === 0) return;
     const queue = [...this.pendingEvents];
     this.pendingEvents = [];
-    queue.forEach(event => this.applyDataChange(event));
+    this.applyDataChanges(queue);
   }
 
   /**
@@ -119,7 +185,7 @@ export class TauriEventListener {
     }
 
     this.isHydrating = true;
-    await this.updateImageList();
+    await Promise.all([this.updateImageList(), settingsSync.sync()]);
     this.isHydrating = false;
     this.flushPendingEvents();
 
@@ -136,19 +202,41 @@ export class TauriEventListener {
 
     this.unlisteners.push(dataChangeUnlisten);
 
+    // 連続した変更はRust側で data-changed-batch にまとめて届く
+    const dataChangeBatchUnlisten = await listen<DataChangeEvent[]>('data-changed-batch', (event) => {
+      const events = event.payload;
+      if (!Array.isArray(events) || events.length === 0) return;
+      if (this.isHydrating) {
+        this.pendingEvents.push(...events);
+        return;
+      }
+      this.applyDataChanges(events);
+    });
+
+    this.unlisteners.push(dataChangeBatchUnlisten);
+
     // ワークスペース関連のイベント
-    const workspaceChangedUnlisten = await listen('workspace-changed', async () => {
+    const workspaceChangedUnlisten = await listen<{ path?: string } | null>('workspace-changed', async (event) => {
       const store = useWorkspaceStore.getState();
       const manager = WorkspaceManager.getInstance();
+      // Rust側の switch_workspace で切り替えた場合はパスが届く
+      const path = event.payload?.path;
+      if (path && store.currentWorkspace !== path) {
+        store.setCurrentWorkspace(path);
+      }
       
-      // ワークスペース設定を再読み込み
-      const settings = await manager.getWorkspaceSettings();
-      if (settings) {
-        store.setSettings(settings);
+      // ワークスペース設定を再読み込み（表示専用ウィンドウはメインウィンドウが接続したものを取り直す）
+      if (manager.isViewerWindow()) {
+        await manager.attachCurrentWorkspace();
+      } else {
+        const settings = await manager.getWorkspaceSettings();
+        if (settings) {
+          store.setSettings(settings);
+        }
       }
 
       this.isHydrating = true;
-      await this.updateImageList();
+      await Promise.all([this.updateImageList(), settingsSync.reset()]);
       this.isHydrating = false;
       this.flushPendingEvents();
     });
@@ -165,6 +253,18 @@ export class TauriEventListener {
     });
 
     this.unlisteners.push(workspaceSettingsUpdatedUnlisten);
+
+    // プロビジョニング設定（global_settings.json）が書き換えられたら有効設定を作り直す
+    const provisioningChangedUnlisten = await listen('provisioning-changed', async () => {
+      GlobalSettingsService.reset();
+      try {
+        await GlobalSettingsService.loadEffective();
+      } catch (error) {
+        console.error('[TauriEventListener] Failed to reload provisioning settings:', error);
+      }
+    });
+
+    this.unlisteners.push(provisioningChangedUnlisten);
   }
   
   /**
@@ -0,0 +1,11 @@
+import type { EventCallback, UnlistenFn } from '@tauri-apps/api/event';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+
+/**
+ * このウィンドウ宛てのイベントだけを受け取る listen
+ * （@tauri-apps/api/event の listen は他のウィンドウ宛てに送ったイベントも受け取るため、
+ *   Rust側のウィンドウ別ルーティングが効かなくなる）
+ */
+export function listen<T>(event: string, handler: EventCallback<T>): Promise<UnlistenFn> {
+  return getCurrentWebviewWindow().listen<T>(event, handler);
+}
@@ -6,7 +6,7 @@ import App from "./App";
 import AnimationWindow from "./windows/AnimationWindow";
 import { QrDisplayWindow } from "./windows/QrDisplayWindow";
 import "./styles/reset.scss";
-import { listen } from '@tauri-apps/api/event';
+import { listen } from './events/windowListen';
 
 console.log('[main.tsx] Imports completed');
 
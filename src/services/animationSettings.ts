@@ -163,6 +163,8 @@ export interface AnimatedImage {
   emoteTimer?: number;
   lastUpdateTime?: number;
   createdAt?: number; // 画像が追加された時刻
+  displayStartedAt?: number; // 表示開始時刻（時刻補正済み、削除期限の基準）
+  expiresAt?: number; // display-expiring で届いた削除期限（時刻補正済み）
   deletionTime?: string; // 削除時間設定（'unlimited', '1', '2', etc.）
   // noise smoothing (sampled at intervals)
   noisePrevX?: number;
@@ -1,10 +1,11 @@
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { AppSettingsService, MovementSettingsService, ImageMetadataService } from './database';
 
 interface AutoImportStarted {
   image_id: string;
   original_path: string;
+  tag?: string;
 }
 
 interface AutoImportResult {
@@ -12,17 +13,52 @@ interface AutoImportResult {
   original_path: string;
   processed_path: string;
   animation_settings: AnimationSettings;
+  tag?: string;
 }
 
 interface AutoImportError {
   image_id: string;
   error: string;
+  tag?: string;
+}
+
+// フォルダ監視の取り込みプロファイル（ブースごとの動きや大きさ）
+export interface ImportProfile {
+  movementType?: 'fly' | 'walk' | null;
+  patterns?: string[];
+  speedMin?: number;
+  speedMax?: number;
+  sizeMin?: number;
+  sizeMax?: number;
+  removeBackground?: boolean;
+  tag?: string | null;
+}
+
+// フォルダ監視の設定（サブフォルダの監視と振り分け）
+export interface WatchOptions {
+  recursive?: boolean;
+  include?: string[];
+  exclude?: string[];
+  routes?: Record<string, { tag?: string; imageType?: 'processed' | 'background' | 'bgm' | 'soundEffect' }>;
+  // ファイルサイズがこの時間変わらなければ書き込み完了とみなす（ミリ秒）
+  stableMs?: number;
+  // 取り込み後の元ファイル（leave: 残す / archive: imported/ へ移動 / delete: 削除）
+  afterImport?: 'leave' | 'archive' | 'delete';
+  // 動きの範囲や背景除去の有無（省略時は前回保存したもの）
+  profile?: ImportProfile;
+}
+
+interface DuplicateSkipped {
+  original_path: string;
+  content_hash: string;
+  existing_image_id: string | null;
 }
 
 interface AnimationSettings {
   animation_type: string;
   speed: number;
   size: number;
+  movement_type?: 'fly' | 'walk';
 }
 
 export class AutoImportService {
@@ -41,7 +77,7 @@ export class AutoImportService {
     return AutoImportService.instance;
   }
 
-  async startWatching(watchPath: string): Promise<void> {
+  async startWatching(watchPath: string, options?: WatchOptions): Promise<void> {
     if (this.isWatching || AutoImportService.isStarting) {
       console.warn('Already watching or starting to watch a folder');
       return;
@@ -52,7 +88,7 @@ export class AutoImportService {
 
     try {
       // Rust側でフォルダ監視を開始
-      await invoke('start_folder_watching', { watchPath });
+      await invoke('start_folder_watching', { watchPath, options });
       
       // 複数のイベントリスナーを設定
       const startListener = await listen<AutoImportStarted>('auto-import-started', (event) => {
@@ -70,7 +106,17 @@ export class AutoImportService {
         this.handleAutoImportError(event.payload);
       });
       
-      this.unlisteners = [startListener, completeListener, errorListener];
+      // 同じ内容のファイルは取り込まれない（ログのみ）
+      const duplicateListener = await listen<DuplicateSkipped>('duplicate-skipped', (event) => {
+        console.log('Duplicate import skipped:', event.payload);
+      });
+      
+      // フォルダが見えなくなった（USBの取り外しなど）。戻れば Rust 側で自動的に再開する
+      const watchErrorListener = await listen<{ watch_path: string; error: string }>('folder-watch-error', (event) => {
+        console.warn('Folder watch error:', event.payload);
+      });
+      
+      this.unlisteners = [startListener, completeListener, errorListener, duplicateListener, watchErrorListener];
       this.isWatching = true;
       console.log(`Started watching folder: ${watchPath}`);
       
@@ -159,10 +205,10 @@ export class AutoImportService {
         try { await ImageMetadataService.updateImageFilePath(image_id, processed_path); } catch {}
       }
 
-      // 取込時は常に「浮遊(fly)」で登録（ギャラリーからの手動編集は従来どおり可能）
+      // 取込時は「浮遊(fly)」で登録（取り込みプロファイルで歩く動きを指定した場合のみ walk）
       await MovementSettingsService.saveMovementSettings({
         image_id,
-        movement_type: 'fly',
+        movement_type: animation_settings.movement_type ?? 'fly',
         movement_pattern: animation_settings.animation_type,
         speed: animation_settings.speed,
         size: animation_settings.size.toString(),
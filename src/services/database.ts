@@ -46,6 +46,12 @@ export interface MovementSettings {
   size: string;
   created_at: string;
   updated_at: string;
+  gravity?: number | null;
+  bounce_elasticity?: number | null;
+  rotation_speed?: number | null;
+  amplitude?: number | null;
+  direction_bias?: number | null;
+  z_order?: number | null;
 }
 
 export class DatabaseService {
@@ -60,6 +66,15 @@ export class DatabaseService {
     return await invoke<string>('get_current_timestamp');
   }
 
+  // 保存用ファイル名へ正規化（全角・特殊文字を安全な形に変換）
+  static async sanitizeFileName(name: string): Promise<string> {
+    return await invoke<string>('sanitize_file_name', { name });
+  }
+
+  static async generateSavedFileName(dir: string, imageId: string, originalFileName: string, extension: string): Promise<string> {
+    return await invoke<string>('generate_saved_file_name', { dir, imageId, originalFileName, extension });
+  }
+
   // 画像のfile_pathを更新
   static async updateImageFilePath(id: string, filePath: string): Promise<void> {
     await invoke('update_image_file_path', { id, filePath });
@@ -172,6 +187,11 @@ export class DatabaseService {
     await invoke('save_movement_settings', { settings });
   }
 
+  // 画像メタデータと動き設定を同時に保存
+  static async saveImageWithSettings(metadata: ImageMetadata, settings: MovementSettings): Promise<void> {
+    await invoke('save_image_with_settings', { metadata, settings });
+  }
+
   // 動き設定の取得
   static async getMovementSettings(imageId: string): Promise<MovementSettings | null> {
     return await invoke<MovementSettings | null>('get_movement_settings', { imageId });
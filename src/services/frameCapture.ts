@@ -0,0 +1,89 @@
+/**
+ * アニメーション画面の静止画保存
+ * Rust からの依頼を受けて表示中の背景とキャラクターを canvas に描き、PNG を返す
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
+
+interface CaptureRequest {
+  requestId: string;
+}
+
+function loadImage(src: string): Promise<HTMLImageElement> {
+  return new Promise((resolve, reject) => {
+    const img = new Image();
+    img.onload = () => resolve(img);
+    img.onerror = reject;
+    img.src = src;
+  });
+}
+
+// 背景画像は CSS の background-image から取り出す
+function backgroundImageUrl(container: HTMLElement): string | null {
+  const match = /url\(["']?(.*?)["']?\)/.exec(getComputedStyle(container).backgroundImage);
+  return match ? match[1] : null;
+}
+
+export async function renderSceneToPng(container: HTMLElement): Promise<string> {
+  const rect = container.getBoundingClientRect();
+  const scale = window.devicePixelRatio || 1;
+  const canvas = document.createElement('canvas');
+  canvas.width = Math.round(rect.width * scale);
+  canvas.height = Math.round(rect.height * scale);
+  const ctx = canvas.getContext('2d');
+  if (!ctx) throw new Error('canvas を作成できません');
+  ctx.scale(scale, scale);
+
+  const video = container.querySelector('video');
+  const bgUrl = backgroundImageUrl(container);
+  if (video && video.readyState >= 2) {
+    ctx.drawImage(video, 0, 0, rect.width, rect.height);
+  } else if (bgUrl) {
+    try {
+      ctx.drawImage(await loadImage(bgUrl), 0, 0, rect.width, rect.height);
+    } catch {
+      ctx.fillStyle = '#E0F6FF';
+      ctx.fillRect(0, 0, rect.width, rect.height);
+    }
+  } else {
+    const gradient = ctx.createLinearGradient(0, 0, 0, rect.height);
+    gradient.addColorStop(0, '#87CEEB');
+    gradient.addColorStop(0.5, '#E0F6FF');
+    gradient.addColorStop(1, '#E0F6FF');
+    ctx.fillStyle = gradient;
+    ctx.fillRect(0, 0, rect.width, rect.height);
+  }
+
+  // 画面上の位置・大きさそのままでキャラクターを重ねる
+  container.querySelectorAll('img').forEach(img => {
+    if (!img.complete || img.naturalWidth === 0) return;
+    const r = img.getBoundingClientRect();
+    const opacity = parseFloat(getComputedStyle(img).opacity || '1');
+    ctx.globalAlpha = Number.isFinite(opacity) ? opacity : 1;
+    ctx.drawImage(img, r.left - rect.left, r.top - rect.top, r.width, r.height);
+  });
+  ctx.globalAlpha = 1;
+
+  return canvas.toDataURL('image/png');
+}
+
+export async function listenCaptureRequests(
+  getContainer: () => HTMLElement | null
+): Promise<UnlistenFn> {
+  return listen<CaptureRequest>('capture-frame-request', async event => {
+    const { requestId } = event.payload;
+    try {
+      const container = getContainer();
+      if (!container) throw new Error('表示領域がありません');
+      const data = await renderSceneToPng(container);
+      await invoke('submit_animation_frame', { requestId, data });
+    } catch (e) {
+      console.warn('[frameCapture] 撮影に失敗しました:', e);
+      // 時間切れまで待たせず、失敗した理由をRust側へ返す
+      const message = e instanceof Error ? e.message : String(e);
+      invoke('report_animation_frame_error', { requestId, message }).catch(() => {});
+    }
+  });
+}
@@ -46,6 +46,16 @@ function deepMerge<T>(base: any, over: any): T {
   return out as T;
 }
 
+// Rust側の型付きグローバル設定（app_data_dir/global_settings.json）
+export type StoredGlobalSettings = {
+  version: number;
+  relay: { baseUrl?: string; eventId?: string; pcId?: string; wsProtocol?: string };
+  defaults: { operationMode?: 'auto'|'relay'|'local' };
+  ui: { hideRelaySettings?: boolean; lockRelaySettings?: boolean };
+  network: { port?: number; portRange?: string; tlsEnabled?: boolean };
+  [key: string]: any;
+};
+
 let effectiveCache: EffectiveSettings | null = null;
 let lockRelay = false;
 
@@ -66,6 +76,17 @@ export class GlobalSettingsService {
     }
   }
 
+  static async getStored(): Promise<StoredGlobalSettings> {
+    return await invoke<StoredGlobalSettings>('get_global_settings');
+  }
+
+  // 入れ子のままマージして保存（nullを渡した項目は削除）
+  static async update(patch: Record<string, any>): Promise<StoredGlobalSettings> {
+    const updated = await invoke<StoredGlobalSettings>('update_global_settings', { patch });
+    GlobalSettingsService.reset();
+    return updated;
+  }
+
   static async loadEffective(): Promise<EffectiveSettings> {
     if (effectiveCache) return effectiveCache;
     // 1) read bundle/user/env provisioning JSONs
@@ -35,7 +35,7 @@ let migrationChecked = false;
  */
 export async function saveBackgroundFile(dataUrl: string, fileName: string): Promise<ImageMetadata> {
   const id = await DatabaseService.generateId();
-  const savedFileName = `background-${id}-${fileName}`;
+  const savedFileName = `background-${id}-${await DatabaseService.sanitizeFileName(fileName)}`;
   const saveDir = await AppSettingsService.getSaveDirectory();
   
   // ディレクトリパスを構築
@@ -90,7 +90,7 @@ export async function saveBackgroundFile(dataUrl: string, fileName: string): Pro
  */
 export async function saveAudioFile(dataUrl: string, fileName: string, type: 'bgm' | 'soundEffect'): Promise<ImageMetadata> {
   const id = await DatabaseService.generateId();
-  const savedFileName = `${type}-${id}-${fileName}`;
+  const savedFileName = `${type}-${id}-${await DatabaseService.sanitizeFileName(fileName)}`;
   const saveDir = await AppSettingsService.getSaveDirectory();
   
   // ディレクトリパスを構築
@@ -238,15 +238,20 @@ export async function saveImage(
   try {
     await initializeStorage();
 
-    // ユニークなファイル名を生成
-    const timestamp = new Date().toISOString().replace(/[:.]/g, '-');
-    const extension = originalFileName.split('.').pop() || 'png';
-    const savedFileName = `${timestamp}-${Math.random().toString(36).substr(2, 9)}.${extension}`;
-
     // 保存パスを決定
     const saveDir = await AppSettingsService.getSaveDirectory();
     
     const subDir = type === 'original' ? ORIGINALS_DIR : PROCESSED_DIR;
+
+    // 命名テンプレートの設定に従ってファイル名を生成（処理済みはPNG）
+    const id = await DatabaseService.generateId();
+    const extension = type === 'processed' ? 'png' : (originalFileName.split('.').pop() || 'png');
+    const savedFileName = await DatabaseService.generateSavedFileName(
+      await join(saveDir, IMAGES_DIR, subDir),
+      id,
+      originalFileName,
+      extension
+    );
     const imagePath = await join(saveDir, IMAGES_DIR, subDir, savedFileName);
 
     // 画像データをバイナリに変換して保存
@@ -275,7 +280,7 @@ export async function saveImage(
 
     // データベースにメタデータを保存
     const dbMetadata: any = {
-      id: await DatabaseService.generateId(),
+      id,
       original_file_name: originalFileName,
       saved_file_name: savedFileName,
       image_type: type,
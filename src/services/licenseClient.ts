@@ -61,6 +61,32 @@ export async function deleteDeviceToken(): Promise<void> {
   try { await invoke('delete_license_token'); } catch {}
 }
 
+// Rust側で検証したライセンス状態（時刻はUNIX秒）
+export type LicenseStatus = {
+  state: 'unlicensed' | 'valid' | 'grace' | 'expired' | 'invalid';
+  expiresAt: number | null;
+  graceUntil: number | null;
+  licenseId: string | null;
+  reason: string | null;
+  lastRenewedAt: number | null;
+  nextRenewalAt: number | null;
+  lastRenewalError: string | null;
+};
+
+export async function getLicenseStatus(): Promise<LicenseStatus | null> {
+  try { return await invoke<LicenseStatus>('get_license_status'); } catch { return null; }
+}
+
+export function licenseStateLabel(status: LicenseStatus | null): string {
+  switch (status?.state) {
+    case 'valid': return '有効化済み';
+    case 'grace': return '期限切れ（猶予期間中）';
+    case 'expired': return '期限切れ';
+    case 'invalid': return '無効なトークン';
+    default: return '未有効化';
+  }
+}
+
 export function parseJwtExp(token: string): number | null {
   try { const p = JSON.parse(atob(token.split('.')[1].replace(/-/g,'+').replace(/_/g,'/'))); return typeof p?.exp === 'number' ? p.exp : null; } catch { return null; }
 }
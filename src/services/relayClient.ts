@@ -1,5 +1,5 @@
 import { GlobalSettingsService } from './globalSettings';
-import { loadDeviceToken } from './licenseClient';
+import { invoke } from '@tauri-apps/api/core';
 import { PROTOCOL_VERSION } from '../protocol/version';
 
 export type RelayResponse<T> = { ok: true; data: T } | { ok: false; status?: number; error?: string; retryAfterMs?: number; code?: string };
@@ -38,15 +38,6 @@ async function baseUrl(): Promise<string> {
   return resolveBaseUrl();
 }
 
-function parseRetryAfter(header: string | null): number | undefined {
-  if (!header) return undefined;
-  const seconds = parseInt(header, 10);
-  if (!Number.isNaN(seconds)) return seconds * 1000;
-  const date = Date.parse(header);
-  if (!Number.isNaN(date)) return Math.max(0, date - Date.now());
-  return undefined;
-}
-
 export async function registerPc(params: { eventId: string; pcid: string; force?: boolean }): Promise<RelayResponse<{ ok: true }>> {
   const cacheKey = `${params.eventId}:${params.pcid}`;
   const now = Date.now();
@@ -57,35 +48,13 @@ export async function registerPc(params: { eventId: string; pcid: string; force?
     }
   }
 
+  // デバイストークンはRust側で付ける（WebViewには渡さない）
   const base = await baseUrl();
-  const path = `/e/${encodeURIComponent(params.eventId)}/register-pc`;
-  const body = { pcid: params.pcid };
-  const bearer = await loadDeviceToken();
-  if (!bearer) {
-    return { ok: false, error: 'E_MISSING_TOKEN' };
-  }
-
   try {
-    const url = base + path;
-    const res = await fetch(url, {
-      method: 'POST',
-      headers: { 'Content-Type': 'application/json; charset=utf-8', 'Authorization': `Bearer ${bearer}` },
-      body: JSON.stringify(body),
-      credentials: 'omit'
-    });
-    if (res.status === 429 || res.status === 503) {
-      return { ok: false, status: res.status, retryAfterMs: parseRetryAfter(res.headers.get('Retry-After')) };
-    }
-    if (!res.ok) {
-      let code: string | undefined;
-      try { const err = await res.json(); code = err?.code; } catch {}
-      if (res.status === 409 && code === 'E_ALREADY_REGISTERED') {
-        registerCache.set(cacheKey, now);
-        return { ok: true, data: { ok: true } as any };
-      }
-      return { ok: false, status: res.status, code };
+    const res = await invoke<RelayResponse<{ ok: true }>>('relay_register_pc', { baseUrl: base, eventId: params.eventId, pcid: params.pcid });
+    if (!res.ok && !(res.status === 409 && res.code === 'E_ALREADY_REGISTERED')) {
+      return res;
     }
-    await res.json();
     registerCache.set(cacheKey, now);
     return { ok: true, data: { ok: true } as any };
   } catch (e: any) {
@@ -96,22 +65,13 @@ export async function registerPc(params: { eventId: string; pcid: string; force?
 
 export async function pendingSid(params: { eventId: string; pcid: string; sid: string; ttl: number; ts?: number }): Promise<RelayResponse<{ ok: true }>> {
   const base = await baseUrl();
-  const path = `/e/${encodeURIComponent(params.eventId)}/pending-sid`;
   // clamp ttl to [30,120]
   const ttl = Math.max(30, Math.min(120, Math.floor(params.ttl)));
-  const body = { pcid: params.pcid, sid: params.sid, ttl };
-  const bearer = await loadDeviceToken();
-  if (bearer) {
-    try {
-      const url = base + path;
-      const res = await fetch(url, { method: 'POST', headers: { 'Content-Type': 'application/json; charset=utf-8', 'Authorization': `Bearer ${bearer}` }, body: JSON.stringify(body), credentials: 'omit' });
-      if (res.status === 429 || res.status === 503) return { ok: false, status: res.status, retryAfterMs: parseRetryAfter(res.headers.get('Retry-After')) };
-      if (!res.ok) { let code: string | undefined; try { const err = await res.json(); code = err?.code; } catch {}; return { ok: false, status: res.status, code }; }
-      const data = await res.json();
-      return { ok: true, data } as RelayResponse<{ ok: true }>;
-    } catch (e: any) { return { ok: false, error: e?.message || String(e) }; }
+  try {
+    return await invoke<RelayResponse<{ ok: true }>>('relay_pending_sid', { baseUrl: base, eventId: params.eventId, pcid: params.pcid, sid: params.sid, ttl });
+  } catch (e: any) {
+    return { ok: false, error: e?.message || String(e) };
   }
-  return { ok: false, error: 'E_MISSING_TOKEN' };
 }
 
 export async function getHealthz(): Promise<RelayResponse<{ ok: boolean; version: number }>> {
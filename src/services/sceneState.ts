@@ -0,0 +1,72 @@
+/**
+ * アニメーション画面の状態保存
+ * 表示中のキャラクターと位置を定期的に保存し、ウィンドウの再起動後に復元する
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
+
+export interface SceneCharacter {
+  id: string;
+  x: number;
+  y: number;
+  velocityX: number;
+  velocityY: number;
+  flipped: boolean;
+}
+
+export interface SceneState {
+  characters: SceneCharacter[];
+}
+
+interface SceneStateUpdatedPayload {
+  source: string;
+  state: SceneState;
+}
+
+interface SceneStateSnapshot {
+  state: SceneState;
+  savedAt: string;
+}
+
+// これより古い保存内容は復元しない
+const MAX_RESTORE_AGE_MS = 10 * 60 * 1000;
+
+export async function persistSceneState(state: SceneState): Promise<void> {
+  try {
+    await invoke('persist_scene_state', { json: JSON.stringify(state) });
+  } catch (e) {
+    console.warn('[sceneState] 保存に失敗しました:', e);
+  }
+}
+
+export async function loadSceneState(): Promise<SceneState | null> {
+  try {
+    const snapshot = await invoke<SceneStateSnapshot | null>('load_scene_state');
+    if (!snapshot || !Array.isArray(snapshot.state?.characters)) return null;
+    const age = Date.now() - new Date(snapshot.savedAt).getTime();
+    if (!(age >= 0 && age <= MAX_RESTORE_AGE_MS)) return null;
+    return snapshot.state;
+  } catch (e) {
+    console.warn('[sceneState] 読み込みに失敗しました:', e);
+    return null;
+  }
+}
+
+// 複製表示（animation-<suffix>）のウィンドウは自分では保存せず、元のウィンドウの状態に合わせる
+export function isCloneWindow(): boolean {
+  return getCurrentWebviewWindow().label.startsWith('animation-');
+}
+
+export async function listenSceneState(
+  onUpdate: (state: SceneState) => void
+): Promise<UnlistenFn> {
+  const self = getCurrentWebviewWindow().label;
+  return listen<SceneStateUpdatedPayload>('scene-state-updated', event => {
+    const { source, state } = event.payload;
+    if (source === self || !Array.isArray(state?.characters)) return;
+    onUpdate(state);
+  });
+}
@@ -0,0 +1,94 @@
+import { invoke } from '@tauri-apps/api/core';
+
+type SettingChange = { key: string; value: string | null };
+
+type SettingsDelta = {
+  version: number;
+  full: boolean;
+  changes: SettingChange[];
+};
+
+type Listener = (key: string, value: string | null) => void;
+
+/**
+ * app_settings のウィンドウ内キャッシュ
+ * 変更イベントのバージョンが連続していればそのまま反映し、飛びがあれば差分だけ取得する
+ */
+class SettingsSync {
+  private version = 0;
+  private values = new Map<string, string>();
+  private listeners = new Set<Listener>();
+  private syncing: Promise<void> | null = null;
+
+  get(key: string): string | null {
+    return this.values.get(key) ?? null;
+  }
+
+  getVersion(): number {
+    return this.version;
+  }
+
+  subscribe(listener: Listener): () => void {
+    this.listeners.add(listener);
+    return () => { this.listeners.delete(listener); };
+  }
+
+  /** ワークスペース切り替え時などにキャッシュを捨てて取り直す */
+  async reset(): Promise<void> {
+    this.version = 0;
+    await this.sync();
+  }
+
+  /** app-setting-changed を受け取ったとき */
+  async onChanged(key: string, value: string, version?: number): Promise<void> {
+    if (typeof version === 'number' && version === this.version + 1) {
+      this.version = version;
+      this.set(key, value);
+      return;
+    }
+    if (typeof version === 'number' && version <= this.version) {
+      return;
+    }
+    await this.sync();
+  }
+
+  /** 手元のバージョン以降の変更を取得（同時に呼ばれても1回にまとめる） */
+  sync(): Promise<void> {
+    if (!this.syncing) {
+      this.syncing = this.fetchDelta().finally(() => { this.syncing = null; });
+    }
+    return this.syncing;
+  }
+
+  private async fetchDelta(): Promise<void> {
+    try {
+      const delta = await invoke<SettingsDelta>('get_settings_delta', { sinceVersion: this.version });
+      if (delta.full) {
+        const removed = [...this.values.keys()].filter((key) => !delta.changes.some((c) => c.key === key));
+        this.values.clear();
+        removed.forEach((key) => this.notify(key, null));
+      }
+      delta.changes.forEach((change) => this.set(change.key, change.value));
+      this.version = delta.version;
+    } catch (error) {
+      console.warn('[settingsSync] 差分の取得に失敗しました:', error);
+    }
+  }
+
+  private set(key: string, value: string | null): void {
+    if (value === null) {
+      this.values.delete(key);
+    } else {
+      this.values.set(key, value);
+    }
+    this.notify(key, value);
+  }
+
+  private notify(key: string, value: string | null): void {
+    this.listeners.forEach((listener) => {
+      try { listener(key, value); } catch (error) { console.error('[settingsSync] listener error:', error); }
+    });
+  }
+}
+
+export const settingsSync = new SettingsSync();
@@ -1,6 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import { join } from '@tauri-apps/api/path';
 import { emit } from '@tauri-apps/api/event';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { useWorkspaceStore } from '../stores/workspaceStore';
 
 // ワークスペース関連のイベントタイプ
@@ -39,6 +40,15 @@ export interface WorkspaceInfo {
   settings?: WorkspaceSettings;
 }
 
+// ワークスペースの置き場所の候補
+export interface WorkspaceLocation {
+  kind: 'documents' | 'pictures';
+  parentDir: string;
+  suggestedPath: string;
+  freeBytes: number | null;
+  alreadyExists: boolean;
+}
+
 // ワークスペースイベント
 export interface WorkspaceEvent {
   type: WorkspaceEventType;
@@ -68,6 +78,31 @@ export class WorkspaceManager {
     return useWorkspaceStore.getState().currentWorkspace;
   }
 
+  /**
+   * 表示専用ウィンドウ（アニメーション・QR）か（ワークスペースへの接続やファイル操作はメインウィンドウのみ）
+   */
+  isViewerWindow(): boolean {
+    return getCurrentWebviewWindow().label !== 'main';
+  }
+
+  /**
+   * メインウィンドウが接続したワークスペースを使う（表示専用ウィンドウ向け。未接続ならnull）
+   */
+  async attachCurrentWorkspace(): Promise<string | null> {
+    const current = await invoke<{ path: string; settings: WorkspaceSettings | null } | null>('get_current_workspace');
+    if (!current) {
+      return null;
+    }
+    if (current.settings) {
+      if (typeof current.settings.imageDisplaySize !== 'number') {
+        current.settings.imageDisplaySize = 18;
+      }
+      useWorkspaceStore.getState().setSettings(current.settings);
+    }
+    useWorkspaceStore.getState().setCurrentWorkspace(current.path);
+    return current.path;
+  }
+
   /**
    * ワークスペースの存在確認
    */
@@ -266,6 +301,27 @@ export class WorkspaceManager {
     });
   }
 
+  /**
+   * ワークスペースの置き場所の候補（書類・ピクチャ）
+   */
+  async suggestWorkspaceLocations(): Promise<WorkspaceLocation[]> {
+    return await invoke<WorkspaceLocation[]>('suggest_workspace_locations');
+  }
+
+  /**
+   * フォルダ構成とDBをまとめて作成し、作成したパスを返す
+   */
+  async createWorkspace(parentDir: string, name: string): Promise<string> {
+    return await invoke<string>('create_workspace', { parentDir, name });
+  }
+
+  /**
+   * 異常終了で残ったワークスペースのロックを解除（解除したらtrue）
+   */
+  async forceUnlockWorkspace(path: string): Promise<boolean> {
+    return await invoke<boolean>('force_unlock_workspace', { path });
+  }
+
   /**
    * 最後に使用したワークスペースをクリア
    */
@@ -302,6 +358,12 @@ export class WorkspaceManager {
       return state.settings;
     }
     
+    // 表示専用ウィンドウはメインウィンドウが接続したワークスペースの設定を使う
+    if (this.isViewerWindow()) {
+      await this.attachCurrentWorkspace();
+      return useWorkspaceStore.getState().settings;
+    }
+
     // ストアにない場合はファイルから読み込む
     const currentWorkspace = state.currentWorkspace;
     if (!currentWorkspace) {
@@ -34,6 +34,18 @@ export const useAppStore = create<AppStore>((set, get) => ({
   // 初期化処理（厳密な順序制御）
   initialize: async () => {
     const manager = WorkspaceManager.getInstance();
+
+    // 表示専用ウィンドウは接続せず、メインウィンドウが開いたワークスペースを使う
+    if (manager.isViewerWindow()) {
+      try {
+        const path = await manager.attachCurrentWorkspace();
+        set(path ? { status: 'ready', currentWorkspace: path, error: null } : { status: 'workspace-needed' });
+      } catch (error) {
+        console.error('[AppStore] ワークスペースの取得エラー:', error);
+        set({ status: 'workspace-needed', error: null });
+      }
+      return;
+    }
     
     try {
       // ステップ1: グローバル設定から最後のワークスペースを取得
@@ -26,6 +26,8 @@ interface WorkspaceState {
   deletionTime: string;
   backgroundUrl: string | null;
   backgroundType: 'image' | 'video';
+  // 背景スケジュールで有効になっている背景（未設定ならnull）
+  activeBackgroundId: string | null;
   settings: any | null;
   isLoading: boolean;
   processedImages: WorkspaceImage[];
@@ -37,6 +39,7 @@ interface WorkspaceState {
   setGroundPosition: (position: number) => void;
   setDeletionTime: (time: string) => void;
   setBackground: (url: string | null, type: 'image' | 'video') => void;
+  setActiveBackgroundId: (id: string | null) => void;
   setSettings: (settings: any) => void;
   updateSettings: (partialSettings: Partial<any>) => void;
   setLoading: (loading: boolean) => void;
@@ -44,6 +47,7 @@ interface WorkspaceState {
   setProcessedImages: (images: WorkspaceImage[]) => void;
   upsertProcessedImage: (image: WorkspaceImage) => void;
   removeProcessedImage: (id: string) => void;
+  applyProcessedImageChanges: (upserts: WorkspaceImage[], removedIds: string[]) => void;
   setProcessedCursor: (cursor: number | null) => void;
   setImageDisplaySize: (size: number) => void;
 }
@@ -93,6 +97,7 @@ export const useWorkspaceStore = create<WorkspaceState>((set) => ({
   deletionTime: 'unlimited',
   backgroundUrl: null,
   backgroundType: 'image',
+  activeBackgroundId: null,
   settings: null,
   isLoading: false,
   processedImages: [],
@@ -133,6 +138,10 @@ export const useWorkspaceStore = create<WorkspaceState>((set) => ({
     });
     // 背景は保存対象外なので saveStateToFile は呼ばない
   },
+
+  setActiveBackgroundId: (id) => {
+    set({ activeBackgroundId: id });
+  },
   
   setSettings: (settings) => {
     set((state) => ({
@@ -172,6 +181,7 @@ export const useWorkspaceStore = create<WorkspaceState>((set) => ({
       deletionTime: 'unlimited',
       backgroundUrl: null,
       backgroundType: 'image',
+      activeBackgroundId: null,
       settings: null,
       isLoading: false,
       processedImages: [],
@@ -206,6 +216,20 @@ export const useWorkspaceStore = create<WorkspaceState>((set) => ({
     saveStateToFile();
   },
 
+  // まとめて届いた追加・削除を1回の更新で反映（一括取り込み時の再描画を抑える）
+  applyProcessedImageChanges: (upserts, removedIds) => {
+    if (upserts.length === 0 && removedIds.length === 0) return;
+    set((state) => {
+      const byId = new Map(state.processedImages.map(item => [item.id, item] as const));
+      upserts.forEach(image => byId.set(image.id, image));
+      removedIds.forEach(id => byId.delete(id));
+      const next = Array.from(byId.values());
+      next.sort((a, b) => new Date(b.createdAt).getTime() - new Date(a.createdAt).getTime());
+      return { processedImages: next };
+    });
+    saveStateToFile();
+  },
+
   setProcessedCursor: (cursor) => {
     set({ processedCursor: cursor });
     saveStateToFile();
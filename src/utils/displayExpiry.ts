@@ -0,0 +1,18 @@
+/**
+ * 展示の削除期限（src-tauri/src/display_expiry.rs と同じ基準）
+ * 表示開始時刻（時刻補正済み）に削除設定の分数を足した時刻で消す
+ */
+
+/** "unlimited" や不正な値なら null */
+export function parseDeletionMinutes(deletionTime: string): number | null {
+  const trimmed = deletionTime.trim();
+  if (!/^[+-]?\d+$/.test(trimmed)) return null;
+  const minutes = Number(trimmed);
+  return minutes > 0 ? minutes : null;
+}
+
+/** 削除期限（ミリ秒）。無制限なら null */
+export function displayExpiresAt(displayStartedAt: number, deletionTime: string): number | null {
+  const minutes = parseDeletionMinutes(deletionTime);
+  return minutes === null ? null : displayStartedAt + minutes * 60 * 1000;
+}
@@ -1,13 +1,12 @@
 // src/windows/QrDisplayWindow.tsx
 import React, { useEffect, useState, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { listen } from '../events/windowListen';
 import { useWorkspaceStore, loadStateFromFile } from '../stores/workspaceStore';
 import { AppSettingsService } from '../services/database';
 import { GlobalSettingsService } from '../services/globalSettings';
 import { checkRelayHealth } from '../services/connectivityProbe';
 import { pendingSid, registerPc, retryWithBackoff, resolveBaseUrl, getSidStatus } from '../services/relayClient';
-import { loadDeviceToken } from '../services/licenseClient';
 import { TauriEventListener } from '../events/tauriEventListener';
 import styles from './QrDisplayWindow.module.scss';
 
@@ -48,6 +47,8 @@ export const QrDisplayWindow: React.FC = () => {
   const [banner, setBanner] = useState<string | null>(null);
   const [regenTick, setRegenTick] = useState<number>(0);
   const [showDebug, setShowDebug] = useState<boolean>(false);
+  // 一時停止中の待機メッセージ（nullなら通常表示）
+  const [maintenanceMessage, setMaintenanceMessage] = useState<string | null>(null);
 
   const showDebugRef = useRef<boolean>(false);
   useEffect(() => { showDebugRef.current = showDebug; }, [showDebug]);
@@ -207,6 +208,18 @@ export const QrDisplayWindow: React.FC = () => {
     });
   };
 
+  const markSessionDisconnected = (sessionId: string, explicitImageId?: string) => {
+    const resolvedImageId = explicitImageId || sessionByIdRef.current.get(sessionId);
+    if (!resolvedImageId) return;
+    updateSessions((prev) => {
+      const next = new Map(prev);
+      const target = next.get(resolvedImageId);
+      if (!target || target.sessionId !== sessionId || !target.connected) return prev;
+      next.set(resolvedImageId, { ...target, connected: false });
+      return next;
+    });
+  };
+
   const triggerFallbackForAllSessions = (immediate = false) => {
     sessionsRef.current.forEach((session) => {
       if (!session.connected && session.sessionId) {
@@ -269,9 +282,19 @@ export const QrDisplayWindow: React.FC = () => {
   };
 
   // 設定値の再読込
+  // 機能フラグで Relay が無効な会場ではローカル固定にする
+  const loadOperationMode = async (): Promise<'auto' | 'relay' | 'local' | null> => {
+    const mode = (await AppSettingsService.getAppSetting('operation_mode')) as 'auto' | 'relay' | 'local' | null;
+    try {
+      const flags = await invoke<Record<string, { enabled: boolean }>>('get_feature_flags');
+      if (flags?.relay_mode && !flags.relay_mode.enabled) return 'local';
+    } catch {}
+    return mode;
+  };
+
   const reloadAppSettings = async () => {
     try {
-      const mode = await AppSettingsService.getAppSetting('operation_mode');
+      const mode = await loadOperationMode();
       if (mode === 'relay' || mode === 'local' || mode === 'auto') setOperationMode(mode);
 
       await GlobalSettingsService.loadEffective();
@@ -280,30 +303,17 @@ export const QrDisplayWindow: React.FC = () => {
       const base = await resolveBaseUrl();
       if (base) setRelayBaseUrl(base);
 
-      let eid = (eff?.relay?.eventId || (await GlobalSettingsService.get('relay_event_id'))) || '';
-      if (!eid) {
-        eid = await GlobalSettingsService.ensureEventId();
-      }
+      // イベントID・PCIDの発行と保存はメインウィンドウが行う（QR画面からは設定を書き換えない）
+      const eid = (eff?.relay?.eventId || (await GlobalSettingsService.get('relay_event_id'))) || '';
       if (eid) setRelayEventId(eid);
 
-      let pid = (eff?.relay?.pcId || (await GlobalSettingsService.get('pcid'))) || '';
-      if (!pid) {
-        pid = generateDefaultPcid();
-        try { await GlobalSettingsService.save('pcid', pid); } catch {}
-      }
+      const pid = (eff?.relay?.pcId || (await GlobalSettingsService.get('pcid'))) || '';
       if (pid) setPcId(pid);
 
       debug(`settings: mode=${mode} base=${base} eid=${eid} pcid=${pid}`);
     } catch {}
   };
 
-  function generateDefaultPcid(): string {
-    const alphabet = '0123456789abcdefghjkmnpqrstvwxyz';
-    let s = '';
-    for (let i = 0; i < 6; i++) s += alphabet[Math.floor(Math.random() * alphabet.length)];
-    return `pc-${s}`;
-  }
-
   // 初期ロード
   useEffect(() => {
     loadStateFromFile();
@@ -363,6 +373,7 @@ export const QrDisplayWindow: React.FC = () => {
 
     register('workspace-data-loaded', () => setRegenTick((t) => t + 1));
     register('app-settings-changed', () => { void reloadAppSettings(); setRegenTick((t) => t + 1); });
+    register('feature-flags-changed', () => { void reloadAppSettings(); setRegenTick((t) => t + 1); });
 
     return () => {
       disposed = true;
@@ -384,13 +395,10 @@ export const QrDisplayWindow: React.FC = () => {
         await reloadAppSettings();
 
         // 直近の値で判定
-        const mode = (await AppSettingsService.getAppSetting('operation_mode')) as 'auto' | 'relay' | 'local' | null;
+        const mode = await loadOperationMode();
         await GlobalSettingsService.loadEffective();
         const eff = GlobalSettingsService.getEffective();
-        let eid = eff?.relay?.eventId || (await GlobalSettingsService.get('relay_event_id')) || '';
-        if (!eid) {
-          eid = await GlobalSettingsService.ensureEventId();
-        }
+        const eid = eff?.relay?.eventId || (await GlobalSettingsService.get('relay_event_id')) || '';
         const base = await resolveBaseUrl();
         if (base) setRelayBaseUrl(base);
         if (eid) setRelayEventId(eid);
@@ -438,6 +446,22 @@ export const QrDisplayWindow: React.FC = () => {
     initialize();
   }, []);
 
+  // 一時停止（メンテナンスモード）
+  useEffect(() => {
+    type MaintenancePayload = { enabled: boolean; message: string };
+    const apply = (payload: MaintenancePayload | null) => {
+      setMaintenanceMessage(payload?.enabled ? payload.message : null);
+    };
+    invoke<MaintenancePayload>('get_maintenance_mode').then(apply).catch(() => {});
+    const unlisten = listen('maintenance-mode-changed', (event) => {
+      apply(event.payload as MaintenancePayload);
+    });
+
+    return () => {
+      unlisten.then((fn) => { try { fn(); } catch {} }).catch(() => {});
+    };
+  }, []);
+
   // モバイル接続イベント
   useEffect(() => {
     const unlisten = listen('mobile-connected', (event) => {
@@ -447,9 +471,25 @@ export const QrDisplayWindow: React.FC = () => {
       const imageId = payload?.imageId || sessionByIdRef.current.get(sessionId);
       markSessionConnected(sessionId, imageId);
     });
+    // 再接続トークンで復帰したスマホ（QRの読み直しなし）
+    const unlistenReconnected = listen('mobile-reconnected', (event) => {
+      const payload = event.payload as { sessionId?: string; imageId?: string };
+      if (!payload?.sessionId) return;
+      debug(`mobile-reconnected sid=${payload.sessionId}`);
+      markSessionConnected(payload.sessionId, payload.imageId || sessionByIdRef.current.get(payload.sessionId));
+    });
+    // スマホが離れたら「接続待ち」に戻してQRを再び案内する
+    const unlistenDisconnected = listen('mobile-disconnected', (event) => {
+      const payload = event.payload as { sessionId?: string; imageId?: string | null; reason?: string };
+      if (!payload?.sessionId) return;
+      debug(`mobile-disconnected sid=${payload.sessionId} reason=${payload.reason ?? '-'}`);
+      markSessionDisconnected(payload.sessionId, payload.imageId || undefined);
+    });
 
     return () => {
       unlisten.then((fn) => { try { fn(); } catch {} }).catch(() => {});
+      unlistenDisconnected.then((fn) => { try { fn(); } catch {} }).catch(() => {});
+      unlistenReconnected.then((fn) => { try { fn(); } catch {} }).catch(() => {});
     };
   }, []);
 
@@ -541,13 +581,7 @@ export const QrDisplayWindow: React.FC = () => {
           inflightRef.current.delete(imageId);
           return;
         }
-        const bearer = await loadDeviceToken();
-        if (!bearer) {
-          handleLicenseBlocking('missing', imageId, sessionKey, envKey);
-          inflightRef.current.delete(imageId);
-          return;
-        }
-
+        // デバイストークンの有無はRelayへの登録（Rust側）で判定する（E_MISSING_TOKEN）
         if (!relayEventId || !pcId) {
           // Relay 不足：Auto は Local にフォールバック / Relay 固定はエラー
           if (operationMode === 'relay') {
@@ -734,9 +768,6 @@ export const QrDisplayWindow: React.FC = () => {
   useEffect(() => {
     const onKey = (e: KeyboardEvent) => {
       if ((e.key === 'd' || e.key === 'D') && (e.metaKey || e.ctrlKey)) setShowDebug((s) => !s);
-      if (e.key === 'I' && e.metaKey && e.altKey) {
-        try { invoke('open_devtools', { window_label: 'qr-display' } as any); } catch {}
-      }
     };
     window.addEventListener('keydown', onKey);
     return () => window.removeEventListener('keydown', onKey);
@@ -860,6 +891,9 @@ export const QrDisplayWindow: React.FC = () => {
           return displayBanner ? <div className={styles.banner}>{displayBanner}</div> : null;
         })()}
 
+        {maintenanceMessage ? (
+          <div className={styles.maintenance}>{maintenanceMessage}</div>
+        ) : (<>
         <div className={styles.controls}>
           <button onClick={regenerateAll} style={{ fontSize: 12 }}>すべて再生成</button>
         </div>
@@ -882,6 +916,7 @@ export const QrDisplayWindow: React.FC = () => {
             ))
           )}
         </div>
+        </>)}
       </div>
     </ErrorBoundary>
   );